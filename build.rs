@@ -1,5 +1,34 @@
+use std::{env, fs, path::PathBuf};
+
 fn main() {
-    println!("cargo:rustc-link-arg-bins=--nmagic");
-    println!("cargo:rustc-link-arg-bins=-Tlink.x");
-    println!("cargo:rustc-link-arg-bins=-Tdefmt.x");
+    // memory.x propio para reservar las paginas de flash de datos
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::copy("memory.x", out.join("memory.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=memory.x");
+
+    // El registro de sucesos (`event-log`) usa las dos paginas anteriores.
+    // El enlazador toma el memory.x de la raiz antes que uno generado, asi
+    // que en vez de achicar FLASH se comprueba que el programa no las pise
+    let event_log = env::var_os("CARGO_FEATURE_EVENT_LOG").is_some();
+    if event_log {
+        fs::write(
+            out.join("event_log.x"),
+            "ASSERT(LOADADDR(.data) + SIZEOF(.data) <= ORIGIN(FLASH) + LENGTH(FLASH) - 2K,\n  \
+             \"el programa pisa las paginas del registro de sucesos\");\n",
+        )
+        .unwrap();
+    }
+
+    // Igual para las pruebas en la placa (tests/hardware.rs)
+    for target in ["bins", "tests"] {
+        println!("cargo:rustc-link-arg-{target}=--nmagic");
+        println!("cargo:rustc-link-arg-{target}=-Tlink.x");
+        if env::var_os("CARGO_FEATURE_DEFMT").is_some() {
+            println!("cargo:rustc-link-arg-{target}=-Tdefmt.x");
+        }
+        if event_log {
+            println!("cargo:rustc-link-arg-{target}=-Tevent_log.x");
+        }
+    }
 }
@@ -0,0 +1,10 @@
+/* STM32F103C8: 64K de flash y 20K de RAM. Las ultimas paginas (1K cada
+   una) de la flash quedan fuera del programa: el registro de advertencias
+   los umbrales aprendidos, las reglas y las dos paginas del almacen de
+   los contadores y los ajustes (ver storage.rs). Con `event-log` el registro
+   de sucesos usa las dos paginas anteriores (ver build.rs) */
+MEMORY
+{
+  FLASH : ORIGIN = 0x08000000, LENGTH = 59K
+  RAM   : ORIGIN = 0x20000000, LENGTH = 20K
+}
@@ -0,0 +1,68 @@
+use crate::sensor::MAX_LUX_VALUE;
+
+// Numero de intervalos del histograma de luz ambiental
+pub const BINS: usize = 64;
+const BIN_WIDTH: f32 = MAX_LUX_VALUE / BINS as f32;
+
+// Muestras necesarias antes de derivar un umbral (un dia a una por minuto)
+pub const MIN_SAMPLES: u32 = 24 * 60;
+// Al llegar a este total los conteos se reducen a la mitad, de modo que
+// las muestras viejas pierden peso y el umbral sigue a las estaciones
+const MAX_SAMPLES: u32 = 7 * 24 * 60;
+
+// Aprendizaje lento de la luz ambiental de un lugar. Se acumula un
+// histograma de la luz medida a lo largo de los dias y el umbral de
+// oscuridad se toma de un percentil de esa distribucion
+pub struct AmbientLearner {
+    counts: [u16; BINS],
+    total: u32,
+    percentile: u8,
+}
+
+impl AmbientLearner {
+    // `percentile` (0 a 100): porcentaje del tiempo que se considera oscuro
+    pub const fn new(percentile: u8) -> Self {
+        Self {
+            counts: [0; BINS],
+            total: 0,
+            percentile: if percentile > 100 { 100 } else { percentile },
+        }
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.total
+    }
+
+    pub fn record(&mut self, lux: f32) {
+        let bin = ((lux / BIN_WIDTH) as usize).min(BINS - 1);
+        self.counts[bin] += 1;
+        self.total += 1;
+
+        if self.total >= MAX_SAMPLES {
+            self.total = 0;
+            for count in &mut self.counts {
+                *count /= 2;
+                self.total += *count as u32;
+            }
+        }
+    }
+
+    // Umbral de oscuridad aprendido: limite superior del intervalo donde
+    // el acumulado alcanza el percentil. None mientras no haya suficientes
+    // muestras
+    pub fn dark_threshold(&self) -> Option<f32> {
+        if self.total < MIN_SAMPLES {
+            return None;
+        }
+
+        let target = (self.total as u64 * self.percentile as u64).div_ceil(100);
+        let mut cumulative = 0;
+        for (bin, &count) in self.counts.iter().enumerate() {
+            cumulative += count as u64;
+            if cumulative >= target {
+                return Some((bin + 1) as f32 * BIN_WIDTH);
+            }
+        }
+        Some(MAX_LUX_VALUE)
+    }
+}
@@ -0,0 +1,56 @@
+// Ventana del watchdog analogico del ADC. El STM32F103 compara cada
+// conversion de la secuencia contra un solo par de limites, comun a todas
+// las entradas, por lo que la ventana depende de si es de noche:
+//
+// - De dia solo se vigila la luz, por debajo del umbral de oscuridad mas
+//   alto de las zonas.
+// - De noche se vigilan tambien los sensores de distancia, cuyo voltaje
+//   sube al acercarse alguien, y la luz que vuelve. Ambos se vigilan por
+//   arriba con el nivel mas bajo de los dos, lo que a veces avisa de mas.
+//
+// Avisar de mas solo adelanta una muestra: cada zona decide con sus
+// propios umbrales
+
+// Lectura maxima del ADC de 12 bits
+pub const FULL_SCALE: u16 = 0x0FFF;
+
+// Lecturas crudas de los umbrales de una zona
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Levels {
+    // La luz por debajo de este nivel es oscuridad
+    pub dark: u16,
+    // La distancia por arriba de este nivel es presencia
+    pub near: u16,
+}
+
+// Limites del watchdog: avisa con una lectura fuera de `low..=high`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Window {
+    pub low: u16,
+    pub high: u16,
+}
+
+impl Window {
+    // Vigilar luz y distancia juntas (de noche) o solo la luz (de dia)
+    pub fn new(levels: &[Levels], night: bool) -> Self {
+        if night {
+            let high = levels
+                .iter()
+                .map(|l| l.dark.min(l.near))
+                .min()
+                .unwrap_or(FULL_SCALE);
+            Self { low: 0, high }
+        } else {
+            let low = levels.iter().map(|l| l.dark).max().unwrap_or(0);
+            Self {
+                low,
+                high: FULL_SCALE,
+            }
+        }
+    }
+
+    // La lectura no dispara el watchdog
+    pub fn contains(&self, raw: u16) -> bool {
+        (self.low..=self.high).contains(&raw)
+    }
+}
@@ -0,0 +1,60 @@
+// Aprendizaje de la distancia de fondo: la pared o el piso que el sensor
+// ve cuando no hay nadie. La presencia se detecta como algo mas cerca que
+// el fondo por un margen, sin importar a que distancia quedo instalado
+
+// Lecturas iniciales en las que solo se aprende el fondo
+const WARMUP_SAMPLES: u32 = 50;
+// Peso de cada lectura en el promedio durante el arranque y despues
+const WARMUP_WEIGHT: f32 = 1. / 8.;
+const WEIGHT: f32 = 1. / 256.;
+// Con presencia el fondo se sigue ajustando, mucho mas lento, para
+// absorber un objeto que se queda fijo (un mueble nuevo)
+const PRESENT_WEIGHT: f32 = WEIGHT / 16.;
+
+// Como detecta presencia una zona
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Presence {
+    // Distancia menor al umbral de la zona
+    Threshold,
+    // Distancia menor al fondo aprendido por al menos `margin_m`
+    Background { margin_m: f32 },
+}
+
+pub struct Background {
+    margin_m: f32,
+    baseline: f32,
+    samples: u32,
+}
+
+impl Background {
+    pub const fn new(margin_m: f32) -> Self {
+        Self {
+            margin_m,
+            baseline: 0.,
+            samples: 0,
+        }
+    }
+
+    // Fondo aprendido; None durante el arranque
+    pub fn baseline(&self) -> Option<f32> {
+        (self.samples >= WARMUP_SAMPLES).then_some(self.baseline)
+    }
+
+    // Agrega una lectura y devuelve si hay presencia; None mientras el
+    // fondo todavia se esta aprendiendo
+    pub fn update(&mut self, distance: f32) -> Option<bool> {
+        if self.samples == 0 {
+            self.baseline = distance;
+        }
+        if self.samples < WARMUP_SAMPLES {
+            self.samples += 1;
+            self.baseline += (distance - self.baseline) * WARMUP_WEIGHT;
+            return None;
+        }
+
+        let present = distance < self.baseline - self.margin_m;
+        let weight = if present { PRESENT_WEIGHT } else { WEIGHT };
+        self.baseline += (distance - self.baseline) * weight;
+        Some(present)
+    }
+}
@@ -0,0 +1,66 @@
+// Vigilancia de la bateria de un nodo solar. Por debajo del voltaje
+// critico el nodo se apaga y solo vuelve cuando el panel la recarga por
+// encima del de recuperacion; la diferencia entre ambos evita que el nodo
+// se encienda y apague con cada nube. Una caida breve (el arranque de una
+// lampara) no cuenta: hacen falta varias lecturas seguidas bajo el critico
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thresholds {
+    pub critical_v: f32,
+    pub recover_v: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    // Apagar el nodo
+    Critical,
+    // La bateria se recupero
+    Recovered,
+}
+
+pub struct BatteryMonitor {
+    thresholds: Thresholds,
+    confirm: u8,
+    below: u8,
+    low: bool,
+}
+
+impl BatteryMonitor {
+    // `low` es el estado de partida, por ejemplo al despertar de un apagado
+    // por bateria baja
+    pub const fn new(thresholds: Thresholds, confirm: u8, low: bool) -> Self {
+        Self {
+            thresholds,
+            confirm,
+            below: 0,
+            low,
+        }
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.low
+    }
+
+    // Registra una lectura; devuelve el cambio de estado, si lo hay
+    pub fn update(&mut self, voltage: f32) -> Option<Event> {
+        if self.low {
+            if voltage >= self.thresholds.recover_v {
+                self.low = false;
+                self.below = 0;
+                return Some(Event::Recovered);
+            }
+            return None;
+        }
+
+        if voltage >= self.thresholds.critical_v {
+            self.below = 0;
+            return None;
+        }
+        self.below = self.below.saturating_add(1);
+        if self.below < self.confirm {
+            return None;
+        }
+        self.low = true;
+        Some(Event::Critical)
+    }
+}
@@ -0,0 +1,62 @@
+// Sonidos del zumbador, descritos como secuencias de tonos para que el
+// firmware solo tenga que reproducirlos
+
+// Un tono de la secuencia; frecuencia cero es un silencio
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tone {
+    pub freq_hz: u32,
+    pub duration_ms: u32,
+}
+
+const fn tone(freq_hz: u32, duration_ms: u32) -> Tone {
+    Tone {
+        freq_hz,
+        duration_ms,
+    }
+}
+
+const fn rest(duration_ms: u32) -> Tone {
+    tone(0, duration_ms)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Beep {
+    // Confirmacion de una pulsacion
+    Click,
+    // Entrada y salida del modo manual: tonos ascendentes y descendentes
+    ManualOn,
+    ManualOff,
+    // Falla de un sensor: tono grave repetido
+    Fault,
+    // Alarma de proximidad
+    Alarm,
+}
+
+const CLICK: &[Tone] = &[tone(4000, 20)];
+const MANUAL_ON: &[Tone] = &[tone(1500, 80), rest(40), tone(2500, 80)];
+const MANUAL_OFF: &[Tone] = &[tone(2500, 80), rest(40), tone(1500, 80)];
+const FAULT: &[Tone] = &[
+    tone(400, 300),
+    rest(200),
+    tone(400, 300),
+    rest(200),
+    tone(400, 300),
+];
+const ALARM: &[Tone] = &[
+    tone(3000, 150),
+    tone(2000, 150),
+    tone(3000, 150),
+    tone(2000, 150),
+];
+
+impl Beep {
+    pub fn tones(self) -> &'static [Tone] {
+        match self {
+            Beep::Click => CLICK,
+            Beep::ManualOn => MANUAL_ON,
+            Beep::ManualOff => MANUAL_OFF,
+            Beep::Fault => FAULT,
+            Beep::Alarm => ALARM,
+        }
+    }
+}
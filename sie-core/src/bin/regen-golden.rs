@@ -0,0 +1,44 @@
+// Regenera las salidas esperadas de las trazas de referencia.
+// Uso: cargo run --bin regen-golden
+//
+// Solo debe ejecutarse cuando un cambio de comportamiento es intencional;
+// revisar el diff de los archivos .expected.csv antes de confirmarlo.
+
+use std::{fs, path::Path, process::ExitCode};
+
+use sie_core::{
+    control::Thresholds,
+    golden::{EXPECTED_SUFFIX, TRACE_SUFFIX, parse_trace, run},
+};
+
+fn main() -> ExitCode {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut entries: Vec<_> = fs::read_dir(&dir)
+        .expect("Cannot read fixtures directory")
+        .map(|entry| entry.expect("Cannot read fixture entry").path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let Some(stem) = name.strip_suffix(TRACE_SUFFIX) else {
+            continue;
+        };
+
+        let trace = fs::read_to_string(&path).expect("Cannot read trace");
+        let samples = match parse_trace(&trace) {
+            Ok(samples) => samples,
+            Err((line, content)) => {
+                eprintln!("{name}:{line}: linea invalida: {content}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let expected = run(&samples, &Thresholds::default());
+        let out = dir.join(format!("{stem}{EXPECTED_SUFFIX}"));
+        fs::write(&out, expected).expect("Cannot write expected output");
+        println!("{stem}: {} muestras", samples.len());
+    }
+
+    ExitCode::SUCCESS
+}
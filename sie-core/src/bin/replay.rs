@@ -0,0 +1,118 @@
+// Reproduce trazas grabadas en la computadora.
+// Uso:
+//   cargo run --bin replay -- convert captura.bin [zona] > traza.csv
+//       Convierte una captura de la telemetria (tramas COBS con los
+//       voltajes medidos) en una traza.
+//   cargo run --bin replay -- check traza.csv esperada.csv [luz distancia]
+//       Pasa la traza por el controlador con los umbrales indicados (luxes
+//       y metros; por defecto los de fabrica) y compara las decisiones con
+//       las esperadas.
+//
+// Una traza con su salida esperada sirve tambien como prueba golden o para
+// reproducirla en la placa con la opcion `replay` del firmware.
+
+use std::{env, fs, process::ExitCode};
+
+use sie_core::{
+    control::{Reading, Thresholds, decide},
+    replay::{self, Sample, TRACE_HEADER, Verdict},
+    telemetry::{self, SAMPLE_MAX},
+};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["convert", capture, rest @ ..] => {
+            let zone = rest.first().map_or(0, |z| z.parse().expect("Invalid zone"));
+            convert(capture, zone)
+        }
+        ["check", trace, expected, rest @ ..] => {
+            let thresholds = match rest {
+                [] => Thresholds::default(),
+                [light, distance] => Thresholds {
+                    light: light.parse().expect("Invalid light threshold"),
+                    distance: distance.parse().expect("Invalid distance threshold"),
+                },
+                _ => return usage(),
+            };
+            check(trace, expected, &thresholds)
+        }
+        _ => usage(),
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("uso: replay convert CAPTURA [ZONA] | replay check TRAZA ESPERADA [LUZ DISTANCIA]");
+    ExitCode::FAILURE
+}
+
+// Las tramas de otras zonas, sin voltajes o danadas se saltan; el tiempo
+// se cuenta desde la primera muestra
+fn convert(capture: &str, zone: u8) -> ExitCode {
+    let data = fs::read(capture).expect("Cannot read capture");
+    let mut start = None;
+    let mut skipped = 0;
+
+    println!("# Convertida de {capture}, zona {zone}");
+    println!("{TRACE_HEADER}");
+    for frame in data.split(|&b| b == 0).filter(|f| !f.is_empty()) {
+        let mut decoded = [0; SAMPLE_MAX];
+        let sample = telemetry::cobs_decode(frame, &mut decoded)
+            .and_then(|len| telemetry::Sample::deserialize(&decoded[..len]));
+        let Some(sample) = sample.filter(|s| s.zone == zone) else {
+            skipped += 1;
+            continue;
+        };
+        let Some(sample) = Sample::from_telemetry(&sample) else {
+            skipped += 1;
+            continue;
+        };
+        let start = *start.get_or_insert(sample.t_ms);
+        println!(
+            "{},{},{}",
+            sample.t_ms - start,
+            sample.raw_distance,
+            sample.raw_lux
+        );
+    }
+
+    eprintln!("{skipped} tramas saltadas");
+    ExitCode::SUCCESS
+}
+
+fn check(trace: &str, expected: &str, thresholds: &Thresholds) -> ExitCode {
+    let trace = fs::read_to_string(trace).expect("Cannot read trace");
+    let expected = fs::read_to_string(expected).expect("Cannot read expected output");
+
+    let mut verdict = Verdict::default();
+    for (sample, expected) in replay::samples(&trace).zip(replay::outputs(&expected)) {
+        let (sample, expected) = match (sample, expected) {
+            (Ok(sample), Ok(expected)) => (sample, expected),
+            (Err(line), _) => {
+                eprintln!("traza:{line}: linea invalida");
+                return ExitCode::FAILURE;
+            }
+            (_, Err(line)) => {
+                eprintln!("esperada:{line}: linea invalida");
+                return ExitCode::FAILURE;
+            }
+        };
+        let reading = Reading::from_raw(sample.raw_distance, sample.raw_lux);
+        verdict.record(&expected, &decide(&reading, thresholds));
+    }
+
+    match verdict.first_mismatch {
+        None => {
+            println!("{} decisiones iguales", verdict.samples);
+            ExitCode::SUCCESS
+        }
+        Some(t_ms) => {
+            println!(
+                "{} de {} decisiones distintas, la primera a {t_ms} ms",
+                verdict.mismatches, verdict.samples
+            );
+            ExitCode::FAILURE
+        }
+    }
+}
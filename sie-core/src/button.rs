@@ -0,0 +1,122 @@
+use crate::clock::Clock;
+
+// Clasificacion de una pulsacion segun su duracion y repeticiones
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Press {
+    Single,
+    Double,
+    Triple,
+    Long,
+}
+
+impl Press {
+    fn from_clicks(clicks: u8) -> Self {
+        match clicks {
+            1 => Press::Single,
+            2 => Press::Double,
+            _ => Press::Triple,
+        }
+    }
+}
+
+// Tiempos del boton en milisegundos
+#[derive(Clone, Copy, Debug)]
+pub struct Timing {
+    // Tiempo que el nivel debe mantenerse estable para aceptarse
+    pub settle_ms: u64,
+    // Duracion a partir de la cual una pulsacion se considera larga
+    pub long_press_ms: u64,
+    // Tiempo maximo entre clics para agruparlos en un mismo gesto.
+    // Con cero cada clic se reporta de inmediato
+    pub click_window_ms: u64,
+}
+
+// Maquina de estados con antirrebote y clasificacion de gestos. Recibe el
+// nivel crudo del pin (true = presionado) cada vez que cambia o cuando se
+// cumple el plazo indicado por `next_deadline`
+pub struct Gesture<C: Clock> {
+    clock: C,
+    timing: Timing,
+    raw: bool,
+    raw_since: u64,
+    stable: bool,
+    press_start: u64,
+    last_release: u64,
+    clicks: u8,
+    // La pulsacion larga ya se reporto; se ignora la liberacion
+    long_reported: bool,
+}
+
+impl<C: Clock> Gesture<C> {
+    pub fn new(clock: C, timing: Timing) -> Self {
+        let now = clock.now_ms();
+        Self {
+            clock,
+            timing,
+            raw: false,
+            raw_since: now,
+            stable: false,
+            press_start: now,
+            last_release: now,
+            clicks: 0,
+            long_reported: false,
+        }
+    }
+
+    // Procesa el nivel actual del pin y devuelve el gesto completado, si hay
+    pub fn update(&mut self, pressed: bool) -> Option<Press> {
+        let now = self.clock.now_ms();
+
+        if pressed != self.raw {
+            self.raw = pressed;
+            self.raw_since = now;
+        }
+
+        // El nivel solo se acepta si se mantuvo durante el asentamiento
+        if self.raw != self.stable && now - self.raw_since >= self.timing.settle_ms {
+            self.stable = self.raw;
+
+            if self.stable {
+                self.press_start = self.raw_since;
+            } else if self.long_reported {
+                self.long_reported = false;
+            } else {
+                self.clicks += 1;
+                self.last_release = self.raw_since;
+                if self.clicks == 3 {
+                    self.clicks = 0;
+                    return Some(Press::Triple);
+                }
+            }
+        }
+
+        // Una pulsacion larga se reporta sin esperar a que se suelte
+        if self.stable && !self.long_reported && now - self.press_start >= self.timing.long_press_ms
+        {
+            self.long_reported = true;
+            self.clicks = 0;
+            return Some(Press::Long);
+        }
+
+        if !self.stable && self.clicks > 0 && now - self.last_release >= self.timing.click_window_ms
+        {
+            let press = Press::from_clicks(self.clicks);
+            self.clicks = 0;
+            return Some(press);
+        }
+
+        None
+    }
+
+    // Siguiente instante (ms) en que hay que llamar a `update` aunque el pin
+    // no cambie; None si solo un cambio de nivel puede producir un gesto
+    pub fn next_deadline(&self) -> Option<u64> {
+        let settle = (self.raw != self.stable).then_some(self.raw_since + self.timing.settle_ms);
+        let long = (self.stable && !self.long_reported)
+            .then_some(self.press_start + self.timing.long_press_ms);
+        let window = (!self.stable && self.clicks > 0)
+            .then_some(self.last_release + self.timing.click_window_ms);
+
+        [settle, long, window].into_iter().flatten().min()
+    }
+}
@@ -0,0 +1,105 @@
+// Tramas del bus CAN para varias lamparas en el mismo bus. Cada nodo
+// tiene un numero (0 a 127) y usa identificadores estandar:
+//   0x100 + nodo  estado de una zona, periodico
+//   0x200 + nodo  orden para el nodo
+//   0x27F         orden para todos los nodos
+// Los datos van en little endian
+
+pub const STATUS_BASE: u16 = 0x100;
+pub const COMMAND_BASE: u16 = 0x200;
+pub const BROADCAST_NODE: u8 = 0x7F;
+
+pub fn status_id(node: u8) -> u16 {
+    STATUS_BASE | (node & 0x7F) as u16
+}
+
+pub fn command_id(node: u8) -> u16 {
+    COMMAND_BASE | (node & 0x7F) as u16
+}
+
+// Estado de una zona (8 bytes): zona, brillo (%), banderas, luxes (u16)
+// y distancia en centimetros (u16)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Status {
+    pub zone: u8,
+    pub brightness: u8,
+    pub manual: bool,
+    pub enabled: bool,
+    pub lux: u16,
+    pub distance_cm: u16,
+}
+
+const FLAG_MANUAL: u8 = 1 << 0;
+const FLAG_ENABLED: u8 = 1 << 1;
+
+impl Status {
+    pub fn encode(&self) -> [u8; 8] {
+        let flags =
+            if self.manual { FLAG_MANUAL } else { 0 } | if self.enabled { FLAG_ENABLED } else { 0 };
+        let [lux_lo, lux_hi] = self.lux.to_le_bytes();
+        let [distance_lo, distance_hi] = self.distance_cm.to_le_bytes();
+        [
+            self.zone,
+            self.brightness,
+            flags,
+            lux_lo,
+            lux_hi,
+            distance_lo,
+            distance_hi,
+            0,
+        ]
+    }
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let data: &[u8; 8] = data.try_into().ok()?;
+        Some(Self {
+            zone: data[0],
+            brightness: data[1],
+            manual: data[2] & FLAG_MANUAL != 0,
+            enabled: data[2] & FLAG_ENABLED != 0,
+            lux: u16::from_le_bytes([data[3], data[4]]),
+            distance_cm: u16::from_le_bytes([data[5], data[6]]),
+        })
+    }
+}
+
+// Ordenes: el primer byte indica cual y el resto sus parametros
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    // 1, modo: 0 automatico, 1 manual
+    SetManual(bool),
+    // 2, umbral de luz en luxes (u16) de todas las zonas
+    SetLightThreshold(u16),
+    // 3, umbral de distancia en centimetros (u16) de todas las zonas
+    SetDistanceThreshold(u16),
+}
+
+impl Command {
+    pub fn encode(&self) -> ([u8; 3], usize) {
+        match *self {
+            Self::SetManual(manual) => ([1, manual as u8, 0], 2),
+            Self::SetLightThreshold(lux) => {
+                let [lo, hi] = lux.to_le_bytes();
+                ([2, lo, hi], 3)
+            }
+            Self::SetDistanceThreshold(cm) => {
+                let [lo, hi] = cm.to_le_bytes();
+                ([3, lo, hi], 3)
+            }
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let word = || Some(u16::from_le_bytes([*data.get(1)?, *data.get(2)?]));
+        match *data.first()? {
+            1 => match *data.get(1)? {
+                0 => Some(Self::SetManual(false)),
+                1 => Some(Self::SetManual(true)),
+                _ => None,
+            },
+            2 => word().map(Self::SetLightThreshold),
+            3 => word().map(Self::SetDistanceThreshold),
+            _ => None,
+        }
+    }
+}
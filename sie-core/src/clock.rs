@@ -0,0 +1,49 @@
+use core::cell::Cell;
+
+use crate::schedule::TimeOfDay;
+
+// Fuente de tiempo monotono. En el firmware la provee el timer del sistema
+// y en las pruebas un reloj virtual que se avanza a mano
+pub trait Clock {
+    // Milisegundos desde el arranque
+    fn now_ms(&self) -> u64;
+}
+
+// Reloj de tiempo real con la hora del dia. A diferencia de `Clock` puede
+// no estar ajustado, por ejemplo tras quedarse sin bateria de respaldo
+pub trait WallClock {
+    fn now(&mut self) -> Option<TimeOfDay>;
+    fn set(&mut self, time: TimeOfDay);
+}
+
+impl<C: Clock> Clock for &C {
+    fn now_ms(&self) -> u64 {
+        (**self).now_ms()
+    }
+}
+
+// Reloj virtual para simular el paso del tiempo de forma determinista
+#[derive(Default)]
+pub struct VirtualClock {
+    now: Cell<u64>,
+}
+
+impl VirtualClock {
+    pub const fn new() -> Self {
+        Self { now: Cell::new(0) }
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.now.set(self.now.get() + ms);
+    }
+
+    pub fn set(&self, ms: u64) {
+        self.now.set(ms);
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now_ms(&self) -> u64 {
+        self.now.get()
+    }
+}
@@ -0,0 +1,152 @@
+// Catalogo de codigos de falla. Cada codigo es el modulo por cien mas la
+// condicion (201: zona, encendida demasiado tiempo) y no cambia entre
+// versiones, para que la documentacion de soporte pueda citarlo. El mismo
+// numero aparece en el registro en flash, la consola, MQTT y los registros
+// I2C. Un codigo retirado no se reutiliza; 0 significa sin falla
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Module {
+    // Flash: contadores, ajustes, reglas y umbrales aprendidos
+    Storage = 1,
+    // Controlador de una zona
+    Zone = 2,
+    // Salida de las lamparas
+    Lamp = 3,
+    // Ordenes y cambios recibidos a distancia
+    Remote = 4,
+    // Puertos serie y buses: consola, telemetria, DMX, ESP, pantalla,
+    // tarjeta SD
+    Serial = 5,
+    // Radios y red: LoRa, nRF24, MQTT
+    Radio = 6,
+    // Red electrica y medidor de energia
+    Power = 7,
+    // Firmware
+    System = 8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Code {
+    CountersNotSaved = 101,
+    SettingsInvalid = 102,
+    SettingsNotSaved = 103,
+    RulesInvalid = 104,
+    AmbientNotErased = 105,
+    KnownGoodNotSaved = 106,
+    KnownGoodNotCleared = 107,
+    FactoryResetIncomplete = 108,
+
+    OnTooLong = 201,
+    SensorLinkLost = 202,
+    NoReadingToTeach = 203,
+    Inconsistent = 204,
+    Simulated = 205,
+    ReplayMismatch = 206,
+
+    PwmOutOfRange = 301,
+
+    RevertedStuckOn = 401,
+    RevertedNeverOn = 402,
+    UnknownCommand = 403,
+    InvalidWrite = 404,
+
+    ConsoleSetup = 501,
+    TelemetrySetup = 502,
+    DmxSetup = 503,
+    DmxFrame = 504,
+    EspSetup = 505,
+    DisplayMissing = 506,
+    SdCardMissing = 507,
+    SdCardWrite = 508,
+
+    LoraMissing = 601,
+    LoraTimeout = 602,
+    RadioBus = 603,
+    Nrf24Missing = 604,
+    MqttDisconnected = 605,
+
+    MainsDropout = 701,
+    MainsLow = 702,
+    MainsHigh = 703,
+    EnergyOver = 704,
+    EnergyUnder = 705,
+    BatteryCritical = 706,
+
+    Panic = 801,
+    TaskStalled = 802,
+    StackLow = 803,
+    SpawnFailed = 804,
+    AdcInit = 805,
+}
+
+impl Code {
+    pub const ALL: [Self; 43] = [
+        Self::CountersNotSaved,
+        Self::SettingsInvalid,
+        Self::SettingsNotSaved,
+        Self::RulesInvalid,
+        Self::AmbientNotErased,
+        Self::KnownGoodNotSaved,
+        Self::KnownGoodNotCleared,
+        Self::FactoryResetIncomplete,
+        Self::OnTooLong,
+        Self::SensorLinkLost,
+        Self::NoReadingToTeach,
+        Self::Inconsistent,
+        Self::Simulated,
+        Self::ReplayMismatch,
+        Self::PwmOutOfRange,
+        Self::RevertedStuckOn,
+        Self::RevertedNeverOn,
+        Self::UnknownCommand,
+        Self::InvalidWrite,
+        Self::ConsoleSetup,
+        Self::TelemetrySetup,
+        Self::DmxSetup,
+        Self::DmxFrame,
+        Self::EspSetup,
+        Self::DisplayMissing,
+        Self::SdCardMissing,
+        Self::SdCardWrite,
+        Self::LoraMissing,
+        Self::LoraTimeout,
+        Self::RadioBus,
+        Self::Nrf24Missing,
+        Self::MqttDisconnected,
+        Self::MainsDropout,
+        Self::MainsLow,
+        Self::MainsHigh,
+        Self::EnergyOver,
+        Self::EnergyUnder,
+        Self::BatteryCritical,
+        Self::Panic,
+        Self::TaskStalled,
+        Self::StackLow,
+        Self::SpawnFailed,
+        Self::AdcInit,
+    ];
+
+    pub const fn number(self) -> u16 {
+        self as u16
+    }
+
+    // None si el numero no es un codigo del catalogo (incluido el 0)
+    pub fn from_number(number: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|code| code.number() == number)
+    }
+
+    pub const fn module(self) -> Module {
+        match self.number() / 100 {
+            1 => Module::Storage,
+            2 => Module::Zone,
+            3 => Module::Lamp,
+            4 => Module::Remote,
+            5 => Module::Serial,
+            6 => Module::Radio,
+            7 => Module::Power,
+            _ => Module::System,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::sensor::{DistanceModel, LightModel, LuxPolarity, Profile, get_voltage};
+
+// Umbrales por defecto para el sensor
+pub const LIGHT_THRESHOLD: f32 = 1000.; // Luxes
+pub const DISTANCE_THRESHOLD: f32 = 2.5; // Metters
+
+// Umbrales con los que se decide encender la luz
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thresholds {
+    pub light: f32,
+    pub distance: f32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            light: LIGHT_THRESHOLD,
+            distance: DISTANCE_THRESHOLD,
+        }
+    }
+}
+
+impl Thresholds {
+    // Umbrales por defecto para otros modelos de sensores
+    pub const fn for_models(distance: DistanceModel, light: LightModel) -> Self {
+        Self {
+            light: light.profile().threshold,
+            distance: distance.profile().threshold,
+        }
+    }
+}
+
+// Lectura de ambos sensores convertida a unidades fisicas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reading {
+    pub distance_voltage: f32,
+    pub lux_voltage: f32,
+    pub distance: f32,
+    pub lux: f32,
+}
+
+impl Reading {
+    // Convierte los valores crudos del ADC
+    pub fn from_raw(raw_distance: u16, raw_lux: u16) -> Self {
+        Self::from_raw_oriented(raw_distance, raw_lux, LuxPolarity::Rising)
+    }
+
+    // Igual, con un modulo de luz de la orientacion indicada. El voltaje
+    // guardado es el medido
+    pub fn from_raw_oriented(raw_distance: u16, raw_lux: u16, polarity: LuxPolarity) -> Self {
+        Self::from_raw_profiled(
+            raw_distance,
+            raw_lux,
+            &DistanceModel::default().profile(),
+            &LightModel::default().profile(),
+            polarity,
+        )
+    }
+
+    // Igual, con los parametros de otros modelos de sensores
+    pub fn from_raw_profiled(
+        raw_distance: u16,
+        raw_lux: u16,
+        distance: &Profile,
+        light: &Profile,
+        polarity: LuxPolarity,
+    ) -> Self {
+        let distance_voltage = get_voltage(raw_distance as f32);
+        let lux_voltage = get_voltage(raw_lux as f32);
+
+        Self {
+            distance_voltage,
+            lux_voltage,
+            distance: distance.value(distance_voltage),
+            lux: light.value(polarity.normalize_in(lux_voltage, light)),
+        }
+    }
+}
+
+// Resultado de evaluar una lectura
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Decision {
+    // Hay poca luz ambiental
+    pub dark: bool,
+    // Hay alguien cerca del sensor
+    pub present: bool,
+    // La luz debe encenderse
+    pub light_on: bool,
+}
+
+// Se enciende la luz cuando esta oscuro y hay alguien cerca
+pub fn decide(reading: &Reading, thresholds: &Thresholds) -> Decision {
+    let dark = reading.lux < thresholds.light;
+    let present = reading.distance < thresholds.distance;
+    Decision {
+        dark,
+        present,
+        light_on: dark && present,
+    }
+}
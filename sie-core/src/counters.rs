@@ -0,0 +1,50 @@
+// Totales de toda la vida del equipo. Se guardan en el almacen clave-valor
+// (ver kv), que ya los protege de un corte de energia
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub boots: u32,
+    pub activations: u32,
+    // Tiempo encendida sumando todas las zonas
+    pub on_seconds: u32,
+    pub faults: u32,
+}
+
+// Cuatro u32 en little endian
+pub const ENCODED_SIZE: usize = 16;
+
+impl Counters {
+    pub fn encode(&self) -> [u8; ENCODED_SIZE] {
+        let mut out = [0; ENCODED_SIZE];
+        for (i, value) in [self.boots, self.activations, self.on_seconds, self.faults]
+            .into_iter()
+            .enumerate()
+        {
+            out[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn decode(data: &[u8; ENCODED_SIZE]) -> Self {
+        let word = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        Self {
+            boots: word(0),
+            activations: word(4),
+            on_seconds: word(8),
+            faults: word(12),
+        }
+    }
+}
+
+// CRC-32 (IEEE) bit a bit; se calcula pocas veces y asi no ocupa tabla
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,113 @@
+// Filas CSV del registro en la tarjeta SD: una por zona en cada intervalo.
+// Los numeros se escriben a mano (sin `core::fmt`) para no sumar el
+// formateo de flotantes al firmware. La hora queda vacia mientras el RTC
+// no este ajustado; el tiempo desde el arranque siempre esta
+
+use crate::schedule::TimeOfDay;
+
+pub const HEADER: &[u8] =
+    b"uptime_s,hora,zona,luz_lx,distancia_m,ocupada,lampara,manual,habilitado\r\n";
+
+// Largo maximo de una fila con todos los campos en su maximo
+pub const MAX_ROW: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Row {
+    pub uptime_s: u32,
+    pub time: Option<TimeOfDay>,
+    pub zone: u8,
+    // None si la zona todavia no tiene lectura
+    pub lux: Option<f32>,
+    pub distance: Option<f32>,
+    pub occupied: bool,
+    pub light_on: bool,
+    pub manual: bool,
+    pub enabled: bool,
+}
+
+struct Writer<'a> {
+    out: &'a mut [u8; MAX_ROW],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn push_byte(&mut self, byte: u8) {
+        if self.len < MAX_ROW {
+            self.out[self.len] = byte;
+            self.len += 1;
+        }
+    }
+
+    fn push_number(&mut self, value: u32) {
+        let mut digits = [0; 10];
+        let mut len = 0;
+        let mut rest = value;
+        loop {
+            digits[len] = b'0' + (rest % 10) as u8;
+            len += 1;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        for &digit in digits[..len].iter().rev() {
+            self.push_byte(digit);
+        }
+    }
+
+    fn push_two_digits(&mut self, value: u32) {
+        self.push_byte(b'0' + (value / 10 % 10) as u8);
+        self.push_byte(b'0' + (value % 10) as u8);
+    }
+
+    // Valor positivo con `decimals` decimales (1 o 2)
+    fn push_fixed(&mut self, value: f32, decimals: u32) {
+        let scale = 10u32.pow(decimals);
+        let scaled = (value.max(0.) * scale as f32 + 0.5) as u32;
+        self.push_number(scaled / scale);
+        self.push_byte(b'.');
+        match decimals {
+            1 => self.push_byte(b'0' + (scaled % 10) as u8),
+            _ => self.push_two_digits(scaled % scale),
+        }
+    }
+
+    fn push_bool(&mut self, value: bool) {
+        self.push_byte(if value { b'1' } else { b'0' });
+    }
+
+    fn separator(&mut self) {
+        self.push_byte(b',');
+    }
+}
+
+// Escribe la fila con su fin de linea en `out` y devuelve su largo
+pub fn format(row: &Row, out: &mut [u8; MAX_ROW]) -> usize {
+    let mut w = Writer { out, len: 0 };
+    w.push_number(row.uptime_s);
+    w.separator();
+    if let Some(time) = row.time {
+        w.push_two_digits(time.hours());
+        w.push_byte(b':');
+        w.push_two_digits(time.minutes());
+        w.push_byte(b':');
+        w.push_two_digits(time.seconds() % 60);
+    }
+    w.separator();
+    w.push_number(row.zone as u32);
+    w.separator();
+    if let Some(lux) = row.lux {
+        w.push_fixed(lux, 1);
+    }
+    w.separator();
+    if let Some(distance) = row.distance {
+        w.push_fixed(distance, 2);
+    }
+    for value in [row.occupied, row.light_on, row.manual, row.enabled] {
+        w.separator();
+        w.push_bool(value);
+    }
+    w.push_byte(b'\r');
+    w.push_byte(b'\n');
+    w.len
+}
@@ -0,0 +1,230 @@
+use crate::{
+    codes::Code,
+    font::{self, LINE_HEIGHT},
+    framebuffer::{Framebuffer, WIDTH},
+};
+
+// Pantalla de estado del OLED: el modo en la primera linea, dos lineas por
+// zona (la lampara y las ultimas lecturas) y la ultima falla al pie, para
+// usar el equipo sin una sonda de depuracion
+//
+//     AUTOMATICO
+//
+//     Z0 LAMPARA ENCENDIDA
+//        12 LX    2.35 M
+//     Z1 LAMPARA APAGADA
+//        850 LX   5.50 M
+//
+//     FALLA 206
+
+// Caracteres por linea
+const COLUMNS: usize = WIDTH / font::ADVANCE;
+// Las mismas lecturas en paginas de un LCD de caracteres de 16x2: el modo
+// y la ultima falla, y despues una pagina por zona
+//
+//     AUTOMATICO          Z0 ENCENDIDA
+//     FALLA 206           12 LX   2.35 M
+pub const LCD_COLUMNS: usize = 16;
+pub type LcdPage = [[u8; LCD_COLUMNS]; 2];
+// Lineas hasta la de las fallas, la ultima
+const FAULT_LINE: usize = 7;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ZoneView {
+    // None hasta la primera lectura
+    pub lux: Option<f32>,
+    pub distance: Option<f32>,
+    pub light_on: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dashboard<const ZONES: usize> {
+    pub zones: [ZoneView; ZONES],
+    pub manual: bool,
+    pub enabled: bool,
+    pub fault: Option<Code>,
+}
+
+impl<const ZONES: usize> Default for Dashboard<ZONES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const ZONES: usize> Dashboard<ZONES> {
+    pub const fn new() -> Self {
+        Self {
+            zones: [ZoneView {
+                lux: None,
+                distance: None,
+                light_on: false,
+            }; ZONES],
+            manual: false,
+            enabled: true,
+            fault: None,
+        }
+    }
+
+    // Redibuja la pantalla completa
+    pub fn draw(&self, fb: &mut Framebuffer) {
+        fb.clear();
+
+        font::draw_text(fb, 0, 0, self.mode().as_bytes());
+
+        for (id, zone) in self.zones.iter().enumerate() {
+            // Las zonas empiezan en la tercera linea; mas de dos no caben
+            let y = (2 + 2 * id) * LINE_HEIGHT;
+            if y + 2 * LINE_HEIGHT > FAULT_LINE * LINE_HEIGHT {
+                break;
+            }
+
+            let mut line = Line::<COLUMNS>::new();
+            line.push("Z");
+            line.push_number(id as u32);
+            line.push(if zone.light_on {
+                " LAMPARA ENCENDIDA"
+            } else {
+                " LAMPARA APAGADA"
+            });
+            line.draw(fb, y);
+
+            let mut line = Line::<COLUMNS>::new();
+            line.push("   ");
+            line.push_readings(zone, 12);
+            line.draw(fb, y + LINE_HEIGHT);
+        }
+
+        if let Some(code) = self.fault {
+            let mut line = Line::<COLUMNS>::new();
+            line.push_fault(code);
+            line.draw(fb, FAULT_LINE * LINE_HEIGHT);
+        }
+    }
+
+    // Paginas del LCD
+    pub const fn pages(&self) -> usize {
+        1 + ZONES
+    }
+
+    // Texto de la pagina `index` (modulo el numero de paginas), con
+    // espacios hasta el final de cada linea
+    pub fn page(&self, index: usize) -> LcdPage {
+        let mut top = Line::<LCD_COLUMNS>::new();
+        let mut bottom = Line::<LCD_COLUMNS>::new();
+        match (index % self.pages()).checked_sub(1) {
+            None => {
+                top.push(self.mode());
+                match self.fault {
+                    Some(code) => bottom.push_fault(code),
+                    None => bottom.push("SIN FALLAS"),
+                }
+            }
+            Some(id) => {
+                let zone = &self.zones[id];
+                top.push("Z");
+                top.push_number(id as u32);
+                top.push(if zone.light_on {
+                    " ENCENDIDA"
+                } else {
+                    " APAGADA"
+                });
+                bottom.push_readings(zone, 8);
+            }
+        }
+        [top.text, bottom.text]
+    }
+
+    fn mode(&self) -> &'static str {
+        if !self.enabled {
+            "APAGADO"
+        } else if self.manual {
+            "MANUAL"
+        } else {
+            "AUTOMATICO"
+        }
+    }
+}
+
+// Texto de una linea de `N` caracteres, recortado al ancho de la pantalla
+struct Line<const N: usize> {
+    text: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Line<N> {
+    fn new() -> Self {
+        Self {
+            text: [b' '; N],
+            len: 0,
+        }
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        if self.len < N {
+            self.text[self.len] = byte;
+            self.len += 1;
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        for &byte in text.as_bytes() {
+            self.push_byte(byte);
+        }
+    }
+
+    fn push_number(&mut self, value: u32) {
+        let mut digits = [0; 10];
+        let mut len = 0;
+        let mut rest = value;
+        loop {
+            digits[len] = b'0' + (rest % 10) as u8;
+            len += 1;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        for &digit in digits[..len].iter().rev() {
+            self.push_byte(digit);
+        }
+    }
+
+    // Valor positivo con dos decimales
+    fn push_hundredths(&mut self, value: f32) {
+        let hundredths = (value.max(0.) * 100. + 0.5) as u32;
+        self.push_number(hundredths / 100);
+        self.push_byte(b'.');
+        self.push_byte(b'0' + (hundredths / 10 % 10) as u8);
+        self.push_byte(b'0' + (hundredths % 10) as u8);
+    }
+
+    // Luz y distancia, con la distancia en la columna `column`
+    fn push_readings(&mut self, zone: &ZoneView, column: usize) {
+        match (zone.lux, zone.distance) {
+            (Some(lux), Some(distance)) => {
+                self.push_number((lux.max(0.) + 0.5) as u32);
+                self.push(" LX");
+                self.pad_to(column);
+                self.push_hundredths(distance);
+                self.push(" M");
+            }
+            _ => self.push("SIN LECTURAS"),
+        }
+    }
+
+    fn push_fault(&mut self, code: Code) {
+        self.push("FALLA ");
+        self.push_number(code.number() as u32);
+    }
+
+    // Alinea lo que sigue en la columna `column`
+    fn pad_to(&mut self, column: usize) {
+        while self.len < column {
+            self.push_byte(b' ');
+        }
+    }
+
+    fn draw(&self, fb: &mut Framebuffer, y: usize) {
+        font::draw_text(fb, 0, y, &self.text[..self.len]);
+    }
+}
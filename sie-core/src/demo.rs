@@ -0,0 +1,141 @@
+// Secuencias de demostracion: lecturas inventadas por pasos temporizados,
+// para mostrar el sistema en exposiciones y clases sin mover los sensores
+// reales. Cada paso cambia la lectura y se mantiene el tiempo indicado
+
+use crate::sensor::{DIST_MIN_M, MAX_LUX_VALUE};
+
+// Luz y distancia de las lecturas inventadas, a cada lado de los umbrales
+// de todos los modelos de sensores
+pub const DARK_LUX: f32 = 5.;
+pub const BRIGHT_LUX: f32 = MAX_LUX_VALUE;
+pub const NEAR_M: f32 = 0.3;
+pub const FAR_M: f32 = DIST_MIN_M;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    // Se hace de noche
+    Dark,
+    // Se hace de dia
+    Bright,
+    // Alguien se acerca
+    Approach,
+    // Se va
+    Leave,
+    // Falla simulada; la lectura no cambia
+    Fault,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scene {
+    pub step: Step,
+    pub seconds: u16,
+}
+
+const fn scene(step: Step, seconds: u16) -> Scene {
+    Scene { step, seconds }
+}
+
+// Lectura que reemplaza a la de los sensores de todas las zonas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FakeReading {
+    pub lux: f32,
+    pub distance: f32,
+}
+
+impl FakeReading {
+    // De dia y sin nadie cerca
+    pub const START: Self = Self {
+        lux: BRIGHT_LUX,
+        distance: FAR_M,
+    };
+
+    pub fn apply(self, step: Step) -> Self {
+        match step {
+            Step::Dark => Self {
+                lux: DARK_LUX,
+                ..self
+            },
+            Step::Bright => Self {
+                lux: BRIGHT_LUX,
+                ..self
+            },
+            Step::Approach => Self {
+                distance: NEAR_M,
+                ..self
+            },
+            Step::Leave => Self {
+                distance: FAR_M,
+                ..self
+            },
+            Step::Fault => self,
+        }
+    }
+}
+
+pub struct Script {
+    pub name: &'static str,
+    pub scenes: &'static [Scene],
+}
+
+// Secuencias guardadas. Los tiempos dejan ver el encendido, el tiempo de
+// espera al irse la persona y el apagado
+pub const SCRIPTS: [Script; 2] = [
+    // Anochece, alguien pasa dos veces y amanece
+    Script {
+        name: "noche",
+        scenes: &[
+            scene(Step::Bright, 5),
+            scene(Step::Dark, 5),
+            scene(Step::Approach, 10),
+            scene(Step::Leave, 20),
+            scene(Step::Approach, 10),
+            scene(Step::Leave, 20),
+            scene(Step::Bright, 5),
+        ],
+    },
+    // Una falla con la lampara encendida
+    Script {
+        name: "falla",
+        scenes: &[
+            scene(Step::Dark, 5),
+            scene(Step::Approach, 10),
+            scene(Step::Fault, 10),
+            scene(Step::Leave, 20),
+        ],
+    },
+];
+
+impl Script {
+    pub fn find(name: &str) -> Option<&'static Self> {
+        SCRIPTS.iter().find(|script| script.name == name)
+    }
+}
+
+// Recorre una secuencia llevando la lectura inventada
+pub struct Demo {
+    scenes: &'static [Scene],
+    next: usize,
+    reading: FakeReading,
+}
+
+impl Demo {
+    pub const fn new(script: &'static Script) -> Self {
+        Self {
+            scenes: script.scenes,
+            next: 0,
+            reading: FakeReading::START,
+        }
+    }
+
+    // Pasa a la siguiente escena; None al terminar la secuencia
+    pub fn advance(&mut self) -> Option<Scene> {
+        let scene = *self.scenes.get(self.next)?;
+        self.next += 1;
+        self.reading = self.reading.apply(scene.step);
+        Some(scene)
+    }
+
+    pub fn reading(&self) -> FakeReading {
+        self.reading
+    }
+}
@@ -0,0 +1,55 @@
+// Universo DMX512: un codigo de inicio y 512 canales de un byte. Los
+// dimmers de escenario y arquitectonicos toman el nivel de su canal
+
+pub const SLOTS: usize = 512;
+// Codigo de inicio mas los canales
+pub const FRAME: usize = SLOTS + 1;
+// Codigo de inicio de los datos de dimmer
+pub const START_CODE: u8 = 0;
+
+// Nivel de un canal para un ciclo de trabajo de 0 a 1
+pub fn level(duty: f32) -> u8 {
+    (duty.clamp(0., 1.) * 255. + 0.5) as u8
+}
+
+#[derive(Clone, Debug)]
+pub struct Universe {
+    frame: [u8; FRAME],
+}
+
+impl Default for Universe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Universe {
+    pub const fn new() -> Self {
+        let mut frame = [0; FRAME];
+        frame[0] = START_CODE;
+        Self { frame }
+    }
+
+    // Los canales van de 1 a 512; false si `channel` no existe
+    pub fn set(&mut self, channel: u16, level: u8) -> bool {
+        match self.frame.get_mut(channel as usize) {
+            Some(slot) if channel > 0 => {
+                *slot = level;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get(&self, channel: u16) -> Option<u8> {
+        if channel == 0 {
+            return None;
+        }
+        self.frame.get(channel as usize).copied()
+    }
+
+    // Lo que se transmite despues del break
+    pub fn frame(&self) -> &[u8; FRAME] {
+        &self.frame
+    }
+}
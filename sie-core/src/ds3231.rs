@@ -0,0 +1,57 @@
+// Registros del RTC externo DS3231 (I2C). La hora se guarda en BCD
+
+use crate::schedule::TimeOfDay;
+
+pub const ADDRESS: u8 = 0x68;
+
+// Segundos, minutos y horas, consecutivos a partir de este registro
+pub const REG_TIME: u8 = 0x00;
+pub const REG_STATUS: u8 = 0x0F;
+
+// Bandera de oscilador detenido: la hora no es valida
+pub const STATUS_OSF: u8 = 0x80;
+
+// Bits del registro de horas
+const HOURS_12H: u8 = 0x40;
+const HOURS_PM: u8 = 0x20;
+
+// Hora de los registros de tiempo, o None si no son BCD valido
+pub fn decode_time(regs: [u8; 3]) -> Option<TimeOfDay> {
+    let seconds = bcd(regs[0] & 0x7F, 60)?;
+    let minutes = bcd(regs[1] & 0x7F, 60)?;
+    let hours = if regs[2] & HOURS_12H != 0 {
+        // 12 a 11 en formato de 12 horas
+        let hour = bcd(regs[2] & 0x1F, 13).filter(|&h| h > 0)? % 12;
+        if regs[2] & HOURS_PM != 0 {
+            hour + 12
+        } else {
+            hour
+        }
+    } else {
+        bcd(regs[2] & 0x3F, 24)?
+    };
+
+    Some(TimeOfDay::from_seconds(
+        hours * 3600 + minutes * 60 + seconds,
+    ))
+}
+
+// Registros de tiempo para una hora, en formato de 24 horas
+pub fn encode_time(time: TimeOfDay) -> [u8; 3] {
+    let seconds = time.seconds() % 60;
+    [
+        to_bcd(seconds),
+        to_bcd(time.minutes()),
+        to_bcd(time.hours()),
+    ]
+}
+
+fn bcd(value: u8, max: u32) -> Option<u32> {
+    let (tens, units) = ((value >> 4) as u32, (value & 0x0F) as u32);
+    let decoded = tens * 10 + units;
+    (units < 10 && decoded < max).then_some(decoded)
+}
+
+fn to_bcd(value: u32) -> u8 {
+    (((value / 10) << 4) | (value % 10)) as u8
+}
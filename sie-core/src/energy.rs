@@ -0,0 +1,68 @@
+// Energia del circuito de iluminacion. Un medidor con salida S0 da un
+// pulso por cada fraccion fija de kWh; el firmware ademas estima la
+// energia a partir del tiempo encendida, la potencia nominal de las
+// lamparas y su ciclo de trabajo. Comparar ambas descubre lamparas
+// fundidas, drivers que consumen de mas o cargas ajenas en el circuito
+
+// Wh de `pulses` pulsos para un medidor de `pulses_per_kwh` (la
+// constante impresa en el frente, por ejemplo 1000 imp/kWh)
+pub fn metered_wh(pulses: u32, pulses_per_kwh: u32) -> f32 {
+    if pulses_per_kwh == 0 {
+        return 0.;
+    }
+    pulses as f32 * 1000. / pulses_per_kwh as f32
+}
+
+// Energia estimada a partir de la potencia de las lamparas
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Estimate {
+    wh: f32,
+}
+
+impl Estimate {
+    pub const fn new() -> Self {
+        Self { wh: 0. }
+    }
+
+    // Suma `elapsed_ms` a una potencia de `watts`
+    pub fn add(&mut self, watts: f32, elapsed_ms: u64) {
+        self.wh += watts.max(0.) * elapsed_ms as f32 / 3_600_000.;
+    }
+
+    pub fn wh(&self) -> f32 {
+        self.wh
+    }
+}
+
+// Resultado de comparar la energia medida con la estimada
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    // Todavia no hay energia suficiente para comparar
+    Pending,
+    Consistent,
+    // El medidor registra mas de lo esperado: cargas ajenas o drivers
+    // ineficientes
+    Over,
+    // El medidor registra menos: lamparas fundidas o desconectadas
+    Under,
+}
+
+// Energia minima para comparar y diferencia aceptada con las que el
+// firmware concilia el medidor
+pub const MIN_WH: f32 = 50.;
+pub const TOLERANCE: f32 = 0.25;
+
+// Compara cuando alguna de las dos supera `min_wh`; `tolerance` es la
+// diferencia relativa aceptada (0.2 = 20 %)
+pub fn reconcile(metered_wh: f32, estimated_wh: f32, min_wh: f32, tolerance: f32) -> Verdict {
+    if metered_wh.max(estimated_wh) < min_wh {
+        return Verdict::Pending;
+    }
+    if metered_wh > estimated_wh * (1. + tolerance) {
+        Verdict::Over
+    } else if metered_wh < estimated_wh * (1. - tolerance) {
+        Verdict::Under
+    } else {
+        Verdict::Consistent
+    }
+}
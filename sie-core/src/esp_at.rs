@@ -0,0 +1,64 @@
+// Partes del protocolo de comandos AT del ESP8266/ESP32 (firmware ESP-AT
+// con MQTT) que no dependen del hardware: escape de parametros,
+// respuestas y comandos remotos
+
+// Resultado final de un comando
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Response {
+    Ok,
+    Error,
+}
+
+impl Response {
+    // None si la linea no termina un comando (eco, datos, avisos)
+    pub fn parse(line: &str) -> Option<Self> {
+        match line.trim_end() {
+            // Las publicaciones con `AT+MQTTPUBRAW` terminan con su propio aviso
+            "OK" | "+MQTTPUB:OK" => Some(Self::Ok),
+            "ERROR" | "FAIL" | "+MQTTPUB:FAIL" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+// Mensaje recibido en un tema suscrito:
+// `+MQTTSUBRECV:<enlace>,"<tema>",<longitud>,<datos>`
+pub fn parse_message(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix("+MQTTSUBRECV:")?;
+    let (_link, rest) = rest.split_once(',')?;
+    let rest = rest.strip_prefix('"')?;
+    let (topic, rest) = rest.split_once("\",")?;
+    let (len, data) = rest.split_once(',')?;
+    let len: usize = len.parse().ok()?;
+    Some((topic, data.get(..len)?))
+}
+
+// Ordenes aceptadas en el tema de comandos
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemoteCommand {
+    // Encender o apagar las lamparas (pasa a modo manual)
+    On,
+    Off,
+    // Volver al modo automatico
+    Auto,
+}
+
+impl RemoteCommand {
+    pub fn parse(payload: &str) -> Option<Self> {
+        match payload.trim() {
+            "on" | "encender" => Some(Self::On),
+            "off" | "apagar" => Some(Self::Off),
+            "auto" => Some(Self::Auto),
+            _ => None,
+        }
+    }
+}
+
+// Bytes de un parametro entre comillas: las comillas, comas y diagonales
+// invertidas se escapan con `\`
+pub fn escaped(text: &str) -> impl Iterator<Item = u8> + '_ {
+    text.bytes().flat_map(|byte| {
+        let escape = matches!(byte, b'"' | b',' | b'\\');
+        [b'\\', byte].into_iter().skip(if escape { 0 } else { 1 })
+    })
+}
@@ -0,0 +1,338 @@
+// Registro persistente de sucesos: la lampara que se enciende o se apaga y
+// por que, los cambios de modo, los botones, las fallas y los arranques.
+// Va en dos paginas de flash que se usan por turnos: al llenarse la activa
+// se borra la otra y se sigue en ella, asi que siempre queda al menos una
+// pagina de historia. Cada registro lleva la hora del dia si el reloj de
+// tiempo real esta ajustado, o los segundos desde el arranque; el registro
+// de arranque separa las ejecuciones. Un corte durante una escritura deja
+// un registro invalido que se salta al leer
+
+use crate::{
+    button::Press,
+    codes::Code,
+    kv::{ERASED, Pages},
+    schedule::TimeOfDay,
+};
+
+// Encabezado de pagina: generacion (4) y marca (4). La marca va al final
+// para que un encabezado cortado no sea valido. La pagina activa es la de
+// mayor generacion y la anterior tiene la generacion previa
+const MAGIC: [u8; 4] = *b"SIEV";
+const HEADER_SIZE: usize = 8;
+
+// Registro: marca de tiempo (4), tipo (1) y tres bytes de datos. La marca
+// es la hora del dia en segundos con el bit alto en 1, o los segundos
+// desde el arranque
+pub const RECORD_SIZE: usize = 8;
+const WALL_TIME: u32 = 1 << 31;
+
+const BOOT: u8 = 0;
+const LIGHT: u8 = 1;
+const MODE: u8 = 2;
+const BUTTON: u8 = 3;
+const FAULT: u8 = 4;
+
+// Motivo de un encendido o un apagado
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Reason {
+    // Modo automatico: alguien cerca con poca luz
+    Presence = 0,
+    // Modo automatico: las reglas piden luz sin presencia
+    Rules = 1,
+    // Modo automatico: paso la espera sin presencia
+    Vacant = 2,
+    // Modo automatico: hay luz suficiente
+    Daylight = 3,
+    // Encendida demasiado tiempo
+    TimeLimit = 4,
+    // Fuera del horario de operacion
+    Schedule = 5,
+    Button = 6,
+    Console = 7,
+    // MQTT o el esclavo I2C
+    Remote = 8,
+    // Se deshabilito el sistema
+    Disabled = 9,
+    // Bateria baja del nodo solar
+    Battery = 10,
+    // Sin lecturas del nodo de sensores
+    LinkLost = 11,
+    // El controlador de la zona se trabo y la lampara queda encendida
+    Watchdog = 12,
+}
+
+impl Reason {
+    pub const ALL: [Self; 13] = [
+        Self::Presence,
+        Self::Rules,
+        Self::Vacant,
+        Self::Daylight,
+        Self::TimeLimit,
+        Self::Schedule,
+        Self::Button,
+        Self::Console,
+        Self::Remote,
+        Self::Disabled,
+        Self::Battery,
+        Self::LinkLost,
+        Self::Watchdog,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Presence => "presencia",
+            Self::Rules => "reglas",
+            Self::Vacant => "sin presencia",
+            Self::Daylight => "hay luz",
+            Self::TimeLimit => "limite de tiempo",
+            Self::Schedule => "horario",
+            Self::Button => "boton",
+            Self::Console => "consola",
+            Self::Remote => "remoto",
+            Self::Disabled => "deshabilitado",
+            Self::Battery => "bateria",
+            Self::LinkLost => "enlace perdido",
+            Self::Watchdog => "watchdog",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stamp {
+    // Segundos desde el arranque
+    Uptime(u32),
+    Wall(TimeOfDay),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entry {
+    Boot,
+    Light { zone: u8, on: bool, reason: Reason },
+    Mode { manual: bool, enabled: bool },
+    // El numero del boton lo asigna el firmware
+    Button { button: u8, press: Press },
+    Fault(Code),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub stamp: Stamp,
+    pub entry: Entry,
+}
+
+const PRESSES: [Press; 4] = [Press::Single, Press::Double, Press::Triple, Press::Long];
+
+impl Record {
+    pub fn encode(&self) -> [u8; RECORD_SIZE] {
+        let stamp = match self.stamp {
+            Stamp::Uptime(seconds) => seconds & !WALL_TIME,
+            Stamp::Wall(time) => time.seconds() | WALL_TIME,
+        };
+        let (kind, data) = match self.entry {
+            Entry::Boot => (BOOT, [0; 3]),
+            Entry::Light { zone, on, reason } => (LIGHT, [zone, on as u8, reason as u8]),
+            Entry::Mode { manual, enabled } => (MODE, [manual as u8, enabled as u8, 0]),
+            Entry::Button { button, press } => {
+                let press = PRESSES.iter().position(|&p| p == press).unwrap_or(0);
+                (BUTTON, [button, press as u8, 0])
+            }
+            Entry::Fault(code) => {
+                let [low, high] = code.number().to_le_bytes();
+                (FAULT, [low, high, 0])
+            }
+        };
+        let mut out = [0; RECORD_SIZE];
+        out[..4].copy_from_slice(&stamp.to_le_bytes());
+        out[4] = kind;
+        out[5..].copy_from_slice(&data);
+        out
+    }
+
+    // None si los bytes no son un registro (por ejemplo, uno cortado)
+    pub fn decode(data: &[u8; RECORD_SIZE]) -> Option<Self> {
+        let stamp = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let stamp = match stamp & WALL_TIME {
+            0 => Stamp::Uptime(stamp),
+            _ => {
+                let seconds = stamp & !WALL_TIME;
+                if seconds >= 24 * 60 * 60 {
+                    return None;
+                }
+                Stamp::Wall(TimeOfDay::from_seconds(seconds))
+            }
+        };
+        let flag = |byte: u8| match byte {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        };
+        let entry = match data[4] {
+            BOOT => Entry::Boot,
+            LIGHT => Entry::Light {
+                zone: data[5],
+                on: flag(data[6])?,
+                reason: Reason::from_u8(data[7])?,
+            },
+            MODE => Entry::Mode {
+                manual: flag(data[5])?,
+                enabled: flag(data[6])?,
+            },
+            BUTTON => Entry::Button {
+                button: data[5],
+                press: *PRESSES.get(data[6] as usize)?,
+            },
+            FAULT => Entry::Fault(Code::from_number(u16::from_le_bytes([data[5], data[6]]))?),
+            _ => return None,
+        };
+        Some(Self { stamp, entry })
+    }
+}
+
+// Posicion de la lectura: pagina (0 la anterior, 1 la activa), registro y
+// fin de los registros de la pagina
+#[derive(Default)]
+pub struct Cursor {
+    page: usize,
+    offset: usize,
+    end: Option<usize>,
+}
+
+pub struct EventLog<P> {
+    pages: P,
+    page_size: usize,
+    active: usize,
+    generation: u32,
+    // Donde va el siguiente registro de la pagina activa
+    next: usize,
+}
+
+impl<P: Pages> EventLog<P> {
+    // Abre el registro; si ninguna pagina tiene encabezado (la primera vez)
+    // se prepara la primera
+    pub fn open(pages: P, page_size: usize) -> Self {
+        let mut log = Self {
+            pages,
+            page_size,
+            active: 0,
+            generation: 0,
+            next: page_size,
+        };
+
+        match [0, 1].map(|page| log.header(page)) {
+            [Some(a), Some(b)] if b > a => (log.active, log.generation) = (1, b),
+            [Some(a), _] => log.generation = a,
+            [None, Some(b)] => (log.active, log.generation) = (1, b),
+            [None, None] => {
+                if log.pages.erase(0) && log.write_header(0, 0) {
+                    log.next = HEADER_SIZE;
+                }
+                return log;
+            }
+        }
+        log.next = log.end(log.active);
+        log
+    }
+
+    // Agrega un registro; con la pagina activa llena borra la anterior y
+    // sigue en ella
+    pub fn append(&mut self, record: &Record) -> bool {
+        if self.next + RECORD_SIZE > self.page_size {
+            let target = 1 - self.active;
+            let generation = self.generation.wrapping_add(1);
+            if !self.pages.erase(target) || !self.write_header(target, generation) {
+                return false;
+            }
+            (self.active, self.generation, self.next) = (target, generation, HEADER_SIZE);
+        }
+        let offset = self.next;
+        // Aunque falle, el espacio queda usado
+        self.next += RECORD_SIZE;
+        self.pages.write(self.active, offset, &record.encode())
+    }
+
+    // Borra las dos paginas y empieza de nuevo en la activa
+    pub fn clear(&mut self) -> bool {
+        let generation = self.generation.wrapping_add(1);
+        let cleared = self.pages.erase(1 - self.active)
+            && self.pages.erase(self.active)
+            && self.write_header(self.active, generation);
+        if cleared {
+            (self.generation, self.next) = (generation, HEADER_SIZE);
+        }
+        cleared
+    }
+
+    // Siguiente registro valido, del mas antiguo al mas reciente
+    pub fn next(&mut self, cursor: &mut Cursor) -> Option<Record> {
+        while cursor.page < 2 {
+            let page = match cursor.page {
+                0 => 1 - self.active,
+                _ => self.active,
+            };
+            let end = match cursor.end {
+                Some(end) => end,
+                None => {
+                    let previous = self.header(page) == Some(self.generation.wrapping_sub(1));
+                    let end = match cursor.page {
+                        0 if previous => self.end(page),
+                        0 => HEADER_SIZE,
+                        _ => self.next.min(self.page_size),
+                    };
+                    *cursor.end.insert(end)
+                }
+            };
+            let offset = cursor.offset.max(HEADER_SIZE);
+            if offset + RECORD_SIZE > end {
+                *cursor = Cursor {
+                    page: cursor.page + 1,
+                    ..Cursor::default()
+                };
+                continue;
+            }
+            cursor.offset = offset + RECORD_SIZE;
+            let mut data = [ERASED; RECORD_SIZE];
+            if self.pages.read(page, offset, &mut data)
+                && let Some(record) = Record::decode(&data)
+            {
+                return Some(record);
+            }
+        }
+        None
+    }
+
+    fn header(&mut self, page: usize) -> Option<u32> {
+        let mut header = [0; HEADER_SIZE];
+        if !self.pages.read(page, 0, &mut header) || header[4..] != MAGIC {
+            return None;
+        }
+        Some(u32::from_le_bytes([
+            header[0], header[1], header[2], header[3],
+        ]))
+    }
+
+    fn write_header(&mut self, page: usize, generation: u32) -> bool {
+        let mut header = [0; HEADER_SIZE];
+        header[..4].copy_from_slice(&generation.to_le_bytes());
+        header[4..].copy_from_slice(&MAGIC);
+        self.pages.write(page, 0, &header)
+    }
+
+    // Primer lugar borrado de la pagina; un registro cortado cuenta como
+    // usado
+    fn end(&mut self, page: usize) -> usize {
+        let mut offset = HEADER_SIZE;
+        while offset + RECORD_SIZE <= self.page_size {
+            let mut data = [0; RECORD_SIZE];
+            if !self.pages.read(page, offset, &mut data) || data == [ERASED; RECORD_SIZE] {
+                break;
+            }
+            offset += RECORD_SIZE;
+        }
+        offset
+    }
+}
@@ -0,0 +1,47 @@
+// Rampa lineal de brillo para que la lampara no cambie de golpe.
+// Los niveles van de 0 a 100 %
+pub struct Fade {
+    current: f32,
+    target: f32,
+    // Tiempo para recorrer la escala completa (0 a 100 %); con cero los
+    // cambios son inmediatos
+    full_scale_ms: u32,
+}
+
+impl Fade {
+    pub const fn new(full_scale_ms: u32) -> Self {
+        Self {
+            current: 0.,
+            target: 0.,
+            full_scale_ms,
+        }
+    }
+
+    pub fn target(&self) -> u8 {
+        self.target as u8
+    }
+
+    pub fn set_target(&mut self, target: u8) {
+        self.target = target.min(100) as f32;
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.current == self.target
+    }
+
+    // Avanza la rampa y devuelve el nivel actual
+    pub fn advance(&mut self, elapsed_ms: u32) -> f32 {
+        if self.full_scale_ms == 0 {
+            self.current = self.target;
+            return self.current;
+        }
+
+        let step = 100. * elapsed_ms as f32 / self.full_scale_ms as f32;
+        self.current = if self.current < self.target {
+            (self.current + step).min(self.target)
+        } else {
+            (self.current - step).max(self.target)
+        };
+        self.current
+    }
+}
@@ -0,0 +1,84 @@
+use crate::framebuffer::Framebuffer;
+
+// Letra de 5x7 pixeles para el OLED: cada glifo son 5 columnas con el bit
+// 0 arriba, como el framebuffer. Solo digitos, mayusculas (las minusculas
+// se dibujan en mayuscula) y algunos signos, para no gastar flash
+
+pub const GLYPH_WIDTH: usize = 5;
+// Con un pixel de separacion caben 21 caracteres por linea
+pub const ADVANCE: usize = GLYPH_WIDTH + 1;
+// Una linea por pagina del SSD1306
+pub const LINE_HEIGHT: usize = 8;
+
+const DIGITS: [[u8; GLYPH_WIDTH]; 10] = [
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1E],
+];
+
+const LETTERS: [[u8; GLYPH_WIDTH]; 26] = [
+    [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x49, 0x49, 0x7A],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x3F, 0x40, 0x38, 0x40, 0x3F],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+];
+
+// Columnas de un caracter; los que no estan en la letra salen como '?'
+pub fn glyph(c: u8) -> [u8; GLYPH_WIDTH] {
+    match c.to_ascii_uppercase() {
+        b'0'..=b'9' => DIGITS[(c - b'0') as usize],
+        c @ b'A'..=b'Z' => LETTERS[(c - b'A') as usize],
+        b' ' => [0; GLYPH_WIDTH],
+        b'.' => [0x00, 0x60, 0x60, 0x00, 0x00],
+        b':' => [0x00, 0x36, 0x36, 0x00, 0x00],
+        b'-' => [0x08, 0x08, 0x08, 0x08, 0x08],
+        b'%' => [0x23, 0x13, 0x08, 0x64, 0x62],
+        _ => [0x02, 0x01, 0x51, 0x09, 0x06],
+    }
+}
+
+// Dibuja `text` con la esquina superior izquierda en (x, y) y devuelve la
+// columna donde seguiria el texto. Lo que no cabe se recorta
+pub fn draw_text(fb: &mut Framebuffer, x: usize, y: usize, text: &[u8]) -> usize {
+    let mut x = x;
+    for &c in text {
+        for (dx, column) in glyph(c).into_iter().enumerate() {
+            for dy in 0..LINE_HEIGHT - 1 {
+                if column & 1 << dy != 0 {
+                    fb.set(x + dx, y + dy, true);
+                }
+            }
+        }
+        x += ADVANCE;
+    }
+    x
+}
@@ -0,0 +1,75 @@
+// Imagen monocromatica de 128x64 con la organizacion de memoria del
+// SSD1306: cada byte es una columna de 8 pixeles de una pagina (bit 0
+// arriba), asi que el buffer se envia al display tal cual
+
+pub const WIDTH: usize = 128;
+pub const HEIGHT: usize = 64;
+pub const BUFFER: usize = WIDTH * HEIGHT / 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    pixels: [u8; BUFFER],
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Framebuffer {
+    pub const fn new() -> Self {
+        Self {
+            pixels: [0; BUFFER],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels = [0; BUFFER];
+    }
+
+    // Los pixeles fuera de la pantalla se ignoran
+    pub fn set(&mut self, x: usize, y: usize, on: bool) {
+        if x >= WIDTH || y >= HEIGHT {
+            return;
+        }
+        let byte = &mut self.pixels[y / 8 * WIDTH + x];
+        let bit = 1 << (y % 8);
+        if on {
+            *byte |= bit;
+        } else {
+            *byte &= !bit;
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < WIDTH && y < HEIGHT && self.pixels[y / 8 * WIDTH + x] & 1 << (y % 8) != 0
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        for px in x..x + width {
+            for py in y..y + height {
+                self.set(px, py, true);
+            }
+        }
+    }
+
+    // Contorno de un rectangulo
+    pub fn rect(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        for px in x..x + width {
+            self.set(px, y, true);
+            self.set(px, y + height - 1, true);
+        }
+        for py in y..y + height {
+            self.set(x, py, true);
+            self.set(x + width - 1, py, true);
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8; BUFFER] {
+        &self.pixels
+    }
+}
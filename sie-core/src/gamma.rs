@@ -0,0 +1,52 @@
+// Correccion perceptual del brillo. El ojo no percibe el ciclo de trabajo
+// de forma lineal: al 10 % de PWM la lampara ya parece a media intensidad.
+// Se usa la luminosidad CIE 1976 (L*) para convertir el brillo logico
+// (0 a 100 %) en la fraccion de ciclo de trabajo
+
+// Escala de la tabla: 1.0 de ciclo de trabajo
+const FULL_SCALE: f32 = u16::MAX as f32;
+
+// Fraccion de ciclo de trabajo para cada porcentaje de brillo, en
+// unidades de 1/65535
+pub const CIE_TABLE: [u16; 101] = cie_table();
+
+const fn cie_table() -> [u16; 101] {
+    let mut table = [0; 101];
+    let mut i = 0;
+    while i <= 100 {
+        table[i] = (cie_lightness_to_luminance(i as f32) * FULL_SCALE + 0.5) as u16;
+        i += 1;
+    }
+    table
+}
+
+// Inversa de L*: luminancia relativa (0 a 1) para una luminosidad de 0 a 100
+const fn cie_lightness_to_luminance(lightness: f32) -> f32 {
+    if lightness <= 8. {
+        lightness / 903.3
+    } else {
+        let t = (lightness + 16.) / 116.;
+        t * t * t
+    }
+}
+
+// Fraccion de ciclo de trabajo (0 a 1) para un brillo logico de 0 a 100 %.
+// Los niveles intermedios de una rampa se interpolan entre los de la tabla
+pub fn duty_fraction(level: f32) -> f32 {
+    let level = level.clamp(0., 100.);
+    let index = level as usize;
+    if index >= 100 {
+        return 1.;
+    }
+
+    let low = CIE_TABLE[index] as f32;
+    let high = CIE_TABLE[index + 1] as f32;
+    (low + (high - low) * (level - index as f32)) / FULL_SCALE
+}
+
+// Minimo de ciclo de trabajo de un driver: muchos parpadean por debajo de
+// cierto nivel, asi que la salida corregida se apaga en lugar de quedarse
+// debajo de `floor`
+pub fn apply_floor(duty: f32, floor: f32) -> f32 {
+    if duty < floor { 0. } else { duty }
+}
@@ -0,0 +1,53 @@
+// Trazas de referencia ("golden"): una traza de muestras crudas del ADC se
+// pasa por el controlador y su salida se compara contra la esperada, de
+// modo que cualquier cambio de comportamiento tenga que ser deliberado.
+// Los formatos estan en `replay`
+
+use std::{fmt::Write, string::String, vec::Vec};
+
+use crate::{
+    control::{Reading, Thresholds, decide},
+    replay::{self, OUTPUT_HEADER},
+};
+
+pub use crate::replay::Sample;
+
+pub const TRACE_SUFFIX: &str = ".trace.csv";
+pub const EXPECTED_SUFFIX: &str = ".expected.csv";
+
+// Lee las muestras de una traza. Devuelve el numero de linea y el contenido
+// de la primera linea invalida
+pub fn parse_trace(trace: &str) -> Result<Vec<Sample>, (usize, String)> {
+    replay::samples(trace)
+        .collect::<Result<_, _>>()
+        .map_err(|line| {
+            (
+                line,
+                String::from(trace.lines().nth(line - 1).unwrap_or("").trim()),
+            )
+        })
+}
+
+// Ejecuta el controlador sobre las muestras y genera la salida esperada
+pub fn run(samples: &[Sample], thresholds: &Thresholds) -> String {
+    let mut out = String::from(OUTPUT_HEADER);
+    out.push('\n');
+
+    for sample in samples {
+        let reading = Reading::from_raw(sample.raw_distance, sample.raw_lux);
+        let decision = decide(&reading, thresholds);
+
+        writeln!(
+            out,
+            "{},{:.3},{:.1},{},{}",
+            sample.t_ms,
+            reading.distance,
+            reading.lux,
+            decision.dark as u8,
+            decision.light_on as u8
+        )
+        .unwrap();
+    }
+
+    out
+}
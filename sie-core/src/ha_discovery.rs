@@ -0,0 +1,179 @@
+// Descubrimiento MQTT de Home Assistant: al conectarse el equipo publica
+// (retenida) la configuracion de cada entidad en
+// `homeassistant/<componente>/sie/<objeto>/config` y Home Assistant la
+// agrega sola, sin escribir YAML. Los mensajes se arman con fragmentos
+// estaticos para no necesitar un buffer; las zonas son de un digito
+
+pub const DISCOVERY_PREFIX: &str = "homeassistant";
+
+// Entidades publicadas
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entity {
+    // Lampara con brillo (`light`)
+    Lamp,
+    Lux,
+    Distance,
+    // Presencia detectada (`binary_sensor` de ocupacion)
+    Occupancy,
+    // Interruptor del modo manual, uno para todo el equipo
+    Manual,
+}
+
+impl Entity {
+    // Entidades de cada zona
+    pub const PER_ZONE: [Entity; 4] = [Self::Lamp, Self::Lux, Self::Distance, Self::Occupancy];
+
+    fn component(self) -> &'static [u8] {
+        match self {
+            Self::Lamp => b"light",
+            Self::Lux | Self::Distance => b"sensor",
+            Self::Occupancy => b"binary_sensor",
+            Self::Manual => b"switch",
+        }
+    }
+
+    // Nombre del tema de estado bajo `sie/zona/N/` (o `sie/` el modo manual)
+    pub fn object(self) -> &'static str {
+        match self {
+            Self::Lamp => "lampara",
+            Self::Lux => "luz",
+            Self::Distance => "distancia",
+            Self::Occupancy => "presencia",
+            Self::Manual => "manual",
+        }
+    }
+
+    fn name(self) -> &'static [u8] {
+        match self {
+            Self::Lamp => b"Lampara",
+            Self::Lux => b"Luz",
+            Self::Distance => b"Distancia",
+            Self::Occupancy => b"Presencia",
+            Self::Manual => b"Modo manual",
+        }
+    }
+
+    // Campos propios de cada componente. El brillo va en porcentaje y
+    // los estados binarios se publican como `1` o `0`
+    fn fields(self) -> &'static [u8] {
+        match self {
+            Self::Lamp => {
+                br#","cmd_t":"~/lampara/set","bri_stat_t":"~/brillo","bri_cmd_t":"~/brillo/set","bri_scl":100,"on_cmd_type":"brightness","pl_on":"1","pl_off":"0""#
+            }
+            Self::Lux => br#","unit_of_meas":"lx","dev_cla":"illuminance","stat_cla":"measurement""#,
+            Self::Distance => br#","unit_of_meas":"m","dev_cla":"distance","stat_cla":"measurement""#,
+            Self::Occupancy => br#","dev_cla":"occupancy","pl_on":"1","pl_off":"0""#,
+            Self::Manual => br#","cmd_t":"~/manual/set","pl_on":"1","pl_off":"0""#,
+        }
+    }
+
+    fn zoned(self) -> bool {
+        self != Self::Manual
+    }
+}
+
+// Fragmentos de un mensaje; los que no aplican quedan vacios
+pub type Parts = [&'static [u8]; 16];
+
+// Tema de configuracion de una entidad; `zone` se ignora en el modo manual
+pub fn config_topic(entity: Entity, zone: u8) -> Parts {
+    let (separator, zone) = zone_suffix(entity, zone);
+    let mut parts: Parts = [b""; 16];
+    for (part, piece) in parts.iter_mut().zip([
+        DISCOVERY_PREFIX.as_bytes(),
+        b"/",
+        entity.component(),
+        b"/sie/",
+        entity.object().as_bytes(),
+        separator,
+        zone,
+        b"/config",
+    ]) {
+        *part = piece;
+    }
+    parts
+}
+
+// Configuracion JSON de una entidad
+pub fn config(entity: Entity, zone: u8) -> Parts {
+    let (separator, suffix) = zone_suffix(entity, zone);
+    let (base, name_separator) = if entity.zoned() {
+        (&br#"{"~":"sie/zona/"#[..], &b" "[..])
+    } else {
+        (&br#"{"~":"sie"#[..], &b""[..])
+    };
+    [
+        base,
+        suffix,
+        br#"","name":""#,
+        entity.name(),
+        name_separator,
+        suffix,
+        br#"","uniq_id":"sie_"#,
+        entity.object().as_bytes(),
+        separator,
+        suffix,
+        br#"","stat_t":"~/"#,
+        entity.object().as_bytes(),
+        b"\"",
+        entity.fields(),
+        br#","dev":{"ids":["sie"],"name":"SIE","mdl":"EI_SIE"}}"#,
+        b"",
+    ]
+}
+
+// Longitud total de un mensaje en fragmentos
+pub fn length(parts: &Parts) -> usize {
+    parts.iter().map(|p| p.len()).sum()
+}
+
+fn zone_suffix(entity: Entity, zone: u8) -> (&'static [u8], &'static [u8]) {
+    const DIGITS: &[u8] = b"0123456789";
+    if entity.zoned() {
+        let zone = zone as usize % DIGITS.len();
+        (b"_", &DIGITS[zone..zone + 1])
+    } else {
+        (b"", b"")
+    }
+}
+
+// Temas de ordenes a suscribir
+pub const COMMAND_TOPICS: [&str; 2] = ["sie/zona/+/+/set", "sie/manual/set"];
+
+// Orden enviada desde Home Assistant
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Lamp { zone: u8, on: bool },
+    // Brillo en porcentaje
+    Brightness { zone: u8, percent: u8 },
+    Manual(bool),
+}
+
+impl Command {
+    pub fn parse(topic: &str, payload: &str) -> Option<Self> {
+        let payload = payload.trim();
+        let switch = || match payload {
+            "1" => Some(true),
+            "0" => Some(false),
+            _ => None,
+        };
+        if topic == "sie/manual/set" {
+            return switch().map(Self::Manual);
+        }
+
+        let rest = topic.strip_prefix("sie/zona/")?.strip_suffix("/set")?;
+        let (zone, object) = rest.split_once('/')?;
+        let zone = zone.parse().ok()?;
+        match object {
+            "lampara" => Some(Self::Lamp {
+                zone,
+                on: switch()?,
+            }),
+            "brillo" => Some(Self::Brightness {
+                zone,
+                percent: payload.parse::<u8>().ok()?.min(100),
+            }),
+            _ => None,
+        }
+    }
+}
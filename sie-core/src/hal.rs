@@ -0,0 +1,177 @@
+use core::{
+    future::{Future, poll_fn},
+    pin::pin,
+    task::Poll,
+};
+
+use embedded_hal::{digital::InputPin, pwm::SetDutyCycle};
+use embedded_hal_async::{delay::DelayNs, digital::Wait};
+
+use crate::{
+    button::{Gesture, Press, Timing},
+    clock::Clock,
+    control::{Reading, Thresholds},
+    gamma,
+    rules::RuleSet,
+    sensor::{LuxPolarity, Profile},
+    zone::ZoneLogic,
+};
+
+// Capa de portabilidad sobre los traits de embedded-hal y
+// embedded-hal-async: con un adaptador del ADC, una salida PWM y los pines
+// de los botones de cualquier placa (RP2040, ESP32-C3) corren la misma
+// conversion, decision y deteccion de gestos que en el STM32
+
+// Entrada analogica de 12 bits. embedded-hal 1.0 no define el ADC; cada
+// placa adapta el suyo
+pub trait AnalogInput {
+    fn read(&mut self) -> impl Future<Output = u16>;
+}
+
+// Lectura de los sensores de distancia y de luz de una zona, tomadas
+// seguidas para que correspondan al mismo instante
+pub trait Sensors {
+    fn sample(&mut self) -> impl Future<Output = (u16, u16)>;
+}
+
+impl<D: AnalogInput, L: AnalogInput> Sensors for (D, L) {
+    async fn sample(&mut self) -> (u16, u16) {
+        let distance = self.0.read().await;
+        let light = self.1.read().await;
+        (distance, light)
+    }
+}
+
+// Lampara en una salida PWM con la correccion perceptual del brillo
+pub struct Lamp<P: SetDutyCycle> {
+    pwm: P,
+    // Ciclo de trabajo minimo del driver (0 a 1)
+    floor: f32,
+}
+
+impl<P: SetDutyCycle> Lamp<P> {
+    pub fn new(pwm: P, floor: f32) -> Self {
+        Self { pwm, floor }
+    }
+
+    // Aplica un brillo logico de 0 a 100 %
+    pub fn set(&mut self, level: f32) -> Result<(), P::Error> {
+        let duty = gamma::apply_floor(gamma::duty_fraction(level), self.floor);
+        let max = self.pwm.max_duty_cycle();
+        self.pwm.set_duty_cycle((duty * max as f32 + 0.5) as u16)
+    }
+}
+
+// Boton con antirrebote y deteccion de gestos. Presionado equivale a nivel
+// alto (pull-down); un error del pin se toma como suelto
+pub struct Debounced<I, D, C: Clock> {
+    input: I,
+    delay: D,
+    clock: C,
+    gesture: Gesture<C>,
+}
+
+impl<I: InputPin + Wait, D: DelayNs, C: Clock + Clone> Debounced<I, D, C> {
+    pub fn new(input: I, delay: D, clock: C, timing: Timing) -> Self {
+        Self {
+            input,
+            delay,
+            gesture: Gesture::new(clock.clone(), timing),
+            clock,
+        }
+    }
+
+    // Espera un gesto completo. Los clics se agrupan mientras el siguiente
+    // empiece dentro de la ventana; una pulsacion larga se reporta en cuanto
+    // se cumple el tiempo, aunque el boton siga presionado
+    pub async fn wait_for_press(&mut self) -> Press {
+        loop {
+            let pressed = self.input.is_high().unwrap_or(false);
+            if let Some(press) = self.gesture.update(pressed) {
+                return press;
+            }
+
+            // Se espera el nivel contrario en lugar de un flanco para no
+            // perder un cambio ocurrido justo despues de leer el pin
+            let input = &mut self.input;
+            let change = async {
+                let _ = if pressed {
+                    input.wait_for_low().await
+                } else {
+                    input.wait_for_high().await
+                };
+            };
+
+            match self.gesture.next_deadline() {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_sub(self.clock.now_ms());
+                    first(change, self.delay.delay_ms(remaining as u32)).await;
+                }
+                None => change.await,
+            }
+        }
+    }
+}
+
+// Termina con el primero de los dos futuros
+async fn first(a: impl Future<Output = ()>, b: impl Future<Output = ()>) {
+    let mut a = pin!(a);
+    let mut b = pin!(b);
+    poll_fn(|cx| {
+        if a.as_mut().poll(cx).is_ready() || b.as_mut().poll(cx).is_ready() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+// Parametros de una zona para `control`
+pub struct ZoneConfig {
+    pub distance: Profile,
+    pub light: Profile,
+    pub light_polarity: LuxPolarity,
+    pub thresholds: Thresholds,
+    pub rules: RuleSet,
+    // Periodo de muestreo
+    pub period_ms: u32,
+}
+
+// Controlador minimo de una zona para otras placas: muestrea, decide y
+// aplica el brillo sin rampas, sin horario y sin reloj de tiempo real
+pub async fn control<S, P, D, C>(
+    sensors: &mut S,
+    lamp: &mut Lamp<P>,
+    logic: &mut ZoneLogic<C>,
+    config: &ZoneConfig,
+    delay: &mut D,
+) -> !
+where
+    S: Sensors,
+    P: SetDutyCycle,
+    D: DelayNs,
+    C: Clock + Clone,
+{
+    loop {
+        let (raw_distance, raw_lux) = sensors.sample().await;
+        let reading = Reading::from_raw_profiled(
+            raw_distance,
+            raw_lux,
+            &config.distance,
+            &config.light,
+            config.light_polarity,
+        );
+        let step = logic.step(
+            &reading,
+            &config.thresholds,
+            &config.rules,
+            None,
+            false,
+            None,
+        );
+        // Un error de la salida se reintenta en la siguiente muestra
+        let _ = lamp.set(step.brightness as f32);
+        delay.delay_ms(config.period_ms).await;
+    }
+}
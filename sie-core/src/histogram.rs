@@ -0,0 +1,67 @@
+use crate::{
+    clock::Clock,
+    sensor::{DIST_MAX_M, DIST_MIN_M},
+};
+
+// Intervalos de 0.5 m en el rango del sensor (1 a 5.5 m)
+pub const BINS: usize = 9;
+const BIN_WIDTH: f32 = (DIST_MIN_M - DIST_MAX_M) / BINS as f32;
+
+// La ventana se divide en tramos; al avanzar se descarta el mas viejo
+const SLOTS: usize = 6;
+
+// Histograma de las distancias medidas en una ventana reciente (por
+// ejemplo la ultima hora). Sirve al instalar el sensor: si casi todo cae
+// en el ultimo intervalo el sensor ve la pared del fondo y no el paso
+pub struct DistanceHistogram<C: Clock> {
+    clock: C,
+    slot_ms: u64,
+    // Tramo absoluto (tiempo / slot_ms) del ultimo registro
+    current: u64,
+    slots: [[u16; BINS]; SLOTS],
+}
+
+impl<C: Clock> DistanceHistogram<C> {
+    pub const fn new(clock: C, window_ms: u64) -> Self {
+        Self {
+            clock,
+            slot_ms: window_ms / SLOTS as u64,
+            current: 0,
+            slots: [[0; BINS]; SLOTS],
+        }
+    }
+
+    // Distancia donde empieza un intervalo
+    pub fn bin_start(bin: usize) -> f32 {
+        DIST_MAX_M + bin as f32 * BIN_WIDTH
+    }
+
+    pub fn record(&mut self, distance: f32) {
+        self.advance();
+        let bin = ((distance - DIST_MAX_M) / BIN_WIDTH).clamp(0., (BINS - 1) as f32) as usize;
+        let count = &mut self.slots[self.current as usize % SLOTS][bin];
+        *count = count.saturating_add(1);
+    }
+
+    // Conteos de cada intervalo dentro de la ventana
+    pub fn counts(&mut self) -> [u32; BINS] {
+        self.advance();
+        let mut counts = [0; BINS];
+        for slot in &self.slots {
+            for (total, &count) in counts.iter_mut().zip(slot) {
+                *total += count as u32;
+            }
+        }
+        counts
+    }
+
+    // Vacia los tramos que quedaron fuera de la ventana
+    fn advance(&mut self) {
+        let slot = self.clock.now_ms() / self.slot_ms.max(1);
+        let stale = (slot - self.current).min(SLOTS as u64);
+        for i in 1..=stale {
+            self.slots[(self.current + i) as usize % SLOTS] = [0; BINS];
+        }
+        self.current = slot;
+    }
+}
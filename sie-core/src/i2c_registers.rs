@@ -0,0 +1,110 @@
+// Mapa de registros del equipo como esclavo I2C, para que una computadora
+// (una Raspberry Pi, por ejemplo) lo consulte como a cualquier sensor: se
+// escribe el numero de registro y se leen o escriben los bytes siguientes,
+// con incremento automatico. Los valores de 16 bits van en little endian.
+//
+//   0x00     identificador (0x5E), solo lectura
+//   0x01     version del mapa
+//   0x02     banderas: bit 0 modo manual, bit 1 sistema habilitado
+//   0x03     numero de zonas
+//   0x04     codigo de la ultima falla (u16, 0 sin fallas; ver
+//            sie_core::codes)
+//   0x0F     orden (solo escritura): 1 automatico, 2 manual, 3 encender
+//            y 4 apagar las lamparas
+//   0x10 + 0x10 * zona:
+//     +0     brillo de la lampara (%)
+//     +1     banderas: bit 0 lampara encendida
+//     +2     luz en luxes (u16)
+//     +4     distancia en centimetros (u16)
+//     +6     umbral de luz en luxes (u16, lectura y escritura)
+//     +8     umbral de distancia en centimetros (u16, lectura y escritura)
+
+pub const WHO_AM_I: u8 = 0x5E;
+pub const VERSION: u8 = 2;
+pub const COMMAND: u8 = 0x0F;
+pub const MAX_ZONES: usize = 4;
+pub const MAP_SIZE: usize = ZONE_BASE + MAX_ZONES * ZONE_STRIDE;
+
+const ZONE_BASE: usize = 0x10;
+const ZONE_STRIDE: usize = 0x10;
+const LIGHT_THRESHOLD: usize = 6;
+const DISTANCE_THRESHOLD: usize = 8;
+
+// Estado de una zona tal como se expone
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ZoneRegisters {
+    pub brightness: u8,
+    pub lamp_on: bool,
+    pub lux: u16,
+    pub distance_cm: u16,
+    pub light_threshold: u16,
+    pub distance_threshold_cm: u16,
+}
+
+// Contenido de los registros de lectura
+pub fn image(manual: bool, enabled: bool, fault: u16, zones: &[ZoneRegisters]) -> [u8; MAP_SIZE] {
+    let mut map = [0; MAP_SIZE];
+    map[0] = WHO_AM_I;
+    map[1] = VERSION;
+    map[2] = manual as u8 | (enabled as u8) << 1;
+    map[3] = zones.len().min(MAX_ZONES) as u8;
+    map[4..6].copy_from_slice(&fault.to_le_bytes());
+    for (zone, registers) in zones.iter().take(MAX_ZONES).enumerate() {
+        let block = &mut map[ZONE_BASE + zone * ZONE_STRIDE..][..ZONE_STRIDE];
+        block[0] = registers.brightness;
+        block[1] = registers.lamp_on as u8;
+        for (offset, value) in [
+            (2, registers.lux),
+            (4, registers.distance_cm),
+            (LIGHT_THRESHOLD, registers.light_threshold),
+            (DISTANCE_THRESHOLD, registers.distance_threshold_cm),
+        ] {
+            block[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+    map
+}
+
+// Orden escrita en el registro `COMMAND`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Auto,
+    Manual,
+    LampsOn,
+    LampsOff,
+}
+
+// Escritura del anfitrion ya interpretada
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Write {
+    Command(Command),
+    LightThreshold { zone: usize, lux: u16 },
+    DistanceThreshold { zone: usize, cm: u16 },
+}
+
+// Interpreta una escritura de `data` a partir del registro `register`.
+// None si no es un registro escribible o faltan bytes
+pub fn decode_write(register: u8, data: &[u8]) -> Option<Write> {
+    let register = register as usize;
+    if register == COMMAND as usize {
+        return match data.first()? {
+            1 => Some(Write::Command(Command::Auto)),
+            2 => Some(Write::Command(Command::Manual)),
+            3 => Some(Write::Command(Command::LampsOn)),
+            4 => Some(Write::Command(Command::LampsOff)),
+            _ => None,
+        };
+    }
+
+    let offset = register.checked_sub(ZONE_BASE)?;
+    let zone = offset / ZONE_STRIDE;
+    if zone >= MAX_ZONES {
+        return None;
+    }
+    let value = u16::from_le_bytes([*data.first()?, *data.get(1)?]);
+    match offset % ZONE_STRIDE {
+        LIGHT_THRESHOLD => Some(Write::LightThreshold { zone, lux: value }),
+        DISTANCE_THRESHOLD => Some(Write::DistanceThreshold { zone, cm: value }),
+        _ => None,
+    }
+}
@@ -0,0 +1,293 @@
+// Almacen clave-valor sobre dos paginas de flash (emulacion de EEPROM).
+// Cada escritura agrega un registro al final de la pagina activa y el
+// valor vigente de una clave es su ultimo registro valido. Al llenarse la
+// pagina se copian los valores vigentes a la otra, que pasa a ser la
+// activa: las dos paginas se borran por turnos. Quitar una clave agrega un
+// registro de borrado, que la copia descarta. Un corte durante una
+// escritura deja un registro con CRC invalido que se ignora; un corte
+// durante la copia deja la otra pagina sin encabezado y la anterior sigue
+// activa
+
+use crate::counters::crc32;
+
+// Acceso a las dos paginas; las operaciones devuelven false si fallan.
+// Escribir solo puede pasar bits de 1 a 0, como en la flash
+pub trait Pages {
+    fn read(&mut self, page: usize, offset: usize, buf: &mut [u8]) -> bool;
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> bool;
+    fn erase(&mut self, page: usize) -> bool;
+}
+
+// Valor mas largo de una clave
+pub const MAX_VALUE: usize = 32;
+
+// Una flash borrada queda en 0xFF; por eso no es una clave valida
+pub const ERASED: u8 = 0xFF;
+
+// Encabezado de pagina: marca (4) y generacion (4). La pagina activa es la
+// de mayor generacion
+const MAGIC: [u8; 4] = *b"SIKV";
+const HEADER_SIZE: usize = 8;
+
+// Registro: clave (1), longitud (1), marca (1), relleno (1), el valor con
+// relleno hasta multiplo de 4 y el CRC-32 de la clave, la longitud y el
+// valor (4). Un registro de borrado lleva la marca, ningun valor y el CRC
+// negado, para que no se confunda con un valor vacio
+const RECORD_HEADER: usize = 4;
+const REMOVED: u8 = 1;
+const RECORD_MAX: usize = record_size(MAX_VALUE);
+
+const fn record_size(len: usize) -> usize {
+    RECORD_HEADER + len.next_multiple_of(4) + 4
+}
+
+// Registro valido leido de la flash
+struct Entry {
+    key: u8,
+    value: [u8; MAX_VALUE],
+    len: usize,
+    removed: bool,
+}
+
+pub struct Store<P> {
+    pages: P,
+    page_size: usize,
+    active: usize,
+    generation: u32,
+    // Donde va el siguiente registro de la pagina activa
+    next: usize,
+}
+
+impl<P: Pages> Store<P> {
+    // Abre el almacen; si ninguna pagina tiene encabezado (la primera vez)
+    // se prepara la primera
+    pub fn open(pages: P, page_size: usize) -> Self {
+        let mut store = Self {
+            pages,
+            page_size,
+            active: 0,
+            generation: 0,
+            next: page_size,
+        };
+
+        match [0, 1].map(|page| store.header(page)) {
+            [Some(a), Some(b)] if b > a => (store.active, store.generation) = (1, b),
+            [Some(a), _] => store.generation = a,
+            [None, Some(b)] => (store.active, store.generation) = (1, b),
+            [None, None] => {
+                if store.pages.erase(0) && store.write_header(0, 0) {
+                    store.next = HEADER_SIZE;
+                }
+                return store;
+            }
+        }
+        store.next = store.end();
+        store
+    }
+
+    // Copia el valor vigente de `key` en `buf` y devuelve su longitud
+    pub fn get(&mut self, key: u8, buf: &mut [u8]) -> Option<usize> {
+        let mut found = None;
+        let mut offset = HEADER_SIZE;
+        while let Some((size, record)) = self.record(self.active, offset) {
+            if let Some(entry) = record
+                && entry.key == key
+                && entry.len <= buf.len()
+            {
+                buf[..entry.len].copy_from_slice(&entry.value[..entry.len]);
+                found = (!entry.removed).then_some(entry.len);
+            }
+            offset += size;
+        }
+        found
+    }
+
+    // Guarda `value` en `key`; no escribe nada si ya tiene ese valor
+    pub fn set(&mut self, key: u8, value: &[u8]) -> bool {
+        if value.len() > MAX_VALUE {
+            return false;
+        }
+        self.put(key, Some(value))
+    }
+
+    // Quita `key`; no escribe nada si no tiene valor
+    pub fn remove(&mut self, key: u8) -> bool {
+        self.put(key, None)
+    }
+
+    // Escribe un valor, o un borrado con None
+    fn put(&mut self, key: u8, value: Option<&[u8]>) -> bool {
+        if key == ERASED {
+            return false;
+        }
+        let mut current = [0; MAX_VALUE];
+        if self.get(key, &mut current).map(|len| &current[..len]) == value {
+            return true;
+        }
+
+        let size = record_size(value.map_or(0, <[u8]>::len));
+        if self.next + size <= self.page_size {
+            let offset = self.next;
+            // Aunque falle, el espacio queda usado
+            self.next += size;
+            return self.write_record(self.active, offset, key, value);
+        }
+        self.compact(key, value)
+    }
+
+    // Copia los valores vigentes y el nuevo a la otra pagina y la activa
+    fn compact(&mut self, key: u8, value: Option<&[u8]>) -> bool {
+        let target = 1 - self.active;
+        if !self.pages.erase(target) {
+            return false;
+        }
+
+        let mut to = HEADER_SIZE;
+        let mut from = HEADER_SIZE;
+        while let Some((size, record)) = self.record(self.active, from) {
+            from += size;
+            let Some(old) = record else {
+                continue;
+            };
+            if old.removed || old.key == key || self.superseded(old.key, from) {
+                continue;
+            }
+            if !self.write_record(target, to, old.key, Some(&old.value[..old.len])) {
+                return false;
+            }
+            to += record_size(old.len);
+        }
+        // Un borrado no hace falta en la pagina nueva
+        if let Some(value) = value {
+            if to + record_size(value.len()) > self.page_size
+                || !self.write_record(target, to, key, Some(value))
+            {
+                return false;
+            }
+            to += record_size(value.len());
+        }
+
+        // El encabezado va al final: hasta entonces manda la pagina anterior
+        if !self.write_header(target, self.generation.wrapping_add(1)) {
+            return false;
+        }
+        self.active = target;
+        self.generation = self.generation.wrapping_add(1);
+        self.next = to;
+        true
+    }
+
+    // Hay un registro valido de `key` a partir de `offset`
+    fn superseded(&mut self, key: u8, mut offset: usize) -> bool {
+        while let Some((size, record)) = self.record(self.active, offset) {
+            if record.is_some_and(|entry| entry.key == key) {
+                return true;
+            }
+            offset += size;
+        }
+        false
+    }
+
+    // Fin de los registros de la pagina activa. Si despues hay datos que no
+    // son un registro el resto de la pagina ya no se usa
+    fn end(&mut self) -> usize {
+        let mut offset = HEADER_SIZE;
+        while let Some((size, _)) = self.record(self.active, offset) {
+            offset += size;
+        }
+        let mut key = [ERASED];
+        if offset < self.page_size
+            && (!self.pages.read(self.active, offset, &mut key) || key[0] != ERASED)
+        {
+            return self.page_size;
+        }
+        offset
+    }
+
+    // Registro en `offset`: su tamano y, si es valido, la clave y el valor.
+    // None al llegar al espacio libre o a datos que no son un registro
+    fn record(&mut self, page: usize, offset: usize) -> Option<(usize, Option<Entry>)> {
+        let mut data = [0; RECORD_MAX];
+        if offset + RECORD_HEADER > self.page_size
+            || !self.pages.read(page, offset, &mut data[..RECORD_HEADER])
+        {
+            return None;
+        }
+        let (key, len) = (data[0], data[1] as usize);
+        let size = record_size(len);
+        if key == ERASED || len > MAX_VALUE || offset + size > self.page_size {
+            return None;
+        }
+        if !self
+            .pages
+            .read(page, offset + RECORD_HEADER, &mut data[RECORD_HEADER..size])
+        {
+            return None;
+        }
+
+        let mut value = [0; MAX_VALUE];
+        value[..len].copy_from_slice(&data[RECORD_HEADER..RECORD_HEADER + len]);
+        let crc = u32::from_le_bytes([
+            data[size - 4],
+            data[size - 3],
+            data[size - 2],
+            data[size - 1],
+        ]);
+        let removed = data[2] == REMOVED;
+        let valid = match removed {
+            true => len == 0 && crc == !record_crc(key, &[]),
+            false => crc == record_crc(key, &value[..len]),
+        };
+        Some((
+            size,
+            valid.then_some(Entry {
+                key,
+                value,
+                len,
+                removed,
+            }),
+        ))
+    }
+
+    fn write_record(&mut self, page: usize, offset: usize, key: u8, value: Option<&[u8]>) -> bool {
+        let (bytes, crc) = match value {
+            Some(value) => (value, record_crc(key, value)),
+            None => (&[][..], !record_crc(key, &[])),
+        };
+        let size = record_size(bytes.len());
+        let mut data = [0; RECORD_MAX];
+        data[0] = key;
+        data[1] = bytes.len() as u8;
+        if value.is_none() {
+            data[2] = REMOVED;
+        }
+        data[RECORD_HEADER..RECORD_HEADER + bytes.len()].copy_from_slice(bytes);
+        data[size - 4..size].copy_from_slice(&crc.to_le_bytes());
+        self.pages.write(page, offset, &data[..size])
+    }
+
+    fn header(&mut self, page: usize) -> Option<u32> {
+        let mut header = [0; HEADER_SIZE];
+        if !self.pages.read(page, 0, &mut header) || header[..4] != MAGIC {
+            return None;
+        }
+        Some(u32::from_le_bytes([
+            header[4], header[5], header[6], header[7],
+        ]))
+    }
+
+    fn write_header(&mut self, page: usize, generation: u32) -> bool {
+        let mut header = [0; HEADER_SIZE];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4..].copy_from_slice(&generation.to_le_bytes());
+        self.pages.write(page, 0, &header)
+    }
+}
+
+fn record_crc(key: u8, value: &[u8]) -> u32 {
+    let mut data = [0; 2 + MAX_VALUE];
+    data[0] = key;
+    data[1] = value.len() as u8;
+    data[2..2 + value.len()].copy_from_slice(value);
+    crc32(&data[..2 + value.len()])
+}
@@ -0,0 +1,122 @@
+// Estadisticas de latencia entre la adquisicion de una muestra y la
+// aplicacion del cambio que provoco en un actuador
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatencySummary {
+    pub min_us: u32,
+    pub mean_us: u32,
+    pub max_us: u32,
+    pub count: u32,
+}
+
+// Acumula latencias durante una ventana; quien la usa decide cuando se
+// cierra la ventana con `take`
+pub struct LatencyWindow {
+    min_us: u32,
+    max_us: u32,
+    sum_us: u64,
+    count: u32,
+}
+
+impl LatencyWindow {
+    pub const fn new() -> Self {
+        Self {
+            min_us: u32::MAX,
+            max_us: 0,
+            sum_us: 0,
+            count: 0,
+        }
+    }
+
+    pub fn record(&mut self, latency_us: u32) {
+        self.min_us = self.min_us.min(latency_us);
+        self.max_us = self.max_us.max(latency_us);
+        self.sum_us += latency_us as u64;
+        self.count += 1;
+    }
+
+    // Cierra la ventana y devuelve sus estadisticas; None si no hubo cambios
+    pub fn take(&mut self) -> Option<LatencySummary> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let summary = LatencySummary {
+            min_us: self.min_us,
+            mean_us: (self.sum_us / self.count as u64) as u32,
+            max_us: self.max_us,
+            count: self.count,
+        };
+        *self = Self::new();
+        Some(summary)
+    }
+}
+
+impl Default for LatencyWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Duracion y periodo del bucle de control de una zona
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoopSummary {
+    // Desde la muestra hasta aplicar la decision
+    pub duration: LatencySummary,
+    // Entre muestras seguidas; None si no hubo dos seguidas
+    pub period: Option<LatencySummary>,
+}
+
+impl LoopSummary {
+    // Diferencia entre el periodo mas largo y el mas corto
+    pub fn jitter_us(&self) -> u32 {
+        self.period.map_or(0, |p| p.max_us - p.min_us)
+    }
+}
+
+// Acumula la duracion de cada vuelta y el periodo entre vueltas seguidas.
+// Cuando el bucle deja de muestrear (modo manual, fuera del horario) se
+// llama a `idle` para que la pausa no cuente como periodo
+pub struct LoopTiming {
+    duration: LatencyWindow,
+    period: LatencyWindow,
+    last_start_us: Option<u64>,
+}
+
+impl LoopTiming {
+    pub const fn new() -> Self {
+        Self {
+            duration: LatencyWindow::new(),
+            period: LatencyWindow::new(),
+            last_start_us: None,
+        }
+    }
+
+    pub fn record(&mut self, start_us: u64, end_us: u64) {
+        let clamp = |us: u64| us.min(u32::MAX as u64) as u32;
+        self.duration.record(clamp(end_us.saturating_sub(start_us)));
+        if let Some(last) = self.last_start_us {
+            self.period.record(clamp(start_us.saturating_sub(last)));
+        }
+        self.last_start_us = Some(start_us);
+    }
+
+    pub fn idle(&mut self) {
+        self.last_start_us = None;
+    }
+
+    // Cierra la ventana; None si no hubo vueltas. El periodo sigue contando
+    // desde la ultima vuelta
+    pub fn take(&mut self) -> Option<LoopSummary> {
+        let duration = self.duration.take()?;
+        Some(LoopSummary {
+            duration,
+            period: self.period.take(),
+        })
+    }
+}
+
+impl Default for LoopTiming {
+    fn default() -> Self {
+        Self::new()
+    }
+}
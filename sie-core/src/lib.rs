@@ -0,0 +1,60 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod ambient;
+pub mod analog_watchdog;
+pub mod background;
+pub mod battery;
+pub mod beep;
+pub mod button;
+pub mod can_frames;
+pub mod clock;
+pub mod codes;
+pub mod control;
+pub mod counters;
+pub mod csv_log;
+pub mod dashboard;
+pub mod demo;
+pub mod dmx;
+pub mod ds3231;
+pub mod energy;
+pub mod esp_at;
+pub mod event_log;
+pub mod fade;
+pub mod font;
+pub mod framebuffer;
+pub mod gamma;
+#[cfg(feature = "std")]
+pub mod golden;
+pub mod ha_discovery;
+#[cfg(feature = "hal")]
+pub mod hal;
+pub mod histogram;
+pub mod i2c_registers;
+pub mod kv;
+pub mod latency;
+pub mod lora_packets;
+pub mod mains;
+pub mod menu;
+pub mod nrf24_packets;
+pub mod occupancy;
+pub mod on_limit;
+pub mod recorder;
+pub mod regulator;
+pub mod replay;
+pub mod report;
+pub mod rules;
+pub mod schedule;
+pub mod screensaver;
+pub mod sensor;
+pub mod settings;
+pub mod seven_segment;
+pub mod sim;
+pub mod sparkline;
+pub mod stack;
+pub mod stats;
+pub mod status;
+pub mod supervisor;
+pub mod telemetry;
+pub mod trial;
+pub mod units;
+pub mod zone;
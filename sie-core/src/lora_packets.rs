@@ -0,0 +1,92 @@
+// Paquetes LoRa para instalaciones fuera del alcance del WiFi. El aire es
+// lento y el tiempo de transmision esta limitado, asi que van compactos:
+//
+//   subida: nodo, secuencia, banderas (bit 0 manual, bit 1 habilitado) y
+//           por zona brillo (%), luxes (u16) y distancia en cm (u16)
+//   bajada: nodo destino (o `BROADCAST_NODE`) y una orden con el formato de
+//           `can_frames::Command`
+//
+// Los valores de 16 bits van en little endian
+
+use crate::can_frames::{BROADCAST_NODE, Command};
+
+pub const MAX_ZONES: usize = 4;
+pub const UPLINK_MAX: usize = HEADER + MAX_ZONES * ZONE_SIZE;
+
+const HEADER: usize = 3;
+const ZONE_SIZE: usize = 5;
+// Oscilador de referencia del SX1276
+const CRYSTAL_HZ: u64 = 32_000_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Header {
+    pub node: u8,
+    pub sequence: u8,
+    pub manual: bool,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ZoneSample {
+    pub brightness: u8,
+    pub lux: u16,
+    pub distance_cm: u16,
+}
+
+pub fn encode_uplink(header: Header, zones: &[ZoneSample]) -> ([u8; UPLINK_MAX], usize) {
+    let mut packet = [0; UPLINK_MAX];
+    packet[0] = header.node;
+    packet[1] = header.sequence;
+    packet[2] = header.manual as u8 | (header.enabled as u8) << 1;
+    let zones = &zones[..zones.len().min(MAX_ZONES)];
+    for (chunk, zone) in packet[HEADER..].chunks_exact_mut(ZONE_SIZE).zip(zones) {
+        let [lux_lo, lux_hi] = zone.lux.to_le_bytes();
+        let [distance_lo, distance_hi] = zone.distance_cm.to_le_bytes();
+        chunk.copy_from_slice(&[zone.brightness, lux_lo, lux_hi, distance_lo, distance_hi]);
+    }
+    (packet, HEADER + zones.len() * ZONE_SIZE)
+}
+
+pub fn decode_uplink(data: &[u8]) -> Option<(Header, impl Iterator<Item = ZoneSample> + '_)> {
+    let zones = data.get(HEADER..)?;
+    if zones.len() % ZONE_SIZE != 0 || zones.len() / ZONE_SIZE > MAX_ZONES {
+        return None;
+    }
+    let header = Header {
+        node: data[0],
+        sequence: data[1],
+        manual: data[2] & 1 != 0,
+        enabled: data[2] & 2 != 0,
+    };
+    let zones = zones.chunks_exact(ZONE_SIZE).map(|chunk| ZoneSample {
+        brightness: chunk[0],
+        lux: u16::from_le_bytes([chunk[1], chunk[2]]),
+        distance_cm: u16::from_le_bytes([chunk[3], chunk[4]]),
+    });
+    Some((header, zones))
+}
+
+pub fn encode_downlink(node: u8, command: Command) -> ([u8; 4], usize) {
+    let (data, len) = command.encode();
+    let mut packet = [0; 4];
+    packet[0] = node;
+    packet[1..1 + len].copy_from_slice(&data[..len]);
+    (packet, 1 + len)
+}
+
+// La orden de un paquete de bajada si va dirigida a `node` o a todos
+pub fn decode_downlink(node: u8, data: &[u8]) -> Option<Command> {
+    let (&to, command) = data.split_first()?;
+    if to != node && to != BROADCAST_NODE {
+        return None;
+    }
+    Command::decode(command)
+}
+
+// Valor de los registros RegFrf (MSB primero) para una frecuencia
+// portadora: Frf = f * 2^19 / 32 MHz
+pub fn frequency_register(hz: u32) -> [u8; 3] {
+    let frf = ((hz as u64) << 19) / CRYSTAL_HZ;
+    let [_, msb, mid, lsb] = (frf as u32).to_be_bytes();
+    [msb, mid, lsb]
+}
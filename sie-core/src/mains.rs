@@ -0,0 +1,72 @@
+// Frecuencia de la red a partir de un detector de cruce por cero, que da
+// un pulso en cada semiciclo. Las desviaciones y los cortes se registran:
+// una fuente pobre o un generador suelen coincidir con reinicios que de
+// otro modo no tienen explicacion
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Condition {
+    Normal,
+    Low,
+    High,
+    // Sin cruces: corte o caida de la red
+    Dropout,
+}
+
+// Frecuencia en Hz de `crossings` cruces (dos por ciclo) en `elapsed_us`
+pub fn frequency(crossings: u32, elapsed_us: u64) -> f32 {
+    if elapsed_us == 0 {
+        return 0.;
+    }
+    crossings as f32 * 500_000. / elapsed_us as f32
+}
+
+// Vigila la frecuencia e informa solo los cambios de condicion
+#[derive(Clone, Copy, Debug)]
+pub struct MainsMonitor {
+    // 50 o 60 Hz
+    nominal: f32,
+    // Desviacion aceptada en Hz
+    tolerance: f32,
+    condition: Condition,
+}
+
+impl MainsMonitor {
+    pub const fn new(nominal: f32, tolerance: f32) -> Self {
+        Self {
+            nominal,
+            tolerance,
+            condition: Condition::Normal,
+        }
+    }
+
+    pub fn classify(&self, hz: f32) -> Condition {
+        if hz < self.nominal - self.tolerance {
+            Condition::Low
+        } else if hz > self.nominal + self.tolerance {
+            Condition::High
+        } else {
+            Condition::Normal
+        }
+    }
+
+    // Nueva medicion; la condicion si cambio respecto a la anterior
+    pub fn update(&mut self, hz: f32) -> Option<Condition> {
+        self.set(self.classify(hz))
+    }
+
+    // No llegaron cruces en el tiempo esperado
+    pub fn dropout(&mut self) -> Option<Condition> {
+        self.set(Condition::Dropout)
+    }
+
+    pub fn condition(&self) -> Condition {
+        self.condition
+    }
+
+    fn set(&mut self, condition: Condition) -> Option<Condition> {
+        (condition != self.condition).then(|| {
+            self.condition = condition;
+            condition
+        })
+    }
+}
@@ -0,0 +1,96 @@
+// Menu de una pantalla pequena manejado con los dos botones: uno pasa a la
+// siguiente opcion y el otro la elige. Las opciones son las acciones de los
+// gestos de los botones, que mientras el menu esta abierto no se atienden
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Item {
+    Manual,
+    System,
+    Teach,
+    ClosedLoop,
+    Report,
+    Exit,
+}
+
+impl Item {
+    pub const ALL: [Self; 6] = [
+        Self::Manual,
+        Self::System,
+        Self::Teach,
+        Self::ClosedLoop,
+        Self::Report,
+        Self::Exit,
+    ];
+
+    // Hasta 16 caracteres, para un LCD de 16x2
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Manual => "MODO MANUAL",
+            Self::System => "SISTEMA",
+            Self::Teach => "TOMAR UMBRAL",
+            Self::ClosedLoop => "LAZO CERRADO",
+            Self::Report => "RESUMEN",
+            Self::Exit => "SALIR",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    Next,
+    Choose,
+    // Cierra sin elegir
+    Close,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Menu {
+    // Opcion seleccionada; None con el menu cerrado
+    selected: Option<usize>,
+}
+
+impl Menu {
+    pub const fn new() -> Self {
+        Self { selected: None }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.selected.is_some()
+    }
+
+    // Abre en la primera opcion
+    pub fn open(&mut self) {
+        self.selected = Some(0);
+    }
+
+    pub fn close(&mut self) {
+        self.selected = None;
+    }
+
+    pub fn selected(&self) -> Option<Item> {
+        self.selected.map(|index| Item::ALL[index])
+    }
+
+    // Devuelve la opcion elegida. El menu sigue abierto para ver el
+    // cambio, salvo al elegir `Exit`; con el menu cerrado no hace nada
+    pub fn handle(&mut self, input: Input) -> Option<Item> {
+        let index = self.selected?;
+        match input {
+            Input::Next => {
+                self.selected = Some((index + 1) % Item::ALL.len());
+                None
+            }
+            Input::Choose => match Item::ALL[index] {
+                Item::Exit => {
+                    self.close();
+                    None
+                }
+                item => Some(item),
+            },
+            Input::Close => {
+                self.close();
+                None
+            }
+        }
+    }
+}
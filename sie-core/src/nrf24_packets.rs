@@ -0,0 +1,54 @@
+// Paquetes del enlace nRF24L01+ entre luminarias: un nodo solo con
+// sensores transmite las lecturas crudas del ADC de cada zona y un nodo
+// solo con la lampara (o un relevador) decide con ellas como si los
+// sensores fueran suyos. Cada paquete lleva una zona (8 bytes): nodo,
+// secuencia, zona, distancia cruda (u16), luz cruda (u16) y un byte libre.
+// El nRF24 ya agrega su propio CRC
+
+// Direccion compartida por los dos nodos y canal (2400 + canal MHz)
+pub const ADDRESS: [u8; 5] = *b"SIE01";
+pub const CHANNEL: u8 = 76;
+pub const PAYLOAD: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub node: u8,
+    // Cuenta los paquetes enviados, para detectar perdidas
+    pub sequence: u8,
+    pub zone: u8,
+    pub raw_distance: u16,
+    pub raw_light: u16,
+}
+
+impl Sample {
+    pub fn encode(&self) -> [u8; PAYLOAD] {
+        let [distance_lo, distance_hi] = self.raw_distance.to_le_bytes();
+        let [light_lo, light_hi] = self.raw_light.to_le_bytes();
+        [
+            self.node,
+            self.sequence,
+            self.zone,
+            distance_lo,
+            distance_hi,
+            light_lo,
+            light_hi,
+            0,
+        ]
+    }
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let data: &[u8; PAYLOAD] = data.try_into().ok()?;
+        Some(Self {
+            node: data[0],
+            sequence: data[1],
+            zone: data[2],
+            raw_distance: u16::from_le_bytes([data[3], data[4]]),
+            raw_light: u16::from_le_bytes([data[5], data[6]]),
+        })
+    }
+}
+
+// Paquetes perdidos entre dos secuencias consecutivas recibidas
+pub fn lost(previous: u8, current: u8) -> u8 {
+    current.wrapping_sub(previous).wrapping_sub(1)
+}
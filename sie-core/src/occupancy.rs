@@ -0,0 +1,152 @@
+use crate::{
+    clock::Clock,
+    schedule::{Schedule, TimeOfDay},
+};
+
+// Politica del tiempo que la luz sigue encendida despues de dejar de
+// detectar presencia
+pub trait TimeoutPolicy {
+    // Tiempo de espera; `time` es la hora del dia si hay reloj ajustado
+    fn hold_ms(&self, time: Option<TimeOfDay>) -> u64;
+
+    // Duracion de una deteccion completa, de que aparece alguien a que
+    // deja de detectarse. Las politicas que aprenden la usan
+    fn observe(&mut self, _presence_ms: u64) {}
+}
+
+// Tiempo de espera fijo
+#[derive(Clone, Copy, Debug)]
+pub struct FixedTimeout {
+    pub hold_ms: u64,
+}
+
+impl TimeoutPolicy for FixedTimeout {
+    fn hold_ms(&self, _time: Option<TimeOfDay>) -> u64 {
+        self.hold_ms
+    }
+}
+
+// Cuantas veces la duracion tipica de un paso se sigue esperando
+const ADAPTIVE_FACTOR: u64 = 2;
+// Peso de cada nueva duracion en el promedio (1/8)
+const ADAPTIVE_SHIFT: u32 = 3;
+
+// Aprende la duracion tipica de un paso por la zona (promedio movil
+// exponencial) y espera un multiplo de ella: en un pasillo basta poco
+// tiempo y en una sala donde la gente se queda hace falta mas
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveTimeout {
+    typical_ms: u64,
+    min_ms: u64,
+    max_ms: u64,
+}
+
+impl AdaptiveTimeout {
+    pub const fn new(initial_ms: u64, min_ms: u64, max_ms: u64) -> Self {
+        Self {
+            typical_ms: initial_ms / ADAPTIVE_FACTOR,
+            min_ms,
+            max_ms,
+        }
+    }
+}
+
+impl TimeoutPolicy for AdaptiveTimeout {
+    fn hold_ms(&self, _time: Option<TimeOfDay>) -> u64 {
+        (self.typical_ms * ADAPTIVE_FACTOR).clamp(self.min_ms, self.max_ms)
+    }
+
+    fn observe(&mut self, presence_ms: u64) {
+        // Se limita cada muestra para que una estancia muy larga no
+        // desplace el promedio de golpe
+        let sample = presence_ms.min(self.max_ms);
+        self.typical_ms =
+            self.typical_ms - (self.typical_ms >> ADAPTIVE_SHIFT) + (sample >> ADAPTIVE_SHIFT);
+    }
+}
+
+// Tiempo de espera segun el horario: uno dentro y otro fuera de el. Sin
+// hora ajustada se usa el de dentro
+#[derive(Clone, Copy, Debug)]
+pub struct ScheduledTimeout {
+    pub schedule: Schedule,
+    pub active_ms: u64,
+    pub inactive_ms: u64,
+}
+
+impl TimeoutPolicy for ScheduledTimeout {
+    fn hold_ms(&self, time: Option<TimeOfDay>) -> u64 {
+        match time {
+            Some(time) if !self.schedule.is_active(time) => self.inactive_ms,
+            _ => self.active_ms,
+        }
+    }
+}
+
+// Cualquiera de las politicas anteriores, para elegirla por zona en la
+// configuracion
+#[derive(Clone, Copy, Debug)]
+pub enum Timeout {
+    Fixed(FixedTimeout),
+    Adaptive(AdaptiveTimeout),
+    Scheduled(ScheduledTimeout),
+}
+
+impl TimeoutPolicy for Timeout {
+    fn hold_ms(&self, time: Option<TimeOfDay>) -> u64 {
+        match self {
+            Timeout::Fixed(policy) => policy.hold_ms(time),
+            Timeout::Adaptive(policy) => policy.hold_ms(time),
+            Timeout::Scheduled(policy) => policy.hold_ms(time),
+        }
+    }
+
+    fn observe(&mut self, presence_ms: u64) {
+        match self {
+            Timeout::Fixed(policy) => policy.observe(presence_ms),
+            Timeout::Adaptive(policy) => policy.observe(presence_ms),
+            Timeout::Scheduled(policy) => policy.observe(presence_ms),
+        }
+    }
+}
+
+// Ocupacion de una zona: sigue ocupada mientras se detecta presencia y
+// durante el tiempo de espera de la politica despues de la ultima deteccion
+pub struct Occupancy<C: Clock, P: TimeoutPolicy> {
+    clock: C,
+    policy: P,
+    present_since: Option<u64>,
+    last_seen: Option<u64>,
+}
+
+impl<C: Clock, P: TimeoutPolicy> Occupancy<C, P> {
+    pub fn new(clock: C, policy: P) -> Self {
+        Self {
+            clock,
+            policy,
+            present_since: None,
+            last_seen: None,
+        }
+    }
+
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    // Registrar un ciclo del controlador. Devuelve si la zona esta ocupada
+    pub fn update(&mut self, present: bool, time: Option<TimeOfDay>) -> bool {
+        let now = self.clock.now_ms();
+
+        if present {
+            self.present_since.get_or_insert(now);
+            self.last_seen = Some(now);
+            return true;
+        }
+
+        if let Some(since) = self.present_since.take() {
+            self.policy.observe(now - since);
+        }
+        self.last_seen
+            .is_some_and(|last_seen| now - last_seen < self.policy.hold_ms(time))
+    }
+}
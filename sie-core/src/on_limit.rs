@@ -0,0 +1,64 @@
+use crate::clock::Clock;
+
+// Limite de tiempo continuo encendida. Cubre el caso de un sensor que
+// apunta a una pared y detecta presencia todo el tiempo: pasado el maximo
+// la lampara se apaga y solo vuelve a encender con un nuevo movimiento
+// despues del enfriamiento, o con una pulsacion manual
+pub struct OnTimeLimit<C: Clock> {
+    clock: C,
+    max_on_ms: u64,
+    cooldown_ms: u64,
+    on_since: Option<u64>,
+    // Bloqueo activo: cuando empezo y si ya se dejo de detectar presencia
+    locked: Option<(u64, bool)>,
+}
+
+impl<C: Clock> OnTimeLimit<C> {
+    pub fn new(clock: C, max_on_ms: u64, cooldown_ms: u64) -> Self {
+        Self {
+            clock,
+            max_on_ms,
+            cooldown_ms,
+            on_since: None,
+            locked: None,
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.is_some()
+    }
+
+    // Registrar un ciclo del controlador. Devuelve true si la lampara debe
+    // quedar apagada
+    pub fn update(&mut self, lamp_on: bool, present: bool) -> bool {
+        let now = self.clock.now_ms();
+
+        if let Some((since, seen_absent)) = self.locked.as_mut() {
+            *seen_absent |= !present;
+            if *seen_absent && present && now - *since >= self.cooldown_ms {
+                self.release();
+                return false;
+            }
+            return true;
+        }
+
+        if !lamp_on {
+            self.on_since = None;
+            return false;
+        }
+
+        let on_since = *self.on_since.get_or_insert(now);
+        if now - on_since >= self.max_on_ms {
+            self.on_since = None;
+            self.locked = Some((now, false));
+            return true;
+        }
+        false
+    }
+
+    // Pulsacion manual: quita el bloqueo y reinicia la cuenta
+    pub fn release(&mut self) {
+        self.on_since = None;
+        self.locked = None;
+    }
+}
@@ -0,0 +1,89 @@
+// Registro de vuelo en RAM: los ultimos `N` elementos (muestras o
+// eventos) en un anillo que pisa los mas viejos. Al dispararse (con una
+// falla) guarda todavia `after` elementos y se congela, para que la
+// historia que llevo a la falla no se pierda antes de leerla; `clear` lo
+// vacia y lo vuelve a poner en marcha
+
+#[derive(Clone, Copy, Debug)]
+pub struct Recorder<T: Copy, const N: usize> {
+    items: [T; N],
+    len: usize,
+    next: usize,
+    // Elementos que faltan para congelarse; None sin disparar
+    remaining: Option<usize>,
+}
+
+impl<T: Copy, const N: usize> Recorder<T, N> {
+    // `fill` solo ocupa los lugares vacios; nunca se devuelve
+    pub const fn new(fill: T) -> Self {
+        Self {
+            items: [fill; N],
+            len: 0,
+            next: 0,
+            remaining: None,
+        }
+    }
+
+    // Agrega un elemento; congelado no hace nada. Devuelve true si con este
+    // elemento se completo la historia posterior al disparo
+    pub fn push(&mut self, item: T) -> bool {
+        if self.is_frozen() {
+            return false;
+        }
+        self.items[self.next] = item;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+        match &mut self.remaining {
+            Some(remaining) => {
+                *remaining -= 1;
+                *remaining == 0
+            }
+            None => false,
+        }
+    }
+
+    // Guarda `after` elementos mas y se congela; con `after` en 0 se
+    // congela de inmediato. Un segundo disparo antes de `clear` no cambia
+    // nada, la historia de la primera falla es la que interesa
+    pub fn trigger(&mut self, after: usize) {
+        if self.remaining.is_none() {
+            self.remaining = Some(after);
+        }
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.remaining.is_some()
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.remaining == Some(0)
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.next = 0;
+        self.remaining = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Elemento `index` contando desde el mas antiguo
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let start = (self.next + N - self.len) % N;
+        Some(self.items[(start + index) % N])
+    }
+
+    // Del mas antiguo al mas reciente
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..self.len).filter_map(move |i| self.get(i))
+    }
+}
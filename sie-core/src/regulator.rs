@@ -0,0 +1,26 @@
+// Regulador de brillo en lazo cerrado: ajusta el brillo para que la
+// iluminacion total medida (ambiente + lampara) se mantenga cerca de un
+// punto de ajuste. Si la luz ambiental sube la lampara baja y viceversa
+pub struct LuxRegulator {
+    setpoint: f32,
+    gain: f32,
+    output: f32,
+}
+
+impl LuxRegulator {
+    // `gain` es el cambio de brillo (%) por cada lux de error en cada ciclo
+    pub const fn new(setpoint: f32, gain: f32) -> Self {
+        Self {
+            setpoint,
+            gain,
+            output: 0.,
+        }
+    }
+
+    // Integra el error de iluminacion y devuelve el nuevo brillo (0 a 100 %)
+    pub fn update(&mut self, measured_lux: f32) -> u8 {
+        let error = self.setpoint - measured_lux;
+        self.output = (self.output + self.gain * error).clamp(0., 100.);
+        (self.output + 0.5) as u8
+    }
+}
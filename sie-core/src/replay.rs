@@ -0,0 +1,155 @@
+use core::{iter::Enumerate, str::Lines};
+
+use crate::{
+    control::Decision,
+    sensor::{MAX_ADC_VALUE, VOLTAGE_REF},
+    telemetry,
+};
+
+// Reproduccion de trazas grabadas: las muestras crudas de una traza (las
+// de las pruebas golden o una captura de la telemetria) se pasan por el
+// controlador y sus decisiones se comparan con la salida esperada, para
+// probar cambios de ajuste contra lo que se registro en campo. Sin std,
+// para que el firmware reproduzca una traza compilada en el programa
+//
+// Formato de la traza (CSV, lineas con '#' son comentarios):
+//     t_ms,raw_distance,raw_lux
+// Formato de la salida esperada:
+//     t_ms,distance_m,lux,dark,light
+
+pub const TRACE_HEADER: &str = "t_ms,raw_distance,raw_lux";
+pub const OUTPUT_HEADER: &str = "t_ms,distance_m,lux,dark,light";
+
+// Una muestra de la traza
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub t_ms: u32,
+    pub raw_distance: u16,
+    pub raw_lux: u16,
+}
+
+impl Sample {
+    // Muestra de una trama de telemetria con los voltajes medidos; None si
+    // la trama no los incluye
+    pub fn from_telemetry(sample: &telemetry::Sample) -> Option<Self> {
+        let raw = sample.raw?;
+        let adc = |voltage: f32| (voltage / VOLTAGE_REF * MAX_ADC_VALUE + 0.5) as u16;
+        Some(Self {
+            t_ms: sample.uptime_ms as u32,
+            raw_distance: adc(raw.distance),
+            raw_lux: adc(raw.lux),
+        })
+    }
+}
+
+// Decision esperada para una muestra
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Expected {
+    pub t_ms: u32,
+    pub dark: bool,
+    pub light: bool,
+}
+
+// Lineas de datos de un CSV, sin comentarios ni encabezado. Cada error
+// lleva el numero de linea (desde 1)
+struct Records<'a> {
+    lines: Enumerate<Lines<'a>>,
+    header: &'static str,
+}
+
+impl<'a> Records<'a> {
+    fn next_fields(&mut self) -> Option<(usize, impl Iterator<Item = &'a str>)> {
+        for (index, line) in self.lines.by_ref() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line == self.header {
+                continue;
+            }
+            return Some((index + 1, line.split(',').map(str::trim)));
+        }
+        None
+    }
+}
+
+// Muestras de una traza
+pub struct Samples<'a>(Records<'a>);
+
+pub fn samples(trace: &str) -> Samples<'_> {
+    Samples(Records {
+        lines: trace.lines().enumerate(),
+        header: TRACE_HEADER,
+    })
+}
+
+impl Iterator for Samples<'_> {
+    type Item = Result<Sample, usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line, fields) = self.0.next_fields()?;
+        Some(parse_sample(fields).ok_or(line))
+    }
+}
+
+fn parse_sample<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<Sample> {
+    Some(Sample {
+        t_ms: fields.next()?.parse().ok()?,
+        raw_distance: fields.next()?.parse().ok()?,
+        raw_lux: fields.next()?.parse().ok()?,
+    })
+}
+
+// Decisiones de una salida esperada
+pub struct Outputs<'a>(Records<'a>);
+
+pub fn outputs(output: &str) -> Outputs<'_> {
+    Outputs(Records {
+        lines: output.lines().enumerate(),
+        header: OUTPUT_HEADER,
+    })
+}
+
+impl Iterator for Outputs<'_> {
+    type Item = Result<Expected, usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line, fields) = self.0.next_fields()?;
+        Some(parse_expected(fields).ok_or(line))
+    }
+}
+
+// La distancia y la luz convertidas no se comparan
+fn parse_expected<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<Expected> {
+    let t_ms = fields.next()?.parse().ok()?;
+    let mut flags = fields.skip(2).map(|f| match f {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    });
+    Some(Expected {
+        t_ms,
+        dark: flags.next()??,
+        light: flags.next()??,
+    })
+}
+
+// Resultado de comparar las decisiones con las esperadas
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Verdict {
+    pub samples: u32,
+    pub mismatches: u32,
+    // Instante de la primera decision distinta
+    pub first_mismatch: Option<u32>,
+}
+
+impl Verdict {
+    pub fn record(&mut self, expected: &Expected, decision: &Decision) {
+        self.samples += 1;
+        if expected.dark != decision.dark || expected.light != decision.light_on {
+            self.mismatches += 1;
+            self.first_mismatch.get_or_insert(expected.t_ms);
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.mismatches == 0
+    }
+}
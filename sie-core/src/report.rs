@@ -0,0 +1,160 @@
+use crate::{clock::Clock, schedule::TimeOfDay};
+
+// Totales acumulados del resumen de consistencia
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    // Tiempo con la lampara encendida
+    pub lamp_on_ms: u64,
+    // Tiempo en que las condiciones medidas pedian luz
+    pub expected_on_ms: u64,
+    // Tiempo con poca luz ambiental
+    pub dark_ms: u64,
+    pub activations: u32,
+    // Activaciones que las condiciones no pedian
+    pub unexplained: u32,
+    // Periodo encendida sin interrupcion mas largo
+    pub longest_on_ms: u64,
+    // Fallas registradas (por ejemplo el limite de tiempo encendida)
+    pub faults: u32,
+}
+
+impl Summary {
+    // Diferencia entre el tiempo encendido y el esperado
+    pub fn deviation_ms(&self) -> u64 {
+        self.lamp_on_ms.abs_diff(self.expected_on_ms)
+    }
+}
+
+// Resumen de consistencia: compara cuanto tiempo estuvo encendida la
+// lampara contra cuanto lo pedian las condiciones medidas (luz y presencia)
+pub struct ConsistencyReport<C: Clock> {
+    clock: C,
+    period_ms: u64,
+    start: u64,
+    last: u64,
+    summary: Summary,
+    was_on: bool,
+    // Tiempo encendida desde la ultima activacion
+    on_ms: u64,
+    // Hora del dia del corte del resumen y la del ciclo anterior
+    rollover: Option<TimeOfDay>,
+    last_time: Option<TimeOfDay>,
+}
+
+impl<C: Clock> ConsistencyReport<C> {
+    pub fn new(clock: C, period_ms: u64) -> Self {
+        let now = clock.now_ms();
+        Self {
+            clock,
+            period_ms,
+            start: now,
+            last: now,
+            summary: Summary::default(),
+            was_on: false,
+            on_ms: 0,
+            rollover: None,
+            last_time: None,
+        }
+    }
+
+    // Corta el resumen a una hora del dia en lugar de cada periodo. Mientras
+    // no se conozca la hora se sigue usando el periodo
+    pub fn with_rollover(mut self, at: TimeOfDay) -> Self {
+        self.rollover = Some(at);
+        self
+    }
+
+    pub fn summary(&self) -> Summary {
+        self.summary
+    }
+
+    // Reinicia los contadores conservando el estado actual de la lampara
+    pub fn reset(&mut self) {
+        let now = self.clock.now_ms();
+        self.start = now;
+        self.last = now;
+        self.summary = Summary::default();
+        self.on_ms = 0;
+    }
+
+    // Cuenta una falla en el resumen actual
+    pub fn fault(&mut self) {
+        self.summary.faults += 1;
+    }
+
+    // Registrar un ciclo del loop principal. `expected` y `dark` son None
+    // cuando no hubo medicion (modo manual). Al cumplirse el periodo
+    // devuelve el resumen y empieza uno nuevo
+    pub fn record(
+        &mut self,
+        lamp_on: bool,
+        expected: Option<bool>,
+        dark: Option<bool>,
+    ) -> Option<Summary> {
+        self.record_at(lamp_on, expected, dark, None)
+    }
+
+    // Como `record`, con la hora del dia si se conoce; con un corte
+    // configurado el resumen se entrega al pasar esa hora
+    pub fn record_at(
+        &mut self,
+        lamp_on: bool,
+        expected: Option<bool>,
+        dark: Option<bool>,
+        time: Option<TimeOfDay>,
+    ) -> Option<Summary> {
+        let now = self.clock.now_ms();
+        let elapsed = now - self.last;
+        self.last = now;
+
+        if lamp_on {
+            self.summary.lamp_on_ms += elapsed;
+            self.on_ms += elapsed;
+            self.summary.longest_on_ms = self.summary.longest_on_ms.max(self.on_ms);
+        } else {
+            self.on_ms = 0;
+        }
+        if expected == Some(true) {
+            self.summary.expected_on_ms += elapsed;
+        }
+        if dark == Some(true) {
+            self.summary.dark_ms += elapsed;
+        }
+
+        // Una activacion es "sin explicacion" si las condiciones no la pedian
+        if lamp_on && !self.was_on {
+            self.summary.activations += 1;
+            if expected != Some(true) {
+                self.summary.unexplained += 1;
+            }
+        }
+        self.was_on = lamp_on;
+
+        let due = match (self.rollover, self.last_time, time) {
+            (Some(at), Some(last), Some(time)) => crossed(last, time, at),
+            // Con corte configurado y la hora recien conocida se espera al
+            // siguiente ciclo
+            (Some(_), None, Some(_)) => false,
+            _ => now - self.start >= self.period_ms,
+        };
+        self.last_time = time;
+
+        if due {
+            let summary = self.summary;
+            self.reset();
+            return Some(summary);
+        }
+
+        None
+    }
+}
+
+// La hora `at` quedo en el intervalo (last, now], quiza pasando la
+// medianoche
+fn crossed(last: TimeOfDay, now: TimeOfDay, at: TimeOfDay) -> bool {
+    if last <= now {
+        last < at && at <= now
+    } else {
+        at > last || at <= now
+    }
+}
@@ -0,0 +1,391 @@
+// Motor de reglas: en lugar de una expresion fija (`luz < X y distancia
+// < Y`) cada zona evalua una lista de reglas. Cada regla es una cadena de
+// condiciones unidas con "y" / "o" (de izquierda a derecha, sin
+// precedencia) y un brillo; gana la primera regla que se cumpla y si
+// ninguna se cumple la lampara se apaga.
+//
+// Formato de texto de una regla:
+//     luz < umbral_luz y ocupado > 0 => 100
+// Sensores: luz (luxes), distancia (metros), ocupado (1 o 0), afuera (luxes
+// del sensor exterior) y diferencia (afuera menos luz). Sin sensor exterior
+// ninguna condicion sobre afuera o diferencia se cumple
+// Valores: un numero, umbral_luz o umbral_distancia (los umbrales vigentes
+// de la zona, que pueden ajustarse en campo)
+
+use crate::control::Thresholds;
+
+pub const MAX_RULES: usize = 4;
+pub const MAX_CONDITIONS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u8)]
+pub enum Sensor {
+    Lux = 0,
+    Distance = 1,
+    Occupied = 2,
+    Outdoor = 3,
+    Difference = 4,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u8)]
+pub enum Operator {
+    Less = 0,
+    Greater = 1,
+}
+
+// Union con el resultado de las condiciones anteriores
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u8)]
+pub enum Combinator {
+    And = 0,
+    Or = 1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Const(f32),
+    LightThreshold,
+    DistanceThreshold,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Condition {
+    pub sensor: Sensor,
+    pub operator: Operator,
+    pub value: Value,
+    // Se ignora en la primera condicion de la regla
+    pub combinator: Combinator,
+}
+
+// Entradas de un ciclo del controlador
+#[derive(Clone, Copy, Debug)]
+pub struct Inputs {
+    pub lux: f32,
+    pub distance: f32,
+    pub occupied: bool,
+    // Luz exterior, si hay sensor
+    pub outdoor: Option<f32>,
+}
+
+impl Condition {
+    fn holds(&self, inputs: &Inputs, thresholds: &Thresholds) -> bool {
+        let input = match self.sensor {
+            Sensor::Lux => inputs.lux,
+            Sensor::Distance => inputs.distance,
+            Sensor::Occupied => inputs.occupied as u8 as f32,
+            Sensor::Outdoor => match inputs.outdoor {
+                Some(outdoor) => outdoor,
+                None => return false,
+            },
+            Sensor::Difference => match inputs.outdoor {
+                Some(outdoor) => outdoor - inputs.lux,
+                None => return false,
+            },
+        };
+        let value = match self.value {
+            Value::Const(value) => value,
+            Value::LightThreshold => thresholds.light,
+            Value::DistanceThreshold => thresholds.distance,
+        };
+        match self.operator {
+            Operator::Less => input < value,
+            Operator::Greater => input > value,
+        }
+    }
+}
+
+const EMPTY_CONDITION: Condition = Condition {
+    sensor: Sensor::Lux,
+    operator: Operator::Less,
+    value: Value::Const(0.),
+    combinator: Combinator::And,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rule {
+    conditions: [Condition; MAX_CONDITIONS],
+    len: usize,
+    // Brillo (0 a 100 %) cuando se cumple
+    pub brightness: u8,
+}
+
+impl Rule {
+    pub const fn new(brightness: u8) -> Self {
+        Self {
+            conditions: [EMPTY_CONDITION; MAX_CONDITIONS],
+            len: 0,
+            brightness,
+        }
+    }
+
+    // Agrega una condicion; None si la regla ya esta llena
+    pub const fn with(mut self, condition: Condition) -> Option<Self> {
+        if self.len == MAX_CONDITIONS {
+            return None;
+        }
+        self.conditions[self.len] = condition;
+        self.len += 1;
+        Some(self)
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions[..self.len]
+    }
+
+    // Una regla sin condiciones siempre se cumple
+    pub fn holds(&self, inputs: &Inputs, thresholds: &Thresholds) -> bool {
+        let mut conditions = self.conditions().iter();
+        let Some(first) = conditions.next() else {
+            return true;
+        };
+
+        conditions.fold(first.holds(inputs, thresholds), |result, c| {
+            match c.combinator {
+                Combinator::And => result && c.holds(inputs, thresholds),
+                Combinator::Or => result || c.holds(inputs, thresholds),
+            }
+        })
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let (conditions, brightness) = text.split_once("=>")?;
+        let brightness: u8 = brightness.trim().parse().ok()?;
+        if brightness > 100 {
+            return None;
+        }
+
+        let mut rule = Self::new(brightness);
+        let mut words = conditions.split_whitespace();
+        let mut combinator = Combinator::And;
+        loop {
+            let sensor = match words.next()? {
+                "luz" => Sensor::Lux,
+                "distancia" => Sensor::Distance,
+                "ocupado" => Sensor::Occupied,
+                "afuera" => Sensor::Outdoor,
+                "diferencia" => Sensor::Difference,
+                _ => return None,
+            };
+            let operator = match words.next()? {
+                "<" => Operator::Less,
+                ">" => Operator::Greater,
+                _ => return None,
+            };
+            let value = match words.next()? {
+                "umbral_luz" => Value::LightThreshold,
+                "umbral_distancia" => Value::DistanceThreshold,
+                number => Value::Const(parse_decimal(number)?),
+            };
+            rule = rule.with(Condition {
+                sensor,
+                operator,
+                value,
+                combinator,
+            })?;
+
+            combinator = match words.next() {
+                None => return Some(rule),
+                Some("y") => Combinator::And,
+                Some("o") => Combinator::Or,
+                Some(_) => return None,
+            };
+        }
+    }
+}
+
+// Conjunto de reglas de una zona
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RuleSet {
+    rules: [Rule; MAX_RULES],
+    len: usize,
+}
+
+// Bytes por condicion y maximo de un conjunto codificado
+const CONDITION_BYTES: usize = 8;
+pub const ENCODED_MAX: usize = 1 + MAX_RULES * (2 + MAX_CONDITIONS * CONDITION_BYTES);
+
+impl RuleSet {
+    pub const fn empty() -> Self {
+        Self {
+            rules: [Rule::new(0); MAX_RULES],
+            len: 0,
+        }
+    }
+
+    // Equivalente a la decision fija: con poca luz y alguien cerca
+    // `presence`, con poca luz y nadie `idle`
+    pub const fn standard(presence: u8, idle: u8) -> Self {
+        let dark = Condition {
+            sensor: Sensor::Lux,
+            operator: Operator::Less,
+            value: Value::LightThreshold,
+            combinator: Combinator::And,
+        };
+        let occupied = Condition {
+            sensor: Sensor::Occupied,
+            operator: Operator::Greater,
+            value: Value::Const(0.5),
+            combinator: Combinator::And,
+        };
+
+        let mut set = Self::empty();
+        set.rules[0] = match Rule::new(presence).with(dark) {
+            Some(rule) => match rule.with(occupied) {
+                Some(rule) => rule,
+                None => unreachable!(),
+            },
+            None => unreachable!(),
+        };
+        set.rules[1] = match Rule::new(idle).with(dark) {
+            Some(rule) => rule,
+            None => unreachable!(),
+        };
+        set.len = 2;
+        set
+    }
+
+    // Agrega `afuera < dark` a cada regla: solo se enciende cuando tambien
+    // esta oscuro afuera. Las reglas llenas quedan igual
+    pub const fn with_outdoor(mut self, dark: f32) -> Self {
+        let outdoor = Condition {
+            sensor: Sensor::Outdoor,
+            operator: Operator::Less,
+            value: Value::Const(dark),
+            combinator: Combinator::And,
+        };
+        let mut i = 0;
+        while i < self.len {
+            if let Some(rule) = self.rules[i].with(outdoor) {
+                self.rules[i] = rule;
+            }
+            i += 1;
+        }
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules[..self.len]
+    }
+
+    // Agrega una regla al final; false si ya no caben
+    pub fn push(&mut self, rule: Rule) -> bool {
+        if self.len == MAX_RULES {
+            return false;
+        }
+        self.rules[self.len] = rule;
+        self.len += 1;
+        true
+    }
+
+    // Brillo de la primera regla que se cumple; 0 si ninguna
+    pub fn evaluate(&self, inputs: &Inputs, thresholds: &Thresholds) -> u8 {
+        self.rules()
+            .iter()
+            .find(|rule| rule.holds(inputs, thresholds))
+            .map_or(0, |rule| rule.brightness)
+    }
+
+    // Codifica el conjunto para guardarlo. Devuelve los bytes usados
+    pub fn encode(&self, out: &mut [u8; ENCODED_MAX]) -> usize {
+        let mut i = 0;
+        out[i] = self.len as u8;
+        i += 1;
+        for rule in self.rules() {
+            out[i] = rule.len as u8;
+            out[i + 1] = rule.brightness;
+            i += 2;
+            for c in rule.conditions() {
+                let (tag, value) = match c.value {
+                    Value::Const(value) => (0, value),
+                    Value::LightThreshold => (1, 0.),
+                    Value::DistanceThreshold => (2, 0.),
+                };
+                out[i] = c.sensor as u8;
+                out[i + 1] = c.operator as u8;
+                out[i + 2] = c.combinator as u8;
+                out[i + 3] = tag;
+                out[i + 4..i + 8].copy_from_slice(&value.to_le_bytes());
+                i += CONDITION_BYTES;
+            }
+        }
+        i
+    }
+
+    // None si los datos no son un conjunto valido
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let mut set = Self::empty();
+        let (&len, mut data) = data.split_first()?;
+        for _ in 0..len {
+            let [conditions, brightness, rest @ ..] = data else {
+                return None;
+            };
+            let mut rule = Rule::new(*brightness);
+            data = rest;
+            for _ in 0..*conditions {
+                let (c, rest) = data.split_at_checked(CONDITION_BYTES)?;
+                let value = f32::from_le_bytes([c[4], c[5], c[6], c[7]]);
+                rule = rule.with(Condition {
+                    sensor: match c[0] {
+                        0 => Sensor::Lux,
+                        1 => Sensor::Distance,
+                        2 => Sensor::Occupied,
+                        3 => Sensor::Outdoor,
+                        4 => Sensor::Difference,
+                        _ => return None,
+                    },
+                    operator: match c[1] {
+                        0 => Operator::Less,
+                        1 => Operator::Greater,
+                        _ => return None,
+                    },
+                    combinator: match c[2] {
+                        0 => Combinator::And,
+                        1 => Combinator::Or,
+                        _ => return None,
+                    },
+                    value: match c[3] {
+                        0 => Value::Const(value),
+                        1 => Value::LightThreshold,
+                        2 => Value::DistanceThreshold,
+                        _ => return None,
+                    },
+                })?;
+                data = rest;
+            }
+            if rule.brightness > 100 || !set.push(rule) {
+                return None;
+            }
+        }
+        Some(set)
+    }
+}
+
+// Numero decimal sencillo (`800`, `2.5`, `-1`). Evita `str::parse::<f32>`,
+// que ocupa mucha flash en el firmware
+pub fn parse_decimal(text: &str) -> Option<f32> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    let mut value = 0.;
+    for digit in integer.bytes() {
+        value = value * 10. + digit_value(digit)?;
+    }
+    let mut scale = 1.;
+    for digit in fraction.bytes() {
+        scale /= 10.;
+        value += digit_value(digit)? * scale;
+    }
+
+    Some(if negative { -value } else { value })
+}
+
+fn digit_value(digit: u8) -> Option<f32> {
+    digit.is_ascii_digit().then(|| (digit - b'0') as f32)
+}
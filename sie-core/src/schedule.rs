@@ -0,0 +1,69 @@
+// Horario de operacion: el modo automatico solo se arma dentro de una
+// franja del dia, que puede cruzar la medianoche (p. ej. 19:00 a 07:00)
+
+pub const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+// Hora del dia en segundos desde la medianoche
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeOfDay(u32);
+
+impl TimeOfDay {
+    pub const fn hm(hours: u32, minutes: u32) -> Self {
+        Self((hours * 60 + minutes) * 60 % SECONDS_PER_DAY)
+    }
+
+    // Hora del dia de un contador de segundos (p. ej. el del RTC)
+    pub const fn from_seconds(seconds: u32) -> Self {
+        Self(seconds % SECONDS_PER_DAY)
+    }
+
+    pub const fn seconds(self) -> u32 {
+        self.0
+    }
+
+    pub const fn hours(self) -> u32 {
+        self.0 / 3600
+    }
+
+    pub const fn minutes(self) -> u32 {
+        self.0 / 60 % 60
+    }
+
+    // Acepta "HH:MM" o "HH:MM:SS"
+    pub fn parse(text: &str) -> Option<Self> {
+        fn field(text: Option<&str>, max: u32) -> Option<u32> {
+            text?.parse::<u32>().ok().filter(|&v| v < max)
+        }
+
+        let mut fields = text.trim().split(':');
+        let hours = field(fields.next(), 24)?;
+        let minutes = field(fields.next(), 60)?;
+        let seconds = match fields.next() {
+            Some(f) => field(Some(f), 60)?,
+            None => 0,
+        };
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(Self(hours * 3600 + minutes * 60 + seconds))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+}
+
+impl Schedule {
+    // Inicio incluido, fin excluido. Con inicio igual a fin la franja
+    // abarca el dia completo
+    pub fn is_active(&self, now: TimeOfDay) -> bool {
+        if self.start < self.end {
+            self.start <= now && now < self.end
+        } else {
+            now >= self.start || now < self.end
+        }
+    }
+}
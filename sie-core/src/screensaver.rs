@@ -0,0 +1,79 @@
+// Protector de pantalla para un OLED siempre encendido: tras un rato sin
+// actividad baja el contraste y despues apaga el panel, porque los pixeles
+// que muestran siempre lo mismo se desgastan y dejan la imagen marcada.
+// Cualquier boton o evento lo despierta
+
+use crate::clock::Clock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Screen {
+    On,
+    Dimmed,
+    Blank,
+}
+
+impl Screen {
+    // Contraste del SSD1306 (0 a 255); None con el panel apagado
+    pub fn contrast(self, normal: u8, dimmed: u8) -> Option<u8> {
+        match self {
+            Screen::On => Some(normal),
+            Screen::Dimmed => Some(dimmed),
+            Screen::Blank => None,
+        }
+    }
+}
+
+// Tiempos sin actividad; `blank_ms` se cuenta desde la ultima actividad,
+// no desde que se atenuo
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timing {
+    pub dim_ms: u64,
+    pub blank_ms: u64,
+}
+
+pub struct Screensaver<C: Clock> {
+    clock: C,
+    timing: Timing,
+    last_activity: u64,
+}
+
+impl<C: Clock> Screensaver<C> {
+    pub fn new(clock: C, timing: Timing) -> Self {
+        let last_activity = clock.now_ms();
+        Self {
+            clock,
+            timing,
+            last_activity,
+        }
+    }
+
+    // Boton, cambio de modo, falla, etc.
+    pub fn wake(&mut self) {
+        self.last_activity = self.clock.now_ms();
+    }
+
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    pub fn screen(&self) -> Screen {
+        let idle = self.clock.now_ms() - self.last_activity;
+        if idle >= self.timing.blank_ms {
+            Screen::Blank
+        } else if idle >= self.timing.dim_ms {
+            Screen::Dimmed
+        } else {
+            Screen::On
+        }
+    }
+
+    // Tiempo hasta el siguiente cambio de pantalla; None si ya esta apagada
+    pub fn next_change_ms(&self) -> Option<u64> {
+        let idle = self.clock.now_ms() - self.last_activity;
+        [self.timing.dim_ms, self.timing.blank_ms]
+            .into_iter()
+            .filter(|&limit| limit > idle)
+            .map(|limit| limit - idle)
+            .min()
+    }
+}
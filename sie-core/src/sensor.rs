@@ -0,0 +1,231 @@
+use crate::control::{DISTANCE_THRESHOLD, LIGHT_THRESHOLD};
+
+// Todo el sistema se alimenta de una fuente
+// de 3.3V
+pub const VOLTAGE_REF: f32 = 3.3; // volts
+
+// stm32 blue pill tiene un adc de 12 bits
+pub const MAX_ADC_VALUE: f32 = 0b1111_1111_1111 as f32; // 4095.0
+
+// Convertir el valor del ADC a un voltaje
+pub fn get_voltage(adc_value: f32) -> f32 {
+    (adc_value / MAX_ADC_VALUE) * VOLTAGE_REF
+}
+
+// Referencia interna del STM32F103 (VREFINT); el F103 no trae su valor de
+// fabrica, solo el tipico de la hoja de datos
+pub const VREFINT: f32 = 1.2; // volts
+
+// Voltaje real de la alimentacion del ADC segun la lectura de VREFINT
+pub fn supply_from_vrefint(raw_vrefint: u16) -> f32 {
+    VREFINT * MAX_ADC_VALUE / raw_vrefint.max(1) as f32
+}
+
+// Lectura que habria dado el ADC con la alimentacion nominal de
+// `VOLTAGE_REF`, para que las conversiones no dependan de la fuente
+pub fn correct_for_supply(raw: u16, supply: f32) -> u16 {
+    (raw as f32 * supply / VOLTAGE_REF).min(MAX_ADC_VALUE) as u16
+}
+
+// Valores de un sensor GP2Y0A710K0F
+pub const DIST_MIN_V: f32 = 1.4; // 550 cm (5.5m)
+pub const DIST_MAX_V: f32 = 2.5; // 100 cm (1.0m)
+
+// Distancias correspondientes
+pub const DIST_MIN_M: f32 = 5.5; // 5.5 metros (voltaje mínimo)
+pub const DIST_MAX_M: f32 = 1.0; // 1.0 metro (voltaje máximo)
+
+// Mapeo lineal inverso (voltaje alto = distancia corta)
+pub fn voltage_to_distance(voltage: f32) -> f32 {
+    DistanceModel::Gp2y0a710.profile().value(voltage)
+}
+
+// Voltaje del sensor a `distance` metros (inversa de `voltage_to_distance`),
+// para ajustar la referencia de un comparador externo
+pub fn distance_to_voltage(distance: f32) -> f32 {
+    DistanceModel::Gp2y0a710.profile().voltage(distance)
+}
+
+// Valores reales de un sensor DFRobot (DFR0026)
+pub const LUX_MIN_V: f32 = 0.3; // 0 lux
+pub const LUX_MAX_V: f32 = 3.0; // 6000 lux
+
+pub const MAX_LUX_VALUE: f32 = 6000.;
+
+// Orientacion del modulo de luz: algunos modulos con LDR dan mas voltaje
+// con menos luz
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LuxPolarity {
+    // Mas luz, mas voltaje (como el DFR0026)
+    #[default]
+    Rising,
+    // Mas luz, menos voltaje
+    Falling,
+}
+
+impl LuxPolarity {
+    // Voltaje equivalente de un modulo que sube con la luz: el invertido se
+    // refleja dentro del mismo rango
+    pub fn normalize(self, voltage: f32) -> f32 {
+        self.normalize_in(voltage, &LightModel::Dfr0026.profile())
+    }
+
+    // Igual, dentro del rango de otro modelo
+    pub fn normalize_in(self, voltage: f32, profile: &Profile) -> f32 {
+        match self {
+            Self::Rising => voltage,
+            Self::Falling => profile.min_v + profile.max_v - voltage,
+        }
+    }
+}
+
+// Mapeo lineal directo
+pub fn voltage_to_lux(voltage: f32) -> f32 {
+    LightModel::Dfr0026.profile().value(voltage)
+}
+
+// Lectura del ADC que corresponde a `lux` (inversa de `voltage_to_lux`),
+// para comparar directamente en el hardware
+pub fn lux_to_adc(lux: f32) -> u16 {
+    LightModel::Dfr0026.profile().adc(lux)
+}
+
+// Respuesta de un sensor aproximada por una recta entre los dos extremos
+// de su hoja de datos: el voltaje mas bajo y el mas alto que entrega y la
+// medicion que corresponde a cada uno
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Profile {
+    pub min_v: f32,
+    pub max_v: f32,
+    pub at_min_v: f32,
+    pub at_max_v: f32,
+    // Umbral por defecto con este sensor, dentro de su rango
+    pub threshold: f32,
+}
+
+impl Profile {
+    // Medicion a `voltage`, con saturacion a los limites del sensor. Sin
+    // `clamp`: con limites que no son constantes su verificacion arrastra
+    // el formato de flotantes al firmware
+    pub fn value(&self, voltage: f32) -> f32 {
+        let clamped_voltage = voltage.max(self.min_v).min(self.max_v);
+        let factor = (clamped_voltage - self.min_v) / (self.max_v - self.min_v);
+        self.at_min_v + (self.at_max_v - self.at_min_v) * factor
+    }
+
+    // Voltaje que corresponde a `value` (inversa de `value`)
+    pub fn voltage(&self, value: f32) -> f32 {
+        let factor = ((value - self.at_min_v) / (self.at_max_v - self.at_min_v)).clamp(0., 1.);
+        self.min_v + factor * (self.max_v - self.min_v)
+    }
+
+    // Lectura del ADC que corresponde a `value`
+    pub fn adc(&self, value: f32) -> u16 {
+        (self.voltage(value) / VOLTAGE_REF * MAX_ADC_VALUE + 0.5) as u16
+    }
+}
+
+// Sensores de distancia Sharp con parametros incluidos; se eligen por el
+// nombre del modelo
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DistanceModel {
+    // 10 a 80 cm
+    Gp2y0a21,
+    // 20 a 150 cm
+    Gp2y0a02,
+    // 100 a 550 cm
+    #[default]
+    Gp2y0a710,
+}
+
+impl DistanceModel {
+    pub const ALL: [Self; 3] = [Self::Gp2y0a21, Self::Gp2y0a02, Self::Gp2y0a710];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Gp2y0a21 => "GP2Y0A21",
+            Self::Gp2y0a02 => "GP2Y0A02",
+            Self::Gp2y0a710 => "GP2Y0A710",
+        }
+    }
+
+    // Modelo por su nombre, sin importar mayusculas
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|model| model.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub const fn profile(self) -> Profile {
+        match self {
+            Self::Gp2y0a21 => Profile {
+                min_v: 0.4,
+                max_v: 2.3,
+                at_min_v: 0.8,
+                at_max_v: 0.1,
+                threshold: 0.5,
+            },
+            Self::Gp2y0a02 => Profile {
+                min_v: 0.4,
+                max_v: 2.45,
+                at_min_v: 1.5,
+                at_max_v: 0.2,
+                threshold: 1.,
+            },
+            Self::Gp2y0a710 => Profile {
+                min_v: DIST_MIN_V,
+                max_v: DIST_MAX_V,
+                at_min_v: DIST_MIN_M,
+                at_max_v: DIST_MAX_M,
+                threshold: DISTANCE_THRESHOLD,
+            },
+        }
+    }
+}
+
+// Sensores de luz con parametros incluidos
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LightModel {
+    // Modulo analogico DFRobot, 0 a 6000 lux
+    #[default]
+    Dfr0026,
+    // Fotorresistencia generica (GL5528) en un divisor con 10 kohm; solo
+    // distingue de la penumbra a la luz de interiores
+    Ldr,
+}
+
+impl LightModel {
+    pub const ALL: [Self; 2] = [Self::Dfr0026, Self::Ldr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dfr0026 => "DFR0026",
+            Self::Ldr => "LDR",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|model| model.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub const fn profile(self) -> Profile {
+        match self {
+            Self::Dfr0026 => Profile {
+                min_v: LUX_MIN_V,
+                max_v: LUX_MAX_V,
+                at_min_v: 0.,
+                at_max_v: MAX_LUX_VALUE,
+                threshold: LIGHT_THRESHOLD,
+            },
+            Self::Ldr => Profile {
+                min_v: 0.1,
+                max_v: 3.2,
+                at_min_v: 0.,
+                at_max_v: 1000.,
+                threshold: 200.,
+            },
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// Ajustes que se conservan entre reinicios: los umbrales y la calibracion
+// del sensor de luz de cada zona y los modos del equipo. Se guardan como un
+// bloque con version y CRC; un bloque de otra version, incompleto o con
+// valores imposibles se descarta y se arranca con los valores por defecto
+
+use crate::{control::Thresholds, counters::crc32};
+
+// Cambia cuando cambia el formato del bloque
+pub const VERSION: u16 = 1;
+
+// Zonas que caben en el bloque
+pub const MAX_ZONES: usize = 2;
+
+// Version (2), modos (1), reservado (1), 12 bytes por zona y CRC-32 de
+// lo anterior (4)
+pub const BLOCK_SIZE: usize = 4 + 12 * MAX_ZONES + 4;
+
+const MANUAL: u8 = 1 << 0;
+const ENABLED: u8 = 1 << 1;
+const CLOSED_LOOP: u8 = 1 << 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZoneSettings {
+    pub thresholds: Thresholds,
+    // Factor de calibracion del sensor de luz
+    pub lux_scale: f32,
+}
+
+impl Default for ZoneSettings {
+    fn default() -> Self {
+        Self {
+            thresholds: Thresholds::default(),
+            lux_scale: 1.,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    pub manual: bool,
+    pub enabled: bool,
+    pub closed_loop: bool,
+    pub zones: [ZoneSettings; MAX_ZONES],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            manual: false,
+            enabled: true,
+            closed_loop: false,
+            zones: [ZoneSettings::default(); MAX_ZONES],
+        }
+    }
+}
+
+impl Settings {
+    pub fn encode(&self) -> [u8; BLOCK_SIZE] {
+        let mut out = [0; BLOCK_SIZE];
+        out[..2].copy_from_slice(&VERSION.to_le_bytes());
+        out[2] = flag(self.manual, MANUAL)
+            | flag(self.enabled, ENABLED)
+            | flag(self.closed_loop, CLOSED_LOOP);
+        for (zone, chunk) in self.zones.iter().zip(out[4..].chunks_exact_mut(12)) {
+            let t = &zone.thresholds;
+            for (value, bytes) in [t.light, t.distance, zone.lux_scale]
+                .into_iter()
+                .zip(chunk.chunks_exact_mut(4))
+            {
+                bytes.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        let crc = crc32(&out[..BLOCK_SIZE - 4]);
+        out[BLOCK_SIZE - 4..].copy_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    // None si el bloque esta borrado, es de otra version, esta corrupto o
+    // trae umbrales negativos o una escala que no sea positiva
+    pub fn decode(data: &[u8; BLOCK_SIZE]) -> Option<Self> {
+        let crc = u32::from_le_bytes([
+            data[BLOCK_SIZE - 4],
+            data[BLOCK_SIZE - 3],
+            data[BLOCK_SIZE - 2],
+            data[BLOCK_SIZE - 1],
+        ]);
+        if crc != crc32(&data[..BLOCK_SIZE - 4])
+            || u16::from_le_bytes([data[0], data[1]]) != VERSION
+        {
+            return None;
+        }
+
+        let float = |i: usize| f32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        let mut zones = [ZoneSettings::default(); MAX_ZONES];
+        for (id, zone) in zones.iter_mut().enumerate() {
+            let base = 4 + 12 * id;
+            *zone = ZoneSettings {
+                thresholds: Thresholds {
+                    light: float(base),
+                    distance: float(base + 4),
+                },
+                lux_scale: float(base + 8),
+            };
+            let t = &zone.thresholds;
+            let valid = |value: f32| value.is_finite() && value >= 0.;
+            if !valid(t.light)
+                || !valid(t.distance)
+                || !valid(zone.lux_scale)
+                || zone.lux_scale == 0.
+            {
+                return None;
+            }
+        }
+
+        Some(Self {
+            manual: data[2] & MANUAL != 0,
+            enabled: data[2] & ENABLED != 0,
+            closed_loop: data[2] & CLOSED_LOOP != 0,
+            zones,
+        })
+    }
+}
+
+fn flag(set: bool, bit: u8) -> u8 {
+    if set { bit } else { 0 }
+}
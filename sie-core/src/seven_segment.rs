@@ -0,0 +1,61 @@
+// Cifras de un visor de 7 segmentos de 4 digitos: la luz en luxes, o la
+// distancia en centimetros precedida de una 'd' para distinguirlas. Cada
+// digito es un byte con los segmentos a-g en los bits 0-6 y el punto en el
+// bit 7, encendido en 1
+
+pub const DIGITS: usize = 4;
+
+const NUMBERS: [u8; 10] = [
+    0b0011_1111,
+    0b0000_0110,
+    0b0101_1011,
+    0b0100_1111,
+    0b0110_0110,
+    0b0110_1101,
+    0b0111_1101,
+    0b0000_0111,
+    0b0111_1111,
+    0b0110_1111,
+];
+const LETTER_D: u8 = 0b0101_1110;
+const DASH: u8 = 0b0100_0000;
+const BLANK: u8 = 0;
+
+// Sin lectura
+pub const DASHES: [u8; DIGITS] = [DASH; DIGITS];
+
+// `value` alineado a la derecha en los ultimos `width` digitos, sin ceros a
+// la izquierda; lo que no cabe se muestra con guiones
+fn number(digits: &mut [u8; DIGITS], value: u32, width: usize) {
+    let start = DIGITS - width;
+    if value >= 10u32.pow(width as u32) {
+        digits[start..].fill(DASH);
+        return;
+    }
+    let mut rest = value;
+    for (i, digit) in digits[start..].iter_mut().enumerate().rev() {
+        *digit = if rest == 0 && i < width - 1 {
+            BLANK
+        } else {
+            NUMBERS[(rest % 10) as usize]
+        };
+        rest /= 10;
+    }
+}
+
+pub fn lux(lux: f32) -> [u8; DIGITS] {
+    let mut digits = [BLANK; DIGITS];
+    number(&mut digits, (lux.max(0.) + 0.5) as u32, DIGITS);
+    digits
+}
+
+pub fn centimeters(meters: f32) -> [u8; DIGITS] {
+    let mut digits = [BLANK; DIGITS];
+    digits[0] = LETTER_D;
+    number(
+        &mut digits,
+        (meters.max(0.) * 100. + 0.5) as u32,
+        DIGITS - 1,
+    );
+    digits
+}
@@ -0,0 +1,100 @@
+use crate::{
+    demo::{BRIGHT_LUX, DARK_LUX, FAR_M, NEAR_M},
+    sensor::{LuxPolarity, MAX_ADC_VALUE, Profile, VOLTAGE_REF},
+};
+
+// Escenario sintetico para probar la logica de control sin hardware: un
+// dia comprimido (amanecer, dia, atardecer y noche) y visitas periodicas
+// de alguien que se acerca, se queda y se va. Las lecturas son funcion
+// del tiempo, con un poco de ruido, y se entregan como lecturas crudas del
+// ADC para recorrer la misma conversion que los sensores reales
+
+// Fracciones del dia: la noche ocupa la primera mitad, cada rampa (amanecer
+// y atardecer) un decimo
+const NIGHT: f32 = 0.5;
+const RAMP: f32 = 0.1;
+
+// Duracion de cada tramo de una visita
+const APPROACH_MS: u64 = 5_000;
+const STAY_MS: u64 = 10_000;
+const LEAVE_MS: u64 = 5_000;
+
+// Amplitud del ruido de las lecturas, en cuentas del ADC
+const NOISE: u32 = 8;
+
+pub struct Scenario {
+    // Duracion del dia simulado
+    day_ms: u64,
+    // Una visita empieza cada `visit_ms`, desplazada `offset_ms`
+    visit_ms: u64,
+    offset_ms: u64,
+    // Estado del generador de ruido (xorshift)
+    noise: u32,
+}
+
+impl Scenario {
+    // `offset_ms` distingue las visitas de cada zona
+    pub const fn new(day_ms: u64, visit_ms: u64, offset_ms: u64) -> Self {
+        Self {
+            day_ms,
+            visit_ms,
+            offset_ms,
+            noise: 0x2545_F491,
+        }
+    }
+
+    // Luz ambiental en luxes a `t_ms`
+    pub fn lux(&self, t_ms: u64) -> f32 {
+        let phase = (t_ms % self.day_ms) as f32 / self.day_ms as f32;
+        // Fraccion de la luz del dia: 0 de noche, 1 al mediodia
+        let daylight = if phase < NIGHT {
+            0.
+        } else if phase < NIGHT + RAMP {
+            (phase - NIGHT) / RAMP
+        } else if phase < 1. - RAMP {
+            1.
+        } else {
+            (1. - phase) / RAMP
+        };
+        DARK_LUX + (BRIGHT_LUX - DARK_LUX) * daylight
+    }
+
+    // Distancia en metros a lo que ve el sensor a `t_ms`
+    pub fn distance(&self, t_ms: u64) -> f32 {
+        let t = (t_ms + self.visit_ms - self.offset_ms % self.visit_ms) % self.visit_ms;
+        let fraction = if t < APPROACH_MS {
+            t as f32 / APPROACH_MS as f32
+        } else if t < APPROACH_MS + STAY_MS {
+            1.
+        } else if t < APPROACH_MS + STAY_MS + LEAVE_MS {
+            (APPROACH_MS + STAY_MS + LEAVE_MS - t) as f32 / LEAVE_MS as f32
+        } else {
+            0.
+        };
+        FAR_M + (NEAR_M - FAR_M) * fraction
+    }
+
+    // Lecturas crudas (distancia, luz) a `t_ms` para los modelos y la
+    // orientacion del modulo de luz de una zona
+    pub fn sample(
+        &mut self,
+        t_ms: u64,
+        distance: &Profile,
+        light: &Profile,
+        polarity: LuxPolarity,
+    ) -> (u16, u16) {
+        let raw_distance = distance.adc(self.distance(t_ms));
+        // El reflejo del modulo invertido es su propia inversa
+        let lux_voltage = polarity.normalize_in(light.voltage(self.lux(t_ms)), light);
+        let raw_lux = (lux_voltage / VOLTAGE_REF * MAX_ADC_VALUE + 0.5) as u16;
+        (self.noisy(raw_distance), self.noisy(raw_lux))
+    }
+
+    fn noisy(&mut self, raw: u16) -> u16 {
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+        let offset = (self.noise % (2 * NOISE + 1)) as i32 - NOISE as i32;
+        (raw as i32 + offset).clamp(0, MAX_ADC_VALUE as i32) as u16
+    }
+}
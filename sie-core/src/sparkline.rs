@@ -0,0 +1,112 @@
+// Historia de la luz de la ultima hora para mostrarla de un vistazo: un
+// promedio por minuto dibujado como una columna, del mas antiguo a la
+// izquierda al mas reciente a la derecha, y una barra con la distancia
+// actual
+
+use crate::framebuffer::Framebuffer;
+
+pub const MINUTES: usize = 60;
+
+#[derive(Clone, Debug)]
+pub struct LuxHistory {
+    minutes: [u16; MINUTES],
+    len: usize,
+    next: usize,
+    // Lecturas del minuto en curso
+    sum: f32,
+    samples: u32,
+}
+
+impl Default for LuxHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LuxHistory {
+    pub const fn new() -> Self {
+        Self {
+            minutes: [0; MINUTES],
+            len: 0,
+            next: 0,
+            sum: 0.,
+            samples: 0,
+        }
+    }
+
+    pub fn add(&mut self, lux: f32) {
+        self.sum += lux.max(0.);
+        self.samples += 1;
+    }
+
+    // Cierra el minuto en curso con el promedio de sus lecturas; un minuto
+    // sin lecturas no se agrega
+    pub fn close_minute(&mut self) {
+        if self.samples == 0 {
+            return;
+        }
+        let average = self.sum / self.samples as f32;
+        self.minutes[self.next] = average.min(u16::MAX as f32) as u16;
+        self.next = (self.next + 1) % MINUTES;
+        self.len = (self.len + 1).min(MINUTES);
+        self.sum = 0.;
+        self.samples = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Promedios del mas antiguo al mas reciente
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        let start = (self.next + MINUTES - self.len) % MINUTES;
+        (0..self.len).map(move |i| self.minutes[(start + i) % MINUTES])
+    }
+}
+
+// Dibuja la historia en un area de `MINUTES` columnas por `height` filas
+// con la esquina superior izquierda en (x, y). La escala va de cero al
+// maximo de la hora, para que se vean los cambios con poca luz. Los
+// minutos que faltan quedan en blanco a la izquierda
+pub fn draw_sparkline(
+    frame: &mut Framebuffer,
+    x: usize,
+    y: usize,
+    height: usize,
+    history: &LuxHistory,
+) {
+    if height == 0 {
+        return;
+    }
+    let max = history.iter().max().unwrap_or(0).max(1) as usize;
+    let offset = MINUTES - history.len();
+    for (column, lux) in history.iter().enumerate() {
+        // Al menos un pixel para distinguir oscuridad de un minuto faltante
+        let bar = (lux as usize * (height - 1)).div_ceil(max) + 1;
+        for row in 0..bar.min(height) {
+            frame.set(x + offset + column, y + height - 1 - row, true);
+        }
+    }
+}
+
+// Barra horizontal con contorno, llena en `fraction` (0 a 1)
+pub fn draw_bar(
+    frame: &mut Framebuffer,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    fraction: f32,
+) {
+    frame.rect(x, y, width, height);
+    if width < 2 || height < 2 {
+        return;
+    }
+    let inner = width - 2;
+    let filled = (fraction.clamp(0., 1.) * inner as f32 + 0.5) as usize;
+    frame.fill_rect(x + 1, y + 1, filled, height - 2);
+}
@@ -0,0 +1,34 @@
+// Uso de la RAM. Al arrancar se pinta la pila libre con un patron; la
+// parte que sigue pintada desde el fondo nunca se uso, y lo demas es el
+// maximo que llego a ocupar
+
+pub const PAINT: u32 = 0xACE5_ACE5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    // RAM de los estaticos (.data, .bss); con Embassy incluye el estado de
+    // cada tarea
+    pub statics: u32,
+    // RAM que queda para la pila
+    pub stack: u32,
+    // Maximo de pila usado desde el arranque
+    pub stack_peak: u32,
+}
+
+impl MemoryUsage {
+    // `words` son las palabras de la pila desde el fondo; basta con que
+    // llegue hasta la primera que ya no tiene el patron
+    pub fn measure(statics: u32, stack: u32, words: impl IntoIterator<Item = u32>) -> Self {
+        let untouched = words.into_iter().take_while(|&w| w == PAINT).count() as u32 * 4;
+        Self {
+            statics,
+            stack,
+            stack_peak: stack.saturating_sub(untouched),
+        }
+    }
+
+    // Pila que nunca se uso
+    pub fn headroom(&self) -> u32 {
+        self.stack - self.stack_peak
+    }
+}
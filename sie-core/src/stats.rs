@@ -0,0 +1,199 @@
+// Estadisticas moviles de un sensor (minimo, maximo, media y varianza) en
+// una ventana reciente, para elegir los umbrales con datos del lugar: el
+// umbral de luz conviene por debajo del minimo del dia y el de distancia
+// lejos de la media con la zona vacia. La media y la varianza se acumulan
+// con el metodo de Welford, estable en f32 con miles de muestras
+
+use crate::clock::Clock;
+
+// Ventanas de cada sensor: una corta y una larga (por ejemplo el ultimo
+// minuto y la ultima hora)
+pub const WINDOWS: usize = 2;
+
+// La ventana se divide en tramos; al avanzar se descarta el mas viejo
+const SLOTS: usize = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    pub count: u32,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    // Varianza de la poblacion (dividida entre `count`)
+    pub variance: f32,
+}
+
+// Sin muestras todo queda en cero
+impl Summary {
+    pub const EMPTY: Self = Self {
+        count: 0,
+        min: 0.,
+        max: 0.,
+        mean: 0.,
+        variance: 0.,
+    };
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Accumulator {
+    count: u32,
+    mean: f32,
+    // Suma de los cuadrados de las diferencias con la media
+    m2: f32,
+    // Validos con al menos una muestra; todo en cero al crearlo para que
+    // los estaticos que lo contienen no ocupen flash
+    min: f32,
+    max: f32,
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Accumulator {
+    pub const fn new() -> Self {
+        Self {
+            count: 0,
+            mean: 0.,
+            m2: 0.,
+            min: 0.,
+            max: 0.,
+        }
+    }
+
+    pub fn add(&mut self, value: f32) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        }
+        self.count = self.count.saturating_add(1);
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    // Combina dos grupos de muestras (Chan y otros)
+    pub fn merge(&mut self, other: &Self) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = *other;
+            return;
+        }
+        let count = self.count.saturating_add(other.count);
+        let delta = other.mean - self.mean;
+        let weight = other.count as f32 / count as f32;
+        self.mean += delta * weight;
+        self.m2 += other.m2 + delta * delta * self.count as f32 * weight;
+        self.count = count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn summary(&self) -> Summary {
+        if self.count == 0 {
+            return Summary::EMPTY;
+        }
+        Summary {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: self.mean,
+            variance: (self.m2 / self.count as f32).max(0.),
+        }
+    }
+}
+
+// Estadisticas de la ventana que termina ahora (por ejemplo el ultimo
+// minuto o la ultima hora); como el histograma de distancias, los tramos
+// que quedan fuera de la ventana se vacian al avanzar
+pub struct RollingStats<C: Clock> {
+    clock: C,
+    slot_ms: u64,
+    // Tramo absoluto (tiempo / slot_ms) del ultimo registro
+    current: u64,
+    slots: [Accumulator; SLOTS],
+}
+
+impl<C: Clock> RollingStats<C> {
+    pub const fn new(clock: C, window_ms: u64) -> Self {
+        Self {
+            clock,
+            slot_ms: window_ms / SLOTS as u64,
+            current: 0,
+            slots: [Accumulator::new(); SLOTS],
+        }
+    }
+
+    pub fn record(&mut self, value: f32) {
+        self.advance();
+        self.slots[self.current as usize % SLOTS].add(value);
+    }
+
+    pub fn summary(&mut self) -> Summary {
+        self.advance();
+        let mut total = Accumulator::new();
+        for slot in &self.slots {
+            total.merge(slot);
+        }
+        total.summary()
+    }
+
+    fn advance(&mut self) {
+        let slot = self.clock.now_ms() / self.slot_ms.max(1);
+        let stale = (slot - self.current).min(SLOTS as u64);
+        for i in 1..=stale {
+            self.slots[(self.current + i) as usize % SLOTS] = Accumulator::new();
+        }
+        self.current = slot;
+    }
+}
+
+// Resumen de la luz y la distancia de una zona en cada ventana, de la mas
+// corta a la mas larga
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub lux: [Summary; WINDOWS],
+    pub distance: [Summary; WINDOWS],
+}
+
+pub struct ZoneStats<C: Clock + Copy> {
+    lux: [RollingStats<C>; WINDOWS],
+    distance: [RollingStats<C>; WINDOWS],
+}
+
+impl<C: Clock + Copy> ZoneStats<C> {
+    pub const fn new(clock: C, windows_ms: [u64; WINDOWS]) -> Self {
+        Self {
+            lux: [
+                RollingStats::new(clock, windows_ms[0]),
+                RollingStats::new(clock, windows_ms[1]),
+            ],
+            distance: [
+                RollingStats::new(clock, windows_ms[0]),
+                RollingStats::new(clock, windows_ms[1]),
+            ],
+        }
+    }
+
+    pub fn record(&mut self, lux: f32, distance: f32) {
+        for stats in &mut self.lux {
+            stats.record(lux);
+        }
+        for stats in &mut self.distance {
+            stats.record(distance);
+        }
+    }
+
+    pub fn summary(&mut self) -> Stats {
+        Stats {
+            lux: self.lux.each_mut().map(|s| s.summary()),
+            distance: self.distance.each_mut().map(|s| s.summary()),
+        }
+    }
+}
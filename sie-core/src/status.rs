@@ -0,0 +1,69 @@
+// Patrones del LED de estado, para distinguir varios estados del sistema
+// con un solo LED
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    Off,
+    Solid,
+    // 1 s encendido, 1 s apagado
+    SlowBlink,
+    // 100 ms encendido, 100 ms apagado
+    FastBlink,
+    // Encendido con un apagon de 100 ms cada 500 ms
+    Wink,
+    // N pulsos cortos seguidos de una pausa; sirve para codigos
+    Pulses(u8),
+}
+
+const SLOW_HALF_PERIOD_MS: u64 = 1000;
+const FAST_HALF_PERIOD_MS: u64 = 100;
+const WINK_PERIOD_MS: u64 = 500;
+const WINK_OFF_MS: u64 = 100;
+const PULSE_MS: u64 = 200;
+const PULSE_PAUSE_MS: u64 = 1200;
+
+impl Pattern {
+    // Nivel del LED `elapsed_ms` despues de iniciar el patron
+    pub fn level(self, elapsed_ms: u64) -> bool {
+        match self {
+            Pattern::Off => false,
+            Pattern::Solid => true,
+            Pattern::SlowBlink => (elapsed_ms / SLOW_HALF_PERIOD_MS).is_multiple_of(2),
+            Pattern::FastBlink => (elapsed_ms / FAST_HALF_PERIOD_MS).is_multiple_of(2),
+            Pattern::Wink => elapsed_ms % WINK_PERIOD_MS >= WINK_OFF_MS,
+            Pattern::Pulses(0) => false,
+            Pattern::Pulses(count) => {
+                let pulses_ms = 2 * PULSE_MS * count as u64;
+                let t = elapsed_ms % (pulses_ms + PULSE_PAUSE_MS);
+                t < pulses_ms && (t / PULSE_MS).is_multiple_of(2)
+            }
+        }
+    }
+}
+
+// Estado del sistema mostrado en el LED
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Normal,
+    Manual,
+    // Modo manual a punto de volver al automatico por inactividad
+    ManualExpiring,
+    Disabled,
+    Calibrating,
+    // Configuracion de fabrica restablecida o por restablecerse
+    FactoryReset,
+    // Falla con su codigo, mostrado como numero de pulsos
+    Fault(u8),
+}
+
+impl Status {
+    pub fn pattern(self) -> Pattern {
+        match self {
+            Status::Normal => Pattern::Off,
+            Status::Manual => Pattern::Solid,
+            Status::ManualExpiring => Pattern::Wink,
+            Status::Disabled => Pattern::SlowBlink,
+            Status::Calibrating | Status::FactoryReset => Pattern::FastBlink,
+            Status::Fault(code) => Pattern::Pulses(code),
+        }
+    }
+}
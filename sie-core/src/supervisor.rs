@@ -0,0 +1,39 @@
+use crate::clock::Clock;
+
+// Supervision de las tareas criticas para el watchdog: cada tarea se
+// reporta en cada vuelta de su bucle y el watchdog solo se alimenta
+// mientras todas lo hayan hecho dentro de su plazo. Una tarea trabada
+// (un driver que no responde, un bucle sin fin) deja de reportarse y el
+// watchdog reinicia el equipo. El reloj cuenta desde el arranque, asi que
+// cada tarea tiene su plazo completo para el primer reporte
+pub struct Supervisor<C: Clock, const N: usize> {
+    clock: C,
+    allowed_ms: [u64; N],
+    last: [u64; N],
+}
+
+impl<C: Clock, const N: usize> Supervisor<C, N> {
+    pub const fn new(clock: C, allowed_ms: [u64; N]) -> Self {
+        Self {
+            clock,
+            allowed_ms,
+            last: [0; N],
+        }
+    }
+
+    pub fn check_in(&mut self, task: usize) {
+        self.last[task] = self.clock.now_ms();
+    }
+
+    // Deja de vigilar una tarea, por ejemplo una que ya se dio por trabada
+    // y con la que el equipo sigue degradado
+    pub fn release(&mut self, task: usize) {
+        self.allowed_ms[task] = u64::MAX;
+    }
+
+    // Primera tarea que lleva mas que su plazo sin reportarse
+    pub fn stalled(&self) -> Option<usize> {
+        let now = self.clock.now_ms();
+        (0..N).find(|&task| now - self.last[task] > self.allowed_ms[task])
+    }
+}
@@ -0,0 +1,359 @@
+// Tramas binarias de telemetria para registrar desde una computadora o
+// una Raspberry Pi sin interpretar texto de defmt. El contenido sigue el
+// formato de postcard (enteros grandes como varint, u8 como un byte, f32
+// en 4 bytes little-endian, enums por indice y `Option` con un byte de
+// marca), asi que del lado de la
+// computadora basta `postcard::from_bytes` sobre una estructura con los
+// mismos campos en el mismo orden. Cada trama va codificada con COBS y
+// termina en 0, de modo que el receptor se puede sincronizar en cualquier
+// momento
+
+use crate::{
+    counters::Counters,
+    stats::{Stats, Summary},
+};
+
+// Modo de operacion al tomar la muestra
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Mode {
+    Auto = 0,
+    Manual = 1,
+    Disabled = 2,
+}
+
+// Grupos de campos que se incluyen en cada muestra; cada instalacion elige
+// cuanto detalle enviar segun el ancho de banda del enlace
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fields(pub u8);
+
+impl Fields {
+    // Voltajes medidos en los sensores
+    pub const RAW: Self = Self(1 << 0);
+    // Luz y distancia convertidas
+    pub const VALUES: Self = Self(1 << 1);
+    // Brillo y modo
+    pub const STATE: Self = Self(1 << 2);
+    // Contadores de toda la vida del equipo
+    pub const COUNTERS: Self = Self(1 << 3);
+    // Minimo, maximo, media y varianza de la luz y la distancia en cada
+    // ventana (ver `crate::stats`)
+    pub const STATS: Self = Self(1 << 4);
+    pub const ALL: Self = Self(0x1F);
+
+    pub const fn with(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Voltages {
+    pub lux: f32,
+    pub distance: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Values {
+    pub lux: f32,
+    pub distance: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct State {
+    // Brillo de la lampara (0 a 100 %)
+    pub brightness: u8,
+    pub mode: Mode,
+}
+
+// Muestra periodica de una zona. Los grupos no elegidos van como `None`
+// (un solo byte en cero, igual que un `Option` de postcard)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub uptime_ms: u64,
+    pub zone: u8,
+    pub raw: Option<Voltages>,
+    pub values: Option<Values>,
+    pub state: Option<State>,
+    pub counters: Option<Counters>,
+    pub stats: Option<Stats>,
+}
+
+// Resumen de una ventana: varint de u32 (5) y cuatro f32
+const SUMMARY_MAX: usize = 5 + 4 * 4;
+
+// Tamano maximo de una muestra serializada: varint de u64 (10), zona (1),
+// voltajes y valores (1 + 8 cada uno), estado (1 + 2), contadores (1 y
+// cuatro varint de u32 de hasta 5) y estadisticas (1 y un resumen por
+// sensor y ventana)
+pub const SAMPLE_MAX: usize =
+    10 + 1 + 9 + 9 + 3 + 1 + 4 * 5 + 1 + 2 * crate::stats::WINDOWS * SUMMARY_MAX;
+// Trama completa: COBS agrega un byte cada 254 mas el inicial, y el 0 final
+pub const FRAME_MAX: usize = SAMPLE_MAX + SAMPLE_MAX / 254 + 2;
+
+impl Sample {
+    // Quita los grupos que no esten en `fields`
+    pub fn select(mut self, fields: Fields) -> Self {
+        if !fields.contains(Fields::RAW) {
+            self.raw = None;
+        }
+        if !fields.contains(Fields::VALUES) {
+            self.values = None;
+        }
+        if !fields.contains(Fields::STATE) {
+            self.state = None;
+        }
+        if !fields.contains(Fields::COUNTERS) {
+            self.counters = None;
+        }
+        if !fields.contains(Fields::STATS) {
+            self.stats = None;
+        }
+        self
+    }
+
+    // Devuelve los bytes usados
+    pub fn serialize(&self, out: &mut [u8; SAMPLE_MAX]) -> usize {
+        let mut w = Writer { out, len: 0 };
+        w.varint(self.uptime_ms);
+        w.byte(self.zone);
+        if let Some(raw) = w.option(self.raw) {
+            w.f32(raw.lux);
+            w.f32(raw.distance);
+        }
+        if let Some(values) = w.option(self.values) {
+            w.f32(values.lux);
+            w.f32(values.distance);
+        }
+        if let Some(state) = w.option(self.state) {
+            w.byte(state.brightness);
+            // Varint de un solo byte mientras haya menos de 128 modos
+            w.byte(state.mode as u8);
+        }
+        if let Some(c) = w.option(self.counters) {
+            for value in [c.boots, c.activations, c.on_seconds, c.faults] {
+                w.varint(value as u64);
+            }
+        }
+        if let Some(stats) = w.option(self.stats) {
+            for summary in stats.lux.iter().chain(&stats.distance) {
+                w.varint(summary.count as u64);
+                for value in [summary.min, summary.max, summary.mean, summary.variance] {
+                    w.f32(value);
+                }
+            }
+        }
+        w.len
+    }
+
+    // None si los datos no son una muestra completa
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        let mut r = Reader { data };
+        let uptime_ms = r.varint()?;
+        let zone = r.byte()?;
+        let raw = match r.option()? {
+            true => Some(Voltages {
+                lux: r.f32()?,
+                distance: r.f32()?,
+            }),
+            false => None,
+        };
+        let values = match r.option()? {
+            true => Some(Values {
+                lux: r.f32()?,
+                distance: r.f32()?,
+            }),
+            false => None,
+        };
+        let state = match r.option()? {
+            true => Some(State {
+                brightness: r.byte()?,
+                mode: match r.byte()? {
+                    0 => Mode::Auto,
+                    1 => Mode::Manual,
+                    2 => Mode::Disabled,
+                    _ => return None,
+                },
+            }),
+            false => None,
+        };
+        let counters = match r.option()? {
+            true => Some(Counters {
+                boots: r.u32()?,
+                activations: r.u32()?,
+                on_seconds: r.u32()?,
+                faults: r.u32()?,
+            }),
+            false => None,
+        };
+        let stats = match r.option()? {
+            true => {
+                let mut stats = Stats {
+                    lux: [Summary::EMPTY; crate::stats::WINDOWS],
+                    distance: [Summary::EMPTY; crate::stats::WINDOWS],
+                };
+                for summary in stats.lux.iter_mut().chain(&mut stats.distance) {
+                    *summary = Summary {
+                        count: r.u32()?,
+                        min: r.f32()?,
+                        max: r.f32()?,
+                        mean: r.f32()?,
+                        variance: r.f32()?,
+                    };
+                }
+                Some(stats)
+            }
+            false => None,
+        };
+        r.data.is_empty().then_some(Self {
+            uptime_ms,
+            zone,
+            raw,
+            values,
+            state,
+            counters,
+            stats,
+        })
+    }
+
+    // Muestra serializada, codificada con COBS y terminada en 0. Devuelve
+    // los bytes usados
+    pub fn frame(&self, out: &mut [u8; FRAME_MAX]) -> usize {
+        let mut sample = [0; SAMPLE_MAX];
+        let len = self.serialize(&mut sample);
+        let encoded = cobs_encode(&sample[..len], out);
+        out[encoded] = 0;
+        encoded + 1
+    }
+}
+
+struct Writer<'a> {
+    out: &'a mut [u8; SAMPLE_MAX],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn byte(&mut self, byte: u8) {
+        self.out[self.len] = byte;
+        self.len += 1;
+    }
+
+    fn f32(&mut self, value: f32) {
+        for byte in value.to_le_bytes() {
+            self.byte(byte);
+        }
+    }
+
+    // Entero sin signo en grupos de 7 bits, el menos significativo primero
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.byte(byte);
+                return;
+            }
+            self.byte(byte | 0x80);
+        }
+    }
+
+    // Marca de `Option`; el contenido lo escribe quien llama
+    fn option<T>(&mut self, value: Option<T>) -> Option<T> {
+        self.byte(value.is_some() as u8);
+        value
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let (&byte, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(byte)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes([
+            self.byte()?,
+            self.byte()?,
+            self.byte()?,
+            self.byte()?,
+        ]))
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for i in 0..10 {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7F) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        u32::try_from(self.varint()?).ok()
+    }
+
+    fn option(&mut self) -> Option<bool> {
+        match self.byte()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+// COBS: quita los ceros de `data`. `out` debe tener al menos
+// `data.len() + data.len() / 254 + 1` bytes. Devuelve los bytes usados
+pub fn cobs_encode(data: &[u8], out: &mut [u8]) -> usize {
+    // Posicion del byte de conteo del bloque actual
+    let mut code_at = 0;
+    let mut code = 1;
+    let mut len = 1;
+    for &byte in data {
+        if byte != 0 {
+            out[len] = byte;
+            len += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            out[code_at] = code;
+            code_at = len;
+            code = 1;
+            len += 1;
+        }
+    }
+    out[code_at] = code;
+    len
+}
+
+// Inversa de `cobs_encode`, sin el 0 final. None si la trama esta mal
+// formada o no cabe en `out`
+pub fn cobs_decode(data: &[u8], out: &mut [u8]) -> Option<usize> {
+    let mut len = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let code = data[i] as usize;
+        if code == 0 || i + code > data.len() {
+            return None;
+        }
+        let block = &data[i + 1..i + code];
+        out.get_mut(len..len + block.len())?.copy_from_slice(block);
+        len += block.len();
+        i += code;
+        if code < 0xFF && i < data.len() {
+            *out.get_mut(len)? = 0;
+            len += 1;
+        }
+    }
+    Some(len)
+}
@@ -0,0 +1,85 @@
+use crate::clock::Clock;
+
+// Prueba de una configuracion recibida a distancia. Mientras dura se
+// vigilan las lamparas: si alguna queda encendida sin interrupcion mas de
+// `max_on_ms`, o ninguna enciende en `period_ms`, la configuracion se da
+// por mala y hay que volver a la anterior. Si el periodo termina sin
+// problemas la nueva queda como buena
+pub struct ConfigTrial<C: Clock, const N: usize> {
+    clock: C,
+    max_on_ms: u64,
+    period_ms: u64,
+    started: Option<u64>,
+    on_since: [Option<u64>; N],
+    activated: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    // Una lampara quedo encendida todo el tiempo maximo
+    StuckOn,
+    // Ninguna lampara encendio en todo el periodo
+    NeverOn,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed(Fault),
+}
+
+impl<C: Clock, const N: usize> ConfigTrial<C, N> {
+    pub fn new(clock: C, max_on_ms: u64, period_ms: u64) -> Self {
+        Self {
+            clock,
+            max_on_ms,
+            period_ms,
+            started: None,
+            on_since: [None; N],
+            activated: false,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    // Empieza (o reinicia, con otro cambio) la prueba. Una lampara que ya
+    // estaba encendida cuenta desde ahora
+    pub fn start(&mut self) {
+        self.started = Some(self.clock.now_ms());
+        self.on_since = [None; N];
+        self.activated = false;
+    }
+
+    // Registra el estado de las lamparas. Devuelve el resultado cuando la
+    // prueba termina
+    pub fn update(&mut self, lamps: [bool; N]) -> Option<Outcome> {
+        let started = self.started?;
+        let now = self.clock.now_ms();
+
+        for (on_since, on) in self.on_since.iter_mut().zip(lamps) {
+            if !on {
+                *on_since = None;
+                continue;
+            }
+            self.activated = true;
+            if now - *on_since.get_or_insert(now) >= self.max_on_ms {
+                return Some(self.finish(Outcome::Failed(Fault::StuckOn)));
+            }
+        }
+
+        if now - started < self.period_ms {
+            return None;
+        }
+        Some(self.finish(match self.activated {
+            true => Outcome::Passed,
+            false => Outcome::Failed(Fault::NeverOn),
+        }))
+    }
+
+    fn finish(&mut self, outcome: Outcome) -> Outcome {
+        self.started = None;
+        outcome
+    }
+}
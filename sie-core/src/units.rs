@@ -0,0 +1,59 @@
+// Unidades en las que se muestran los valores a las personas. Internamente
+// todo se maneja en metros y grados Celsius; las interfaces para otras
+// maquinas (MQTT, CAN, telemetria binaria) no cambian
+
+const FEET_PER_METER: f32 = 3.280_84;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Units {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+impl Units {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "metric" | "metrico" => Some(Self::Metric),
+            "imperial" => Some(Self::Imperial),
+            _ => None,
+        }
+    }
+
+    // Metros a la unidad de distancia
+    pub fn distance(self, meters: f32) -> f32 {
+        match self {
+            Self::Metric => meters,
+            Self::Imperial => meters * FEET_PER_METER,
+        }
+    }
+
+    // Una distancia escrita en la unidad, a metros
+    pub fn meters(self, value: f32) -> f32 {
+        match self {
+            Self::Metric => value,
+            Self::Imperial => value / FEET_PER_METER,
+        }
+    }
+
+    pub fn distance_unit(self) -> &'static str {
+        match self {
+            Self::Metric => "m",
+            Self::Imperial => "ft",
+        }
+    }
+
+    pub fn temperature(self, celsius: f32) -> f32 {
+        match self {
+            Self::Metric => celsius,
+            Self::Imperial => celsius * 9. / 5. + 32.,
+        }
+    }
+
+    pub fn temperature_unit(self) -> &'static str {
+        match self {
+            Self::Metric => "C",
+            Self::Imperial => "F",
+        }
+    }
+}
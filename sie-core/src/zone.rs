@@ -0,0 +1,146 @@
+use crate::{
+    background::{Background, Presence},
+    clock::Clock,
+    control::{Decision, Reading, Thresholds, decide},
+    event_log::Reason,
+    occupancy::{Occupancy, Timeout},
+    on_limit::OnTimeLimit,
+    regulator::LuxRegulator,
+    rules::{Inputs, RuleSet},
+    schedule::TimeOfDay,
+};
+
+// Decision de una zona en cada muestra, sin el hardware: presencia (umbral
+// fijo o fondo aprendido), ocupacion con su espera, reglas o regulador en
+// lazo cerrado y limite de tiempo encendida. El firmware y cualquier otra
+// placa (ver `hal`) solo ponen las lecturas y aplican el brillo
+
+// Limites de la lampara de una zona
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    // Tiempo maximo encendida sin interrupcion
+    pub max_on_ms: u64,
+    // Pausa tras el limite antes de aceptar un movimiento nuevo
+    pub cooldown_ms: u64,
+}
+
+// Resultado de una muestra
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    pub decision: Decision,
+    // Presencia con el fondo aprendido, si la zona lo usa
+    pub present: bool,
+    pub occupied: bool,
+    // Las reglas piden encender, antes del regulador y del limite
+    pub light_on: bool,
+    // Brillo a aplicar (0 a 100 %)
+    pub brightness: u8,
+    // El limite de tiempo encendida se activo en esta muestra
+    pub limit_reached: bool,
+}
+
+pub struct ZoneLogic<C: Clock + Clone> {
+    background: Option<Background>,
+    occupancy: Occupancy<C, Timeout>,
+    regulator: LuxRegulator,
+    on_limit: OnTimeLimit<C>,
+}
+
+impl Step {
+    // Motivo para el registro de sucesos si con este paso la lampara se
+    // enciende o se apaga
+    pub fn reason(&self) -> Reason {
+        match (self.brightness > 0, self.occupied) {
+            _ if self.limit_reached => Reason::TimeLimit,
+            (true, true) => Reason::Presence,
+            (true, false) => Reason::Rules,
+            (false, _) if !self.decision.dark => Reason::Daylight,
+            (false, _) => Reason::Vacant,
+        }
+    }
+}
+
+impl<C: Clock + Clone> ZoneLogic<C> {
+    pub fn new(
+        clock: C,
+        timeout: Timeout,
+        presence: Presence,
+        regulator: LuxRegulator,
+        limits: Limits,
+    ) -> Self {
+        Self {
+            background: match presence {
+                Presence::Threshold => None,
+                Presence::Background { margin_m } => Some(Background::new(margin_m)),
+            },
+            occupancy: Occupancy::new(clock.clone(), timeout),
+            regulator,
+            on_limit: OnTimeLimit::new(clock, limits.max_on_ms, limits.cooldown_ms),
+        }
+    }
+
+    // Quita el limite de tiempo encendida (una pulsacion manual)
+    pub fn release(&mut self) {
+        self.on_limit.release();
+    }
+
+    // Evalua una lectura. Con `closed_loop` el brillo lo fija el regulador
+    // mientras la zona este ocupada, en lugar de las reglas
+    pub fn step(
+        &mut self,
+        reading: &Reading,
+        thresholds: &Thresholds,
+        rules: &RuleSet,
+        outdoor: Option<f32>,
+        closed_loop: bool,
+        time: Option<TimeOfDay>,
+    ) -> Step {
+        let decision = decide(reading, thresholds);
+        // Mientras se aprende el fondo se usa el umbral fijo
+        let present = self
+            .background
+            .as_mut()
+            .and_then(|b| b.update(reading.distance))
+            .unwrap_or(decision.present);
+        // La zona sigue ocupada un tiempo despues de la ultima deteccion
+        let occupied = self.occupancy.update(present, time);
+
+        let inputs = Inputs {
+            lux: reading.lux,
+            distance: reading.distance,
+            occupied,
+            outdoor,
+        };
+        let brightness = rules.evaluate(&inputs, thresholds);
+        let light_on = brightness > 0;
+        let brightness = if closed_loop {
+            // El regulador ya compensa la luz ambiental, solo hace falta
+            // que haya alguien cerca
+            if occupied {
+                self.regulator.update(reading.lux)
+            } else {
+                0
+            }
+        } else {
+            brightness
+        };
+
+        // Limite de tiempo encendida, por si el sensor detecta presencia
+        // todo el tiempo
+        let was_locked = self.on_limit.is_locked();
+        let brightness = if self.on_limit.update(brightness > 0, present) {
+            0
+        } else {
+            brightness
+        };
+
+        Step {
+            decision,
+            present,
+            occupied,
+            light_on,
+            brightness,
+            limit_reached: self.on_limit.is_locked() && !was_locked,
+        }
+    }
+}
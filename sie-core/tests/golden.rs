@@ -0,0 +1,118 @@
+// Compara la salida del controlador contra las trazas de referencia en
+// tests/fixtures. Si un cambio de comportamiento es intencional, regenerar
+// las salidas esperadas con `cargo run --bin regen-golden` y revisar el diff.
+
+use std::{fs, path::Path};
+
+use sie_core::{
+    control::{Reading, Thresholds, decide},
+    golden::{EXPECTED_SUFFIX, TRACE_SUFFIX, parse_trace, run},
+    replay::{self, Sample, Verdict},
+    telemetry::{self, SAMPLE_MAX, Voltages},
+};
+
+fn fixture(stem: &str) -> (String, String) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let trace = fs::read_to_string(dir.join(format!("{stem}{TRACE_SUFFIX}"))).unwrap();
+    let expected = fs::read_to_string(dir.join(format!("{stem}{EXPECTED_SUFFIX}"))).unwrap();
+    (trace, expected)
+}
+
+fn check(stem: &str) {
+    let (trace, expected) = fixture(stem);
+
+    let samples = parse_trace(&trace).unwrap();
+    let actual = run(&samples, &Thresholds::default());
+
+    for (line, (actual, expected)) in actual.lines().zip(expected.lines()).enumerate() {
+        assert_eq!(
+            actual,
+            expected,
+            "{stem}: la salida difiere en la linea {}; si el cambio es intencional \
+             ejecutar `cargo run --bin regen-golden`",
+            line + 1
+        );
+    }
+    assert_eq!(
+        actual.lines().count(),
+        expected.lines().count(),
+        "{stem}: numero de lineas distinto"
+    );
+}
+
+macro_rules! golden {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                check(stringify!($name));
+            }
+        )*
+    };
+}
+
+golden!(
+    daylight_passerby,
+    dusk_approach,
+    empty_night,
+    threshold_noise
+);
+
+// Reproduce una traza con otros umbrales, como en la placa con `replay`
+fn replay_with(stem: &str, thresholds: &Thresholds) -> Verdict {
+    let (trace, expected) = fixture(stem);
+    let mut verdict = Verdict::default();
+    for (sample, expected) in replay::samples(&trace).zip(replay::outputs(&expected)) {
+        let (sample, expected) = (sample.unwrap(), expected.unwrap());
+        let reading = Reading::from_raw(sample.raw_distance, sample.raw_lux);
+        verdict.record(&expected, &decide(&reading, thresholds));
+    }
+    verdict
+}
+
+#[test]
+fn replay_flags_a_tuning_change() {
+    let same = replay_with("dusk_approach", &Thresholds::default());
+    assert!(same.passed());
+    assert_eq!(same.samples, 240);
+
+    let tuned = replay_with(
+        "dusk_approach",
+        &Thresholds {
+            light: 1500.,
+            ..Thresholds::default()
+        },
+    );
+    assert!(!tuned.passed());
+    assert!(tuned.first_mismatch.is_some());
+}
+
+#[test]
+fn telemetry_capture_converts_to_a_trace_sample() {
+    let sent = telemetry::Sample {
+        uptime_ms: 12_300,
+        zone: 1,
+        raw: Some(Voltages {
+            lux: 1.5,
+            distance: 2.0,
+        }),
+        values: None,
+        state: None,
+        counters: None,
+        stats: None,
+    };
+    let mut frame = [0; telemetry::FRAME_MAX];
+    let len = sent.frame(&mut frame);
+
+    let mut decoded = [0; SAMPLE_MAX];
+    let decoded_len = telemetry::cobs_decode(&frame[..len - 1], &mut decoded).unwrap();
+    let received = telemetry::Sample::deserialize(&decoded[..decoded_len]).unwrap();
+    assert_eq!(
+        Sample::from_telemetry(&received),
+        Some(Sample {
+            t_ms: 12_300,
+            raw_distance: 2482,
+            raw_lux: 1861,
+        })
+    );
+}
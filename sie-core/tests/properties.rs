@@ -0,0 +1,1149 @@
+// Pruebas basadas en propiedades para las conversiones (incluidas las
+// unidades), la decision, el regulador de brillo, la correccion
+// perceptual, las estadisticas de latencia y del bucle, el aprendizaje de
+// la luz ambiental y de la distancia de fondo, el motor de reglas, las
+// tramas de telemetria, del bus CAN, de LoRa y del nRF24, los registros
+// I2C, los comandos AT, el almacen clave-valor en flash, los ajustes
+// guardados, las filas CSV de la tarjeta SD, el registro de vuelo, el
+// registro de sucesos, las estadisticas de los sensores, los modelos de
+// sensores, la ventana del watchdog del ADC, la conciliacion de la
+// energia, la frecuencia de la red, el universo DMX y la grafica de la
+// luz: se generan entradas aleatorias y se verifican invariantes que
+// deben cumplirse siempre.
+
+use proptest::prelude::*;
+use sie_core::{
+    ambient::{AmbientLearner, MIN_SAMPLES},
+    analog_watchdog::{self, Levels, Window},
+    background::Background,
+    battery::{self, BatteryMonitor},
+    button::Press,
+    can_frames::{self, Status},
+    codes::Code,
+    control::{Reading, Thresholds, decide},
+    counters::{self, Counters},
+    csv_log,
+    dmx::{self, Universe},
+    energy::{self, Estimate, MIN_WH, TOLERANCE, Verdict},
+    esp_at::escaped,
+    event_log::{self, Cursor, Entry, EventLog, Reason, Record, Stamp},
+    framebuffer::{Framebuffer, WIDTH},
+    gamma::{apply_floor, duty_fraction},
+    i2c_registers::{self, MAP_SIZE, Write, ZoneRegisters, decode_write},
+    kv::{self, Pages, Store},
+    latency::{LatencyWindow, LoopTiming},
+    lora_packets::{self, Header, ZoneSample},
+    mains::{self, Condition, MainsMonitor},
+    nrf24_packets::{self, Sample as RadioSample},
+    recorder::Recorder,
+    regulator::LuxRegulator,
+    rules::{Inputs, RuleSet},
+    schedule::TimeOfDay,
+    sensor::{
+        DIST_MAX_M, DIST_MAX_V, DIST_MIN_M, DIST_MIN_V, DistanceModel, LUX_MAX_V, LUX_MIN_V,
+        LightModel, LuxPolarity, MAX_ADC_VALUE, MAX_LUX_VALUE, VOLTAGE_REF, VREFINT,
+        correct_for_supply, distance_to_voltage, get_voltage, lux_to_adc, supply_from_vrefint,
+        voltage_to_distance, voltage_to_lux,
+    },
+    settings::{self, Settings, ZoneSettings},
+    sparkline::{LuxHistory, MINUTES, draw_bar, draw_sparkline},
+    stack::{self, MemoryUsage},
+    stats::{Accumulator, Stats, Summary},
+    telemetry::{
+        FRAME_MAX, Fields, Mode, SAMPLE_MAX, Sample, State, Values, Voltages, cobs_decode,
+        cobs_encode,
+    },
+    units::Units,
+};
+
+// Dos paginas de flash en memoria. Escribir solo se puede sobre bytes
+// borrados; al agotarse `budget` (bytes escritos) se corta la energia y
+// nada mas se escribe ni se borra
+const SIM_PAGE: usize = 256;
+
+struct SimPages {
+    data: [[u8; SIM_PAGE]; 2],
+    budget: usize,
+}
+
+impl SimPages {
+    fn new() -> Self {
+        Self {
+            data: [[kv::ERASED; SIM_PAGE]; 2],
+            budget: usize::MAX,
+        }
+    }
+}
+
+impl Pages for &mut SimPages {
+    fn read(&mut self, page: usize, offset: usize, buf: &mut [u8]) -> bool {
+        buf.copy_from_slice(&self.data[page][offset..offset + buf.len()]);
+        true
+    }
+
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> bool {
+        for (cell, &byte) in self.data[page][offset..].iter_mut().zip(data) {
+            if self.budget == 0 || *cell != kv::ERASED {
+                return false;
+            }
+            *cell = byte;
+            self.budget -= 1;
+        }
+        true
+    }
+
+    fn erase(&mut self, page: usize) -> bool {
+        if self.budget == 0 {
+            return false;
+        }
+        self.data[page] = [kv::ERASED; SIM_PAGE];
+        true
+    }
+}
+
+fn stored_counters(store: &mut Store<&mut SimPages>, key: u8) -> Option<Counters> {
+    let mut buf = [0; counters::ENCODED_SIZE];
+    store.get(key, &mut buf).map(|_| Counters::decode(&buf))
+}
+
+// Cualquier suceso con cualquier marca de tiempo
+fn event_record() -> impl Strategy<Value = Record> {
+    let stamp = prop_oneof![
+        (0u32..1 << 31).prop_map(Stamp::Uptime),
+        (0u32..24 * 60 * 60).prop_map(|s| Stamp::Wall(TimeOfDay::from_seconds(s))),
+    ];
+    let press = prop::sample::select(vec![
+        Press::Single,
+        Press::Double,
+        Press::Triple,
+        Press::Long,
+    ]);
+    let entry = prop_oneof![
+        Just(Entry::Boot),
+        (
+            any::<u8>(),
+            any::<bool>(),
+            prop::sample::select(Reason::ALL.to_vec())
+        )
+            .prop_map(|(zone, on, reason)| Entry::Light { zone, on, reason }),
+        (any::<bool>(), any::<bool>())
+            .prop_map(|(manual, enabled)| Entry::Mode { manual, enabled }),
+        (any::<u8>(), press).prop_map(|(button, press)| Entry::Button { button, press }),
+        prop::sample::select(Code::ALL.to_vec()).prop_map(Entry::Fault),
+    ];
+    (stamp, entry).prop_map(|(stamp, entry)| Record { stamp, entry })
+}
+
+fn read_events(log: &mut EventLog<&mut SimPages>) -> Vec<Record> {
+    let mut cursor = Cursor::default();
+    std::iter::from_fn(|| log.next(&mut cursor)).collect()
+}
+
+// Voltajes un poco fuera del rango de la fuente para probar la saturacion
+fn voltage() -> impl Strategy<Value = f32> {
+    -1.0f32..5.0
+}
+
+proptest! {
+    // De dia la luz bajo el umbral de cualquier zona dispara el watchdog y
+    // la que los supera todos no; de noche dispara la presencia (o la luz
+    // que vuelve) en cualquier zona y lo que queda bajo todos los niveles no
+    #[test]
+    fn analog_watchdog_window_catches_every_crossing(
+        levels in prop::collection::vec(
+            (0..=analog_watchdog::FULL_SCALE, 0..=analog_watchdog::FULL_SCALE),
+            1..=2,
+        ),
+        raw in 0..=analog_watchdog::FULL_SCALE,
+    ) {
+        let levels: Vec<Levels> = levels
+            .into_iter()
+            .map(|(dark, near)| Levels { dark, near })
+            .collect();
+
+        let day = Window::new(&levels, false);
+        if levels.iter().any(|l| raw < l.dark) {
+            prop_assert!(!day.contains(raw));
+        }
+        if levels.iter().all(|l| raw >= l.dark) {
+            prop_assert!(day.contains(raw));
+        }
+
+        let night = Window::new(&levels, true);
+        if levels.iter().any(|l| raw > l.near || raw > l.dark) {
+            prop_assert!(!night.contains(raw));
+        }
+        if levels.iter().all(|l| raw <= l.dark.min(l.near)) {
+            prop_assert!(night.contains(raw));
+        }
+    }
+
+    // Cada clave conserva su ultimo valor, tambien al copiar los vigentes
+    // a la otra pagina y al reabrir el almacen
+    #[test]
+    fn kv_store_keeps_the_latest_values(
+        writes in prop::collection::vec((0u8..6, prop::collection::vec(any::<u8>(), 0..=kv::MAX_VALUE)), 1..150),
+    ) {
+        let mut pages = SimPages::new();
+        let mut expected: [Option<Vec<u8>>; 6] = Default::default();
+        for (key, value) in writes {
+            let mut store = Store::open(&mut pages, SIM_PAGE);
+            prop_assert!(store.set(key, &value));
+            expected[key as usize] = Some(value);
+
+            for (key, value) in expected.iter().enumerate() {
+                let mut buf = [0; kv::MAX_VALUE];
+                let len = store.get(key as u8, &mut buf);
+                prop_assert_eq!(len.map(|len| buf[..len].to_vec()), value.clone());
+            }
+        }
+    }
+
+    // Una clave quitada no tiene valor, ni siquiera uno vacio, tambien
+    // despues de pasar los demas a la otra pagina
+    #[test]
+    fn kv_store_forgets_removed_keys(
+        writes in prop::collection::vec((0u8..6, prop::collection::vec(any::<u8>(), 0..=kv::MAX_VALUE), any::<bool>()), 1..150),
+    ) {
+        let mut pages = SimPages::new();
+        let mut expected: [Option<Vec<u8>>; 6] = Default::default();
+        for (key, value, remove) in writes {
+            let mut store = Store::open(&mut pages, SIM_PAGE);
+            if remove {
+                prop_assert!(store.remove(key));
+                expected[key as usize] = None;
+            } else {
+                prop_assert!(store.set(key, &value));
+                expected[key as usize] = Some(value);
+            }
+
+            for (key, value) in expected.iter().enumerate() {
+                let mut buf = [0; kv::MAX_VALUE];
+                let len = store.get(key as u8, &mut buf);
+                prop_assert_eq!(len.map(|len| buf[..len].to_vec()), value.clone());
+            }
+        }
+    }
+
+    // Un corte de energia en cualquier byte de cualquier guardado, incluida
+    // la copia a la otra pagina, deja el valor anterior o el nuevo; los
+    // demas no cambian y el almacen sigue aceptando escrituras
+    #[test]
+    fn kv_store_survives_a_cut_during_a_write(
+        saves in 1u32..60,
+        budget in 0usize..200,
+    ) {
+        let mut pages = SimPages::new();
+        let other = Counters { faults: 7, ..Counters::default() };
+        for n in 0..saves {
+            let mut store = Store::open(&mut pages, SIM_PAGE);
+            let counters = Counters { boots: n, ..Counters::default() };
+            prop_assert!(store.set(0, &counters.encode()));
+            if n == 0 {
+                prop_assert!(store.set(1, &other.encode()));
+            }
+        }
+
+        pages.budget = budget;
+        let last = Counters { boots: saves, ..Counters::default() };
+        Store::open(&mut pages, SIM_PAGE).set(0, &last.encode());
+        pages.budget = usize::MAX;
+
+        let mut store = Store::open(&mut pages, SIM_PAGE);
+        let boots = stored_counters(&mut store, 0).unwrap().boots;
+        prop_assert!(boots == saves - 1 || boots == saves);
+        prop_assert_eq!(stored_counters(&mut store, 1), Some(other));
+
+        prop_assert!(store.set(0, &Counters::default().encode()));
+        prop_assert_eq!(stored_counters(&mut store, 0), Some(Counters::default()));
+    }
+
+    // Un suceso regresa igual de sus bytes
+    #[test]
+    fn event_records_round_trip(record in event_record()) {
+        prop_assert_eq!(Record::decode(&record.encode()), Some(record));
+    }
+
+    // El registro devuelve los ultimos sucesos en orden, aunque se reabra en
+    // cualquier momento; siempre queda al menos una pagina completa
+    #[test]
+    fn event_log_keeps_the_latest_records(
+        records in proptest::collection::vec(event_record(), 0..120),
+        reopen_every in 1usize..50,
+    ) {
+        let per_page = (SIM_PAGE - 8) / event_log::RECORD_SIZE;
+        let mut pages = SimPages::new();
+        for chunk in records.chunks(reopen_every) {
+            let mut log = EventLog::open(&mut pages, SIM_PAGE);
+            for record in chunk {
+                prop_assert!(log.append(record));
+            }
+        }
+
+        let read = read_events(&mut EventLog::open(&mut pages, SIM_PAGE));
+        prop_assert!(read.len() >= records.len().min(per_page));
+        prop_assert!(read.len() <= 2 * per_page);
+        prop_assert_eq!(&read[..], &records[records.len() - read.len()..]);
+    }
+
+    // Un corte de energia en cualquier byte deja los sucesos anteriores y a
+    // lo sumo el que se estaba escribiendo; despues se sigue registrando
+    #[test]
+    fn event_log_survives_a_cut_during_a_write(
+        records in proptest::collection::vec(event_record(), 1..80),
+        last in event_record(),
+        after in event_record(),
+        budget in 0usize..16,
+    ) {
+        let mut pages = SimPages::new();
+        {
+            let mut log = EventLog::open(&mut pages, SIM_PAGE);
+            for record in &records {
+                prop_assert!(log.append(record));
+            }
+        }
+
+        pages.budget = budget;
+        EventLog::open(&mut pages, SIM_PAGE).append(&last);
+        pages.budget = usize::MAX;
+
+        let mut log = EventLog::open(&mut pages, SIM_PAGE);
+        prop_assert!(log.append(&after));
+        let mut read = read_events(&mut log);
+        prop_assert_eq!(read.pop(), Some(after));
+        prop_assert!(!read.is_empty());
+        let kept = match read.split_last() {
+            Some((&cut, rest)) if cut == last && records.ends_with(rest) => rest,
+            _ => &read[..],
+        };
+        prop_assert!(records.ends_with(kept));
+    }
+
+    // Un motivo, un tipo o una marca desconocidos, o lo que queda de un
+    // registro cortado, no se leen como suceso
+    #[test]
+    fn event_records_reject_unknown_bytes(
+        record in event_record(),
+        reason in Reason::ALL.len() as u8..,
+        kind in 5u8..,
+        seconds in 24 * 60 * 60u32..1 << 31,
+    ) {
+        let mut data = record.encode();
+        data[4] = kind;
+        prop_assert_eq!(Record::decode(&data), None);
+
+        let light = Record {
+            entry: Entry::Light { zone: 0, on: true, reason: Reason::Presence },
+            ..record
+        };
+        let mut data = light.encode();
+        data[7] = reason;
+        prop_assert_eq!(Record::decode(&data), None);
+
+        let mut data = light.encode();
+        data[..4].copy_from_slice(&(seconds | 1 << 31).to_le_bytes());
+        prop_assert_eq!(Record::decode(&data), None);
+
+        prop_assert_eq!(Record::decode(&[kv::ERASED; event_log::RECORD_SIZE]), None);
+    }
+
+    // Borrar el registro olvida todo lo anterior, tambien al reabrirlo
+    #[test]
+    fn event_log_clear_forgets_everything(
+        records in proptest::collection::vec(event_record(), 0..80),
+        after in event_record(),
+    ) {
+        let mut pages = SimPages::new();
+        let mut log = EventLog::open(&mut pages, SIM_PAGE);
+        for record in &records {
+            prop_assert!(log.append(record));
+        }
+        prop_assert!(log.clear());
+        prop_assert_eq!(read_events(&mut log), []);
+        prop_assert!(log.append(&after));
+        prop_assert_eq!(
+            read_events(&mut EventLog::open(&mut pages, SIM_PAGE)),
+            [after]
+        );
+    }
+
+    // Los ajustes regresan iguales y cualquier byte alterado descarta el
+    // bloque completo
+    #[test]
+    fn settings_round_trip_and_reject_corruption(
+        flags in any::<[bool; 3]>(),
+        zones in any::<[(u16, u16, u16); settings::MAX_ZONES]>(),
+        corrupt_at in 0usize..settings::BLOCK_SIZE,
+        flip in 1u8..=255,
+    ) {
+        let [manual, enabled, closed_loop] = flags;
+        let saved = Settings {
+            manual,
+            enabled,
+            closed_loop,
+            zones: zones.map(|(light, distance, scale)| ZoneSettings {
+                thresholds: Thresholds {
+                    light: light as f32,
+                    distance: distance as f32 / 100.,
+                },
+                lux_scale: (scale as f32 + 1.) / 1000.,
+            }),
+        };
+        let mut block = saved.encode();
+        prop_assert_eq!(Settings::decode(&block), Some(saved));
+
+        block[corrupt_at] ^= flip;
+        prop_assert_eq!(Settings::decode(&block), None);
+    }
+
+    // Un parametro escapado no tiene comillas ni comas sueltas y se
+    // recupera quitando los escapes
+    #[test]
+    fn at_escape_round_trip(text in "[ -~]{0,40}") {
+        let bytes: Vec<u8> = escaped(&text).collect();
+        let mut unescaped = Vec::new();
+        let mut iter = bytes.iter();
+        while let Some(&byte) = iter.next() {
+            if byte == b'\\' {
+                let next = *iter.next().unwrap();
+                prop_assert!(matches!(next, b'"' | b',' | b'\\'));
+                unescaped.push(next);
+            } else {
+                prop_assert!(!matches!(byte, b'"' | b','));
+                unescaped.push(byte);
+            }
+        }
+        prop_assert_eq!(unescaped, text.into_bytes());
+    }
+
+    // Una trama no tiene ceros salvo el final y regresa a la misma muestra,
+    // con solo los grupos elegidos
+    #[test]
+    fn telemetry_frame_round_trip(
+        uptime_ms in any::<u64>(),
+        zone in any::<u8>(),
+        lux in 0.0f32..6000.,
+        distance in 0.0f32..6.,
+        voltage in 0.0f32..3.3,
+        brightness in 0u8..=100,
+        mode in prop_oneof![Just(Mode::Auto), Just(Mode::Manual), Just(Mode::Disabled)],
+        counters in any::<[u32; 4]>(),
+        count in any::<u32>(),
+        fields in 0u8..32,
+    ) {
+        let fields = Fields(fields);
+        let [boots, activations, on_seconds, faults] = counters;
+        let sample = Sample {
+            uptime_ms,
+            zone,
+            raw: Some(Voltages { lux: voltage, distance: voltage }),
+            values: Some(Values { lux, distance }),
+            state: Some(State { brightness, mode }),
+            counters: Some(Counters { boots, activations, on_seconds, faults }),
+            stats: Some(Stats {
+                lux: [Summary { count, min: 0., max: lux, mean: lux / 2., variance: lux }; 2],
+                distance: [Summary::EMPTY, Summary { count, min: distance, max: distance, mean: distance, variance: 0. }],
+            }),
+        }
+        .select(fields);
+        prop_assert_eq!(sample.raw.is_some(), fields.contains(Fields::RAW));
+        prop_assert_eq!(sample.counters.is_some(), fields.contains(Fields::COUNTERS));
+        prop_assert_eq!(sample.stats.is_some(), fields.contains(Fields::STATS));
+
+        let mut frame = [0; FRAME_MAX];
+        let len = sample.frame(&mut frame);
+
+        prop_assert_eq!(frame[len - 1], 0);
+        prop_assert!(!frame[..len - 1].contains(&0));
+
+        let mut decoded = [0; SAMPLE_MAX];
+        let decoded_len = cobs_decode(&frame[..len - 1], &mut decoded).unwrap();
+        prop_assert_eq!(Sample::deserialize(&decoded[..decoded_len]), Some(sample));
+        prop_assert_eq!(Sample::deserialize(&decoded[..decoded_len - 1]), None);
+    }
+
+    #[test]
+    fn cobs_round_trip(data in proptest::collection::vec(any::<u8>(), 0..600)) {
+        let mut encoded = vec![0; data.len() + data.len() / 254 + 1];
+        let len = cobs_encode(&data, &mut encoded);
+        prop_assert!(!encoded[..len].contains(&0));
+
+        let mut decoded = vec![0; data.len()];
+        prop_assert_eq!(cobs_decode(&encoded[..len], &mut decoded), Some(data.len()));
+        prop_assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn voltage_within_supply(raw in 0u16..=4095) {
+        let v = get_voltage(raw as f32);
+        prop_assert!((0.0..=VOLTAGE_REF).contains(&v));
+    }
+
+    #[test]
+    fn voltage_is_monotonic(a in 0u16..=4095, b in 0u16..=4095) {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        prop_assert!(get_voltage(lo as f32) <= get_voltage(hi as f32));
+    }
+
+    // Voltaje alto = objeto cerca
+    #[test]
+    fn distance_decreases_with_voltage(a in voltage(), b in voltage()) {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        prop_assert!(voltage_to_distance(lo) >= voltage_to_distance(hi));
+    }
+
+    #[test]
+    fn distance_within_sensor_range(v in voltage()) {
+        let d = voltage_to_distance(v);
+        prop_assert!((DIST_MAX_M..=DIST_MIN_M).contains(&d));
+    }
+
+    #[test]
+    fn distance_saturates_at_rails(below in -1.0f32..DIST_MIN_V, above in DIST_MAX_V..5.0) {
+        prop_assert_eq!(voltage_to_distance(below), DIST_MIN_M);
+        prop_assert_eq!(voltage_to_distance(above), DIST_MAX_M);
+    }
+
+    #[test]
+    fn lux_increases_with_voltage(a in voltage(), b in voltage()) {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        prop_assert!(voltage_to_lux(lo) <= voltage_to_lux(hi));
+    }
+
+    #[test]
+    fn lux_within_sensor_range(v in voltage()) {
+        let lux = voltage_to_lux(v);
+        prop_assert!((0.0..=MAX_LUX_VALUE).contains(&lux));
+    }
+
+    #[test]
+    fn lux_saturates_at_rails(below in -1.0f32..LUX_MIN_V, above in LUX_MAX_V..5.0) {
+        prop_assert_eq!(voltage_to_lux(below), 0.0);
+        prop_assert_eq!(voltage_to_lux(above), MAX_LUX_VALUE);
+    }
+
+    // Dentro de los rieles cada voltaje da una medicion distinta
+    #[test]
+    fn conversions_are_strictly_monotonic_between_the_rails(a in 0.0f32..=1., b in 0.0f32..=1.) {
+        prop_assume!((a - b).abs() > 1e-3);
+        let (lo, hi) = if a < b { (a, b) } else { (b, a) };
+        let distance_v = |f: f32| DIST_MIN_V + (DIST_MAX_V - DIST_MIN_V) * f;
+        let lux_v = |f: f32| LUX_MIN_V + (LUX_MAX_V - LUX_MIN_V) * f;
+        prop_assert!(voltage_to_distance(distance_v(lo)) > voltage_to_distance(distance_v(hi)));
+        prop_assert!(voltage_to_lux(lux_v(lo)) < voltage_to_lux(lux_v(hi)));
+    }
+
+    // Ida y vuelta desde el voltaje: la inversa devuelve el voltaje medido
+    #[test]
+    fn distance_round_trips_from_the_voltage(v in DIST_MIN_V..=DIST_MAX_V) {
+        prop_assert!((distance_to_voltage(voltage_to_distance(v)) - v).abs() < 1e-4);
+    }
+
+    #[test]
+    fn lux_round_trips_from_the_voltage(v in LUX_MIN_V..=LUX_MAX_V) {
+        let profile = LightModel::Dfr0026.profile();
+        prop_assert!((profile.voltage(voltage_to_lux(v)) - v).abs() < 1e-4);
+    }
+
+    // La inversa tambien satura: fuera del rango del sensor da el riel
+    #[test]
+    fn distance_to_voltage_saturates_out_of_range(near in 0.0f32..DIST_MAX_M, far in DIST_MIN_M..20.0) {
+        prop_assert_eq!(distance_to_voltage(near), DIST_MAX_V);
+        prop_assert_eq!(distance_to_voltage(far), DIST_MIN_V);
+    }
+
+    // La lectura completa desde el ADC conserva el orden y los limites
+    #[test]
+    fn raw_reading_is_monotonic_and_bounded(a in 0u16..=4095, b in 0u16..=4095) {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        let (low, high) = (Reading::from_raw(lo, lo), Reading::from_raw(hi, hi));
+        prop_assert!(low.distance >= high.distance && low.lux <= high.lux);
+        prop_assert!((DIST_MAX_M..=DIST_MIN_M).contains(&low.distance));
+        prop_assert!((0.0..=MAX_LUX_VALUE).contains(&high.lux));
+    }
+
+    #[test]
+    fn can_frames_round_trip(
+        zone in any::<u8>(),
+        brightness in 0u8..=100,
+        manual in any::<bool>(),
+        enabled in any::<bool>(),
+        lux in any::<u16>(),
+        distance_cm in any::<u16>(),
+        value in any::<u16>(),
+    ) {
+        let status = Status { zone, brightness, manual, enabled, lux, distance_cm };
+        prop_assert_eq!(Status::decode(&status.encode()), Some(status));
+
+        for command in [
+            can_frames::Command::SetManual(manual),
+            can_frames::Command::SetLightThreshold(value),
+            can_frames::Command::SetDistanceThreshold(value),
+        ] {
+            let (data, len) = command.encode();
+            prop_assert_eq!(can_frames::Command::decode(&data[..len]), Some(command));
+            prop_assert_eq!(can_frames::Command::decode(&data[..len - 1]), None);
+        }
+    }
+
+    #[test]
+    fn lora_packets_round_trip(
+        node in 0u8..0x7F,
+        sequence in any::<u8>(),
+        manual in any::<bool>(),
+        enabled in any::<bool>(),
+        zones in prop::collection::vec(
+            (0u8..=100, any::<u16>(), any::<u16>())
+                .prop_map(|(brightness, lux, distance_cm)| ZoneSample { brightness, lux, distance_cm }),
+            0..=lora_packets::MAX_ZONES,
+        ),
+        value in any::<u16>(),
+    ) {
+        let header = Header { node, sequence, manual, enabled };
+        let (packet, len) = lora_packets::encode_uplink(header, &zones);
+        let (decoded, samples) = lora_packets::decode_uplink(&packet[..len]).unwrap();
+        prop_assert_eq!(decoded, header);
+        prop_assert!(samples.eq(zones.iter().copied()));
+        prop_assert!(lora_packets::decode_uplink(&packet[..len - 1]).is_none());
+
+        let command = can_frames::Command::SetLightThreshold(value);
+        let (packet, len) = lora_packets::encode_downlink(node, command);
+        prop_assert_eq!(lora_packets::decode_downlink(node, &packet[..len]), Some(command));
+        prop_assert_eq!(lora_packets::decode_downlink(node + 1, &packet[..len]), None);
+        let (packet, len) = lora_packets::encode_downlink(can_frames::BROADCAST_NODE, command);
+        prop_assert_eq!(lora_packets::decode_downlink(node, &packet[..len]), Some(command));
+    }
+
+    // Una carga constante estimada y medida igual concilia con los
+    // parametros del firmware; con menos de `MIN_WH` todavia no se compara
+    #[test]
+    fn energy_estimate_matches_an_exact_meter(
+        watts in 1.0f32..200.0,
+        hours in 1u64..48,
+        pulses_per_kwh in prop::sample::select(vec![800u32, 1000, 2000, 10000]),
+    ) {
+        let mut estimate = Estimate::new();
+        for _ in 0..hours {
+            estimate.add(watts, 3_600_000);
+        }
+        let pulses = (estimate.wh() * pulses_per_kwh as f32 / 1000.) as u32;
+        let metered = energy::metered_wh(pulses, pulses_per_kwh);
+        prop_assert!((metered - estimate.wh()).abs() <= 1000. / pulses_per_kwh as f32 + 0.01 * metered);
+        let reconcile = |metered| energy::reconcile(metered, estimate.wh(), MIN_WH, TOLERANCE);
+        if metered.max(estimate.wh()) < MIN_WH {
+            prop_assert_eq!(reconcile(metered), Verdict::Pending);
+        } else {
+            prop_assert_eq!(reconcile(metered), Verdict::Consistent);
+            prop_assert_eq!(reconcile(metered * 2.), Verdict::Over);
+            prop_assert_eq!(reconcile(metered / 2.), Verdict::Under);
+        }
+    }
+
+    // Un segundo de cruces a frecuencia fija se mide igual y solo el
+    // primer cambio de condicion se informa
+    #[test]
+    fn mains_frequency_and_conditions(hz in 40.0f32..70.0) {
+        let crossings = (2. * hz).round() as u32;
+        let measured = mains::frequency(crossings, 1_000_000);
+        prop_assert!((measured - hz).abs() <= 0.5);
+
+        let mut monitor = MainsMonitor::new(50., 1.);
+        let expected = monitor.classify(measured);
+        let first = monitor.update(measured);
+        prop_assert_eq!(first, (expected != Condition::Normal).then_some(expected));
+        prop_assert_eq!(monitor.update(measured), None);
+        prop_assert_eq!(monitor.dropout(), Some(Condition::Dropout));
+        prop_assert_eq!(monitor.update(measured), Some(expected));
+    }
+
+    // El nodo se apaga solo tras `confirm` lecturas seguidas bajo el
+    // voltaje critico y vuelve solo por encima del de recuperacion
+    #[test]
+    fn battery_monitor_has_hysteresis(
+        voltages in prop::collection::vec(10.0f32..14.0, 1..200),
+        confirm in 1u8..5,
+    ) {
+        let thresholds = battery::Thresholds { critical_v: 11.5, recover_v: 12.4 };
+        let mut monitor = BatteryMonitor::new(thresholds, confirm, false);
+        let mut below = 0;
+        for voltage in voltages {
+            let was_low = monitor.is_low();
+            below = if voltage < thresholds.critical_v { below + 1 } else { 0 };
+            let event = monitor.update(voltage);
+            let expected = match was_low {
+                true => (voltage >= thresholds.recover_v).then_some(battery::Event::Recovered),
+                false => (below >= confirm).then_some(battery::Event::Critical),
+            };
+            prop_assert_eq!(event, expected);
+            if event.is_some() {
+                below = 0;
+            }
+        }
+    }
+
+    #[test]
+    fn stack_peak_ends_at_the_first_overwritten_word(
+        words in 1usize..512,
+        used in 0usize..512,
+        garbage in any::<u32>().prop_filter("no es el patron", |&w| w != stack::PAINT),
+    ) {
+        let used = used.min(words);
+        let mut stack = vec![stack::PAINT; words];
+        for word in &mut stack[words - used..] {
+            *word = garbage;
+        }
+        // Una palabra con el patron dentro de la parte usada no cuenta
+        if used > 2 {
+            stack[words - 2] = stack::PAINT;
+        }
+        let usage = MemoryUsage::measure(1000, words as u32 * 4, stack);
+        prop_assert_eq!(usage.stack_peak, used as u32 * 4);
+        prop_assert_eq!(usage.headroom(), (words - used) as u32 * 4);
+    }
+
+    #[test]
+    fn nrf24_samples_round_trip(
+        node in any::<u8>(),
+        sequence in any::<u8>(),
+        zone in any::<u8>(),
+        raw_distance in 0u16..4096,
+        raw_light in 0u16..4096,
+        gap in 0u8..=255,
+    ) {
+        let sample = RadioSample { node, sequence, zone, raw_distance, raw_light };
+        prop_assert_eq!(RadioSample::decode(&sample.encode()), Some(sample));
+        prop_assert_eq!(RadioSample::decode(&sample.encode()[..nrf24_packets::PAYLOAD - 1]), None);
+        prop_assert_eq!(nrf24_packets::lost(sequence, sequence.wrapping_add(gap).wrapping_add(1)), gap);
+    }
+
+    // Cada canal valido ocupa su propio byte despues del codigo de inicio
+    #[test]
+    fn dmx_channels_land_in_their_slot(channel in 0u16..600, value in any::<u8>(), duty in -1.0f32..2.0) {
+        let mut universe = Universe::new();
+        let valid = (1..=dmx::SLOTS as u16).contains(&channel);
+        prop_assert_eq!(universe.set(channel, value), valid);
+        prop_assert_eq!(universe.get(channel), valid.then_some(value));
+        prop_assert_eq!(universe.frame()[0], dmx::START_CODE);
+        let set = universe.frame()[1..].iter().filter(|&&slot| slot != 0).count();
+        prop_assert_eq!(set, usize::from(valid && value != 0));
+
+        let level = dmx::level(duty);
+        prop_assert!(level == 0 || duty > 0.);
+        prop_assert!(level == 255 || duty < 1.);
+    }
+
+    // Cada minuto ocupa una columna, alineada a la derecha, y el maximo
+    // llena la altura
+    #[test]
+    fn sparkline_columns_follow_the_history(
+        minutes in prop::collection::vec(0.0f32..2000.0, 0..2 * MINUTES),
+        fraction in 0.0f32..=1.0,
+    ) {
+        let mut history = LuxHistory::new();
+        for &lux in &minutes {
+            history.add(lux);
+            history.close_minute();
+        }
+        prop_assert_eq!(history.len(), minutes.len().min(MINUTES));
+
+        let height = 16;
+        let mut frame = Framebuffer::new();
+        draw_sparkline(&mut frame, 0, 0, height, &history);
+        let column_height = |x| (0..height).filter(|&y| frame.get(x, y)).count();
+        let empty = MINUTES - history.len();
+        prop_assert!((0..empty).all(|x| column_height(x) == 0));
+        prop_assert!((empty..MINUTES).all(|x| column_height(x) >= 1));
+        if !history.is_empty() {
+            prop_assert!((empty..MINUTES).any(|x| column_height(x) == height));
+        }
+
+        let mut frame = Framebuffer::new();
+        draw_bar(&mut frame, 0, 20, WIDTH, 6, fraction);
+        let filled = (1..WIDTH - 1).filter(|&x| frame.get(x, 22)).count();
+        prop_assert_eq!(filled, (fraction * (WIDTH - 2) as f32 + 0.5) as usize);
+    }
+
+    #[test]
+    fn units_round_trip(meters in 0.0f32..10.0) {
+        for units in [Units::Metric, Units::Imperial] {
+            prop_assert!((units.meters(units.distance(meters)) - meters).abs() < 1e-4);
+        }
+        prop_assert!(Units::Imperial.distance(meters) >= meters);
+    }
+
+    // Los umbrales leidos del mapa se pueden volver a escribir tal cual
+    #[test]
+    fn i2c_thresholds_round_trip(
+        zone in 0..i2c_registers::MAX_ZONES,
+        light_threshold in any::<u16>(),
+        distance_threshold_cm in any::<u16>(),
+    ) {
+        let mut zones = [ZoneRegisters::default(); i2c_registers::MAX_ZONES];
+        zones[zone] = ZoneRegisters { light_threshold, distance_threshold_cm, ..Default::default() };
+        let map = i2c_registers::image(false, true, 0, &zones);
+        prop_assert_eq!(map.len(), MAP_SIZE);
+
+        let base = 0x10 + 0x10 * zone;
+        prop_assert_eq!(
+            decode_write((base + 6) as u8, &map[base + 6..base + 8]),
+            Some(Write::LightThreshold { zone, lux: light_threshold })
+        );
+        prop_assert_eq!(
+            decode_write((base + 8) as u8, &map[base + 8..]),
+            Some(Write::DistanceThreshold { zone, cm: distance_threshold_cm })
+        );
+        prop_assert_eq!(decode_write((base + 6) as u8, &map[base + 6..base + 7]), None);
+        prop_assert_eq!(decode_write((base + 2) as u8, &map[base + 2..base + 4]), None);
+    }
+
+    #[test]
+    fn distance_to_voltage_inverts_the_conversion(distance in DIST_MAX_M..=DIST_MIN_M) {
+        let back = voltage_to_distance(distance_to_voltage(distance));
+        prop_assert!((back - distance).abs() < 1e-4);
+    }
+
+    // Cada modelo convierte en los dos sentidos dentro de su rango, y su
+    // umbral por defecto cae dentro del rango
+    #[test]
+    fn sensor_profiles_invert(
+        model in 0usize..DistanceModel::ALL.len() + LightModel::ALL.len(),
+        fraction in 0.0f32..=1.,
+    ) {
+        let profile = match model.checked_sub(DistanceModel::ALL.len()) {
+            None => DistanceModel::ALL[model].profile(),
+            Some(light) => LightModel::ALL[light].profile(),
+        };
+        let (lo, hi) = if profile.at_min_v <= profile.at_max_v {
+            (profile.at_min_v, profile.at_max_v)
+        } else {
+            (profile.at_max_v, profile.at_min_v)
+        };
+        let value = lo + (hi - lo) * fraction;
+        prop_assert!((profile.value(profile.voltage(value)) - value).abs() < 1e-3 * hi);
+        prop_assert!((lo..=hi).contains(&profile.threshold));
+    }
+
+    #[test]
+    fn falling_lux_module_mirrors_the_rising_one(raw_distance in 0u16..=4095, a in 0u16..=4095, b in 0u16..=4095) {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        let falling = |raw| Reading::from_raw_oriented(raw_distance, raw, LuxPolarity::Falling);
+        prop_assert!(falling(lo).lux >= falling(hi).lux);
+        let reading = falling(a);
+        prop_assert_eq!(reading.lux, voltage_to_lux(LUX_MIN_V + LUX_MAX_V - reading.lux_voltage));
+        prop_assert_eq!(reading.distance, Reading::from_raw(raw_distance, a).distance);
+    }
+
+    // El umbral del watchdog del ADC cae a menos de una cuenta del umbral
+    #[test]
+    fn lux_to_adc_inverts_the_conversion(lux in 0.0f32..=MAX_LUX_VALUE) {
+        let step = MAX_LUX_VALUE / ((LUX_MAX_V - LUX_MIN_V) / VOLTAGE_REF * MAX_ADC_VALUE);
+        let back = voltage_to_lux(get_voltage(lux_to_adc(lux) as f32));
+        prop_assert!((back - lux).abs() <= step);
+    }
+
+    // Con la alimentacion medida por VREFINT una lectura corregida da el
+    // voltaje real del pin, aunque la fuente no sea de 3.3 V
+    #[test]
+    fn supply_correction_recovers_the_pin_voltage(supply in 3.0f32..3.6, pin in 0.0f32..3.0) {
+        let raw_at = |volts: f32| (volts / supply * MAX_ADC_VALUE).round() as u16;
+        let measured = supply_from_vrefint(raw_at(VREFINT));
+        prop_assert!((measured - supply).abs() < 0.01);
+
+        let corrected = correct_for_supply(raw_at(pin), measured);
+        prop_assert!((get_voltage(corrected as f32) - pin).abs() < 0.01);
+    }
+
+    #[test]
+    fn decision_matches_thresholds(
+        raw_distance in 0u16..=4095,
+        raw_lux in 0u16..=4095,
+        light in 0.0f32..MAX_LUX_VALUE,
+        distance in DIST_MAX_M..DIST_MIN_M,
+    ) {
+        let reading = Reading::from_raw(raw_distance, raw_lux);
+        let decision = decide(&reading, &Thresholds { light, distance });
+
+        prop_assert_eq!(decision.dark, reading.lux < light);
+        prop_assert_eq!(decision.present, reading.distance < distance);
+        prop_assert_eq!(decision.light_on, decision.dark && decision.present);
+    }
+
+    #[test]
+    fn regulator_output_is_bounded(
+        setpoint in 0.0f32..MAX_LUX_VALUE,
+        gain in 0.0f32..1.0,
+        inputs in prop::collection::vec(0.0f32..MAX_LUX_VALUE, 1..200),
+    ) {
+        let mut regulator = LuxRegulator::new(setpoint, gain);
+        for lux in inputs {
+            prop_assert!(regulator.update(lux) <= 100);
+        }
+    }
+
+    // Con una entrada constante el brillo solo se mueve en una direccion
+    #[test]
+    fn regulator_never_oscillates_for_constant_input(
+        setpoint in 0.0f32..MAX_LUX_VALUE,
+        gain in 0.0f32..1.0,
+        lux in 0.0f32..MAX_LUX_VALUE,
+    ) {
+        let mut regulator = LuxRegulator::new(setpoint, gain);
+        let outputs: Vec<u8> = (0..100).map(|_| regulator.update(lux)).collect();
+
+        let rising = outputs.windows(2).all(|w| w[0] <= w[1]);
+        let falling = outputs.windows(2).all(|w| w[0] >= w[1]);
+        prop_assert!(rising || falling, "{:?}", outputs);
+    }
+
+    #[test]
+    fn duty_is_monotonic(a in -10.0f32..110.0, b in -10.0f32..110.0) {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        prop_assert!(duty_fraction(lo) <= duty_fraction(hi));
+    }
+
+    // La correccion nunca da mas ciclo de trabajo que el brillo lineal
+    #[test]
+    fn duty_is_within_range(level in 0.0f32..=100.0) {
+        let duty = duty_fraction(level);
+        prop_assert!((0.0..=1.0).contains(&duty));
+        prop_assert!(duty <= level / 100. + 1e-6);
+    }
+
+    // Con piso la salida es 0 o al menos el piso, sin cambiar lo demas
+    #[test]
+    fn floor_snaps_to_off(level in 0.0f32..=100.0, floor in 0.0f32..0.2) {
+        let duty = duty_fraction(level);
+        let floored = apply_floor(duty, floor);
+        prop_assert!(floored == 0. || (floored >= floor && floored == duty));
+    }
+
+    // Un percentil mayor nunca da un umbral de oscuridad menor
+    #[test]
+    fn dark_threshold_grows_with_percentile(
+        samples in prop::collection::vec(0.0f32..7000.0, MIN_SAMPLES as usize),
+        a in 0u8..=100,
+        b in 0u8..=100,
+    ) {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        let mut low = AmbientLearner::new(lo);
+        let mut high = AmbientLearner::new(hi);
+        for &lux in &samples {
+            low.record(lux);
+            high.record(lux);
+        }
+        prop_assert!(low.dark_threshold() <= high.dark_threshold());
+    }
+
+    // Las reglas estandar reproducen la decision fija
+    #[test]
+    fn standard_rules_match_decision(
+        lux in 0.0f32..6000.0,
+        distance in 1.0f32..5.5,
+        occupied: bool,
+        light in 0.0f32..6000.0,
+    ) {
+        let thresholds = Thresholds { light, distance: 2.5 };
+        let rules = RuleSet::standard(100, 20);
+        let inputs = Inputs { lux, distance, occupied, outdoor: None };
+
+        let dark = lux < light;
+        let expected = if dark && occupied { 100 } else if dark { 20 } else { 0 };
+        prop_assert_eq!(rules.evaluate(&inputs, &thresholds), expected);
+    }
+
+    // Con la condicion de luz exterior solo se enciende si tambien esta
+    // oscuro afuera; sin sensor exterior nunca
+    #[test]
+    fn outdoor_rules_need_dark_outside(
+        lux in 0.0f32..6000.0,
+        outdoor in prop::option::of(0.0f32..6000.0),
+        occupied: bool,
+    ) {
+        let thresholds = Thresholds::default();
+        let standard = RuleSet::standard(100, 20);
+        let rules = standard.with_outdoor(200.);
+        let inputs = Inputs { lux, distance: 4., occupied, outdoor };
+
+        let expected = match outdoor {
+            Some(outdoor) if outdoor < 200. => standard.evaluate(&inputs, &thresholds),
+            _ => 0,
+        };
+        prop_assert_eq!(rules.evaluate(&inputs, &thresholds), expected);
+    }
+
+    // Con el sensor a cualquier distancia de la pared, alguien que pasa
+    // a medio camino se detecta y la pared sola no
+    #[test]
+    fn background_detects_passers_at_any_wall(wall in 1.5f32..5.5, noise in 0.0f32..0.1) {
+        let mut background = Background::new(0.4);
+        for i in 0..200 {
+            let jitter = if i % 2 == 0 { noise } else { -noise };
+            prop_assert_ne!(background.update(wall + jitter), Some(true));
+        }
+        prop_assert!((background.baseline().unwrap() - wall).abs() < 0.1);
+
+        for _ in 0..20 {
+            prop_assert_eq!(background.update(wall / 2.), Some(true));
+        }
+        prop_assert_eq!(background.update(wall), Some(false));
+    }
+
+    #[test]
+    fn latency_summary_is_ordered(samples in prop::collection::vec(any::<u32>(), 1..200)) {
+        let mut window = LatencyWindow::new();
+        for &latency in &samples {
+            window.record(latency);
+        }
+
+        let summary = window.take().unwrap();
+        prop_assert!(summary.min_us <= summary.mean_us && summary.mean_us <= summary.max_us);
+        prop_assert_eq!(summary.count as usize, samples.len());
+        // Cada ventana empieza vacia
+        prop_assert_eq!(window.take(), None);
+    }
+
+    // El periodo solo se mide entre vueltas seguidas: una pausa (`idle`)
+    // no cuenta como jitter
+    #[test]
+    fn loop_timing_skips_idle_pauses(
+        laps in prop::collection::vec((1u64..1_000_000, 0u64..1_000, any::<bool>()), 1..100),
+    ) {
+        let mut timing = LoopTiming::new();
+        let mut now = 0;
+        let mut periods = Vec::new();
+        let mut last = None;
+        for &(gap, duration, idle) in &laps {
+            if idle {
+                timing.idle();
+                last = None;
+            }
+            now += gap;
+            timing.record(now, now + duration);
+            if let Some(last) = last {
+                periods.push(now - last);
+            }
+            last = Some(now);
+            now += duration;
+        }
+
+        let summary = timing.take().unwrap();
+        prop_assert_eq!(summary.duration.count as usize, laps.len());
+        match summary.period {
+            None => prop_assert!(periods.is_empty()),
+            Some(period) => {
+                prop_assert_eq!(period.count as usize, periods.len());
+                prop_assert_eq!(period.min_us as u64, *periods.iter().min().unwrap());
+                prop_assert_eq!(period.max_us as u64, *periods.iter().max().unwrap());
+                prop_assert_eq!(summary.jitter_us(), period.max_us - period.min_us);
+            }
+        }
+        prop_assert_eq!(timing.take(), None);
+    }
+
+    // El anillo guarda los ultimos N en orden; tras el disparo guarda
+    // `after` mas y deja de cambiar
+    #[test]
+    fn recorder_keeps_the_history_before_a_fault(
+        before in proptest::collection::vec(any::<u16>(), 0..40),
+        after in 0usize..12,
+        later in proptest::collection::vec(any::<u16>(), 0..40),
+    ) {
+        const N: usize = 16;
+        let mut recorder = Recorder::<u16, N>::new(0);
+        for &item in &before {
+            prop_assert!(!recorder.push(item));
+        }
+        let last: Vec<u16> = before[before.len().saturating_sub(N)..].to_vec();
+        prop_assert_eq!(recorder.iter().collect::<Vec<_>>(), last);
+
+        recorder.trigger(after);
+        let mut kept = before.clone();
+        let mut completions = 0;
+        for &item in &later {
+            if recorder.push(item) {
+                completions += 1;
+            }
+            if kept.len() < before.len() + after {
+                kept.push(item);
+            }
+        }
+        prop_assert_eq!(recorder.is_frozen(), later.len() >= after);
+        prop_assert_eq!(completions, usize::from(after > 0 && later.len() >= after));
+        let expected: Vec<u16> = kept[kept.len().saturating_sub(N)..].to_vec();
+        prop_assert_eq!(recorder.iter().collect::<Vec<_>>(), expected);
+    }
+
+    // Welford da el minimo, el maximo, la media y la varianza de la formula
+    // directa, y juntar dos grupos es lo mismo que acumularlos seguidos
+    #[test]
+    fn stats_match_the_direct_formulas(
+        values in proptest::collection::vec(0.0f32..6000., 1..200),
+        split in any::<prop::sample::Index>(),
+    ) {
+        let mut whole = Accumulator::new();
+        for &value in &values {
+            whole.add(value);
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().map(|&v| v as f64).sum::<f64>() / n;
+        let variance = values.iter().map(|&v| (v as f64 - mean).powi(2)).sum::<f64>() / n;
+        let summary = whole.summary();
+        prop_assert_eq!(summary.count as usize, values.len());
+        prop_assert_eq!(summary.min, values.iter().copied().fold(f32::INFINITY, f32::min));
+        prop_assert_eq!(summary.max, values.iter().copied().fold(0., f32::max));
+        prop_assert!((summary.mean as f64 - mean).abs() <= 1e-3 * mean.max(1.));
+        prop_assert!((summary.variance as f64 - variance).abs() <= 1e-3 * variance.max(1.));
+
+        let (first, second) = values.split_at(split.index(values.len()));
+        let mut merged = Accumulator::new();
+        let mut rest = Accumulator::new();
+        first.iter().for_each(|&v| merged.add(v));
+        second.iter().for_each(|&v| rest.add(v));
+        merged.merge(&rest);
+        let merged = merged.summary();
+        prop_assert_eq!(merged.count, summary.count);
+        prop_assert_eq!((merged.min, merged.max), (summary.min, summary.max));
+        prop_assert!((merged.mean - summary.mean).abs() <= 1e-3 * summary.mean.max(1.));
+        prop_assert!((merged.variance - summary.variance).abs() <= 1e-3 * summary.variance.max(1.));
+    }
+
+    // Cualquier fila cabe, tiene todas las columnas del encabezado y la luz
+    // se lee de vuelta con un decimal
+    #[test]
+    fn csv_rows_fit_and_parse(
+        uptime_s in any::<u32>(),
+        seconds in proptest::option::of(0u32..86_400),
+        zone in any::<u8>(),
+        lux in proptest::option::of(0f32..100_000.),
+        distance in proptest::option::of(0f32..10.),
+        flags in any::<[bool; 4]>(),
+    ) {
+        let row = csv_log::Row {
+            uptime_s,
+            time: seconds.map(TimeOfDay::from_seconds),
+            zone,
+            lux,
+            distance,
+            occupied: flags[0],
+            light_on: flags[1],
+            manual: flags[2],
+            enabled: flags[3],
+        };
+        let mut out = [0; csv_log::MAX_ROW];
+        let len = csv_log::format(&row, &mut out);
+        prop_assert!(len < csv_log::MAX_ROW);
+        let text = core::str::from_utf8(&out[..len]).unwrap();
+        let line = text.strip_suffix("\r\n").unwrap();
+        let header = core::str::from_utf8(csv_log::HEADER).unwrap();
+        let fields: Vec<&str> = line.split(',').collect();
+        prop_assert_eq!(fields.len(), header.split(',').count());
+        prop_assert_eq!(fields[0].parse::<u32>().unwrap(), uptime_s);
+        prop_assert_eq!(fields[2].parse::<u8>().unwrap(), zone);
+        if let Some(lux) = lux {
+            let read: f32 = fields[3].parse().unwrap();
+            prop_assert!((read - lux).abs() <= 0.05 + lux * 1e-6);
+        } else {
+            prop_assert_eq!(fields[3], "");
+        }
+    }
+}
@@ -0,0 +1,678 @@
+// Pruebas con tiempo virtual: el reloj se avanza a mano, de modo que el
+// antirrebote, los gestos y los periodos se verifican sin esperas reales.
+
+use sie_core::{
+    background::Presence,
+    button::{Gesture, Press, Timing},
+    clock::{Clock, VirtualClock},
+    control::{Reading, Thresholds},
+    ds3231,
+    histogram::DistanceHistogram,
+    occupancy::{
+        AdaptiveTimeout, FixedTimeout, Occupancy, ScheduledTimeout, Timeout, TimeoutPolicy,
+    },
+    on_limit::OnTimeLimit,
+    regulator::LuxRegulator,
+    report::ConsistencyReport,
+    rules::RuleSet,
+    schedule::{Schedule, TimeOfDay},
+    screensaver::{self, Screen, Screensaver},
+    sensor::{DistanceModel, LightModel, LuxPolarity},
+    sim::Scenario,
+    stats::{Summary, ZoneStats},
+    status::Pattern,
+    supervisor::Supervisor,
+    trial::{ConfigTrial, Fault, Outcome},
+    zone::{Limits, ZoneLogic},
+};
+
+const TIMING: Timing = Timing {
+    settle_ms: 50,
+    long_press_ms: 2000,
+    click_window_ms: 400,
+};
+
+// Simula el bucle del firmware: mantiene el nivel indicado durante `ms`,
+// despertando en cada plazo que pida la maquina de estados
+fn hold(
+    clock: &VirtualClock,
+    gesture: &mut Gesture<&VirtualClock>,
+    pressed: bool,
+    ms: u64,
+) -> Vec<Press> {
+    let end = clock.now_ms() + ms;
+    let mut presses = Vec::new();
+
+    presses.extend(gesture.update(pressed));
+    while let Some(deadline) = gesture.next_deadline() {
+        if deadline > end {
+            break;
+        }
+        clock.set(deadline);
+        presses.extend(gesture.update(pressed));
+    }
+    clock.set(end);
+    presses.extend(gesture.update(pressed));
+
+    presses
+}
+
+fn click(clock: &VirtualClock, gesture: &mut Gesture<&VirtualClock>) -> Vec<Press> {
+    let mut presses = hold(clock, gesture, true, 100);
+    presses.extend(hold(clock, gesture, false, 100));
+    presses
+}
+
+#[test]
+fn single_click_waits_for_the_window() {
+    let clock = VirtualClock::new();
+    let mut gesture = Gesture::new(&clock, TIMING);
+
+    assert!(click(&clock, &mut gesture).is_empty());
+    assert_eq!(hold(&clock, &mut gesture, false, 400), [Press::Single]);
+}
+
+#[test]
+fn zero_window_reports_on_release() {
+    let clock = VirtualClock::new();
+    let mut gesture = Gesture::new(
+        &clock,
+        Timing {
+            click_window_ms: 0,
+            ..TIMING
+        },
+    );
+
+    assert_eq!(click(&clock, &mut gesture), [Press::Single]);
+}
+
+#[test]
+fn double_and_triple_clicks() {
+    let clock = VirtualClock::new();
+    let mut gesture = Gesture::new(&clock, TIMING);
+
+    click(&clock, &mut gesture);
+    click(&clock, &mut gesture);
+    assert_eq!(hold(&clock, &mut gesture, false, 1000), [Press::Double]);
+
+    click(&clock, &mut gesture);
+    click(&clock, &mut gesture);
+    assert_eq!(click(&clock, &mut gesture), [Press::Triple]);
+}
+
+#[test]
+fn bounces_shorter_than_settle_are_ignored() {
+    let clock = VirtualClock::new();
+    let mut gesture = Gesture::new(&clock, TIMING);
+
+    for _ in 0..10 {
+        hold(&clock, &mut gesture, true, 5);
+        hold(&clock, &mut gesture, false, 5);
+    }
+    assert!(hold(&clock, &mut gesture, false, 1000).is_empty());
+}
+
+#[test]
+fn bouncy_click_counts_once() {
+    let clock = VirtualClock::new();
+    let mut gesture = Gesture::new(&clock, TIMING);
+
+    // Rebotes al presionar y al soltar
+    for _ in 0..5 {
+        hold(&clock, &mut gesture, true, 3);
+        hold(&clock, &mut gesture, false, 3);
+    }
+    hold(&clock, &mut gesture, true, 150);
+    for _ in 0..5 {
+        hold(&clock, &mut gesture, false, 3);
+        hold(&clock, &mut gesture, true, 3);
+    }
+    assert_eq!(hold(&clock, &mut gesture, false, 1000), [Press::Single]);
+}
+
+#[test]
+fn long_press_is_reported_while_held() {
+    let clock = VirtualClock::new();
+    let mut gesture = Gesture::new(&clock, TIMING);
+
+    assert_eq!(hold(&clock, &mut gesture, true, 2100), [Press::Long]);
+    // Soltar despues de una pulsacion larga no genera un clic
+    assert!(hold(&clock, &mut gesture, false, 1000).is_empty());
+}
+
+#[test]
+fn report_rolls_over_after_the_period() {
+    let clock = VirtualClock::new();
+    let mut report = ConsistencyReport::new(&clock, 10_000);
+
+    for _ in 0..50 {
+        clock.advance(100);
+        assert_eq!(report.record(true, Some(true), Some(true)), None);
+    }
+    for _ in 0..49 {
+        clock.advance(100);
+        assert_eq!(report.record(false, Some(false), Some(false)), None);
+    }
+
+    clock.advance(100);
+    let summary = report.record(false, Some(false), Some(false)).unwrap();
+    assert_eq!(summary.lamp_on_ms, 5000);
+    assert_eq!(summary.expected_on_ms, 5000);
+    assert_eq!(summary.activations, 1);
+    assert_eq!(summary.unexplained, 0);
+    assert_eq!(report.summary().lamp_on_ms, 0);
+}
+
+#[test]
+fn manual_activation_is_unexplained() {
+    let clock = VirtualClock::new();
+    let mut report = ConsistencyReport::new(&clock, 10_000);
+
+    clock.advance(100);
+    report.record(true, None, None);
+    clock.advance(100);
+    report.record(true, None, None);
+
+    let summary = report.summary();
+    assert_eq!(summary.activations, 1);
+    assert_eq!(summary.unexplained, 1);
+    assert_eq!(summary.deviation_ms(), 200);
+}
+
+#[test]
+fn report_tracks_longest_on_period_and_faults() {
+    let clock = VirtualClock::new();
+    let mut report = ConsistencyReport::new(&clock, 100_000);
+
+    for (on, ms) in [
+        (true, 300),
+        (false, 200),
+        (true, 700),
+        (false, 100),
+        (true, 200),
+    ] {
+        for _ in 0..ms / 100 {
+            clock.advance(100);
+            report.record(on, Some(on), Some(true));
+        }
+    }
+    report.fault();
+
+    let summary = report.summary();
+    assert_eq!(summary.activations, 3);
+    assert_eq!(summary.lamp_on_ms, 1200);
+    assert_eq!(summary.longest_on_ms, 700);
+    assert_eq!(summary.faults, 1);
+}
+
+#[test]
+fn report_rolls_over_at_the_time_of_day() {
+    let clock = VirtualClock::new();
+    let mut report = ConsistencyReport::new(&clock, 10_000).with_rollover(TimeOfDay::hm(0, 0));
+
+    // Cerca de la medianoche, con el periodo ya cumplido varias veces
+    let mut time = TimeOfDay::hm(23, 59).seconds();
+    for _ in 0..59 {
+        clock.advance(1000);
+        time += 1;
+        let now = TimeOfDay::from_seconds(time);
+        assert_eq!(report.record_at(true, Some(true), None, Some(now)), None);
+    }
+
+    clock.advance(1000);
+    let midnight = TimeOfDay::from_seconds(time + 1);
+    let summary = report.record_at(true, Some(true), None, Some(midnight));
+    assert_eq!(summary.unwrap().lamp_on_ms, 60_000);
+}
+
+#[test]
+fn pulse_code_repeats_after_a_pause() {
+    // Flancos de subida en un ciclo completo del codigo
+    let rising = |pattern: Pattern, ms: u64| {
+        (1..ms)
+            .filter(|&t| pattern.level(t) && !pattern.level(t - 1))
+            .count()
+            + pattern.level(0) as usize
+    };
+
+    // 3 pulsos de 400 ms y 1.2 s de pausa
+    assert_eq!(rising(Pattern::Pulses(3), 2400), 3);
+    assert!(!Pattern::Pulses(3).level(2000));
+    assert!(Pattern::Pulses(3).level(2400));
+    assert_eq!(rising(Pattern::Pulses(0), 2400), 0);
+}
+
+#[test]
+fn wink_is_mostly_on() {
+    let on = (0..1000).filter(|&t| Pattern::Wink.level(t)).count();
+    assert_eq!(on, 800);
+    assert!(!Pattern::Wink.level(0));
+    assert!(Pattern::Wink.level(100));
+}
+
+#[test]
+fn overnight_schedule_wraps_midnight() {
+    let schedule = Schedule {
+        start: TimeOfDay::hm(19, 0),
+        end: TimeOfDay::hm(7, 0),
+    };
+
+    assert!(schedule.is_active(TimeOfDay::hm(19, 0)));
+    assert!(schedule.is_active(TimeOfDay::hm(23, 59)));
+    assert!(schedule.is_active(TimeOfDay::hm(0, 0)));
+    assert!(!schedule.is_active(TimeOfDay::hm(7, 0)));
+    assert!(!schedule.is_active(TimeOfDay::hm(12, 0)));
+}
+
+#[test]
+fn time_of_day_parsing() {
+    assert_eq!(TimeOfDay::parse("19:30"), Some(TimeOfDay::hm(19, 30)));
+    assert_eq!(
+        TimeOfDay::parse(" 07:05:30 ").map(TimeOfDay::seconds),
+        Some(25530)
+    );
+    assert_eq!(TimeOfDay::parse("24:00"), None);
+    assert_eq!(TimeOfDay::parse("12:60"), None);
+    assert_eq!(TimeOfDay::parse("12"), None);
+    assert_eq!(TimeOfDay::parse("1:2:3:4"), None);
+}
+
+#[test]
+fn ds3231_time_registers() {
+    let time = TimeOfDay::parse("19:05:42").unwrap();
+    assert_eq!(ds3231::encode_time(time), [0x42, 0x05, 0x19]);
+    assert_eq!(ds3231::decode_time([0x42, 0x05, 0x19]), Some(time));
+
+    // Formato de 12 horas: 7 PM y 12 AM
+    assert_eq!(
+        ds3231::decode_time([0x42, 0x05, 0x40 | 0x20 | 0x07]),
+        Some(time)
+    );
+    assert_eq!(
+        ds3231::decode_time([0x00, 0x00, 0x40 | 0x12]),
+        Some(TimeOfDay::hm(0, 0))
+    );
+
+    // BCD invalido
+    assert_eq!(ds3231::decode_time([0x5A, 0x00, 0x00]), None);
+    assert_eq!(ds3231::decode_time([0x00, 0x00, 0x24]), None);
+}
+
+#[test]
+fn on_limit_needs_new_motion_after_cooldown() {
+    let clock = VirtualClock::new();
+    let mut limit = OnTimeLimit::new(&clock, 10_000, 2_000);
+
+    // Presencia continua: se apaga al cumplir el maximo
+    assert!(!limit.update(true, true));
+    for _ in 0..99 {
+        clock.advance(100);
+        assert!(!limit.update(true, true));
+    }
+    clock.advance(100);
+    assert!(limit.update(true, true));
+
+    // Sigue detectando la pared: no vuelve a encender
+    clock.advance(5_000);
+    assert!(limit.update(false, true));
+
+    // Nadie y luego movimiento: vuelve a encender
+    assert!(limit.update(false, false));
+    clock.advance(100);
+    assert!(!limit.update(false, true));
+    assert!(!limit.is_locked());
+}
+
+#[test]
+fn on_limit_waits_for_cooldown_or_release() {
+    let clock = VirtualClock::new();
+    let mut limit = OnTimeLimit::new(&clock, 1_000, 2_000);
+
+    limit.update(true, true);
+    clock.advance(1_000);
+    assert!(limit.update(true, true));
+
+    // Movimiento dentro del enfriamiento no cuenta
+    clock.advance(500);
+    limit.update(false, false);
+    assert!(limit.update(false, true));
+
+    clock.advance(1_500);
+    assert!(!limit.update(false, true));
+
+    // La pulsacion manual libera en cualquier momento
+    limit.update(true, true);
+    clock.advance(1_000);
+    assert!(limit.update(true, true));
+    limit.release();
+    assert!(!limit.update(true, true));
+}
+
+#[test]
+fn zone_logic_holds_the_lamp_and_applies_the_limit() {
+    let clock = VirtualClock::new();
+    let mut logic = ZoneLogic::new(
+        &clock,
+        Timeout::Fixed(FixedTimeout { hold_ms: 5_000 }),
+        Presence::Threshold,
+        LuxRegulator::new(300., 0.1),
+        Limits {
+            max_on_ms: 60_000,
+            cooldown_ms: 2_000,
+        },
+    );
+    let thresholds = Thresholds::default();
+    let rules = RuleSet::standard(100, 0);
+    let reading = |distance, lux| Reading {
+        distance_voltage: 0.,
+        lux_voltage: 0.,
+        distance,
+        lux,
+    };
+    let mut step = |distance| {
+        logic.step(
+            &reading(distance, 10.),
+            &thresholds,
+            &rules,
+            None,
+            false,
+            None,
+        )
+    };
+
+    // Oscuro y alguien cerca: enciende
+    let on = step(1.);
+    assert!(on.decision.light_on && on.occupied);
+    assert_eq!(on.brightness, 100);
+
+    // Se aleja: sigue encendida durante la espera
+    clock.advance(4_900);
+    assert_eq!(step(4.).brightness, 100);
+    clock.advance(100);
+    let off = step(4.);
+    assert!(!off.occupied);
+    assert_eq!(off.brightness, 0);
+
+    // Presencia continua: el limite apaga y se avisa una sola vez
+    assert_eq!(step(1.).brightness, 100);
+    clock.advance(60_000);
+    let limited = step(1.);
+    assert!(limited.limit_reached && limited.light_on);
+    assert_eq!(limited.brightness, 0);
+    clock.advance(100);
+    assert!(!step(1.).limit_reached);
+}
+
+#[test]
+fn simulated_day_lights_only_the_night_visits() {
+    let clock = VirtualClock::new();
+    // Dia de 10 minutos; visitas cada 2 minutos, tres de ellas de noche
+    let mut scenario = Scenario::new(600_000, 120_000, 0);
+    let mut logic = ZoneLogic::new(
+        &clock,
+        Timeout::Fixed(FixedTimeout { hold_ms: 5_000 }),
+        Presence::Threshold,
+        LuxRegulator::new(300., 0.1),
+        Limits {
+            max_on_ms: 600_000,
+            cooldown_ms: 2_000,
+        },
+    );
+    let (distance_model, light_model) = (DistanceModel::Gp2y0a710, LightModel::Ldr);
+    let (distance, light) = (distance_model.profile(), light_model.profile());
+    let thresholds = Thresholds::for_models(distance_model, light_model);
+    let rules = RuleSet::standard(100, 0);
+
+    let mut switched_on = 0;
+    let mut was_on = false;
+    while clock.now_ms() < 600_000 {
+        let (raw_distance, raw_lux) =
+            scenario.sample(clock.now_ms(), &distance, &light, LuxPolarity::Falling);
+        let reading = Reading::from_raw_profiled(
+            raw_distance,
+            raw_lux,
+            &distance,
+            &light,
+            LuxPolarity::Falling,
+        );
+        let step = logic.step(&reading, &thresholds, &rules, None, false, None);
+
+        let on = step.brightness > 0;
+        assert!(
+            !on || step.decision.dark,
+            "encendida de dia a {} ms",
+            clock.now_ms()
+        );
+        switched_on += (on && !was_on) as u32;
+        was_on = on;
+        clock.advance(100);
+    }
+    assert_eq!(switched_on, 3);
+}
+
+#[test]
+fn occupancy_holds_after_presence_ends() {
+    let clock = VirtualClock::new();
+    let mut occupancy = Occupancy::new(&clock, FixedTimeout { hold_ms: 5_000 });
+
+    assert!(!occupancy.update(false, None));
+    assert!(occupancy.update(true, None));
+    clock.advance(4_900);
+    assert!(occupancy.update(false, None));
+    clock.advance(100);
+    assert!(!occupancy.update(false, None));
+}
+
+#[test]
+fn adaptive_timeout_learns_pass_through_time() {
+    let clock = VirtualClock::new();
+    let mut occupancy = Occupancy::new(&clock, AdaptiveTimeout::new(60_000, 10_000, 300_000));
+    assert_eq!(occupancy.policy().hold_ms(None), 60_000);
+
+    // Pasos cortos de 3 s: la espera baja hasta el minimo
+    for _ in 0..100 {
+        occupancy.update(true, None);
+        clock.advance(3_000);
+        occupancy.update(false, None);
+        clock.advance(60_000);
+    }
+    assert_eq!(occupancy.policy().hold_ms(None), 10_000);
+
+    // Estancias largas: la espera sube hasta el maximo
+    for _ in 0..100 {
+        occupancy.update(true, None);
+        clock.advance(600_000);
+        occupancy.update(false, None);
+    }
+    assert_eq!(occupancy.policy().hold_ms(None), 300_000);
+}
+
+#[test]
+fn scheduled_timeout_depends_on_time_of_day() {
+    let policy = Timeout::Scheduled(ScheduledTimeout {
+        schedule: Schedule {
+            start: TimeOfDay::hm(19, 0),
+            end: TimeOfDay::hm(7, 0),
+        },
+        active_ms: 120_000,
+        inactive_ms: 15_000,
+    });
+
+    assert_eq!(policy.hold_ms(Some(TimeOfDay::hm(22, 0))), 120_000);
+    assert_eq!(policy.hold_ms(Some(TimeOfDay::hm(12, 0))), 15_000);
+    assert_eq!(policy.hold_ms(None), 120_000);
+}
+
+#[test]
+fn distance_histogram_forgets_after_the_window() {
+    let clock = VirtualClock::new();
+    let mut histogram = DistanceHistogram::new(&clock, 60_000);
+
+    histogram.record(1.2);
+    histogram.record(5.4);
+    histogram.record(9.0);
+    clock.advance(30_000);
+    histogram.record(3.1);
+
+    let counts = histogram.counts();
+    assert_eq!(counts[0], 1);
+    assert_eq!(counts[4], 1);
+    assert_eq!(counts[8], 2);
+    assert_eq!(DistanceHistogram::<&VirtualClock>::bin_start(4), 3.0);
+
+    // Pasada la ventana solo queda lo reciente
+    clock.advance(40_000);
+    assert_eq!(histogram.counts().iter().sum::<u32>(), 1);
+    clock.advance(60_000);
+    assert_eq!(histogram.counts(), [0; 9]);
+}
+
+#[test]
+fn zone_stats_forget_after_each_window() {
+    let clock = VirtualClock::new();
+    let mut stats = ZoneStats::new(&clock, [60_000, 3_600_000]);
+
+    stats.record(100., 4.0);
+    clock.advance(30_000);
+    stats.record(300., 2.0);
+
+    let summary = stats.summary();
+    for lux in summary.lux {
+        assert_eq!(
+            (lux.count, lux.min, lux.max, lux.mean),
+            (2, 100., 300., 200.)
+        );
+        assert_eq!(lux.variance, 10_000.);
+    }
+    assert_eq!(summary.distance[0].mean, 3.0);
+
+    // Pasado el minuto solo la ventana de la hora recuerda la primera
+    clock.advance(40_000);
+    let summary = stats.summary();
+    assert_eq!(summary.lux[0].count, 1);
+    assert_eq!(summary.lux[0].min, 300.);
+    assert_eq!(summary.distance[0].variance, 0.);
+    assert_eq!(summary.lux[1].count, 2);
+
+    clock.advance(3_600_000);
+    assert_eq!(stats.summary().lux, [Summary::EMPTY; 2]);
+}
+
+#[test]
+fn screensaver_dims_blanks_and_wakes() {
+    let clock = VirtualClock::new();
+    let timing = screensaver::Timing {
+        dim_ms: 30_000,
+        blank_ms: 120_000,
+    };
+    let mut saver = Screensaver::new(&clock, timing);
+    assert_eq!(saver.screen(), Screen::On);
+    assert_eq!(saver.next_change_ms(), Some(30_000));
+
+    clock.advance(30_000);
+    assert_eq!(saver.screen(), Screen::Dimmed);
+    assert_eq!(saver.screen().contrast(0x7F, 0x08), Some(0x08));
+    assert_eq!(saver.next_change_ms(), Some(90_000));
+
+    clock.advance(90_000);
+    assert_eq!(saver.screen(), Screen::Blank);
+    assert_eq!(saver.screen().contrast(0x7F, 0x08), None);
+    assert_eq!(saver.next_change_ms(), None);
+
+    // Cualquier actividad la enciende de nuevo y reinicia la cuenta
+    saver.wake();
+    assert_eq!(saver.screen(), Screen::On);
+    clock.advance(29_999);
+    assert_eq!(saver.screen(), Screen::On);
+
+    // Un tiempo de atenuado mayor que el de apagado lo salta
+    saver.set_timing(screensaver::Timing {
+        dim_ms: 200_000,
+        blank_ms: 60_000,
+    });
+    clock.advance(30_001);
+    assert_eq!(saver.screen(), Screen::Blank);
+}
+
+#[test]
+fn config_trial_reverts_stuck_or_idle_lamps() {
+    const HOUR: u64 = 60 * 60 * 1000;
+    let clock = VirtualClock::new();
+    let mut trial = ConfigTrial::<_, 2>::new(&clock, 24 * HOUR, 7 * 24 * HOUR);
+
+    // Sin prueba en curso no hay resultado
+    assert_eq!(trial.update([true, true]), None);
+
+    // Una lampara encendida todo un dia
+    trial.start();
+    for _ in 0..24 {
+        assert_eq!(trial.update([false, true]), None);
+        clock.advance(HOUR);
+    }
+    assert_eq!(
+        trial.update([false, true]),
+        Some(Outcome::Failed(Fault::StuckOn))
+    );
+    assert!(!trial.is_running());
+
+    // Una semana sin encender ninguna
+    trial.start();
+    clock.advance(7 * 24 * HOUR - 1);
+    assert_eq!(trial.update([false, false]), None);
+    clock.advance(1);
+    assert_eq!(
+        trial.update([false, false]),
+        Some(Outcome::Failed(Fault::NeverOn))
+    );
+
+    // Encendidas de noche y apagadas de dia: la configuracion queda
+    trial.start();
+    for day in 0..7 {
+        assert_eq!(trial.update([true, day % 2 == 0]), None);
+        clock.advance(8 * HOUR);
+        assert_eq!(trial.update([false, false]), None);
+        clock.advance(16 * HOUR);
+    }
+    assert_eq!(trial.update([false, false]), Some(Outcome::Passed));
+}
+
+#[test]
+fn supervisor_reports_the_task_that_stops_checking_in() {
+    let clock = VirtualClock::new();
+    let mut supervisor = Supervisor::new(&clock, [3000, 1000]);
+
+    // Cada tarea tiene su plazo completo desde el arranque
+    clock.advance(1000);
+    assert_eq!(supervisor.stalled(), None);
+    clock.advance(1);
+    assert_eq!(supervisor.stalled(), Some(1));
+
+    // Mientras se reportan a tiempo no hay ninguna trabada
+    for _ in 0..10 {
+        supervisor.check_in(0);
+        supervisor.check_in(1);
+        clock.advance(500);
+        assert_eq!(supervisor.stalled(), None);
+    }
+
+    // La primera deja de reportarse; la segunda sigue
+    for _ in 0..5 {
+        supervisor.check_in(1);
+        clock.advance(500);
+        assert_eq!(supervisor.stalled(), None);
+    }
+    supervisor.check_in(1);
+    clock.advance(500);
+    assert_eq!(supervisor.stalled(), Some(0));
+
+    // Al volver a reportarse se recupera
+    supervisor.check_in(0);
+    assert_eq!(supervisor.stalled(), None);
+
+    // Una tarea liberada ya no se reporta como trabada; las demas si
+    supervisor.release(0);
+    clock.advance(1_000_000);
+    assert_eq!(supervisor.stalled(), Some(1));
+    supervisor.check_in(1);
+    assert_eq!(supervisor.stalled(), None);
+}
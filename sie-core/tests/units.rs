@@ -0,0 +1,527 @@
+// Pruebas con ejemplos fijos: los casos limite, los valores de las hojas de
+// datos y los textos que se muestran o se publican tal cual. Las
+// invariantes generales van en properties.rs.
+
+use sie_core::{
+    ambient::{AmbientLearner, MIN_SAMPLES},
+    codes::Code,
+    control::{Reading, Thresholds, decide},
+    counters::crc32,
+    csv_log,
+    dashboard::{Dashboard, LCD_COLUMNS, ZoneView},
+    demo::{self, Demo, Script},
+    esp_at::{RemoteCommand, Response, parse_message},
+    font::{self, ADVANCE, LINE_HEIGHT},
+    framebuffer::Framebuffer,
+    gamma::duty_fraction,
+    ha_discovery::{Command, Entity, Parts, config, config_topic, length},
+    i2c_registers::{self, Write, ZoneRegisters, decode_write},
+    lora_packets,
+    menu::{Input, Item, Menu},
+    recorder::Recorder,
+    rules::{Inputs, Rule, RuleSet, parse_decimal},
+    schedule::TimeOfDay,
+    sensor::{
+        DIST_MAX_M, DIST_MAX_V, DIST_MIN_M, DIST_MIN_V, DistanceModel, LUX_MAX_V, LUX_MIN_V,
+        LightModel, MAX_LUX_VALUE, get_voltage, voltage_to_distance, voltage_to_lux,
+    },
+    settings::{self, Settings},
+    seven_segment,
+    units::Units,
+};
+
+// Los rieles mismos dan los extremos del sensor
+#[test]
+fn rails_give_the_sensor_limits() {
+    assert_eq!(voltage_to_distance(DIST_MIN_V), DIST_MIN_M);
+    assert_eq!(voltage_to_distance(DIST_MAX_V), DIST_MAX_M);
+    assert_eq!(voltage_to_lux(LUX_MIN_V), 0.0);
+    assert_eq!(voltage_to_lux(LUX_MAX_V), MAX_LUX_VALUE);
+}
+
+#[test]
+fn duty_endpoints() {
+    assert_eq!(duty_fraction(0.), 0.);
+    assert_eq!(duty_fraction(100.), 1.);
+}
+
+#[test]
+fn dark_threshold_separates_night_from_day() {
+    let mut learner = AmbientLearner::new(30);
+
+    // 8 h de noche (20 luxes) y 16 h de dia (2500 luxes), una muestra por minuto
+    for _ in 0..MIN_SAMPLES / 3 {
+        assert_eq!(learner.dark_threshold(), None);
+        learner.record(20.);
+        learner.record(2500.);
+        learner.record(2500.);
+    }
+
+    let threshold = learner.dark_threshold().unwrap();
+    assert!(20. < threshold && threshold < 2500.);
+
+    // Si las noches se iluminan el umbral termina por seguirlas
+    for _ in 0..7 * MIN_SAMPLES {
+        learner.record(800.);
+    }
+    assert!(learner.dark_threshold().unwrap() > 800.);
+}
+
+#[test]
+fn rules_parse_and_round_trip() {
+    let mut rules = RuleSet::empty();
+    assert!(
+        rules.push(Rule::parse("luz < umbral_luz y distancia < 2 o ocupado > 0 => 80").unwrap())
+    );
+    assert!(rules.push(Rule::parse("luz > 5000 => 0").unwrap()));
+    assert_eq!(rules.rules()[0].conditions().len(), 3);
+
+    let mut encoded = [0; sie_core::rules::ENCODED_MAX];
+    let len = rules.encode(&mut encoded);
+    assert_eq!(RuleSet::decode(&encoded[..len]), Some(rules));
+    assert_eq!(RuleSet::decode(&encoded[..len - 1]), None);
+
+    let thresholds = Thresholds::default();
+    let inputs = Inputs {
+        lux: 10.,
+        distance: 4.,
+        occupied: true,
+        outdoor: None,
+    };
+    assert_eq!(rules.evaluate(&inputs, &thresholds), 80);
+
+    // Ventana: enciende si adentro hay mucha menos luz que afuera
+    let window = Rule::parse("diferencia > 500 o afuera < 50 => 60").unwrap();
+    let mut rules = RuleSet::empty();
+    assert!(rules.push(window));
+    let len = rules.encode(&mut encoded);
+    assert_eq!(RuleSet::decode(&encoded[..len]), Some(rules));
+    let outdoor = |lux, outdoor| Inputs {
+        lux,
+        distance: 4.,
+        occupied: false,
+        outdoor,
+    };
+    assert_eq!(rules.evaluate(&outdoor(100., Some(800.)), &thresholds), 60);
+    assert_eq!(rules.evaluate(&outdoor(400., Some(800.)), &thresholds), 0);
+    assert_eq!(rules.evaluate(&outdoor(10., Some(20.)), &thresholds), 60);
+    assert_eq!(rules.evaluate(&outdoor(10., None), &thresholds), 0);
+
+    assert_eq!(Rule::parse("luz < 10 => 101"), None);
+    assert_eq!(Rule::parse("luz = 10 => 50"), None);
+    assert_eq!(Rule::parse("luz < 10 y => 50"), None);
+    assert_eq!(Rule::parse("=> 50"), None);
+
+    assert_eq!(parse_decimal("2.5"), Some(2.5));
+    assert_eq!(parse_decimal("-800"), Some(-800.));
+    assert_eq!(parse_decimal(".5"), Some(0.5));
+    assert_eq!(parse_decimal("."), None);
+    assert_eq!(parse_decimal("1e3"), None);
+}
+
+#[test]
+fn at_messages_and_responses() {
+    assert_eq!(
+        parse_message("+MQTTSUBRECV:0,\"sie/cmd\",2,on\r\n"),
+        Some(("sie/cmd", "on"))
+    );
+    assert_eq!(parse_message("+MQTTSUBRECV:0,\"sie/cmd\",9,on"), None);
+    assert_eq!(parse_message("WIFI CONNECTED"), None);
+
+    assert_eq!(RemoteCommand::parse("off"), Some(RemoteCommand::Off));
+    assert_eq!(RemoteCommand::parse("auto\r\n"), Some(RemoteCommand::Auto));
+    assert_eq!(RemoteCommand::parse("reboot"), None);
+
+    assert_eq!(Response::parse("OK\r\n"), Some(Response::Ok));
+    assert_eq!(Response::parse("ERROR"), Some(Response::Error));
+    assert_eq!(Response::parse("+MQTTPUB:OK"), Some(Response::Ok));
+    assert_eq!(Response::parse("AT+MQTTPUB=0"), None);
+}
+
+#[test]
+fn i2c_header_and_commands() {
+    let zone = ZoneRegisters {
+        brightness: 80,
+        lamp_on: true,
+        lux: 300,
+        distance_cm: 250,
+        ..Default::default()
+    };
+    let map = i2c_registers::image(true, true, Code::OnTooLong.number(), &[zone]);
+    assert_eq!(
+        map[..6],
+        [
+            i2c_registers::WHO_AM_I,
+            i2c_registers::VERSION,
+            0b11,
+            1,
+            201,
+            0
+        ]
+    );
+    assert_eq!(map[0x10..0x16], [80, 1, 44, 1, 250, 0]);
+
+    assert_eq!(
+        decode_write(i2c_registers::COMMAND, &[4]),
+        Some(Write::Command(i2c_registers::Command::LampsOff))
+    );
+    assert_eq!(decode_write(i2c_registers::COMMAND, &[9]), None);
+    assert_eq!(decode_write(0x00, &[1]), None);
+}
+
+// Una pagina borrada, un bloque de otra version o con valores imposibles
+// dejan los ajustes por defecto
+#[test]
+fn settings_rejected_blocks() {
+    assert_eq!(Settings::decode(&[0xFF; settings::BLOCK_SIZE]), None);
+
+    let reseal = |mut block: [u8; settings::BLOCK_SIZE]| {
+        let crc = crc32(&block[..settings::BLOCK_SIZE - 4]);
+        block[settings::BLOCK_SIZE - 4..].copy_from_slice(&crc.to_le_bytes());
+        block
+    };
+    let mut block = Settings::default().encode();
+    block[0] += 1;
+    assert_eq!(Settings::decode(&reseal(block)), None);
+
+    let mut negative = Settings::default();
+    negative.zones[1].lux_scale = -1.;
+    assert_eq!(Settings::decode(&negative.encode()), None);
+    let mut infinite = Settings::default();
+    infinite.zones[0].thresholds.light = f32::INFINITY;
+    assert_eq!(Settings::decode(&infinite.encode()), None);
+}
+
+// La pantalla de estado es el texto de cada linea con la letra de 5x7
+#[test]
+fn dashboard_shows_mode_zones_and_fault() {
+    let mut dashboard = Dashboard::<2>::new();
+    dashboard.manual = true;
+    dashboard.zones[0] = ZoneView {
+        lux: Some(12.4),
+        distance: Some(2.345),
+        light_on: true,
+    };
+    dashboard.fault = Some(Code::ReplayMismatch);
+    let mut shown = Framebuffer::new();
+    dashboard.draw(&mut shown);
+
+    let mut expected = Framebuffer::new();
+    for (line, text) in [
+        (0, "MANUAL"),
+        (2, "Z0 LAMPARA ENCENDIDA"),
+        (3, "   12 LX    2.35 M"),
+        (4, "Z1 LAMPARA APAGADA"),
+        (5, "   SIN LECTURAS"),
+        (7, "FALLA 206"),
+    ] {
+        let end = font::draw_text(&mut expected, 0, line * LINE_HEIGHT, text.as_bytes());
+        assert_eq!(end, text.len() * ADVANCE);
+    }
+    assert_eq!(shown, expected);
+
+    // Minusculas en mayuscula y lo desconocido como '?'
+    assert_eq!(font::glyph(b'a'), font::glyph(b'A'));
+    assert_eq!(font::glyph(b'~'), font::glyph(b'?'));
+}
+
+// Las paginas del LCD: el modo con la falla y una por zona, en 16 columnas
+#[test]
+fn lcd_pages_show_mode_and_zones() {
+    let mut dashboard = Dashboard::<2>::new();
+    dashboard.zones[1] = ZoneView {
+        lux: Some(5999.6),
+        distance: Some(5.5),
+        light_on: true,
+    };
+    let text = |dashboard: &Dashboard<2>, page: usize| {
+        dashboard
+            .page(page)
+            .map(|line| String::from_utf8(line.to_vec()).unwrap())
+    };
+    let padded = |line: &str| format!("{line:<LCD_COLUMNS$}");
+
+    assert_eq!(dashboard.pages(), 3);
+    assert_eq!(
+        text(&dashboard, 0),
+        [padded("AUTOMATICO"), padded("SIN FALLAS")]
+    );
+    assert_eq!(
+        text(&dashboard, 1),
+        [padded("Z0 APAGADA"), padded("SIN LECTURAS")]
+    );
+    assert_eq!(
+        text(&dashboard, 2),
+        [padded("Z1 ENCENDIDA"), padded("6000 LX 5.50 M")]
+    );
+    // Las paginas dan la vuelta
+    assert_eq!(text(&dashboard, 3), text(&dashboard, 0));
+
+    dashboard.enabled = false;
+    dashboard.fault = Some(Code::DisplayMissing);
+    assert_eq!(
+        text(&dashboard, 0),
+        [padded("APAGADO"), padded("FALLA 506")]
+    );
+}
+
+// El menu recorre las opciones en circulo, entrega la elegida y se cierra
+// con `Exit` o sin elegir
+#[test]
+fn menu_cycles_and_chooses() {
+    let mut menu = Menu::new();
+    assert_eq!(menu.handle(Input::Choose), None);
+    assert!(!menu.is_open());
+
+    menu.open();
+    assert_eq!(menu.selected(), Some(Item::Manual));
+    assert_eq!(menu.handle(Input::Choose), Some(Item::Manual));
+    assert!(menu.is_open());
+
+    for _ in 0..Item::ALL.len() + 2 {
+        menu.handle(Input::Next);
+    }
+    assert_eq!(menu.selected(), Some(Item::Teach));
+    assert_eq!(menu.handle(Input::Choose), Some(Item::Teach));
+
+    while menu.selected() != Some(Item::Exit) {
+        menu.handle(Input::Next);
+    }
+    assert_eq!(menu.handle(Input::Choose), None);
+    assert!(!menu.is_open());
+
+    menu.open();
+    menu.handle(Input::Close);
+    assert_eq!(menu.selected(), None);
+    assert!(
+        Item::ALL
+            .iter()
+            .all(|item| item.label().len() <= LCD_COLUMNS)
+    );
+}
+
+// Luxes sin ceros a la izquierda y centimetros tras una 'd'; lo que no
+// cabe sale con guiones
+#[test]
+fn seven_segment_digits() {
+    const D: u8 = 0b0101_1110;
+    const ONE: u8 = 0b0000_0110;
+    const TWO: u8 = 0b0101_1011;
+    const FIVE: u8 = 0b0110_1101;
+    const ZERO: u8 = 0b0011_1111;
+    const DASH: u8 = 0b0100_0000;
+
+    assert_eq!(seven_segment::lux(0.2), [0, 0, 0, ZERO]);
+    assert_eq!(seven_segment::lux(12.4), [0, 0, ONE, TWO]);
+    assert_eq!(seven_segment::lux(5000.), [FIVE, ZERO, ZERO, ZERO]);
+    assert_eq!(seven_segment::lux(12_000.), [DASH; 4]);
+    assert_eq!(seven_segment::lux(-3.), [0, 0, 0, ZERO]);
+
+    assert_eq!(seven_segment::centimeters(1.0), [D, ONE, ZERO, ZERO]);
+    assert_eq!(seven_segment::centimeters(0.25), [D, 0, TWO, FIVE]);
+    assert_eq!(seven_segment::centimeters(12.0), [D, DASH, DASH, DASH]);
+    assert_eq!(seven_segment::DASHES, [DASH; 4]);
+}
+
+#[test]
+fn recorder_trigger_and_clear() {
+    let mut recorder = Recorder::<u8, 4>::new(0);
+    recorder.push(1);
+    recorder.trigger(0);
+    assert!(recorder.is_frozen());
+    assert!(!recorder.push(2));
+    assert_eq!(recorder.iter().collect::<Vec<_>>(), [1]);
+
+    // Un segundo disparo no acorta la historia posterior al primero
+    recorder.clear();
+    assert!(!recorder.is_triggered());
+    recorder.trigger(2);
+    recorder.trigger(0);
+    assert!(!recorder.push(3));
+    assert!(recorder.push(4));
+    assert!(recorder.is_frozen());
+    assert_eq!(recorder.get(0), Some(3));
+    assert_eq!(recorder.get(2), None);
+}
+
+#[test]
+fn csv_row_columns() {
+    let mut out = [0; csv_log::MAX_ROW];
+    let row = csv_log::Row {
+        uptime_s: 3725,
+        time: Some(TimeOfDay::hm(19, 5)),
+        zone: 1,
+        lux: Some(12.34),
+        distance: Some(1.5),
+        occupied: true,
+        light_on: true,
+        manual: false,
+        enabled: true,
+    };
+    let len = csv_log::format(&row, &mut out);
+    assert_eq!(&out[..len], b"3725,19:05:00,1,12.3,1.50,1,1,0,1\r\n");
+
+    let row = csv_log::Row {
+        time: None,
+        lux: None,
+        distance: None,
+        ..row
+    };
+    let len = csv_log::format(&row, &mut out);
+    assert_eq!(&out[..len], b"3725,,1,,,1,1,0,1\r\n");
+}
+
+// Los modelos se eligen por su nombre y los predeterminados son los de las
+// conversiones y umbrales de siempre
+#[test]
+fn sensor_models_by_name() {
+    for model in DistanceModel::ALL {
+        assert_eq!(DistanceModel::parse(model.name()), Some(model));
+    }
+    for model in LightModel::ALL {
+        assert_eq!(LightModel::parse(model.name()), Some(model));
+    }
+    assert_eq!(
+        DistanceModel::parse("gp2y0a21 "),
+        Some(DistanceModel::Gp2y0a21)
+    );
+    assert_eq!(LightModel::parse("BH1750"), None);
+
+    assert_eq!(
+        Thresholds::for_models(DistanceModel::default(), LightModel::default()),
+        Thresholds::default()
+    );
+    assert_eq!(
+        Reading::from_raw(2000, 2000).distance,
+        voltage_to_distance(get_voltage(2000.))
+    );
+}
+
+// Cada secuencia de demostracion enciende la lampara con cualquier modelo
+// de sensores y termina con la zona vacia
+#[test]
+fn demo_scripts_light_and_release_the_lamp() {
+    for script in &demo::SCRIPTS {
+        assert_eq!(Script::find(script.name).map(|s| s.name), Some(script.name));
+        for distance_model in DistanceModel::ALL {
+            for light_model in LightModel::ALL {
+                let thresholds = Thresholds::for_models(distance_model, light_model);
+                let mut demo = Demo::new(script);
+                let mut lit = false;
+                let mut present = false;
+                while demo.advance().is_some() {
+                    let fake = demo.reading();
+                    let reading = Reading {
+                        distance_voltage: 0.,
+                        lux_voltage: 0.,
+                        distance: fake.distance,
+                        lux: fake.lux,
+                    };
+                    let decision = decide(&reading, &thresholds);
+                    lit |= decision.light_on;
+                    present = decision.present;
+                }
+                assert!(lit, "{}", script.name);
+                assert!(!present, "{}", script.name);
+            }
+        }
+    }
+    assert!(Script::find("dia").is_none());
+}
+
+// Valores de RegFrf de la hoja de datos del SX1276
+#[test]
+fn lora_frequency_registers() {
+    assert_eq!(
+        lora_packets::frequency_register(915_000_000),
+        [0xE4, 0xC0, 0x00]
+    );
+    assert_eq!(
+        lora_packets::frequency_register(868_000_000),
+        [0xD9, 0x00, 0x00]
+    );
+    assert_eq!(
+        lora_packets::frequency_register(433_000_000),
+        [0x6C, 0x40, 0x00]
+    );
+}
+
+#[test]
+fn units_labels_and_temperature() {
+    assert_eq!(Units::parse("imperial"), Some(Units::Imperial));
+    assert_eq!(Units::parse("si"), None);
+    assert_eq!(Units::Imperial.distance_unit(), "ft");
+    assert_eq!(Units::Imperial.temperature(100.), 212.);
+    assert_eq!(Units::Metric.temperature(21.5), 21.5);
+}
+
+#[test]
+fn home_assistant_discovery() {
+    let text = |parts: Parts| {
+        let bytes: Vec<u8> = parts.concat();
+        assert_eq!(bytes.len(), length(&parts));
+        String::from_utf8(bytes).unwrap()
+    };
+
+    assert_eq!(
+        text(config_topic(Entity::Lamp, 1)),
+        "homeassistant/light/sie/lampara_1/config"
+    );
+    assert_eq!(
+        text(config_topic(Entity::Manual, 1)),
+        "homeassistant/switch/sie/manual/config"
+    );
+
+    let lamp = text(config(Entity::Lamp, 1));
+    assert!(lamp.starts_with(
+        r#"{"~":"sie/zona/1","name":"Lampara 1","uniq_id":"sie_lampara_1","stat_t":"~/lampara","#
+    ));
+    assert!(lamp.contains(r#""bri_cmd_t":"~/brillo/set""#));
+    let manual = text(config(Entity::Manual, 0));
+    assert!(manual.starts_with(r#"{"~":"sie","name":"Modo manual","uniq_id":"sie_manual","#));
+    for entity in Entity::PER_ZONE.into_iter().chain([Entity::Manual]) {
+        let json = text(config(entity, 0));
+        // Llaves balanceadas y cadenas cerradas
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+        assert_eq!(json.matches('"').count() % 2, 0);
+        assert!(json.ends_with("}}"));
+    }
+
+    assert_eq!(
+        Command::parse("sie/zona/0/lampara/set", "1"),
+        Some(Command::Lamp { zone: 0, on: true })
+    );
+    assert_eq!(
+        Command::parse("sie/zona/1/brillo/set", "250"),
+        Some(Command::Brightness {
+            zone: 1,
+            percent: 100
+        })
+    );
+    assert_eq!(
+        Command::parse("sie/manual/set", "0"),
+        Some(Command::Manual(false))
+    );
+    assert_eq!(Command::parse("sie/zona/0/luz/set", "1"), None);
+    assert_eq!(Command::parse("sie/manual/set", "si"), None);
+}
+
+#[test]
+fn fault_codes_are_stable_and_unique() {
+    for (i, code) in Code::ALL.into_iter().enumerate() {
+        assert_eq!(Code::from_number(code.number()), Some(code));
+        assert_eq!(code.module() as u16, code.number() / 100);
+        assert!(
+            Code::ALL[..i]
+                .iter()
+                .all(|other| other.number() != code.number())
+        );
+    }
+    assert_eq!(Code::from_number(0), None);
+    assert_eq!(Code::from_number(299), None);
+
+    // Los numeros publicados no cambian
+    assert_eq!(Code::OnTooLong.number(), 201);
+    assert_eq!(Code::RevertedStuckOn.number(), 401);
+    assert_eq!(Code::Panic.number(), 801);
+}
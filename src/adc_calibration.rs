@@ -0,0 +1,120 @@
+use core::{cell::Cell, sync::atomic::Ordering};
+
+use embassy_stm32::{
+    adc::{Adc, SampleTime},
+    pac,
+    peripherals::ADC1,
+};
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::{Duration, Instant, Timer, block_for};
+
+use sie_core::sensor::{VOLTAGE_REF, correct_for_supply, supply_from_vrefint};
+
+use crate::{
+    SharedAdc,
+    error::{self, Error},
+    zone::ZONES,
+};
+
+// Tiempo minimo entre calibraciones
+const PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+// Cada cuanto se revisa si ya toca y si las zonas estan en reposo
+const CHECK_PERIOD: Duration = Duration::from_secs(60);
+// VREFINT necesita al menos 17.1 us de muestreo
+const VREFINT_SAMPLE_TIME: SampleTime = SampleTime::CYCLES239_5;
+// Muestreo de los sensores (el que deja `Adc::new`)
+const SENSOR_SAMPLE_TIME: SampleTime = SampleTime::CYCLES1_5;
+// Cada paso de la calibracion tarda unos cuantos ciclos del ADC; mucho mas
+// que esto es un ADC que no responde
+const CALIBRATION_TIMEOUT: Duration = Duration::from_millis(1);
+
+// Alimentacion del ADC medida en la ultima calibracion
+static SUPPLY: CriticalSectionMutex<Cell<f32>> = CriticalSectionMutex::new(Cell::new(VOLTAGE_REF));
+
+// Lectura corregida por la alimentacion medida, como si fuera de 3.3 V
+pub fn correct(raw: u16) -> u16 {
+    correct_for_supply(raw, SUPPLY.lock(|s| s.get()))
+}
+
+// Repite cada noche la calibracion interna del ADC y vuelve a medir su
+// alimentacion con VREFINT, con las lamparas apagadas y sin nadie en las
+// zonas para no tomar la fuente cargada. Registra cuanto cambio la
+// alimentacion desde la calibracion anterior. Con el RTC ajustado solo se
+// calibra dentro del horario (de noche)
+#[embassy_executor::task]
+pub async fn adc_calibration(adc: &'static SharedAdc) {
+    // Sin calibrar se supone la alimentacion nominal
+    let mut supply = match calibrate(&mut *adc.lock().await).await {
+        Ok(supply) => supply,
+        Err(e) => {
+            error::degrade(e);
+            VOLTAGE_REF
+        }
+    };
+    info!(Sensors, "Alimentacion del ADC: {} V", supply);
+    let mut last = Instant::now();
+
+    loop {
+        Timer::after(CHECK_PERIOD).await;
+        if last.elapsed() < PERIOD || !is_quiet() {
+            continue;
+        }
+
+        let previous = supply;
+        last = Instant::now();
+        supply = match calibrate(&mut *adc.lock().await).await {
+            Ok(supply) => supply,
+            Err(e) => {
+                error::degrade(e);
+                continue;
+            }
+        };
+        info!(
+            Sensors,
+            "ADC recalibrado: alimentacion de {} V, deriva de {} mV",
+            supply,
+            (supply - previous) * 1000.
+        );
+    }
+}
+
+fn is_quiet() -> bool {
+    #[cfg(feature = "schedule")]
+    if crate::wall_clock::now().is_some_and(|now| !crate::config::get().schedule.is_active(now)) {
+        return false;
+    }
+    ZONES
+        .iter()
+        .all(|zone| !zone.light_is_on() && !zone.occupied.load(Ordering::Relaxed))
+}
+
+// Calibracion del fabricante (RM0008 11.4) y medicion de VREFINT; el ADC
+// esta tomado, asi que no hay conversiones en curso
+async fn calibrate(adc: &mut Adc<'static, ADC1>) -> Result<f32, Error> {
+    let regs = pac::ADC1;
+    regs.cr2().modify(|w| w.set_rstcal(true));
+    wait_until(|| !regs.cr2().read().rstcal())?;
+    regs.cr2().modify(|w| w.set_cal(true));
+    wait_until(|| !regs.cr2().read().cal())?;
+    // Un ciclo del ADC despues de calibrar
+    block_for(Duration::from_micros(1));
+
+    let mut vref = adc.enable_vref();
+    adc.set_sample_time(VREFINT_SAMPLE_TIME);
+    let raw = adc.read(&mut vref).await;
+    adc.set_sample_time(SENSOR_SAMPLE_TIME);
+
+    let supply = supply_from_vrefint(raw);
+    SUPPLY.lock(|s| s.set(supply));
+    Ok(supply)
+}
+
+fn wait_until(done: impl Fn() -> bool) -> Result<(), Error> {
+    let start = Instant::now();
+    while !done() {
+        if start.elapsed() > CALIBRATION_TIMEOUT {
+            return Err(Error::AdcInit);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use embassy_stm32::{
+    adc::{self, Adc, SampleTime},
+    bind_interrupts,
+    interrupt::{self, InterruptExt, typelevel},
+    pac,
+    peripherals::{ADC1, ADC2},
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::{Duration, Timer, with_timeout};
+use heapless::Vec;
+
+use sie_core::analog_watchdog::{Levels, Window};
+
+use crate::zone::{ZONE_COUNT, ZONES};
+
+// ADC2 convierte sin parar las entradas de las zonas y su watchdog
+// analogico las compara en hardware con los umbrales: en cuanto una cruza
+// (se apagaron las luces del cuarto, alguien se acerca de noche, vuelve la
+// luz) la interrupcion despierta a los controladores sin esperar su
+// siguiente muestreo. La ventana y las entradas de la secuencia cambian
+// entre el dia y la noche (ver sie_core::analog_watchdog). Solo se vigilan
+// las zonas con modulos de luz que suben con la luz. Ocupa ADC2, por lo que
+// no se combina con `dual-adc`
+
+// ADC1 y ADC2 comparten la interrupcion
+bind_interrupts!(struct Irqs {
+    ADC1_2 => adc::InterruptHandler<ADC1>, CrossingHandler;
+});
+
+// Cada cuanto se actualizan los umbrales y el estado de dia o de noche
+const REFRESH: Duration = Duration::from_secs(5);
+// Tras un aviso el watchdog se rearma despues de esta pausa; si el cruce
+// sigue vuelve a avisar, lo que solo adelanta una muestra
+const REARM_DELAY: Duration = Duration::from_secs(5);
+// Una secuencia de cuatro entradas dura unos 0.3 ms; se espera a que
+// termine antes de cambiarla
+const SCAN_TIME: Duration = Duration::from_millis(1);
+
+static CROSSING: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+// Entradas ADC12_INx de los sensores de una zona vigilada
+#[derive(Clone, Copy)]
+pub struct Inputs {
+    pub zone: usize,
+    pub light: u8,
+    pub distance: u8,
+}
+
+struct CrossingHandler;
+
+impl typelevel::Handler<typelevel::ADC1_2> for CrossingHandler {
+    unsafe fn on_interrupt() {
+        let regs = pac::ADC2;
+        if regs.sr().read().awd() {
+            // Mientras siga fuera de la ventana la bandera se activaria en
+            // cada conversion; la tarea vuelve a habilitarla
+            regs.cr1().modify(|w| w.set_awdie(false));
+            regs.sr().modify(|w| w.set_awd(false));
+            CROSSING.signal(());
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn adc_watchdog(adc2: ADC2, inputs: Vec<Inputs, ZONE_COUNT>) {
+    if inputs.is_empty() {
+        return;
+    }
+    // El driver enciende y calibra ADC2; queda vivo con la tarea
+    let _adc = Adc::new(adc2);
+
+    let regs = pac::ADC2;
+    for &Inputs {
+        light, distance, ..
+    } in &inputs
+    {
+        // Muestreo largo: las entradas son lentas y se comparten con ADC1
+        set_sample_time(light);
+        set_sample_time(distance);
+    }
+    regs.cr1().modify(|w| {
+        w.set_scan(true);
+        w.set_awdsgl(false);
+        w.set_awden(true);
+    });
+    regs.cr2().modify(|w| {
+        w.set_exttrig(true);
+        w.set_extsel(7); // SWSTART
+    });
+
+    interrupt::ADC1_2.unpend();
+    unsafe { interrupt::ADC1_2.enable() };
+    info!(Control, "Watchdog del ADC vigilando los sensores");
+
+    let mut night = None;
+    loop {
+        // De noche solo si todas las zonas vigiladas estan oscuras
+        let now_night = inputs.iter().all(|i| ZONES[i.zone].is_night());
+        if night != Some(now_night) {
+            night = Some(now_night);
+            scan(&inputs, now_night).await;
+        }
+
+        let levels: Vec<Levels, ZONE_COUNT> = inputs
+            .iter()
+            .map(|i| Levels {
+                dark: ZONES[i.zone].dark_level(),
+                near: ZONES[i.zone].near_level(),
+            })
+            .collect();
+        let window = Window::new(&levels, now_night);
+        regs.ltr().write(|w| w.set_lt(window.low));
+        regs.htr().write(|w| w.set_ht(window.high));
+        regs.sr().modify(|w| w.set_awd(false));
+        regs.cr1().modify(|w| w.set_awdie(true));
+
+        if with_timeout(REFRESH, CROSSING.wait()).await.is_ok() {
+            for zone in &ZONES {
+                zone.sample_now.signal(());
+            }
+            Timer::after(REARM_DELAY).await;
+        }
+    }
+}
+
+fn set_sample_time(input: u8) {
+    let regs = pac::ADC2;
+    if input <= 9 {
+        regs.smpr2()
+            .modify(|w| w.set_smp(input as usize, SampleTime::CYCLES239_5));
+    } else {
+        regs.smpr1()
+            .modify(|w| w.set_smp(input as usize - 10, SampleTime::CYCLES239_5));
+    }
+}
+
+// Conversion continua de las entradas de luz y, de noche, tambien de las de
+// distancia
+async fn scan(inputs: &[Inputs], night: bool) {
+    let regs = pac::ADC2;
+    regs.cr2().modify(|w| w.set_cont(false));
+    Timer::after(SCAN_TIME).await;
+
+    let mut rank = 0;
+    for input in inputs {
+        regs.sqr3().modify(|w| w.set_sq(rank, input.light));
+        rank += 1;
+        if night {
+            regs.sqr3().modify(|w| w.set_sq(rank, input.distance));
+            rank += 1;
+        }
+    }
+    regs.sqr1().modify(|w| w.set_l(rank as u8 - 1));
+
+    regs.cr2().modify(|w| w.set_cont(true));
+    regs.cr2().modify(|w| w.set_swstart(true));
+}
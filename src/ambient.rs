@@ -0,0 +1,177 @@
+use embassy_time::{Duration, Instant};
+
+use sie_core::{ambient::AmbientLearner, codes::Code};
+
+use crate::{
+    storage::{self, ERASED, PAGE_SIZE, Page},
+    zone::{ZONE_COUNT, ZoneState},
+};
+
+// Periodo entre muestras del aprendizaje
+const SAMPLE_PERIOD: Duration = Duration::from_secs(60);
+// Tiempo minimo entre escrituras del umbral en flash
+const SAVE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+// Porcentaje del tiempo que se considera oscuro
+const DARK_PERCENTILE: u8 = 30;
+
+// Cada registro en flash: zona (1), marca (1), relleno (2) y el umbral
+// (f32). El ultimo registro de cada zona es el vigente
+const RECORD_SIZE: usize = 8;
+const RECORDS: u32 = PAGE_SIZE / RECORD_SIZE as u32;
+const MARK: u8 = 0xA5;
+
+// Umbral de luz aprendido de una zona. Sustituye al umbral fijo mientras
+// nadie lo ajuste a mano (perilla, potenciometro); en ese caso se respeta
+// el valor manual hasta el siguiente arranque
+pub struct LearnedThreshold {
+    zone: usize,
+    learner: AmbientLearner,
+    // Ultimo umbral escrito en los umbrales de la zona
+    applied: Option<f32>,
+    overridden: bool,
+    last_sample: Instant,
+    saved: Option<(f32, Instant)>,
+}
+
+impl LearnedThreshold {
+    // Aplica el umbral guardado en flash, si lo hay
+    pub fn new(zone: usize, state: &ZoneState) -> Self {
+        let mut learned = Self {
+            zone,
+            learner: AmbientLearner::new(DARK_PERCENTILE),
+            applied: None,
+            overridden: false,
+            last_sample: Instant::now(),
+            saved: None,
+        };
+
+        if let Some(threshold) = load(zone) {
+            info!(
+                Control,
+                "Zona {}: umbral de luz aprendido: {} luxes", zone, threshold
+            );
+            learned.apply(state, threshold);
+        }
+        learned
+    }
+
+    // Agrega una lectura de luz. Con la lampara encendida el sensor mide
+    // tambien su luz, por lo que esas lecturas se descartan
+    pub fn update(&mut self, state: &ZoneState, lux: f32, lamp_on: bool) {
+        if lamp_on || self.last_sample.elapsed() < SAMPLE_PERIOD {
+            return;
+        }
+        self.last_sample = Instant::now();
+        self.learner.record(lux);
+
+        let Some(threshold) = self.learner.dark_threshold() else {
+            return;
+        };
+        if self.applied != Some(threshold) {
+            self.apply(state, threshold);
+        }
+
+        let due = self
+            .saved
+            .is_none_or(|(saved, at)| saved != threshold && at.elapsed() >= SAVE_PERIOD);
+        if due && !self.overridden {
+            save(self.zone, threshold);
+            self.saved = Some((threshold, Instant::now()));
+        }
+    }
+
+    fn apply(&mut self, state: &ZoneState, threshold: f32) {
+        if self.overridden {
+            return;
+        }
+
+        state.thresholds.lock(|t| {
+            let mut thresholds = t.get();
+            // Si el umbral ya no es el ultimo que se aplico, alguien lo
+            // ajusto a mano
+            if self
+                .applied
+                .is_some_and(|applied| applied != thresholds.light)
+            {
+                self.overridden = true;
+                return;
+            }
+            thresholds.light = threshold;
+            t.set(thresholds);
+        });
+
+        if self.overridden {
+            info!(Control, "Zona {}: umbral de luz fijado a mano", self.zone);
+        } else {
+            self.applied = Some(threshold);
+        }
+    }
+}
+
+fn load(zone: usize) -> Option<f32> {
+    let mut threshold = None;
+    for slot in 0..RECORDS {
+        let mut record = [0; RECORD_SIZE];
+        if !storage::read(Page::Ambient, slot_offset(slot), &mut record) || record[1] == ERASED {
+            break;
+        }
+        if record[0] as usize == zone && record[1] == MARK {
+            threshold = Some(f32::from_le_bytes([
+                record[4], record[5], record[6], record[7],
+            ]));
+        }
+    }
+    threshold
+}
+
+// Agrega un registro; al llenarse la pagina se borra y se conservan los
+// umbrales vigentes de todas las zonas
+fn save(zone: usize, threshold: f32) {
+    let mut next = None;
+    for slot in 0..RECORDS {
+        let mut header = [0; 2];
+        if storage::read(Page::Ambient, slot_offset(slot), &mut header) && header == [ERASED; 2] {
+            next = Some(slot);
+            break;
+        }
+    }
+
+    let next = match next {
+        Some(next) => next,
+        None => {
+            let current: [Option<f32>; ZONE_COUNT] = core::array::from_fn(load);
+            if !storage::erase(Page::Ambient) {
+                warn!(
+                    Code::AmbientNotErased,
+                    "No se pudo borrar la pagina de umbrales"
+                );
+                return;
+            }
+
+            let mut next = 0;
+            for (other, value) in current.into_iter().enumerate() {
+                if other != zone
+                    && let Some(value) = value
+                {
+                    write(next, other, value);
+                    next += 1;
+                }
+            }
+            next
+        }
+    };
+    write(next, zone, threshold);
+}
+
+fn write(slot: u32, zone: usize, threshold: f32) {
+    let mut record = [0; RECORD_SIZE];
+    record[0] = zone as u8;
+    record[1] = MARK;
+    record[4..].copy_from_slice(&threshold.to_le_bytes());
+    storage::write(Page::Ambient, slot_offset(slot), &record);
+}
+
+fn slot_offset(slot: u32) -> u32 {
+    slot * RECORD_SIZE as u32
+}
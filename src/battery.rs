@@ -0,0 +1,134 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_stm32::{
+    pac::{self, pwr::vals::Pdds},
+    peripherals::PA4,
+};
+#[cfg(feature = "lora")]
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::{Duration, Timer};
+
+use sie_core::{
+    battery::{BatteryMonitor, Event},
+    codes::Code,
+    sensor::get_voltage,
+};
+
+use crate::{SharedAdc, config, counters, settings, watchdog};
+
+// Periodo de lectura de la bateria
+const READ_PERIOD: Duration = Duration::from_secs(10);
+// Lecturas seguidas bajo el voltaje critico para apagar el nodo
+const CONFIRM: u8 = 3;
+// El divisor de 47k y 10k reduce el voltaje de la bateria 5.7 veces; una
+// bateria de 12 V queda en unos 2.1 V en el pin
+const DIVIDER: f32 = 5.7;
+// Tiempo para que el ultimo estado salga por MQTT o LoRa antes de dormir
+const LAST_GASP: Duration = Duration::from_secs(15);
+// Registro de respaldo (BKP_DR1) que marca un apagado por bateria baja;
+// sobrevive al modo Standby y a los reinicios
+const SHUTDOWN_MARK: u16 = 0xBA77;
+
+// El nodo esta apagandose: las lamparas quedan apagadas y los enlaces
+// mandan su ultimo estado
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+#[cfg(feature = "lora")]
+static SHUTDOWN: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::Relaxed)
+}
+
+// Espera el apagado, para la ultima subida LoRa
+#[cfg(feature = "lora")]
+pub async fn shutdown() {
+    SHUTDOWN.wait().await
+}
+
+// Al despertar de un apagado por bateria baja se vuelve a dormir sin
+// encender nada mientras la bateria no pase el voltaje de recuperacion.
+// Va antes de contar el arranque, para no gastar la flash en cada
+// despertar
+pub async fn check_at_boot(pin: &mut PA4, adc: &'static SharedAdc) {
+    if !was_shut_down() {
+        return;
+    }
+    let voltage = read(pin, adc).await;
+    if voltage < config::get().battery.recover_v {
+        standby();
+    }
+    set_shut_down(false);
+    info!(Sensors, "Bateria recuperada: {} V", voltage);
+}
+
+// Vigila la bateria (divisor en PA4). Bajo el voltaje critico apaga el
+// nodo en orden: guarda los contadores y los ajustes, registra la falla
+// (que los enlaces publican como ultimo estado), apaga las lamparas y
+// entra en Standby. El watchdog lo despierta cada ~26 s para revisar si el
+// panel ya la recargo
+#[embassy_executor::task]
+pub async fn battery(mut pin: PA4, adc: &'static SharedAdc) {
+    let mut monitor = BatteryMonitor::new(config::get().battery, CONFIRM, false);
+
+    loop {
+        let voltage = read(&mut pin, adc).await;
+        info!(Sensors, "Bateria: {} V", voltage);
+        if monitor.update(voltage) == Some(Event::Critical) {
+            break;
+        }
+        Timer::after(READ_PERIOD).await;
+    }
+
+    counters::save();
+    settings::save();
+    warn!(Code::BatteryCritical, "Bateria baja, se apaga el nodo");
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
+    #[cfg(feature = "lora")]
+    SHUTDOWN.signal(());
+    Timer::after(LAST_GASP).await;
+
+    set_shut_down(true);
+    standby();
+}
+
+async fn read(pin: &mut PA4, adc: &'static SharedAdc) -> f32 {
+    let raw = adc.lock().await.read(pin).await;
+    get_voltage(raw as f32) * DIVIDER
+}
+
+fn was_shut_down() -> bool {
+    enable_backup();
+    pac::BKP.dr(0).read().d() == SHUTDOWN_MARK
+}
+
+fn set_shut_down(shut_down: bool) {
+    enable_backup();
+    let mark = if shut_down { SHUTDOWN_MARK } else { 0 };
+    pac::BKP.dr(0).write(|w| w.set_d(mark));
+}
+
+// Reloj de PWR y BKP y escritura del dominio de respaldo
+fn enable_backup() {
+    pac::RCC.apb1enr().modify(|w| {
+        w.set_pwren(true);
+        w.set_bkpen(true);
+    });
+    pac::PWR.cr().modify(|w| w.set_dbp(true));
+}
+
+// Modo Standby: el consumo mas bajo, con los pines en alta impedancia (las
+// lamparas apagadas). Solo sale con el reinicio del watchdog, alargado a
+// su maximo
+fn standby() -> ! {
+    watchdog::slowest();
+    pac::PWR.cr().modify(|w| {
+        w.set_pdds(Pdds::STANDBY_MODE);
+        w.set_cwuf(true);
+    });
+    // SAFETY: solo se usa SCB para pasar a sueno profundo y no se vuelve
+    let mut core = unsafe { cortex_m::Peripherals::steal() };
+    core.SCB.set_sleepdeep();
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
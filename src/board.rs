@@ -0,0 +1,138 @@
+use embassy_stm32::Config;
+#[cfg(feature = "usb-console")]
+use embassy_stm32::rcc::HseMode;
+
+// Placas soportadas. Sin opcion de placa el firmware es para la blue pill;
+// `black-pill` y `nucleo-f103` cambian los pines que difieren y los relojes.
+// Las zonas (PB0/PA7/PB7 y PB1/PA6/PB6) usan los mismos pines en las tres:
+// las salidas del TIM4 no tienen otra asignacion en 48 y 64 pines
+//
+// - Blue pill: LED de la placa en PC13 (activo en bajo), cristales de
+//   8 MHz y de 32.768 kHz.
+// - Black pill (STM32F103C8): el LED de la placa esta en PB12, por lo que
+//   el boton de luz pasa a PB11. Mismos cristales.
+// - Nucleo-F103RB: el LED de estado es LD2 (PA5, activo en alto). Los 8 MHz
+//   llegan del ST-LINK por MCO (HSE en bypass) y el cristal de 32.768 kHz
+//   (X2) esta desde la revision C. USART2 (PA2/PA3) va al puerto serie
+//   virtual del ST-LINK. El STM32F103RB tiene 128K de flash, pero se usa
+//   la distribucion de 64K del C8 (memory.x); para grabar, `probe-rs run
+//   --chip STM32F103RB`
+
+// Pines de cada funcion en la placa elegida: `pin!(p, funcion)` toma el
+// periferico correspondiente de `p`
+#[cfg(not(any(feature = "black-pill", feature = "nucleo-f103")))]
+macro_rules! pin {
+    ($p:ident, manual_button) => {
+        $p.PB13
+    };
+    ($p:ident, manual_exti) => {
+        $p.EXTI13
+    };
+    ($p:ident, light_button) => {
+        $p.PB12
+    };
+    ($p:ident, light_exti) => {
+        $p.EXTI12
+    };
+    ($p:ident, status_led) => {
+        $p.PB5
+    };
+    ($p:ident, board_led) => {
+        $p.PC13
+    };
+}
+
+#[cfg(feature = "black-pill")]
+macro_rules! pin {
+    ($p:ident, manual_button) => {
+        $p.PB13
+    };
+    ($p:ident, manual_exti) => {
+        $p.EXTI13
+    };
+    ($p:ident, light_button) => {
+        $p.PB11
+    };
+    ($p:ident, light_exti) => {
+        $p.EXTI11
+    };
+    ($p:ident, status_led) => {
+        $p.PB5
+    };
+    ($p:ident, board_led) => {
+        $p.PB12
+    };
+}
+
+// En la Nucleo el LED de estado siempre es LD2
+#[cfg(feature = "nucleo-f103")]
+macro_rules! pin {
+    ($p:ident, manual_button) => {
+        $p.PB13
+    };
+    ($p:ident, manual_exti) => {
+        $p.EXTI13
+    };
+    ($p:ident, light_button) => {
+        $p.PB12
+    };
+    ($p:ident, light_exti) => {
+        $p.EXTI12
+    };
+    ($p:ident, status_led) => {
+        $p.PA5
+    };
+}
+
+// Bits de los botones de modo y de luz en el puerto B
+#[cfg(not(feature = "black-pill"))]
+pub const BUTTONS: u32 = 1 << 13 | 1 << 12;
+#[cfg(feature = "black-pill")]
+pub const BUTTONS: u32 = 1 << 13 | 1 << 11;
+
+// Con una radio o la tarjeta SD en SPI1 (PB5 es su MOSI) el LED de estado
+// es el de la placa, que en la blue pill y la black pill enciende en bajo.
+// En la Nucleo el LED de estado no usa PB5
+pub const STATUS_LED_ACTIVE_LOW: bool = cfg!(all(
+    any(feature = "lora", feature = "nrf24", feature = "sd-log"),
+    not(feature = "nucleo-f103")
+));
+
+// Origen de los 8 MHz externos
+#[cfg(all(feature = "usb-console", not(feature = "nucleo-f103")))]
+const HSE_MODE: HseMode = HseMode::Oscillator;
+#[cfg(all(feature = "usb-console", feature = "nucleo-f103"))]
+const HSE_MODE: HseMode = HseMode::Bypass;
+
+// Relojes segun la placa y las opciones. Sin USB el MCU corre con el
+// oscilador interno de 8 MHz
+pub fn config() -> Config {
+    #[allow(unused_mut)]
+    let mut config = Config::default();
+    // El RTC corre con el cristal de 32.768 kHz para no atrasarse
+    #[cfg(any(feature = "schedule", feature = "stop-mode"))]
+    {
+        config.rcc.ls = embassy_stm32::rcc::LsConfig::default_lse();
+    }
+    // El USB necesita 48 MHz: 8 MHz externos por 9 (72 MHz) entre 1.5
+    #[cfg(feature = "usb-console")]
+    {
+        use embassy_stm32::{
+            rcc::{APBPrescaler, Hse, Pll, PllMul, PllPreDiv, PllSource, Sysclk},
+            time::Hertz,
+        };
+
+        config.rcc.hse = Some(Hse {
+            freq: Hertz::mhz(8),
+            mode: HSE_MODE,
+        });
+        config.rcc.pll = Some(Pll {
+            src: PllSource::HSE,
+            prediv: PllPreDiv::DIV1,
+            mul: PllMul::MUL9,
+        });
+        config.rcc.sys = Sysclk::PLL1_P;
+        config.rcc.apb1_pre = APBPrescaler::DIV2;
+    }
+    config
+}
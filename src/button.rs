@@ -0,0 +1,34 @@
+use embassy_stm32::exti::ExtiInput;
+use embassy_time::{Delay, Duration};
+use sie_core::{button::Timing, hal};
+
+pub use sie_core::button::Press;
+
+use crate::clock::SystemClock;
+
+// Boton con antirrebote y deteccion de gestos sobre un pin con EXTI (ver
+// sie_core::hal::Debounced). Los botones usan pull-down, por lo que
+// presionado equivale a nivel alto
+pub struct Debounced<'d>(hal::Debounced<ExtiInput<'d>, Delay, SystemClock>);
+
+impl<'d> Debounced<'d> {
+    // `click_window` en cero reporta cada clic de inmediato
+    pub fn new(
+        input: ExtiInput<'d>,
+        settle: Duration,
+        long_press: Duration,
+        click_window: Duration,
+    ) -> Self {
+        let timing = Timing {
+            settle_ms: settle.as_millis(),
+            long_press_ms: long_press.as_millis(),
+            click_window_ms: click_window.as_millis(),
+        };
+
+        Self(hal::Debounced::new(input, Delay, SystemClock, timing))
+    }
+
+    pub async fn wait_for_press(&mut self) -> Press {
+        self.0.wait_for_press().await
+    }
+}
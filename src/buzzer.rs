@@ -0,0 +1,41 @@
+use sie_core::beep::Beep;
+
+#[cfg(feature = "buzzer")]
+use embassy_stm32::{peripherals::TIM1, time::Hertz, timer::simple_pwm::SimplePwm};
+#[cfg(feature = "buzzer")]
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+#[cfg(feature = "buzzer")]
+use embassy_time::Timer;
+
+// Sonidos pendientes; si la cola esta llena el nuevo sonido se descarta
+#[cfg(feature = "buzzer")]
+static BEEPS: Channel<CriticalSectionRawMutex, Beep, 4> = Channel::new();
+
+// Pide un sonido al zumbador. Sin la opcion `buzzer` no hace nada
+pub fn beep(beep: Beep) {
+    #[cfg(feature = "buzzer")]
+    let _ = BEEPS.try_send(beep);
+    #[cfg(not(feature = "buzzer"))]
+    let _ = beep;
+}
+
+// Reproduce los sonidos en orden con un zumbador piezoelectrico en el
+// TIM1 canal 1 (PA8); el ciclo de trabajo al 50 % da el volumen maximo
+#[cfg(feature = "buzzer")]
+#[embassy_executor::task]
+pub async fn buzzer(mut pwm: SimplePwm<'static, TIM1>) {
+    loop {
+        let beep = BEEPS.receive().await;
+
+        for tone in beep.tones() {
+            if tone.freq_hz > 0 {
+                pwm.set_frequency(Hertz(tone.freq_hz));
+                let mut channel = pwm.ch1();
+                channel.set_duty_cycle_percent(50);
+                channel.enable();
+            }
+            Timer::after_millis(tone.duration_ms as u64).await;
+            pwm.ch1().disable();
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use embassy_stm32::gpio::Output;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::Timer;
+
+use crate::config;
+
+// Deteccion de presencia en alguna zona desde el ultimo pulso
+static DETECTED: Signal<CriticalSectionRawMutex, usize> = Signal::new();
+
+// Un controlador de zona empezo a detectar presencia (ya con el fondo
+// aprendido o el umbral fijo, no solo un flanco del sensor)
+pub fn presence(zone: usize) {
+    DETECTED.signal(zone);
+}
+
+// Salida para camaras de seguridad y grabadores (PB14): un pulso de ancho
+// fijo por cada deteccion de presencia, sin importar la lampara. Una
+// deteccion durante el pulso no lo alarga; la entrada de alarma del
+// grabador va con un optoacoplador o un transistor
+#[embassy_executor::task]
+pub async fn camera_trigger(mut output: Output<'static>) {
+    let width = config::get().camera_pulse;
+    loop {
+        let zone = DETECTED.wait().await;
+        info!(Control, "Zona {}: pulso a la camara", zone);
+
+        output.set_high();
+        Timer::after(width).await;
+        output.set_low();
+        DETECTED.reset();
+    }
+}
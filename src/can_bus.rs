@@ -0,0 +1,121 @@
+use core::sync::atomic::Ordering;
+
+use embassy_futures::join::join;
+use embassy_stm32::{
+    bind_interrupts,
+    can::{self, Can, Fifo, Frame, StandardId, filter::ListEntry16},
+    pac,
+    peripherals::{CAN, PB8, PB9},
+};
+use embassy_time::{Duration, Ticker};
+
+use sie_core::{
+    can_frames::{BROADCAST_NODE, Command, Status, command_id, status_id},
+    codes::Code,
+    control::Thresholds,
+};
+
+use crate::{CAN_NODE_ID, MANUAL_MODE, SYSTEM_ENABLED, config_guard, set_manual, zone::ZONES};
+
+bind_interrupts!(struct Irqs {
+    USB_HP_CAN1_TX => can::TxInterruptHandler<CAN>;
+    USB_LP_CAN1_RX0 => can::Rx0InterruptHandler<CAN>;
+    CAN1_RX1 => can::Rx1InterruptHandler<CAN>;
+    CAN1_SCE => can::SceInterruptHandler<CAN>;
+});
+
+const BITRATE: u32 = 125_000;
+// Cada cuanto se transmite el estado de las zonas
+const STATUS_PERIOD: Duration = Duration::from_secs(1);
+
+// Nodo del bus CAN (RX en PB8, TX en PB9, con un transceptor como el
+// TJA1050): transmite el estado de cada zona y acepta ordenes dirigidas a
+// su numero de nodo o a todos. Ver `sie_core::can_frames`
+#[embassy_executor::task]
+pub async fn can_bus(peripheral: CAN, rx: PB8, tx: PB9) {
+    // CAN en PB8/PB9 es la segunda asignacion de pines
+    pac::AFIO.mapr().modify(|w| w.set_can1_remap(2));
+
+    let mut can = Can::new(peripheral, rx, tx, Irqs);
+    // Solo las ordenes para este nodo y las generales; la lista del banco
+    // lleva cuatro identificadores
+    can.modify_filters().enable_bank(
+        0,
+        Fifo::Fifo0,
+        [
+            ListEntry16::data_frames_with_id(id(command_id(CAN_NODE_ID))),
+            ListEntry16::data_frames_with_id(id(command_id(BROADCAST_NODE))),
+            ListEntry16::data_frames_with_id(id(command_id(CAN_NODE_ID))),
+            ListEntry16::data_frames_with_id(id(command_id(BROADCAST_NODE))),
+        ],
+    );
+    can.set_bitrate(BITRATE);
+    can.enable().await;
+    info!(Links, "Nodo CAN {}", CAN_NODE_ID);
+
+    let (mut can_tx, mut can_rx) = can.split();
+    let transmit = async {
+        let mut ticker = Ticker::every(STATUS_PERIOD);
+        loop {
+            ticker.next().await;
+            for (zone, state) in ZONES.iter().enumerate() {
+                let reading = state.last_reading.lock(|r| r.get());
+                let status = Status {
+                    zone: zone as u8,
+                    brightness: state.with_light(|l| l.brightness()).unwrap_or(0),
+                    manual: MANUAL_MODE.load(Ordering::Relaxed),
+                    enabled: SYSTEM_ENABLED.load(Ordering::Relaxed),
+                    lux: reading.map_or(0, |r| r.lux as u16),
+                    distance_cm: reading.map_or(0, |r| (r.distance * 100.) as u16),
+                };
+                if let Ok(frame) = Frame::new_data(id(status_id(CAN_NODE_ID)), &status.encode()) {
+                    can_tx.write(&frame).await;
+                }
+            }
+        }
+    };
+    let receive = async {
+        loop {
+            let Ok(envelope) = can_rx.read().await else {
+                continue;
+            };
+            match Command::decode(envelope.frame.data()) {
+                Some(command) => apply(command),
+                None => warn!(Code::UnknownCommand, "Orden CAN desconocida"),
+            }
+        }
+    };
+    join(transmit, receive).await;
+}
+
+fn apply(command: Command) {
+    match command {
+        Command::SetManual(manual) => {
+            set_manual(manual);
+        }
+        Command::SetLightThreshold(lux) => {
+            set_thresholds(|t| t.light = lux as f32);
+        }
+        Command::SetDistanceThreshold(cm) => {
+            set_thresholds(|t| t.distance = cm as f32 / 100.);
+        }
+    }
+    info!(Links, "Orden CAN recibida");
+}
+
+fn set_thresholds(change: impl Fn(&mut Thresholds)) {
+    config_guard::remote_change(|| {
+        for zone in &ZONES {
+            zone.thresholds.lock(|t| {
+                let mut thresholds = t.get();
+                change(&mut thresholds);
+                t.set(thresholds);
+            });
+        }
+    });
+}
+
+fn id(raw: u16) -> StandardId {
+    // Los identificadores de `can_frames` son de 11 bits
+    StandardId::new(raw).unwrap_or(StandardId::ZERO)
+}
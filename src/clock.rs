@@ -0,0 +1,12 @@
+use embassy_time::Instant;
+use sie_core::clock::Clock;
+
+// Reloj del sistema basado en el time driver de embassy
+#[derive(Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        Instant::now().as_millis()
+    }
+}
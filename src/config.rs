@@ -0,0 +1,242 @@
+use core::cell::Cell;
+
+use embassy_stm32::time::Hertz;
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::Duration;
+
+use sie_core::{
+    background::Presence,
+    occupancy::{FixedTimeout, Timeout},
+};
+
+use crate::{button::Press, light::MAX_BRIGHTNESS};
+
+// Parametros ajustables del sistema. Los umbrales y los modelos de los
+// sensores son de cada zona (ver `zones!`); aqui va lo comun a todas
+#[derive(Clone, Copy)]
+pub struct Config {
+    // Tiempo de asentamiento para el antirrebote de los botones
+    pub debounce_time: Duration,
+    // Duracion a partir de la cual una pulsacion se considera larga
+    pub long_press_time: Duration,
+    // Tiempo maximo entre clics de un doble o triple clic
+    pub click_window: Duration,
+    // Gesto del boton de modo que alterna el modo manual: doble clic
+    // (`Press::Double`) o pulsacion larga (`Press::Long`), para que un roce
+    // al buscar el boton de la luz en la oscuridad no cambie el modo
+    pub manual_gesture: Press,
+
+    // Frecuencia del PWM de las lamparas. Algunos drivers de LED zumban a
+    // ciertas frecuencias; se limita al rango seguro del timer al aplicarla
+    pub pwm_frequency: Hertz,
+    // Tiempo de la rampa de brillo de apagado a encendido total
+    pub fade_time: Duration,
+    // Ciclo de trabajo minimo de los drivers de las zonas, ya con la
+    // correccion perceptual; por debajo parpadean y se prefiere apagar la
+    // lampara
+    pub min_duty: f32,
+
+    // Brillo del modo automatico cuando esta oscuro y hay presencia
+    pub presence_brightness: u8,
+    // Brillo cuando esta oscuro pero no hay nadie (0 = apagada)
+    pub idle_brightness: u8,
+    // Con `outdoor-light` las reglas estandar solo encienden si afuera hay
+    // menos luz que esta (una ventana no alcanza a iluminar)
+    #[cfg(feature = "outdoor-light")]
+    pub outdoor_dark: f32, // Luxes
+
+    // Sin pulsaciones durante este tiempo el modo manual vuelve al
+    // automatico; el LED de estado avisa durante el ultimo tramo
+    pub manual_timeout: Duration,
+    pub manual_warning: Duration,
+
+    // Politica de espera de las zonas al dejar de detectar presencia:
+    // fija, adaptativa (aprende cuanto tarda la gente en pasar) o segun el
+    // horario (ver sie_core::occupancy)
+    pub timeout: Timeout,
+    // Deteccion de presencia: desviacion del fondo aprendido o
+    // `Presence::Threshold` con el umbral fijo de distancia
+    pub presence: Presence,
+
+    // Tiempo maximo encendida sin interrupcion; despues la lampara se
+    // apaga hasta que haya un nuevo movimiento pasado el enfriamiento, o
+    // hasta un clic del boton de la luz
+    pub max_on_time: Duration,
+    pub on_limit_cooldown: Duration,
+
+    // Tiempo con los dos botones presionados para volver a la
+    // configuracion de fabrica
+    pub factory_reset_hold: Duration,
+
+    // Modo en lazo cerrado: iluminacion total que se busca mantener
+    // y cambio de brillo (%) por lux de error en cada ciclo
+    pub lux_setpoint: f32, // Luxes
+    pub regulator_gain: f32,
+
+    // Voltajes de la bateria de un nodo solar: bajo el critico el nodo se
+    // apaga y vuelve al superar el de recuperacion
+    #[cfg(feature = "battery")]
+    pub battery: sie_core::battery::Thresholds,
+
+    // Ancho del pulso de la salida para camaras
+    #[cfg(feature = "camera-trigger")]
+    pub camera_pulse: Duration,
+
+    // Cada cuanto se agrega una fila por zona al registro de la tarjeta SD
+    #[cfg(feature = "sd-log")]
+    pub sd_log_interval: Duration,
+
+    // Prueba de los umbrales recibidos a distancia: se vuelve a los
+    // anteriores si una lampara queda encendida sin parar
+    // `trial_max_on` o si ninguna enciende en `trial_period`
+    #[cfg(any(feature = "can", feature = "lora", feature = "i2c-slave"))]
+    pub trial_max_on: Duration,
+    #[cfg(any(feature = "can", feature = "lora", feature = "i2c-slave"))]
+    pub trial_period: Duration,
+
+    // Franja horaria en la que se arma el modo automatico. Mientras la
+    // hora del RTC no se haya ajustado el modo automatico queda siempre
+    // armado
+    #[cfg(feature = "schedule")]
+    pub schedule: sie_core::schedule::Schedule,
+    // Hora del corte del resumen diario de cada zona. Mientras la hora del
+    // RTC no se haya ajustado el resumen se emite cada 24 h desde el
+    // arranque
+    #[cfg(feature = "schedule")]
+    pub summary_time: sie_core::schedule::TimeOfDay,
+}
+
+impl Config {
+    // Gesto que habilita o deshabilita el sistema; la pulsacion larga, o el
+    // triple clic si la larga ya cambia el modo
+    pub const fn system_gesture(&self) -> Press {
+        match self.manual_gesture {
+            Press::Long => Press::Triple,
+            _ => Press::Long,
+        }
+    }
+
+    // Gesto que no usan los demas: alterna el visor de 7 segmentos entre la
+    // luz y la distancia o, sin el visor, muestra u oculta las lecturas de
+    // cada muestra por RTT
+    #[cfg(any(feature = "defmt", feature = "seven-segment"))]
+    pub const fn spare_gesture(&self) -> Press {
+        match self.manual_gesture {
+            Press::Single => Press::Triple,
+            _ => Press::Single,
+        }
+    }
+
+    // Gesto que toma la luz actual de cada zona como su umbral de
+    // oscuridad; el que queda libre entre el doble y el triple clic
+    pub const fn teach_gesture(&self) -> Press {
+        match self.manual_gesture {
+            Press::Double => Press::Triple,
+            _ => Press::Double,
+        }
+    }
+}
+
+// Configuracion de arranque
+pub const DEFAULT: Config = Config {
+    debounce_time: Duration::from_millis(50),
+    long_press_time: Duration::from_secs(2),
+    click_window: Duration::from_millis(400),
+    manual_gesture: Press::Double,
+
+    pwm_frequency: Hertz::khz(1),
+    fade_time: Duration::from_millis(800),
+    min_duty: 0.02,
+
+    presence_brightness: MAX_BRIGHTNESS,
+    idle_brightness: 0,
+    #[cfg(feature = "outdoor-light")]
+    outdoor_dark: 200.,
+
+    manual_timeout: Duration::from_secs(30 * 60),
+    manual_warning: Duration::from_secs(60),
+
+    timeout: Timeout::Fixed(FixedTimeout { hold_ms: 30_000 }),
+    // Algo al menos 50 cm mas cerca que el fondo (la pared o el piso), sin
+    // importar a que distancia se instalo el sensor
+    presence: Presence::Background { margin_m: 0.5 },
+
+    max_on_time: Duration::from_secs(2 * 60 * 60),
+    on_limit_cooldown: Duration::from_secs(10 * 60),
+
+    factory_reset_hold: Duration::from_secs(10),
+
+    lux_setpoint: 300.,
+    regulator_gain: 0.02,
+
+    // Bateria de plomo-acido de 12 V
+    #[cfg(feature = "battery")]
+    battery: sie_core::battery::Thresholds {
+        critical_v: 11.5,
+        recover_v: 12.4,
+    },
+
+    #[cfg(feature = "camera-trigger")]
+    camera_pulse: Duration::from_millis(500),
+
+    #[cfg(feature = "sd-log")]
+    sd_log_interval: Duration::from_secs(60),
+
+    #[cfg(any(feature = "can", feature = "lora", feature = "i2c-slave"))]
+    trial_max_on: Duration::from_secs(24 * 60 * 60),
+    #[cfg(any(feature = "can", feature = "lora", feature = "i2c-slave"))]
+    trial_period: Duration::from_secs(7 * 24 * 60 * 60),
+
+    #[cfg(feature = "schedule")]
+    schedule: sie_core::schedule::Schedule {
+        start: sie_core::schedule::TimeOfDay::hm(19, 0),
+        end: sie_core::schedule::TimeOfDay::hm(7, 0),
+    },
+    #[cfg(feature = "schedule")]
+    summary_time: sie_core::schedule::TimeOfDay::hm(8, 0),
+};
+
+// Los gestos del boton de modo no coinciden con ningun `manual_gesture`
+const _: () = {
+    let presses = [Press::Single, Press::Double, Press::Triple, Press::Long];
+    let mut i = 0;
+    while i < presses.len() {
+        let config = Config {
+            manual_gesture: presses[i],
+            ..DEFAULT
+        };
+        #[cfg(any(feature = "defmt", feature = "seven-segment"))]
+        let gestures = [
+            config.manual_gesture as u8,
+            config.system_gesture() as u8,
+            config.spare_gesture() as u8,
+            config.teach_gesture() as u8,
+        ];
+        #[cfg(not(any(feature = "defmt", feature = "seven-segment")))]
+        let gestures = [
+            config.manual_gesture as u8,
+            config.system_gesture() as u8,
+            config.teach_gesture() as u8,
+        ];
+        let mut a = 0;
+        while a < gestures.len() {
+            let mut b = a + 1;
+            while b < gestures.len() {
+                assert!(
+                    gestures[a] != gestures[b],
+                    "Dos gestos del boton de modo coinciden"
+                );
+                b += 1;
+            }
+            a += 1;
+        }
+        i += 1;
+    }
+};
+
+// Configuracion vigente, compartida con las tareas
+static CONFIG: CriticalSectionMutex<Cell<Config>> = CriticalSectionMutex::new(Cell::new(DEFAULT));
+
+pub fn get() -> Config {
+    CONFIG.lock(|c| c.get())
+}
@@ -0,0 +1,139 @@
+use core::cell::Cell;
+
+use embassy_sync::{
+    blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+use embassy_time::{Duration, Timer};
+
+use sie_core::{
+    beep::Beep,
+    codes::Code,
+    settings::{BLOCK_SIZE, Settings},
+    trial::{ConfigTrial, Fault, Outcome},
+};
+
+use crate::{
+    buzzer,
+    clock::SystemClock,
+    config, counters,
+    kv::{self, Key},
+    storage::ERASED,
+    zone::{ZONE_COUNT, ZONES},
+};
+
+// Periodo de revision de las lamparas
+const TICK: Duration = Duration::from_secs(1);
+
+// Ajustes previos al primer cambio remoto mientras dura la prueba. Se
+// guardan tambien en flash para que un reinicio no pierda la vuelta atras
+static KNOWN_GOOD: CriticalSectionMutex<Cell<Option<Settings>>> =
+    CriticalSectionMutex::new(Cell::new(None));
+static CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+// Aplica un cambio de umbrales recibido a distancia y lo pone a prueba. Con
+// otro cambio durante la prueba se conserva la ultima configuracion buena
+// y la prueba empieza de nuevo
+pub fn remote_change(change: impl FnOnce()) {
+    if KNOWN_GOOD.lock(|g| g.get()).is_none() {
+        let good = current();
+        if !kv::set(Key::KnownGood, &good.encode()) {
+            warn!(
+                Code::KnownGoodNotSaved,
+                "No se pudo guardar la configuracion buena"
+            );
+        }
+        KNOWN_GOOD.lock(|g| g.set(Some(good)));
+    }
+    change();
+    CHANGED.signal(());
+}
+
+// Retoma la prueba que haya quedado en curso antes de un reinicio.
+// Requiere `kv::init`
+pub fn load() {
+    let mut block = [ERASED; BLOCK_SIZE];
+    if kv::get(Key::KnownGood, &mut block).is_none() {
+        return;
+    }
+    if let Some(good) = Settings::decode(&block) {
+        KNOWN_GOOD.lock(|g| g.set(Some(good)));
+        CHANGED.signal(());
+        info!(Links, "Cambio remoto a prueba desde antes del reinicio");
+    }
+}
+
+// Vigila las lamparas despues de un cambio remoto: si una queda encendida
+// sin parar o ninguna enciende en el periodo de prueba se vuelve a la
+// configuracion anterior y se avisa
+#[embassy_executor::task]
+pub async fn config_guard() {
+    let config = config::get();
+    let mut trial = ConfigTrial::<_, ZONE_COUNT>::new(
+        SystemClock,
+        config.trial_max_on.as_millis(),
+        config.trial_period.as_millis(),
+    );
+
+    loop {
+        Timer::after(TICK).await;
+
+        if CHANGED.try_take().is_some() {
+            trial.start();
+            info!(Links, "Cambio remoto de umbrales a prueba");
+        }
+
+        let lamps = core::array::from_fn(|zone| ZONES[zone].light_is_on());
+        match trial.update(lamps) {
+            None => {}
+            Some(Outcome::Passed) => {
+                forget();
+                info!(Links, "Cambio remoto de umbrales confirmado");
+            }
+            Some(Outcome::Failed(fault)) => {
+                if let Some(good) = KNOWN_GOOD.lock(|g| g.get()) {
+                    for (zone, saved) in ZONES.iter().zip(&good.zones) {
+                        zone.thresholds.lock(|t| t.set(saved.thresholds));
+                    }
+                }
+                forget();
+                buzzer::beep(Beep::Fault);
+                counters::fault();
+                match fault {
+                    Fault::StuckOn => {
+                        warn!(
+                            Code::RevertedStuckOn,
+                            "Lampara encendida sin parar tras un cambio remoto, se revierte"
+                        )
+                    }
+                    Fault::NeverOn => {
+                        warn!(
+                            Code::RevertedNeverOn,
+                            "Ninguna lampara encendio tras un cambio remoto, se revierte"
+                        )
+                    }
+                }
+            }
+        }
+    }
+}
+
+// La prueba termino; la configuracion vigente queda como la buena
+fn forget() {
+    KNOWN_GOOD.lock(|g| g.set(None));
+    if !kv::remove(Key::KnownGood) {
+        warn!(
+            Code::KnownGoodNotCleared,
+            "No se pudo borrar la configuracion buena"
+        );
+    }
+}
+
+// Solo importan los umbrales, lo unico que se cambia a distancia
+fn current() -> Settings {
+    let mut settings = Settings::default();
+    for (zone, saved) in ZONES.iter().zip(&mut settings.zones) {
+        saved.thresholds = zone.thresholds.lock(|t| t.get());
+    }
+    settings
+}
@@ -0,0 +1,1139 @@
+use core::{
+    cell::Cell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use embassy_futures::select::{Either3, select3};
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+
+#[cfg(feature = "usb-console")]
+use embassy_stm32::{
+    bind_interrupts,
+    gpio::{Level, Output, Speed},
+    peripherals::{PA11, PA12, USB},
+    usb::{self, Driver},
+};
+#[cfg(not(feature = "usb-console"))]
+use embassy_stm32::{
+    bind_interrupts,
+    peripherals::{PA9, PA10, USART1},
+    usart::{self, BufferedUart},
+};
+#[cfg(feature = "usb-console")]
+use embassy_time::Timer;
+use embassy_time::{Duration, Ticker};
+#[cfg(feature = "usb-console")]
+use embassy_usb::{
+    Builder,
+    class::cdc_acm::{CdcAcmClass, State},
+};
+#[cfg(not(feature = "usb-console"))]
+use embedded_io_async::{Read, Write};
+use heapless::Vec;
+use static_cell::StaticCell;
+
+#[cfg(feature = "demo")]
+use sie_core::demo::{SCRIPTS, Script};
+#[cfg(feature = "event-log")]
+use sie_core::event_log::{Cursor, Entry as LogEntry, Record, Stamp};
+#[cfg(any(feature = "schedule", feature = "event-log"))]
+use sie_core::schedule::TimeOfDay;
+#[cfg(feature = "teaching")]
+use sie_core::sensor::{MAX_ADC_VALUE, VOLTAGE_REF};
+use sie_core::{
+    control::Thresholds,
+    event_log::Reason,
+    histogram::DistanceHistogram,
+    rules::{Rule, RuleSet, parse_decimal},
+    units::Units,
+};
+
+use crate::{
+    MANUAL_MODE, SYSTEM_ENABLED,
+    clock::SystemClock,
+    config, counters, crash, diagnostics, flash_log,
+    fmt::{self, LOG_ENABLED, Subsystem},
+    light::MAX_BRIGHTNESS,
+    memory, rules, set_manual,
+    zone::{ZONES, ZoneState, standard_rules},
+};
+
+#[cfg(feature = "demo")]
+use crate::demo;
+#[cfg(feature = "event-log")]
+use crate::event_log;
+#[cfg(feature = "schedule")]
+use crate::wall_clock;
+#[cfg(feature = "stats")]
+use crate::zone::STATS_WINDOWS_MS;
+#[cfg(feature = "teaching")]
+use crate::zone::Trace;
+#[cfg(any(feature = "history", feature = "event-log"))]
+use crate::{button::Press, events::Button};
+#[cfg(feature = "history")]
+use crate::{
+    events::Event,
+    history::{self, Entry},
+};
+
+#[cfg(not(feature = "usb-console"))]
+bind_interrupts!(struct Irqs {
+    USART1 => usart::BufferedInterruptHandler<USART1>;
+});
+#[cfg(feature = "usb-console")]
+bind_interrupts!(struct Irqs {
+    USB_LP_CAN1_RX0 => usb::InterruptHandler<USB>;
+});
+
+// Longitud maxima de una linea de comando y de una respuesta
+const LINE_LENGTH: usize = 80;
+const REPLY_LENGTH: usize = 320;
+
+type Reply = Vec<u8, REPLY_LENGTH>;
+
+// Unidades de las distancias mostradas
+static UNITS: CriticalSectionMutex<Cell<Units>> =
+    CriticalSectionMutex::new(Cell::new(crate::UNITS));
+
+fn units() -> Units {
+    UNITS.lock(|u| u.get())
+}
+
+// Lecturas periodicas activadas con `stream on`
+static STREAM: AtomicBool = AtomicBool::new(false);
+const STREAM_PERIOD: Duration = Duration::from_secs(1);
+
+// Catalogo de los comandos: uso con sus argumentos y descripcion en
+// espanol y en ingles. `ayuda` y `help` lo recorren, asi que un comando
+// nuevo solo se documenta aqui. Los atributos (por ejemplo `#[cfg(...)]`)
+// se aplican a la entrada
+macro_rules! commands {
+    ($( $(#[$attr:meta])* $usage:literal => $es:literal, $en:literal; )+) => {
+        fn for_each_command(mut f: impl FnMut(&Command)) {
+            $(
+                $(#[$attr])*
+                f(&Command { usage: $usage, es: $es, en: $en });
+            )+
+        }
+    };
+}
+
+struct Command {
+    usage: &'static str,
+    es: &'static str,
+    en: &'static str,
+}
+
+impl Command {
+    // Primera palabra del uso
+    fn name(&self) -> &'static str {
+        self.usage.split(' ').next().unwrap_or(self.usage)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Language {
+    Spanish,
+    English,
+}
+
+commands! {
+    "ayuda [COMANDO]" =>
+        "comandos, o el uso de uno",
+        "commands, or how to use one (in Spanish)";
+    "help [COMMAND]" =>
+        "comandos, o el uso de uno (en ingles)",
+        "commands, or how to use one";
+    "status" =>
+        "modo, lamparas, ultimas lecturas y umbrales de cada zona",
+        "mode, lamps, last readings and thresholds of each zone";
+    "set light-threshold LUXES" =>
+        "umbral de luz de todas las zonas",
+        "light threshold of every zone";
+    "set distance-threshold DIST" =>
+        "umbral de distancia de todas las zonas",
+        "distance threshold of every zone";
+    "units metric|imperial" =>
+        "distancias en metros o en pies",
+        "distances in meters or feet";
+    "mode manual|auto" =>
+        "cambia el modo de operacion",
+        "changes the operating mode";
+    "cal lux LUXES|reset" =>
+        "calibra el sensor de luz con un luxometro, o quita la calibracion",
+        "calibrates the light sensor against a lux meter, or clears it";
+    "log [on|off|SUBSISTEMA debug|info|warn]" =>
+        "nivel de registro por RTT de cada subsistema (sensores, control, enlaces, sistema); on|off activa o silencia los mensajes informativos",
+        "RTT log level of each subsystem (sensores, control, enlaces, sistema); on|off enables or mutes the informational messages";
+    "lamp on|off|toggle" =>
+        "enciende o apaga las lamparas (pasa a modo manual)",
+        "switches the lamps (enters manual mode)";
+    "stream on|off" =>
+        "una linea por zona cada segundo: Z<zona> <brillo> <luxes> <metros>",
+        "one line per zone every second: Z<zone> <level> <lux> <meters>";
+    #[cfg(feature = "schedule")]
+    "hora [HH:MM[:SS]]" =>
+        "muestra o ajusta la hora del reloj de tiempo real",
+        "shows or sets the real time clock";
+    "distancias" =>
+        "histograma de las distancias de la ultima hora por zona",
+        "histogram of the last hour of distances per zone";
+    #[cfg(feature = "stats")]
+    "estadisticas Z" =>
+        "minimo, maximo, media y varianza de la luz y la distancia de la zona Z en el ultimo minuto y la ultima hora",
+        "min, max, mean and variance of the light and distance of zone Z over the last minute and hour";
+    "mem" =>
+        "RAM de los estaticos y maximo de pila usado",
+        "static RAM and peak stack use";
+    "bucle" =>
+        "duracion y jitter del bucle de cada zona en el ultimo minuto",
+        "control loop duration and jitter of each zone over the last minute";
+    "panic" =>
+        "mensaje del panic que causo el ultimo reinicio",
+        "message of the panic behind the last reset";
+    #[cfg(feature = "teaching")]
+    "explica Z" =>
+        "cada paso de la ultima muestra de la zona Z",
+        "each conversion step of the last sample of zone Z";
+    #[cfg(feature = "demo")]
+    "demo [NOMBRE|alto]" =>
+        "secuencias de demostracion: lista, corre o detiene",
+        "demo sequences: list, run or stop";
+    "regla Z TEXTO" =>
+        "agrega una regla a la zona Z (ver sie_core::rules)",
+        "appends a rule to zone Z (see sie_core::rules)";
+    "reglas Z [borrar|estandar]" =>
+        "numero de reglas de la zona Z; las quita o restablece las estandar",
+        "number of rules of zone Z; clears them or restores the standard ones";
+    "reglas guardar" =>
+        "guarda las reglas de todas las zonas en flash",
+        "saves the rules of every zone to flash";
+    #[cfg(feature = "history")]
+    "historial [borrar]" =>
+        "ultimas muestras y eventos en RAM (se muestran solos tras una falla); borrar vuelve a registrar",
+        "last samples and events kept in RAM (shown on their own after a fault); borrar resumes recording";
+    #[cfg(feature = "event-log")]
+    "eventos [borrar]" =>
+        "sucesos guardados en flash (lamparas con su motivo, modo, botones, fallas y arranques), o los borra",
+        "events kept in flash (lamps with their reason, mode, buttons, faults and boots), or erases them";
+}
+
+// Transporte de la consola: USART1 o, con la opcion `usb-console`, un
+// puerto serie virtual USB (CDC)
+trait Port {
+    // Lee al menos un byte; None si el enlace fallo
+    async fn read(&mut self, buf: &mut [u8]) -> Option<usize>;
+    async fn write(&mut self, data: &[u8]);
+}
+
+#[cfg(not(feature = "usb-console"))]
+impl Port for BufferedUart<'static> {
+    async fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        Read::read(self, buf).await.ok()
+    }
+
+    async fn write(&mut self, data: &[u8]) {
+        let _ = self.write_all(data).await;
+    }
+}
+
+// Paquetes de 64 bytes, el maximo de un endpoint bulk de velocidad completa
+#[cfg(feature = "usb-console")]
+const USB_PACKET: usize = 64;
+
+#[cfg(feature = "usb-console")]
+impl Port for CdcAcmClass<'static, Driver<'static, USB>> {
+    async fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        // Sin host conectado se espera a que abra el puerto
+        self.wait_connection().await;
+        self.read_packet(buf).await.ok()
+    }
+
+    async fn write(&mut self, data: &[u8]) {
+        for packet in data.chunks(USB_PACKET) {
+            if self.write_packet(packet).await.is_err() {
+                return;
+            }
+        }
+        // Un paquete completo al final necesita uno vacio para que el host
+        // entregue los datos
+        if data.len().is_multiple_of(USB_PACKET) {
+            let _ = self.write_packet(&[]).await;
+        }
+    }
+}
+
+// Consola serie en USART1 (TX en PA9, RX en PA10, 115200 8N1). Con la
+// opcion `bluetooth` un modulo HC-05/HC-06 ocupa el lugar del adaptador
+// USB-UART (a 9600 8N1, su velocidad de fabrica) y la consola se usa
+// desde un telefono con cualquier terminal Bluetooth.
+// Los comandos estan en el catalogo (`commands!`), que la consola muestra
+// con `ayuda` o `help`
+#[cfg(not(feature = "usb-console"))]
+#[embassy_executor::task]
+pub async fn console(usart: USART1, tx: PA9, rx: PA10) {
+    static TX_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+    static RX_BUF: StaticCell<[u8; 32]> = StaticCell::new();
+
+    #[allow(unused_mut)]
+    let mut config = usart::Config::default();
+    #[cfg(feature = "bluetooth")]
+    {
+        config.baudrate = 9600;
+    }
+    let Ok(uart) = BufferedUart::new(
+        usart,
+        Irqs,
+        rx,
+        tx,
+        TX_BUF.init([0; 64]),
+        RX_BUF.init([0; 32]),
+        config,
+    ) else {
+        crate::error::degrade(crate::error::Error::Setup(
+            sie_core::codes::Code::ConsoleSetup,
+        ));
+        return;
+    };
+
+    serve(uart).await;
+}
+
+// La misma consola como puerto serie virtual USB (D+ en PA12, D- en PA11),
+// sin adaptador USB-UART. Requiere el cristal de 8 MHz para los 48 MHz del
+// USB
+#[cfg(feature = "usb-console")]
+#[embassy_executor::task]
+pub async fn console(usb: USB, mut dp: PA12, dm: PA11) {
+    static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+    static STATE: StaticCell<State> = StaticCell::new();
+
+    // La Blue Pill tiene la resistencia de D+ fija; bajar D+ un momento
+    // hace que el host vuelva a enumerar el equipo tras un reinicio
+    {
+        let _dp = Output::new(&mut dp, Level::Low, Speed::Low);
+        Timer::after_millis(10).await;
+    }
+
+    let driver = Driver::new(usb, Irqs, dp, dm);
+    let mut config = embassy_usb::Config::new(0xc0de, 0xcafe);
+    config.manufacturer = Some("EI SIE");
+    config.product = Some("Consola SIE");
+    config.max_power = 100;
+    config.max_packet_size_0 = 64;
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        CONFIG_DESCRIPTOR.init([0; 256]),
+        BOS_DESCRIPTOR.init([0; 256]),
+        &mut [],
+        CONTROL_BUF.init([0; 64]),
+    );
+    let class = CdcAcmClass::new(&mut builder, STATE.init(State::new()), USB_PACKET as u16);
+    let mut device = builder.build();
+
+    embassy_futures::join::join(device.run(), serve(class)).await;
+}
+
+// Arma lineas con lo recibido y responde cada una
+async fn serve(mut port: impl Port) {
+    let mut line: Vec<u8, LINE_LENGTH> = Vec::new();
+    let mut buf = [0; 64];
+    let mut stream = Ticker::every(STREAM_PERIOD);
+    loop {
+        let len = match select3(port.read(&mut buf), stream.next(), fault_captured()).await {
+            Either3::First(Some(len)) => len,
+            Either3::First(None) => continue,
+            Either3::Second(()) => {
+                if STREAM.load(Ordering::Relaxed) {
+                    port.write(&stream_line()).await;
+                }
+                continue;
+            }
+            Either3::Third(()) => {
+                #[cfg(feature = "history")]
+                write_history(&mut port).await;
+                continue;
+            }
+        };
+
+        for &byte in &buf[..len] {
+            match byte {
+                b'\r' | b'\n' => {
+                    if !line.is_empty() {
+                        let command = core::str::from_utf8(&line).unwrap_or("");
+                        // La historia no cabe en una respuesta; va por lineas
+                        #[cfg(feature = "history")]
+                        if command.trim() == "historial" {
+                            write_history(&mut port).await;
+                            line.clear();
+                            continue;
+                        }
+                        #[cfg(feature = "event-log")]
+                        if command.trim() == "eventos" {
+                            write_events(&mut port).await;
+                            line.clear();
+                            continue;
+                        }
+                        let reply = execute(command);
+                        port.write(&reply).await;
+                        line.clear();
+                    }
+                }
+                // Una linea demasiado larga se descarta completa
+                c => {
+                    if line.push(c).is_err() {
+                        line.clear();
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Se completo la historia de una falla; sin el registro de vuelo nunca
+async fn fault_captured() {
+    #[cfg(feature = "history")]
+    history::fault_captured().await;
+    #[cfg(not(feature = "history"))]
+    core::future::pending::<()>().await;
+}
+
+// Una linea por muestra o evento, del mas antiguo al mas reciente
+#[cfg(feature = "history")]
+async fn write_history(port: &mut impl Port) {
+    history::pause(true);
+    let mut reply = Reply::new();
+    push(&mut reply, "historial");
+    if let Some(code) = history::frozen_by() {
+        push(&mut reply, " hasta la falla ");
+        push_number(&mut reply, code.number() as u32);
+    }
+    push(&mut reply, "\r\n");
+    port.write(&reply).await;
+
+    let mut cursor = history::Cursor::default();
+    while let Some(entry) = history::next(&mut cursor) {
+        reply.clear();
+        push_entry(&mut reply, entry);
+        push(&mut reply, "\r\n");
+        port.write(&reply).await;
+    }
+    history::pause(false);
+}
+
+// Una linea por suceso guardado en flash, del mas antiguo al mas reciente
+#[cfg(feature = "event-log")]
+async fn write_events(port: &mut impl Port) {
+    let mut reply = Reply::new();
+    let mut cursor = Cursor::default();
+    while let Some(record) = event_log::next(&mut cursor) {
+        reply.clear();
+        push_record(&mut reply, record);
+        push(&mut reply, "\r\n");
+        port.write(&reply).await;
+    }
+    port.write(b"fin\r\n").await;
+}
+
+// Ejecuta una linea y devuelve la respuesta
+fn execute(line: &str) -> Reply {
+    let mut reply = Vec::new();
+
+    if let Some(rest) = line.trim().strip_prefix("regla ") {
+        add_rule(&mut reply, rest);
+        push(&mut reply, "\r\n");
+        return reply;
+    }
+
+    let mut words = line.split_whitespace();
+
+    match (words.next(), words.next()) {
+        (Some("ayuda"), topic) => push_help(&mut reply, Language::Spanish, topic),
+        (Some("help"), topic) => push_help(&mut reply, Language::English, topic),
+        (Some("status"), None) => push_status(&mut reply),
+        (Some("set"), Some(setting)) => match words.next().and_then(parse_decimal) {
+            Some(value) if value > 0. => match setting {
+                "light-threshold" => set_thresholds(&mut reply, |t| t.light = value),
+                "distance-threshold" => {
+                    let meters = units().meters(value);
+                    set_thresholds(&mut reply, |t| t.distance = meters)
+                }
+                _ => push(&mut reply, "ajuste desconocido"),
+            },
+            _ => push(&mut reply, "valor invalido"),
+        },
+        (Some("mode"), Some(mode @ ("manual" | "auto"))) => {
+            let manual = mode == "manual";
+            set_manual(manual);
+            info!(Control, "Modo manual {}", manual);
+            push(&mut reply, "ok");
+        }
+        (Some("cal"), Some("lux")) => match words.next() {
+            Some("reset") => {
+                ZONES.iter().for_each(ZoneState::reset_lux_calibration);
+                push(&mut reply, "ok");
+            }
+            Some(reference) => match parse_decimal(reference) {
+                Some(reference) if reference > 0. => calibrate_lux(&mut reply, reference),
+                _ => push(&mut reply, "valor invalido"),
+            },
+            None => push(&mut reply, "uso: cal lux LUXES"),
+        },
+        (Some("log"), Some(state @ ("on" | "off"))) => {
+            LOG_ENABLED.store(state == "on", Ordering::Relaxed);
+            push(&mut reply, "ok");
+        }
+        (Some("log"), None) => push_log_levels(&mut reply),
+        (Some("log"), Some(subsystem)) => {
+            match (
+                Subsystem::parse(subsystem),
+                words.next().and_then(fmt::Level::parse),
+            ) {
+                (Some(subsystem), Some(level)) => {
+                    fmt::set_level(subsystem, level);
+                    push(&mut reply, "ok");
+                }
+                _ => push(&mut reply, "uso: log SUBSISTEMA debug|info|warn"),
+            }
+        }
+        (Some("lamp"), Some(action @ ("on" | "off" | "toggle"))) => {
+            set_lamps(action);
+            push(&mut reply, "ok");
+        }
+        (Some("units"), Some(name)) => match Units::parse(name) {
+            Some(selected) => {
+                UNITS.lock(|u| u.set(selected));
+                push(&mut reply, "ok");
+            }
+            None => push(&mut reply, "uso: units metric|imperial"),
+        },
+        (Some("stream"), Some(state @ ("on" | "off"))) => {
+            STREAM.store(state == "on", Ordering::Relaxed);
+            push(&mut reply, "ok");
+        }
+        #[cfg(feature = "schedule")]
+        (Some("hora"), None) => match wall_clock::now() {
+            Some(time) => push_time(&mut reply, time),
+            None => push(&mut reply, "sin ajustar"),
+        },
+        #[cfg(feature = "schedule")]
+        (Some("hora"), Some(arg)) => match TimeOfDay::parse(arg) {
+            Some(time) => {
+                wall_clock::set(time);
+                info!(
+                    Control,
+                    "Hora ajustada a {}:{}",
+                    time.hours(),
+                    time.minutes()
+                );
+                push(&mut reply, "ok");
+            }
+            None => push(&mut reply, "hora invalida"),
+        },
+        (Some("distancias"), None) => push_distances(&mut reply),
+        #[cfg(feature = "stats")]
+        (Some("estadisticas"), Some(zone)) => match parse_zone(zone) {
+            Some(zone) => push_stats(&mut reply, zone),
+            None => push(&mut reply, "zona invalida"),
+        },
+        (Some("mem"), None) => push_memory(&mut reply),
+        (Some("bucle"), None) => push_loops(&mut reply),
+        (Some("panic"), None) => push(&mut reply, crash::last().unwrap_or("sin panic")),
+        #[cfg(feature = "teaching")]
+        (Some("explica"), Some(zone)) => match parse_zone(zone) {
+            Some(zone) => match zone.trace.lock(|t| t.get()) {
+                Some(trace) => push_trace(&mut reply, &trace),
+                None => push(&mut reply, "sin muestras en modo automatico"),
+            },
+            None => push(&mut reply, "zona invalida"),
+        },
+        #[cfg(feature = "demo")]
+        (Some("demo"), None) => {
+            for (i, script) in SCRIPTS.iter().enumerate() {
+                if i > 0 {
+                    push(&mut reply, " ");
+                }
+                push(&mut reply, script.name);
+            }
+        }
+        #[cfg(feature = "demo")]
+        (Some("demo"), Some("alto")) => {
+            demo::stop();
+            push(&mut reply, "ok");
+        }
+        #[cfg(feature = "demo")]
+        (Some("demo"), Some(name)) => match Script::find(name) {
+            Some(script) => {
+                demo::start(script);
+                push(&mut reply, "ok");
+            }
+            None => push(&mut reply, "secuencia desconocida"),
+        },
+        #[cfg(feature = "event-log")]
+        (Some("eventos"), Some("borrar")) => push(
+            &mut reply,
+            if event_log::clear() {
+                "ok"
+            } else {
+                "no se pudo borrar"
+            },
+        ),
+        #[cfg(feature = "history")]
+        (Some("historial"), Some("borrar")) => {
+            history::clear();
+            push(&mut reply, "ok");
+        }
+        (Some("reglas"), Some("guardar")) => push(
+            &mut reply,
+            if rules::save() {
+                "ok"
+            } else {
+                "no se pudo guardar"
+            },
+        ),
+        (Some("reglas"), Some(zone)) => match parse_zone(zone) {
+            Some(zone) => match words.next() {
+                None => {
+                    let count = zone.rules.lock(|r| r.borrow().rules().len());
+                    push_number(&mut reply, count as u32);
+                }
+                Some("borrar") => {
+                    zone.rules.lock(|r| *r.borrow_mut() = RuleSet::empty());
+                    push(&mut reply, "ok");
+                }
+                Some("estandar") => {
+                    zone.rules
+                        .lock(|r| *r.borrow_mut() = standard_rules(&config::get()));
+                    push(&mut reply, "ok");
+                }
+                Some(_) => push(&mut reply, "comando desconocido"),
+            },
+            None => push(&mut reply, "zona invalida"),
+        },
+        _ => push(&mut reply, "comando desconocido"),
+    }
+
+    push(&mut reply, "\r\n");
+    reply
+}
+
+fn parse_zone(zone: &str) -> Option<&'static ZoneState> {
+    ZONES.get(zone.parse::<usize>().ok()?)
+}
+
+// `Z TEXTO`: agrega la regla al final de las de la zona Z
+fn add_rule(reply: &mut Reply, args: &str) {
+    let Some((zone, text)) = args.trim().split_once(' ') else {
+        push(reply, "uso: regla ZONA TEXTO");
+        return;
+    };
+    let Some(zone) = parse_zone(zone) else {
+        push(reply, "zona invalida");
+        return;
+    };
+    let Some(rule) = Rule::parse(text) else {
+        push(reply, "regla invalida");
+        return;
+    };
+
+    if zone.rules.lock(|r| r.borrow_mut().push(rule)) {
+        push(reply, "ok");
+    } else {
+        push(reply, "no caben mas reglas");
+    }
+}
+
+// Sin tema, los nombres de los comandos; con tema, el uso y la descripcion
+// de cada forma de ese comando
+fn push_help(reply: &mut Reply, language: Language, topic: Option<&str>) {
+    let Some(topic) = topic else {
+        let mut last = "";
+        for_each_command(|command| {
+            if command.name() != last {
+                if !last.is_empty() {
+                    push(reply, " ");
+                }
+                last = command.name();
+                push(reply, last);
+            }
+        });
+        return;
+    };
+
+    let mut found = false;
+    for_each_command(|command| {
+        if command.name() == topic {
+            if found {
+                push(reply, "\r\n");
+            }
+            found = true;
+            push(reply, command.usage);
+            push(reply, ": ");
+            push(
+                reply,
+                match language {
+                    Language::Spanish => command.es,
+                    Language::English => command.en,
+                },
+            );
+        }
+    });
+    if !found {
+        push(
+            reply,
+            match language {
+                Language::Spanish => "comando desconocido",
+                Language::English => "unknown command",
+            },
+        );
+    }
+}
+
+// Modo y una linea por zona: lampara, luz, distancia y umbrales
+fn push_status(reply: &mut Reply) {
+    push(reply, "modo ");
+    push(
+        reply,
+        if !SYSTEM_ENABLED.load(Ordering::Relaxed) {
+            "deshabilitado"
+        } else if MANUAL_MODE.load(Ordering::Relaxed) {
+            "manual"
+        } else {
+            "auto"
+        },
+    );
+    push(reply, "\r\n");
+
+    let totals = counters::get();
+    push(reply, "arranques ");
+    push_number(reply, totals.boots);
+    push(reply, ", activaciones ");
+    push_number(reply, totals.activations);
+    push(reply, ", encendida ");
+    push_number(reply, totals.on_seconds / 3600);
+    push(reply, " h, fallas ");
+    push_number(reply, totals.faults);
+    if let Some(code) = flash_log::last_fault() {
+        push(reply, ", ultima E");
+        push_number(reply, code.number() as u32);
+    }
+    push(reply, "\r\n");
+
+    for (id, zone) in ZONES.iter().enumerate() {
+        push(reply, "zona ");
+        push_number(reply, id as u32);
+        push(
+            reply,
+            if zone.light_is_on() {
+                ": encendida"
+            } else {
+                ": apagada"
+            },
+        );
+        if let Some(reading) = zone.last_reading.lock(|r| r.get()) {
+            push(reply, ", ");
+            push_decimal(reply, reading.lux);
+            push(reply, " lx, ");
+            push_distance(reply, reading.distance);
+        }
+        let thresholds = zone.thresholds.lock(|t| t.get());
+        push(reply, ", umbrales ");
+        push_decimal(reply, thresholds.light);
+        push(reply, " lx ");
+        push_distance(reply, thresholds.distance);
+        push(reply, "\r\n");
+    }
+    reply.truncate(reply.len() - 2);
+}
+
+// Todas las lamparas al mismo estado, como el boton en modo manual
+fn set_lamps(action: &str) {
+    let on = match action {
+        "on" => true,
+        "off" => false,
+        _ => !ZONES.iter().any(ZoneState::light_is_on),
+    };
+    set_manual(true);
+    for zone in &ZONES {
+        zone.switch_light(Reason::Console, |l| {
+            l.set_brightness(if on { MAX_BRIGHTNESS } else { 0 })
+        });
+    }
+}
+
+// `Z<zona> <brillo> <luxes> <metros>` por zona, facil de leer desde una
+// aplicacion
+fn stream_line() -> Reply {
+    let mut reply = Vec::new();
+    for (id, zone) in ZONES.iter().enumerate() {
+        push(&mut reply, "Z");
+        push_number(&mut reply, id as u32);
+        push(&mut reply, " ");
+        push_number(
+            &mut reply,
+            zone.with_light(|l| l.brightness()).unwrap_or(0) as u32,
+        );
+        if let Some(reading) = zone.last_reading.lock(|r| r.get()) {
+            push(&mut reply, " ");
+            push_decimal(&mut reply, reading.lux);
+            push(&mut reply, " ");
+            push_decimal(&mut reply, units().distance(reading.distance));
+        }
+        push(&mut reply, "\r\n");
+    }
+    reply
+}
+
+// Aplica el cambio a los umbrales de todas las zonas
+fn set_thresholds(reply: &mut Reply, change: impl Fn(&mut Thresholds)) {
+    for zone in &ZONES {
+        zone.thresholds.lock(|t| {
+            let mut thresholds = t.get();
+            change(&mut thresholds);
+            t.set(thresholds);
+        });
+    }
+    push(reply, "ok");
+}
+
+// Calibra cada zona con la misma referencia; responde el factor de cada una
+fn calibrate_lux(reply: &mut Reply, reference: f32) {
+    for (id, zone) in ZONES.iter().enumerate() {
+        if id > 0 {
+            push(reply, ", ");
+        }
+        match zone.calibrate_lux(reference) {
+            Some(scale) => {
+                push(reply, "x");
+                push_decimal(reply, scale);
+            }
+            None => push(reply, "sin lectura"),
+        }
+    }
+}
+
+fn push(reply: &mut Reply, text: &str) {
+    let _ = reply.extend_from_slice(text.as_bytes());
+}
+
+fn push_number(reply: &mut Reply, value: u32) {
+    let mut digits = [0; 10];
+    let mut len = 0;
+    let mut rest = value;
+    loop {
+        digits[len] = b'0' + (rest % 10) as u8;
+        len += 1;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    for &digit in digits[..len].iter().rev() {
+        let _ = reply.push(digit);
+    }
+}
+
+// Distancia en las unidades elegidas, con su simbolo
+fn push_distance(reply: &mut Reply, meters: f32) {
+    let units = units();
+    push_decimal(reply, units.distance(meters));
+    push(reply, " ");
+    push(reply, units.distance_unit());
+}
+
+// `S.D s` desde el arranque y la muestra o el evento
+#[cfg(feature = "history")]
+fn push_entry(reply: &mut Reply, entry: Entry) {
+    let at_ms = match entry {
+        Entry::Sample(sample) => sample.at_ms,
+        Entry::Event(logged) => logged.at_ms,
+    };
+    push_number(reply, at_ms / 1000);
+    push(reply, ".");
+    push_number(reply, at_ms % 1000 / 100);
+    push(reply, " s ");
+    match entry {
+        Entry::Sample(sample) => {
+            push(reply, "Z");
+            push_number(reply, sample.zone as u32);
+            push(reply, " ");
+            push_decimal(reply, sample.lux);
+            push(reply, " lx ");
+            push_distance(reply, sample.distance);
+        }
+        Entry::Event(logged) => match logged.event {
+            Event::ButtonPressed { button, press } => push_button(reply, Some(button), press),
+            Event::ModeChanged { manual, enabled } => push_mode(reply, manual, enabled),
+            Event::LightChanged { zone, on, reason } => push_light(reply, zone, on, reason),
+            Event::Fault(code) => {
+                push(reply, "falla ");
+                push_number(reply, code.number() as u32);
+            }
+            Event::NewLuxReading { .. } | Event::NewDistance { .. } => {}
+        },
+    }
+}
+
+// Hora del dia o `S s` desde el arranque, y el suceso
+#[cfg(feature = "event-log")]
+fn push_record(reply: &mut Reply, record: Record) {
+    match record.stamp {
+        Stamp::Wall(time) => push_time(reply, time),
+        Stamp::Uptime(seconds) => {
+            push_number(reply, seconds);
+            push(reply, " s");
+        }
+    }
+    push(reply, " ");
+    match record.entry {
+        LogEntry::Boot => push(reply, "arranque"),
+        LogEntry::Light { zone, on, reason } => push_light(reply, zone, on, reason),
+        LogEntry::Mode { manual, enabled } => push_mode(reply, manual, enabled),
+        LogEntry::Button { button, press } => push_button(reply, event_log::button(button), press),
+        LogEntry::Fault(code) => {
+            push(reply, "falla ");
+            push_number(reply, code.number() as u32);
+        }
+    }
+}
+
+#[cfg(any(feature = "history", feature = "event-log"))]
+fn push_button(reply: &mut Reply, button: Option<Button>, press: Press) {
+    push(reply, "boton ");
+    push(
+        reply,
+        match button {
+            Some(Button::Manual) => "manual ",
+            Some(Button::Light) => "luz ",
+            #[cfg(feature = "encoder")]
+            Some(Button::Select) => "encoder ",
+            None => "? ",
+        },
+    );
+    push(
+        reply,
+        match press {
+            Press::Single => "clic",
+            Press::Double => "doble clic",
+            Press::Triple => "triple clic",
+            Press::Long => "pulsacion larga",
+        },
+    );
+}
+
+#[cfg(any(feature = "history", feature = "event-log"))]
+fn push_mode(reply: &mut Reply, manual: bool, enabled: bool) {
+    push(reply, if manual { "modo manual" } else { "modo auto" });
+    push(reply, if enabled { "" } else { ", deshabilitado" });
+}
+
+#[cfg(any(feature = "history", feature = "event-log"))]
+fn push_light(reply: &mut Reply, zone: u8, on: bool, reason: Reason) {
+    push(reply, "Z");
+    push_number(reply, zone as u32);
+    push(reply, if on { " encendida (" } else { " apagada (" });
+    push(reply, reason.name());
+    push(reply, ")");
+}
+
+// Valor positivo con un decimal
+fn push_decimal(reply: &mut Reply, value: f32) {
+    let tenths = (value * 10. + 0.5) as u32;
+    push_number(reply, tenths / 10);
+    push(reply, ".");
+    push_number(reply, tenths % 10);
+}
+
+// Una linea por etapa de la conversion, con la cuenta de cada una
+#[cfg(feature = "teaching")]
+fn push_trace(reply: &mut Reply, trace: &Trace) {
+    push(reply, "ADC (0 a ");
+    push_number(reply, MAX_ADC_VALUE as u32);
+    push(reply, "): distancia ");
+    push_number(reply, trace.raw_distance as u32);
+    push(reply, ", luz ");
+    push_number(reply, trace.raw_lux as u32);
+
+    push(reply, "\r\nvoltaje = ADC * ");
+    push_decimal(reply, VOLTAGE_REF);
+    push(reply, " / ");
+    push_number(reply, MAX_ADC_VALUE as u32);
+    push(reply, ": distancia ");
+    push_hundredths(reply, trace.converted.distance_voltage);
+    push(reply, " V, luz ");
+    push_hundredths(reply, trace.converted.lux_voltage);
+    push(reply, " V");
+
+    push(reply, "\r\ncurva de cada sensor: ");
+    push_distance(reply, trace.converted.distance);
+    push(reply, ", ");
+    push_decimal(reply, trace.converted.lux);
+    push(reply, " lx");
+
+    push(reply, "\r\ncalibracion x");
+    push_hundredths(reply, trace.lux_scale);
+    push(reply, ": ");
+    push_decimal(reply, trace.converted.lux * trace.lux_scale);
+    push(reply, " lx");
+
+    push(reply, "\r\noscuro (< ");
+    push_decimal(reply, trace.thresholds.light);
+    push(reply, " lx): ");
+    push_yes_no(reply, trace.decision.dark);
+    push(reply, ", cerca (< ");
+    push_distance(reply, trace.thresholds.distance);
+    push(reply, "): ");
+    push_yes_no(reply, trace.decision.present);
+    push(reply, "\r\npresencia con el fondo: ");
+    push_yes_no(reply, trace.present);
+    push(reply, ", ocupada con la espera: ");
+    push_yes_no(reply, trace.occupied);
+
+    push(reply, "\r\nbrillo ");
+    push_number(reply, trace.brightness as u32);
+    push(reply, "%");
+}
+
+#[cfg(feature = "teaching")]
+fn push_yes_no(reply: &mut Reply, value: bool) {
+    push(reply, if value { "si" } else { "no" });
+}
+
+// Valor positivo con dos decimales
+#[cfg(feature = "teaching")]
+fn push_hundredths(reply: &mut Reply, value: f32) {
+    let hundredths = (value * 100. + 0.5) as u32;
+    push_number(reply, hundredths / 100);
+    push(reply, if hundredths % 100 < 10 { ".0" } else { "." });
+    push_number(reply, hundredths % 100);
+}
+
+// `estaticos N pila N/N` en bytes
+fn push_memory(reply: &mut Reply) {
+    let usage = memory::usage();
+    push(reply, "estaticos ");
+    push_number(reply, usage.statics);
+    push(reply, " pila ");
+    push_number(reply, usage.stack_peak);
+    push(reply, "/");
+    push_number(reply, usage.stack);
+}
+
+// Una linea por subsistema: `nombre nivel`
+fn push_log_levels(reply: &mut Reply) {
+    for subsystem in Subsystem::ALL {
+        push(reply, subsystem.name());
+        push(reply, " ");
+        push(reply, fmt::level(subsystem).name());
+        push(reply, "\r\n");
+    }
+}
+
+// Por zona `zona N: media N us max N us jitter N us`
+fn push_loops(reply: &mut Reply) {
+    for zone in 0..ZONES.len() {
+        push(reply, "zona ");
+        push_number(reply, zone as u32);
+        match diagnostics::last_loop(zone) {
+            Some(summary) => {
+                push(reply, ": media ");
+                push_number(reply, summary.duration.mean_us);
+                push(reply, " us max ");
+                push_number(reply, summary.duration.max_us);
+                push(reply, " us jitter ");
+                push_number(reply, summary.jitter_us());
+                push(reply, " us\r\n");
+            }
+            None => push(reply, ": sin datos\r\n"),
+        }
+    }
+}
+
+// Una linea por intervalo: distancia inicial (m) y numero de lecturas
+fn push_distances(reply: &mut Reply) {
+    for (id, zone) in ZONES.iter().enumerate() {
+        let counts = zone.distances.lock(|h| h.borrow_mut().counts());
+
+        push(reply, "zona ");
+        push_number(reply, id as u32);
+        push(reply, "\r\n");
+        for (bin, count) in counts.into_iter().enumerate() {
+            push_distance(reply, DistanceHistogram::<SystemClock>::bin_start(bin));
+            push(reply, ": ");
+            push_number(reply, count);
+            push(reply, "\r\n");
+        }
+    }
+}
+
+// Por ventana: el numero de muestras y una linea por sensor. La varianza
+// de la distancia va en la unidad al cuadrado y con dos decimales
+#[cfg(feature = "stats")]
+fn push_stats(reply: &mut Reply, zone: &ZoneState) {
+    let stats = zone.stats.lock(|s| s.borrow_mut().summary());
+    let units = units();
+    for (i, window_ms) in STATS_WINDOWS_MS.into_iter().enumerate() {
+        let (lux, distance) = (stats.lux[i], stats.distance[i]);
+        push_number(reply, (window_ms / 60_000) as u32);
+        push(reply, " min, ");
+        push_number(reply, lux.count);
+        push(reply, " muestras\r\n");
+        if lux.count == 0 {
+            continue;
+        }
+        push(reply, "luz lx:");
+        for (label, value) in [
+            (" min ", lux.min),
+            (" max ", lux.max),
+            (" media ", lux.mean),
+            (" var ", lux.variance),
+        ] {
+            push(reply, label);
+            push_decimal(reply, value);
+        }
+        push(reply, "\r\ndist ");
+        push(reply, units.distance_unit());
+        push(reply, ":");
+        for (label, value) in [
+            (" min ", distance.min),
+            (" max ", distance.max),
+            (" media ", distance.mean),
+        ] {
+            push(reply, label);
+            push_decimal(reply, units.distance(value));
+        }
+        push(reply, " var ");
+        push_hundredths(reply, units.distance(units.distance(distance.variance)));
+        push(reply, "\r\n");
+    }
+}
+
+// Como `push_decimal` con dos decimales
+#[cfg(feature = "stats")]
+fn push_hundredths(reply: &mut Reply, value: f32) {
+    let hundredths = (value * 100. + 0.5) as u32;
+    push_number(reply, hundredths / 100);
+    push(reply, ".");
+    push_number(reply, hundredths / 10 % 10);
+    push_number(reply, hundredths % 10);
+}
+
+// HH:MM:SS sin usar core::fmt, que ocupa bastante flash
+#[cfg(any(feature = "schedule", feature = "event-log"))]
+fn push_time(reply: &mut Reply, time: TimeOfDay) {
+    let seconds = time.seconds();
+    for (i, value) in [time.hours(), time.minutes(), seconds % 60]
+        .into_iter()
+        .enumerate()
+    {
+        if i > 0 {
+            let _ = reply.push(b':');
+        }
+        let _ = reply.push(b'0' + (value / 10) as u8);
+        let _ = reply.push(b'0' + (value % 10) as u8);
+    }
+}
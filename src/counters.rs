@@ -0,0 +1,108 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::{Duration, Timer};
+
+use sie_core::{
+    codes::Code,
+    counters::{Counters, ENCODED_SIZE},
+};
+
+use crate::kv::{self, Key};
+
+// Cada cuanto se guardan los contadores si cambiaron. Con unos 40 registros
+// por pagina del almacen cada pagina se borra una vez cada ~20 h
+const SAVE_PERIOD: Duration = Duration::from_secs(15 * 60);
+
+struct Lifetime {
+    counters: Counters,
+    // Fraccion de segundo encendida aun no sumada
+    on_ms: u64,
+    dirty: bool,
+}
+
+static LIFETIME: CriticalSectionMutex<RefCell<Lifetime>> =
+    CriticalSectionMutex::new(RefCell::new(Lifetime {
+        counters: Counters {
+            boots: 0,
+            activations: 0,
+            on_seconds: 0,
+            faults: 0,
+        },
+        on_ms: 0,
+        dirty: false,
+    }));
+
+// Recupera los totales guardados y cuenta este arranque; requiere
+// `kv::init`
+pub fn init() {
+    let mut data = [0; ENCODED_SIZE];
+    let found = kv::get(Key::Counters, &mut data) == Some(ENCODED_SIZE);
+    with(|l| {
+        if found {
+            l.counters = Counters::decode(&data);
+        }
+        l.counters.boots += 1;
+        l.dirty = true;
+    });
+    match found {
+        true => info!("Contadores recuperados"),
+        false => info!("Sin contadores guardados, se empieza de cero"),
+    }
+    save();
+}
+
+pub fn activation() {
+    with(|l| {
+        l.counters.activations += 1;
+        l.dirty = true;
+    });
+}
+
+pub fn fault() {
+    with(|l| {
+        l.counters.faults += 1;
+        l.dirty = true;
+    });
+}
+
+pub fn add_on_time(elapsed: Duration) {
+    with(|l| {
+        l.on_ms += elapsed.as_millis();
+        l.counters.on_seconds += (l.on_ms / 1000) as u32;
+        l.on_ms %= 1000;
+        l.dirty = true;
+    });
+}
+
+#[cfg(any(feature = "console", feature = "telemetry"))]
+pub fn get() -> Counters {
+    with(|l| l.counters)
+}
+
+// Guarda periodicamente los contadores que hayan cambiado
+#[embassy_executor::task]
+pub async fn counters() {
+    loop {
+        Timer::after(SAVE_PERIOD).await;
+        save();
+    }
+}
+
+pub fn save() {
+    let Some(counters) = with(|l| l.dirty.then_some(l.counters)) else {
+        return;
+    };
+    if kv::set(Key::Counters, &counters.encode()) {
+        with(|l| l.dirty = false);
+    } else {
+        warn!(
+            Code::CountersNotSaved,
+            "No se pudieron guardar los contadores"
+        );
+    }
+}
+
+fn with<R>(f: impl FnOnce(&mut Lifetime) -> R) -> R {
+    LIFETIME.lock(|l| f(&mut l.borrow_mut()))
+}
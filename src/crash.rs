@@ -0,0 +1,116 @@
+use core::{
+    mem::MaybeUninit,
+    panic::PanicInfo,
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "defmt")]
+use core::fmt::{self, Write};
+
+use crate::error::{self, Error};
+
+// Marca de un registro valido; al encender la RAM trae basura
+const MAGIC: u32 = 0x9A41_C0DE;
+// Mensaje y ubicacion del panic, recortados
+const MESSAGE_SIZE: usize = 96;
+
+struct Record {
+    magic: u32,
+    len: usize,
+    message: [u8; MESSAGE_SIZE],
+}
+
+// En `.uninit` cortex-m-rt no la borra al arrancar: sobrevive al reinicio
+// del watchdog o por software, no a un corte de energia
+#[unsafe(link_section = ".uninit.CRASH")]
+static mut RECORD: MaybeUninit<Record> = MaybeUninit::uninit();
+
+// El arranque encontro el registro de un panic
+static FOUND: AtomicBool = AtomicBool::new(false);
+
+// Copia lo que cabe y descarta el resto
+struct Truncating<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Truncating<'_> {
+    fn push(&mut self, s: &str) {
+        let n = s.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl fmt::Write for Truncating<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s);
+        Ok(())
+    }
+}
+
+// Guarda el mensaje y la ubicacion del panic. Solo la llama el manejador
+// de panic, que ya no regresa. Sin defmt solo queda "panic": leer el
+// mensaje o la ubicacion conserva el formato de todos los panics del
+// programa, unos 27K que no caben en la flash
+pub fn store(info: &PanicInfo) {
+    // SAFETY: nada mas escribe el registro y el panic no se anida
+    let record = unsafe { &mut *(&raw mut RECORD).cast::<Record>() };
+    let mut text = Truncating {
+        buf: &mut record.message,
+        len: 0,
+    };
+    #[cfg(not(feature = "defmt"))]
+    {
+        let _ = info;
+        text.push("panic");
+    }
+    // Con defmt el formato ya esta en la flash para imprimir el panic
+    #[cfg(feature = "defmt")]
+    {
+        let _ = write!(text, "{}", info.message());
+        if let Some(location) = info.location() {
+            let _ = write!(text, " ({}:{})", location.file(), location.line());
+        }
+    }
+    record.len = text.len;
+    // SAFETY: puntero a un estatico valido; la marca va al final para no
+    // dejar un registro a medias
+    unsafe { write_volatile(&raw mut (*(&raw mut RECORD).cast::<Record>()).magic, MAGIC) };
+}
+
+// Reporta el panic de la ejecucion anterior, si lo hubo, como falla del
+// modo degradado: su mensaje va al registro en flash y por RTT, y el LED
+// muestra el codigo. Requiere `flash_log::init`
+pub fn check_at_boot() {
+    let record = (&raw mut RECORD).cast::<Record>();
+    // SAFETY: solo se leen enteros; la marca dice si el resto es valido
+    unsafe {
+        if read_volatile(&raw const (*record).magic) != MAGIC {
+            return;
+        }
+        write_volatile(&raw mut (*record).magic, 0);
+    }
+    FOUND.store(true, Ordering::Relaxed);
+    if let Some(message) = last() {
+        error::degrade(Error::Panicked(message));
+    }
+}
+
+// Mensaje del panic que causo el ultimo reinicio
+pub fn last() -> Option<&'static str> {
+    if !FOUND.load(Ordering::Relaxed) {
+        return None;
+    }
+    // SAFETY: el registro ya no cambia hasta el siguiente panic, que
+    // reinicia el equipo
+    let record = unsafe { &*(&raw const RECORD).cast::<Record>() };
+    let bytes = &record.message[..record.len.min(MESSAGE_SIZE)];
+    // Un recorte puede partir un caracter
+    Some(match core::str::from_utf8(bytes) {
+        Ok(message) => message,
+        Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or(""),
+    })
+}
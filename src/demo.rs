@@ -0,0 +1,73 @@
+use core::cell::Cell;
+
+use embassy_futures::select::{Either, select};
+use embassy_sync::{
+    blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+use embassy_time::Timer;
+
+use sie_core::{
+    codes::Code,
+    demo::{Demo, FakeReading, Script, Step},
+};
+
+use crate::set_manual;
+
+// Lectura inventada que reemplaza a la de los sensores mientras corre una
+// secuencia
+static FAKE: CriticalSectionMutex<Cell<Option<FakeReading>>> =
+    CriticalSectionMutex::new(Cell::new(None));
+// Secuencia a correr, o None para detener la que corre
+static COMMAND: Signal<CriticalSectionRawMutex, Option<&'static Script>> = Signal::new();
+
+pub fn start(script: &'static Script) {
+    COMMAND.signal(Some(script));
+}
+
+pub fn stop() {
+    COMMAND.signal(None);
+}
+
+// Lectura para los controladores de zona; None sin demostracion
+pub fn reading() -> Option<FakeReading> {
+    FAKE.lock(|f| f.get())
+}
+
+// Corre las secuencias de demostracion pedidas por la consola: cada escena
+// cambia la lectura de todas las zonas y dura su tiempo. Una secuencia
+// nueva reemplaza a la que corre; al terminar se vuelve a los sensores
+#[embassy_executor::task]
+pub async fn demo() {
+    let mut command = None;
+    loop {
+        if command.is_none() {
+            command = COMMAND.wait().await;
+        }
+        let Some(script) = command.take() else {
+            continue;
+        };
+        info!(Control, "Demostracion: {}", script.name);
+        // Las zonas solo deciden en modo automatico
+        set_manual(false);
+
+        let mut demo = Demo::new(script);
+        command = loop {
+            let Some(scene) = demo.advance() else {
+                break None;
+            };
+            if scene.step == Step::Fault {
+                warn!(Code::Simulated, "Falla simulada");
+            }
+            FAKE.lock(|f| f.set(Some(demo.reading())));
+
+            let hold = Timer::after_secs(scene.seconds.into());
+            if let Either::Second(next) = select(hold, COMMAND.wait()).await {
+                break next;
+            }
+        };
+
+        FAKE.lock(|f| f.set(None));
+        info!(Control, "Demostracion terminada");
+    }
+}
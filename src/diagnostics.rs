@@ -0,0 +1,105 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::{Duration, Instant, Timer};
+
+#[cfg(feature = "console")]
+use sie_core::latency::LoopSummary;
+use sie_core::{
+    codes::Code,
+    latency::{LatencyWindow, LoopTiming},
+};
+
+use crate::{memory, zone::ZONE_COUNT};
+
+// Ventana sobre la que se resumen las latencias
+const LATENCY_WINDOW: Duration = Duration::from_secs(60);
+// Pila que nunca se uso por debajo de la cual se avisa; cada opcion nueva
+// agrega estaticos y le quita lugar a la pila
+const LOW_HEADROOM: u32 = 1024;
+
+// Latencias de la ventana en curso, de todas las zonas
+static LATENCY: CriticalSectionMutex<RefCell<LatencyWindow>> =
+    CriticalSectionMutex::new(RefCell::new(LatencyWindow::new()));
+
+// Duracion y periodo del bucle de cada zona en la ventana en curso y en la
+// anterior, que muestra la consola
+static LOOPS: CriticalSectionMutex<RefCell<[LoopTiming; ZONE_COUNT]>> =
+    CriticalSectionMutex::new(RefCell::new([const { LoopTiming::new() }; ZONE_COUNT]));
+#[cfg(feature = "console")]
+static LAST_LOOPS: CriticalSectionMutex<RefCell<[Option<LoopSummary>; ZONE_COUNT]>> =
+    CriticalSectionMutex::new(RefCell::new([None; ZONE_COUNT]));
+
+// Una vuelta del bucle de control de `zone` que muestreo en `sampled_at`
+// y acaba de aplicar su decision
+pub fn record_loop(zone: usize, sampled_at: Instant) {
+    let end = Instant::now().as_micros();
+    LOOPS.lock(|l| l.borrow_mut()[zone].record(sampled_at.as_micros(), end));
+}
+
+// El bucle de `zone` dejo de muestrear por un rato
+pub fn loop_idle(zone: usize) {
+    LOOPS.lock(|l| l.borrow_mut()[zone].idle());
+}
+
+// Duracion y periodo del bucle de `zone` en la ultima ventana
+#[cfg(feature = "console")]
+pub fn last_loop(zone: usize) -> Option<LoopSummary> {
+    LAST_LOOPS.lock(|l| l.borrow()[zone])
+}
+
+// Registra el tiempo desde que se adquirio una muestra hasta que el cambio
+// que provoco llego al actuador
+pub fn record_latency(sampled_at: Instant) {
+    let latency = sampled_at.elapsed().as_micros().min(u32::MAX as u64) as u32;
+    LATENCY.lock(|l| l.borrow_mut().record(latency));
+}
+
+// Reporta periodicamente la distribucion de latencias del ultimo minuto, la
+// duracion y el jitter del bucle de cada zona y el uso de la RAM, para que
+// se note cuando una opcion nueva los empeora. Con Embassy las tareas
+// comparten la pila principal (ver `memory`), por lo que su maximo es uno
+// solo. Avisa una vez si a la pila le queda poco margen
+#[embassy_executor::task]
+pub async fn diagnostics() {
+    let mut warned = false;
+    loop {
+        Timer::after(LATENCY_WINDOW).await;
+
+        let usage = memory::usage();
+        info!(
+            "RAM: estaticos {} bytes, pila {} de {} bytes",
+            usage.statics, usage.stack_peak, usage.stack
+        );
+        if usage.headroom() < LOW_HEADROOM && !warned {
+            warn!(
+                Code::StackLow,
+                "Poca pila libre: {} bytes",
+                usage.headroom()
+            );
+            warned = true;
+        }
+
+        if let Some(summary) = LATENCY.lock(|l| l.borrow_mut().take()) {
+            info!(
+                "Latencia muestra-actuacion: min {} us, media {} us, max {} us ({} cambios)",
+                summary.min_us, summary.mean_us, summary.max_us, summary.count
+            );
+        }
+
+        for zone in 0..ZONE_COUNT {
+            let summary = LOOPS.lock(|l| l.borrow_mut()[zone].take());
+            if let Some(summary) = summary {
+                info!(
+                    "Zona {}: bucle media {} us, max {} us; jitter {} us",
+                    zone,
+                    summary.duration.mean_us,
+                    summary.duration.max_us,
+                    summary.jitter_us()
+                );
+            }
+            #[cfg(feature = "console")]
+            LAST_LOOPS.lock(|l| l.borrow_mut()[zone] = summary);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use core::sync::atomic::Ordering;
+
+use sie_core::{
+    dashboard::Dashboard,
+    screensaver::{Screensaver, Timing},
+};
+
+use crate::{
+    MANUAL_MODE, SYSTEM_ENABLED,
+    clock::SystemClock,
+    events::Event,
+    flash_log,
+    zone::{ZONE_COUNT, ZONES},
+};
+
+// Lo que muestran las pantallas (`oled` y `lcd`), al dia con el bus de
+// eventos
+pub struct Status {
+    pub dashboard: Dashboard<ZONE_COUNT>,
+    pub screensaver: Screensaver<SystemClock>,
+}
+
+impl Status {
+    pub fn new(screensaver: Timing) -> Self {
+        let mut dashboard = Dashboard::new();
+        dashboard.manual = MANUAL_MODE.load(Ordering::Relaxed);
+        dashboard.enabled = SYSTEM_ENABLED.load(Ordering::Relaxed);
+        dashboard.fault = flash_log::last_fault();
+        Self {
+            dashboard,
+            screensaver: Screensaver::new(SystemClock, screensaver),
+        }
+    }
+
+    // Los botones, los cambios de modo y las fallas despiertan la pantalla
+    pub fn apply(&mut self, event: Event) {
+        match event {
+            Event::NewLuxReading { zone, lux } => {
+                if let Some(view) = self.dashboard.zones.get_mut(zone as usize) {
+                    view.lux = Some(lux);
+                }
+            }
+            Event::NewDistance { zone, meters } => {
+                if let Some(view) = self.dashboard.zones.get_mut(zone as usize) {
+                    view.distance = Some(meters);
+                }
+            }
+            Event::ButtonPressed { .. } => self.screensaver.wake(),
+            Event::ModeChanged { manual, enabled } => {
+                self.dashboard.manual = manual;
+                self.dashboard.enabled = enabled;
+                self.screensaver.wake();
+            }
+            Event::Fault(code) => {
+                self.dashboard.fault = Some(code);
+                self.screensaver.wake();
+            }
+            Event::LightChanged { .. } => {}
+        }
+    }
+
+    // Los cambios de brillo no se publican; las lamparas se leen antes de
+    // redibujar
+    pub fn refresh_lamps(&mut self) {
+        for (view, zone) in self.dashboard.zones.iter_mut().zip(&ZONES) {
+            view.light_on = zone.light_is_on();
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use embassy_stm32::{
+    peripherals::{DMA1_CH2, PB10, USART3},
+    usart::{self, StopBits, UartTx},
+};
+use embassy_time::{Duration, Ticker};
+
+use sie_core::{
+    codes::Code,
+    dmx::{Universe, level},
+};
+
+use crate::{
+    DMX_CHANNELS,
+    error::{self, Error},
+    zone::ZONES,
+};
+
+const BAUDRATE: u32 = 250_000;
+// Un 0x00 a esta velocidad deja la linea en bajo 112 us (break, minimo
+// 92 us) y sus bits de parada la devuelven a alto 25 us (mark after break)
+const BREAK_BAUDRATE: u32 = 80_000;
+// Un universo completo tarda 23 ms; se repite a unas 33 tramas por segundo
+const REFRESH: Duration = Duration::from_millis(30);
+
+// Transmisor DMX512 en USART3 TX (PB10) hacia un transceptor RS-485 (por
+// ejemplo un MAX485 con DE y /RE a 3.3 V). El brillo de cada zona va en el
+// canal de `DMX_CHANNELS`, para manejar dimmers en lugar de una lampara
+// propia; la salida PWM sigue funcionando
+#[embassy_executor::task]
+pub async fn dmx(usart: USART3, tx: PB10, dma: DMA1_CH2) {
+    let mut config = usart::Config::default();
+    config.baudrate = BAUDRATE;
+    config.stop_bits = StopBits::STOP2;
+    let Ok(mut uart) = UartTx::new(usart, tx, dma, config) else {
+        error::degrade(Error::Setup(Code::DmxSetup));
+        return;
+    };
+    info!(Links, "Salida DMX en los canales {}", DMX_CHANNELS);
+
+    let mut universe = Universe::new();
+    let mut ticker = Ticker::every(REFRESH);
+    loop {
+        ticker.next().await;
+        for (zone, &channel) in ZONES.iter().zip(&DMX_CHANNELS) {
+            let duty = zone.with_light(|l| l.duty()).unwrap_or(0.);
+            universe.set(channel, level(duty));
+        }
+
+        // Break y mark after break, luego el codigo de inicio y los canales
+        let sent = async {
+            uart.set_baudrate(BREAK_BAUDRATE).ok()?;
+            uart.write(&[0]).await.ok()?;
+            uart.flush().await.ok()?;
+            uart.set_baudrate(BAUDRATE).ok()?;
+            uart.write(universe.frame()).await.ok()?;
+            uart.flush().await.ok()
+        };
+        if sent.await.is_none() {
+            warn!(Code::DmxFrame, "Fallo la trama DMX");
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use embassy_stm32::{i2c::I2c, mode::Blocking};
+
+use sie_core::{
+    clock::WallClock,
+    ds3231::{ADDRESS, REG_STATUS, REG_TIME, STATUS_OSF, decode_time, encode_time},
+    schedule::TimeOfDay,
+};
+
+// RTC externo DS3231 con bateria de respaldo; deriva mucho menos que el
+// cristal del RTC interno. Va en el I2C2 (SCL en PB10, SDA en PB11)
+pub struct Ds3231 {
+    i2c: I2c<'static, Blocking>,
+}
+
+impl Ds3231 {
+    // Devuelve el modulo si responde en el bus
+    pub fn probe(mut i2c: I2c<'static, Blocking>) -> Option<Self> {
+        let mut status = [0];
+        i2c.blocking_write_read(ADDRESS, &[REG_STATUS], &mut status)
+            .ok()?;
+        Some(Self { i2c })
+    }
+
+    fn status(&mut self) -> Option<u8> {
+        let mut status = [0];
+        self.i2c
+            .blocking_write_read(ADDRESS, &[REG_STATUS], &mut status)
+            .ok()?;
+        Some(status[0])
+    }
+}
+
+impl WallClock for Ds3231 {
+    // None si el oscilador se detuvo (sin bateria) o si falla el bus
+    fn now(&mut self) -> Option<TimeOfDay> {
+        if self.status()? & STATUS_OSF != 0 {
+            return None;
+        }
+
+        let mut regs = [0; 3];
+        self.i2c
+            .blocking_write_read(ADDRESS, &[REG_TIME], &mut regs)
+            .ok()?;
+        decode_time(regs)
+    }
+
+    fn set(&mut self, time: TimeOfDay) {
+        let [seconds, minutes, hours] = encode_time(time);
+        if self
+            .i2c
+            .blocking_write(ADDRESS, &[REG_TIME, seconds, minutes, hours])
+            .is_err()
+        {
+            return;
+        }
+
+        // La hora vuelve a ser valida
+        if let Some(status) = self.status() {
+            let _ = self
+                .i2c
+                .blocking_write(ADDRESS, &[REG_STATUS, status & !STATUS_OSF]);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use embassy_stm32::{
+    adc::{Adc, AdcChannel, AnyAdcChannel, SampleTime},
+    pac::{self, adc::vals::Dualmod},
+    peripherals::{ADC1, ADC2},
+};
+use static_cell::StaticCell;
+
+// Muestreo simultaneo con ADC1 y ADC2 (modo dual "regular simultaneo"):
+// al iniciar una conversion en ADC1 con el driver, ADC2 convierte su canal
+// en el mismo instante. Asi la distancia y la luz de una zona corresponden
+// a la misma muestra, sin el desfase de dos conversiones seguidas
+
+// ADC2 queda encendido y calibrado mientras exista su driver
+static ADC2_DRIVER: StaticCell<Adc<'static, ADC2>> = StaticCell::new();
+
+// Canal de ADC2; el numero es el de la entrada ADC12_INx del pin
+pub struct Adc2Channel(u8);
+
+impl Adc2Channel {
+    pub fn new(_pin: impl AdcChannel<ADC2>, channel: u8) -> Self {
+        Self(channel)
+    }
+}
+
+// Calibra ADC2 y lo deja como esclavo de ADC1. Las lecturas sencillas de
+// ADC1 siguen funcionando igual
+pub fn init(adc2: ADC2) {
+    ADC2_DRIVER.init(Adc::new(adc2));
+
+    // En modo dual el esclavo se configura con disparo por software
+    pac::ADC2.cr2().modify(|w| {
+        w.set_cont(false);
+        w.set_exttrig(true);
+        w.set_extsel(7); // SWSTART
+    });
+    pac::ADC1.cr1().modify(|w| w.set_dualmod(Dualmod::REGULAR));
+}
+
+// Convierte `first` en ADC1 y `second` en ADC2 al mismo tiempo
+pub async fn read(
+    adc: &mut Adc<'static, ADC1>,
+    first: &mut AnyAdcChannel<ADC1>,
+    second: &mut Adc2Channel,
+) -> (u16, u16) {
+    let adc2 = pac::ADC2;
+    // Mismo tiempo de muestreo que el driver usa en ADC1, para que ambas
+    // conversiones terminen juntas
+    if second.0 <= 9 {
+        adc2.smpr2()
+            .modify(|w| w.set_smp(second.0 as usize, SampleTime::CYCLES1_5));
+    } else {
+        adc2.smpr1()
+            .modify(|w| w.set_smp(second.0 as usize - 10, SampleTime::CYCLES1_5));
+    }
+    adc2.sqr1().modify(|w| w.set_l(0));
+    adc2.sqr3().write(|w| w.set_sq(0, second.0));
+    // Descarta un resultado viejo, de una lectura sencilla de ADC1
+    let _ = adc2.dr().read();
+
+    let first = adc.read(first).await;
+    while !adc2.sr().read().eoc() {}
+
+    (first, adc2.dr().read().0 as u16)
+}
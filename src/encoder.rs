@@ -0,0 +1,132 @@
+use core::cell::Cell;
+
+use embassy_futures::join::join;
+use embassy_stm32::{peripherals::TIM2, time::Hertz, timer::qei::Qei};
+use embassy_time::Timer;
+
+use sie_core::{
+    beep::Beep,
+    sensor::{DIST_MAX_M, DIST_MIN_M, MAX_LUX_VALUE},
+};
+
+use crate::{
+    button::{Debounced, Press},
+    buzzer,
+    events::{self, Button, Event},
+    light,
+    zone::{ZONE_COUNT, ZONES},
+};
+
+// Un encoder tipico genera 4 cuentas por cada paso (detent)
+const COUNTS_PER_STEP: i16 = 4;
+
+// Cambio de cada umbral por paso de la perilla
+const LIGHT_STEP: f32 = 50.; // Luxes
+const DISTANCE_STEP: f32 = 0.1; // Metros
+const PWM_FREQUENCY_STEP: u32 = 500; // Hz
+
+// Valor que se ajusta al girar la perilla
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Light(usize),
+    Distance(usize),
+    // Frecuencia del PWM de las lamparas, para evitar el zumbido del driver
+    PwmFrequency,
+}
+
+// Perilla para ajustar los umbrales en campo. Al girarla se modifica el
+// valor seleccionado y al presionarla se pasa al siguiente: luz y
+// distancia de la primera zona, luego de la segunda, etc., y al final la
+// frecuencia del PWM
+#[embassy_executor::task]
+pub async fn encoder(qei: Qei<'static, TIM2>, mut select_btn: Debounced<'static>) {
+    let target = Cell::new(Target::Light(0));
+
+    let select_loop = async {
+        loop {
+            let press = select_btn.wait_for_press().await;
+            events::publish(Event::ButtonPressed {
+                button: Button::Select,
+                press,
+            });
+            if press != Press::Single {
+                continue;
+            }
+            buzzer::beep(Beep::Click);
+
+            let next = match target.get() {
+                Target::Light(zone) => Target::Distance(zone),
+                Target::Distance(zone) if zone + 1 < ZONE_COUNT => Target::Light(zone + 1),
+                Target::Distance(_) => Target::PwmFrequency,
+                Target::PwmFrequency => Target::Light(0),
+            };
+            target.set(next);
+
+            match next {
+                Target::Light(zone) => {
+                    info!(
+                        Control,
+                        "Perilla ajusta el umbral de luz de la zona {}", zone
+                    )
+                }
+                Target::Distance(zone) => {
+                    info!(
+                        Control,
+                        "Perilla ajusta el umbral de distancia de la zona {}", zone
+                    )
+                }
+                Target::PwmFrequency => info!(Control, "Perilla ajusta la frecuencia del PWM"),
+            }
+        }
+    };
+
+    let rotation_loop = async {
+        let mut last = qei.count();
+        let mut pending: i16 = 0;
+
+        loop {
+            Timer::after_millis(50).await;
+
+            let count = qei.count();
+            pending += count.wrapping_sub(last) as i16;
+            last = count;
+
+            let steps = pending / COUNTS_PER_STEP;
+            pending %= COUNTS_PER_STEP;
+            if steps == 0 {
+                continue;
+            }
+
+            match target.get() {
+                Target::Light(zone) => ZONES[zone].thresholds.lock(|t| {
+                    let mut thresholds = t.get();
+                    thresholds.light =
+                        (thresholds.light + steps as f32 * LIGHT_STEP).clamp(0., MAX_LUX_VALUE);
+                    info!(
+                        Control,
+                        "Zona {}: umbral de luz: {} luxes", zone, thresholds.light
+                    );
+                    t.set(thresholds);
+                }),
+                Target::Distance(zone) => ZONES[zone].thresholds.lock(|t| {
+                    let mut thresholds = t.get();
+                    thresholds.distance = (thresholds.distance + steps as f32 * DISTANCE_STEP)
+                        .clamp(DIST_MAX_M, DIST_MIN_M);
+                    info!(
+                        Control,
+                        "Zona {}: umbral de distancia: {} metros", zone, thresholds.distance
+                    );
+                    t.set(thresholds);
+                }),
+                Target::PwmFrequency => {
+                    let current = light::pwm_frequency().0 as i32;
+                    let requested = current + steps as i32 * PWM_FREQUENCY_STEP as i32;
+                    let applied = light::set_pwm_frequency(Hertz(requested.max(0) as u32));
+                    info!(Control, "Frecuencia del PWM: {} Hz", applied.0);
+                }
+            }
+        }
+    };
+
+    join(select_loop, rotation_loop).await;
+}
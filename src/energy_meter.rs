@@ -0,0 +1,90 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use embassy_futures::join::join;
+use embassy_stm32::exti::ExtiInput;
+use embassy_time::{Duration, Instant, Ticker, Timer};
+
+use sie_core::{
+    codes::Code,
+    energy::{Estimate, MIN_WH, TOLERANCE, Verdict, metered_wh, reconcile},
+};
+
+use crate::{ENERGY_METER, zone::ZONES};
+
+// Un pulso S0 dura al menos 30 ms; lo que llegue antes es rebote
+const PULSE_WIDTH: Duration = Duration::from_millis(30);
+// Cada cuanto se suma la potencia estimada
+const ESTIMATE_TICK: Duration = Duration::from_secs(1);
+// Cada cuanto se informa y se concilia
+const REPORT_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+// Pulsos desde el arranque
+static PULSES: AtomicU32 = AtomicU32::new(0);
+
+// Parametros del medidor y de las lamparas del circuito
+pub struct EnergyMeter {
+    // Constante del medidor (imp/kWh)
+    pub pulses_per_kwh: u32,
+    // Potencia de cada lampara a brillo maximo
+    pub lamp_watts: f32,
+}
+
+// Cuenta los pulsos de la salida S0 de un medidor de energia (colector
+// abierto hacia tierra, con pull-up) que solo mide el circuito de
+// iluminacion, y concilia los kWh acumulados con la estimacion a partir
+// del ciclo de trabajo de cada lampara
+#[embassy_executor::task]
+pub async fn energy_meter(mut s0: ExtiInput<'static>) {
+    let count = async {
+        loop {
+            s0.wait_for_falling_edge().await;
+            PULSES.fetch_add(1, Ordering::Relaxed);
+            Timer::after(PULSE_WIDTH).await;
+            s0.wait_for_high().await;
+        }
+    };
+
+    let estimate = async {
+        let mut estimate = Estimate::new();
+        let mut ticker = Ticker::every(ESTIMATE_TICK);
+        let mut last_report = Instant::now();
+        loop {
+            ticker.next().await;
+            estimate.add(lamp_power(), ESTIMATE_TICK.as_millis());
+            if last_report.elapsed() < REPORT_PERIOD {
+                continue;
+            }
+            last_report = Instant::now();
+
+            let metered = metered_wh(PULSES.load(Ordering::Relaxed), ENERGY_METER.pulses_per_kwh);
+            info!(
+                Sensors,
+                "Energia: {} Wh medidos, {} Wh estimados",
+                metered,
+                estimate.wh()
+            );
+            match reconcile(metered, estimate.wh(), MIN_WH, TOLERANCE) {
+                Verdict::Over => warn!(
+                    Code::EnergyOver,
+                    "El medidor registra mas energia que la estimada"
+                ),
+                Verdict::Under => warn!(
+                    Code::EnergyUnder,
+                    "El medidor registra menos energia que la estimada"
+                ),
+                Verdict::Pending | Verdict::Consistent => {}
+            }
+        }
+    };
+
+    join(count, estimate).await;
+}
+
+// Potencia estimada de todas las lamparas en este momento
+fn lamp_power() -> f32 {
+    ZONES
+        .iter()
+        .filter_map(|zone| zone.with_light(|l| l.duty()))
+        .map(|duty| duty * ENERGY_METER.lamp_watts)
+        .sum()
+}
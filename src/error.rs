@@ -0,0 +1,102 @@
+use core::sync::atomic::{AtomicU16, Ordering};
+
+use embassy_executor::{SpawnToken, Spawner};
+
+use sie_core::codes::Code;
+
+use crate::{flash_log, watchdog::Task};
+
+// Fallas que dejan al equipo sin alguna de sus partes. Ninguna lo detiene:
+// `degrade` las registra y lo demas sigue funcionando
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    // No quedo lugar para la tarea
+    SpawnFailed(&'static str),
+    // Una tarea supervisada dejo de reportarse (ver `watchdog`)
+    Stalled(Task),
+    // La ejecucion anterior termino en un panic; lleva su mensaje
+    Panicked(&'static str),
+    // La calibracion del ADC no termino
+    #[cfg(feature = "adc-calibration")]
+    AdcInit,
+    // Un puerto serie no se pudo configurar o una radio, la pantalla o la
+    // tarjeta SD no respondieron al arrancar; lleva el codigo del periferico
+    #[cfg(any(
+        all(feature = "console", not(feature = "usb-console")),
+        feature = "telemetry",
+        feature = "dmx",
+        feature = "mqtt",
+        feature = "lora",
+        feature = "nrf24",
+        feature = "oled",
+        feature = "lcd",
+        feature = "sd-log"
+    ))]
+    Setup(Code),
+}
+
+// Codigo de la primera falla que dejo al equipo degradado; 0 sin fallas
+static DEGRADED: AtomicU16 = AtomicU16::new(0);
+
+// Modo degradado: registra la falla y la muestra en el LED de estado. El
+// equipo sigue sin la parte que fallo en lugar de detenerse
+pub fn degrade(error: Error) {
+    let code = match error {
+        Error::SpawnFailed(task) => {
+            warn!(Code::SpawnFailed, "No se pudo lanzar la tarea {}", task);
+            Code::SpawnFailed
+        }
+        Error::Stalled(Task::Zone(zone)) => {
+            warn!(Code::TaskStalled, "Tarea trabada: zona {}", zone);
+            Code::TaskStalled
+        }
+        Error::Stalled(task) => {
+            warn!(Code::TaskStalled, "Tarea trabada: {}", task.name());
+            Code::TaskStalled
+        }
+        // El mensaje mismo, no un texto fijo, va al registro en flash
+        Error::Panicked(message) => {
+            flash_log::record(flash_log::Level::Error, Some(Code::Panic), message);
+            #[cfg(feature = "defmt")]
+            ::defmt::error!(
+                "E{=u16} Reinicio por panic: {=str}",
+                Code::Panic.number(),
+                message
+            );
+            Code::Panic
+        }
+        #[cfg(feature = "adc-calibration")]
+        Error::AdcInit => {
+            warn!(Code::AdcInit, "La calibracion del ADC no termino");
+            Code::AdcInit
+        }
+        #[cfg(any(
+            all(feature = "console", not(feature = "usb-console")),
+            feature = "telemetry",
+            feature = "dmx",
+            feature = "mqtt",
+            feature = "lora",
+            feature = "nrf24",
+            feature = "oled",
+            feature = "lcd",
+            feature = "sd-log"
+        ))]
+        Error::Setup(code) => {
+            warn!(code, "Periferico sin configurar");
+            code
+        }
+    };
+    let _ = DEGRADED.compare_exchange(0, code.number(), Ordering::Relaxed, Ordering::Relaxed);
+}
+
+// Primera falla desde el arranque, si el equipo esta degradado
+pub fn degraded() -> Option<Code> {
+    Code::from_number(DEGRADED.load(Ordering::Relaxed))
+}
+
+// Lanza una tarea; si no se puede, el equipo sigue sin ella
+pub fn spawn<S>(spawner: Spawner, token: SpawnToken<S>, task: &'static str) {
+    if spawner.spawn(token).is_err() {
+        degrade(Error::SpawnFailed(task));
+    }
+}
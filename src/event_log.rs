@@ -0,0 +1,106 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::Instant;
+
+use sie_core::{
+    event_log::{Cursor, Entry, EventLog, Record, Stamp},
+    kv::Pages,
+};
+
+use crate::{
+    events::{self, Button, Event},
+    storage::{self, PAGE_SIZE, Page},
+};
+
+const PAGES: [Page; 2] = [Page::EventsA, Page::EventsB];
+
+struct FlashPages;
+
+impl Pages for FlashPages {
+    fn read(&mut self, page: usize, offset: usize, buf: &mut [u8]) -> bool {
+        storage::read(PAGES[page], offset as u32, buf)
+    }
+
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> bool {
+        storage::write(PAGES[page], offset as u32, data)
+    }
+
+    fn erase(&mut self, page: usize) -> bool {
+        storage::erase(PAGES[page])
+    }
+}
+
+static LOG: CriticalSectionMutex<RefCell<Option<EventLog<FlashPages>>>> =
+    CriticalSectionMutex::new(RefCell::new(None));
+
+// Abre el registro y anota el arranque; requiere `storage::init`
+pub fn init() {
+    let log = EventLog::open(FlashPages, PAGE_SIZE as usize);
+    LOG.lock(|l| *l.borrow_mut() = Some(log));
+    append(Entry::Boot);
+}
+
+// Numero de cada boton en el registro: su posicion
+#[cfg(feature = "encoder")]
+const BUTTONS: &[Button] = &[Button::Manual, Button::Light, Button::Select];
+#[cfg(not(feature = "encoder"))]
+const BUTTONS: &[Button] = &[Button::Manual, Button::Light];
+
+// Boton de un registro; None si es de un firmware con otros botones
+pub fn button(number: u8) -> Option<Button> {
+    BUTTONS.get(number as usize).copied()
+}
+
+// Siguiente suceso, del mas antiguo al mas reciente
+pub fn next(cursor: &mut Cursor) -> Option<Record> {
+    with(|log| log.next(cursor)).flatten()
+}
+
+pub fn clear() -> bool {
+    with(EventLog::clear).unwrap_or(false)
+}
+
+fn append(entry: Entry) {
+    let record = Record {
+        stamp: stamp(),
+        entry,
+    };
+    with(|log| log.append(&record));
+}
+
+// La hora del dia si el reloj de tiempo real esta ajustado
+fn stamp() -> Stamp {
+    #[cfg(feature = "schedule")]
+    if let Some(time) = crate::wall_clock::now() {
+        return Stamp::Wall(time);
+    }
+    Stamp::Uptime(Instant::now().as_secs() as u32)
+}
+
+// Con el registro ocupado (un panic a mitad de una escritura en la flash)
+// la operacion se descarta
+fn with<R>(f: impl FnOnce(&mut EventLog<FlashPages>) -> R) -> Option<R> {
+    LOG.lock(|l| l.try_borrow_mut().ok()?.as_mut().map(f))
+}
+
+// Registro persistente de sucesos: guarda en flash los encendidos y
+// apagados de las lamparas con su motivo, los cambios de modo, los botones
+// y las fallas que llegan por el bus (ver sie_core::event_log)
+#[embassy_executor::task]
+pub async fn event_log() {
+    let mut events = events::subscribe();
+    loop {
+        let entry = match events.next_message_pure().await {
+            Event::LightChanged { zone, on, reason } => Entry::Light { zone, on, reason },
+            Event::ModeChanged { manual, enabled } => Entry::Mode { manual, enabled },
+            Event::ButtonPressed { button, press } => Entry::Button {
+                button: BUTTONS.iter().position(|&b| b == button).unwrap_or(0) as u8,
+                press,
+            },
+            Event::Fault(code) => Entry::Fault(code),
+            Event::NewLuxReading { .. } | Event::NewDistance { .. } => continue,
+        };
+        append(entry);
+    }
+}
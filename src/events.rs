@@ -0,0 +1,67 @@
+#[cfg(feature = "events-subscribers")]
+use embassy_sync::pubsub::Subscriber;
+#[cfg(feature = "events")]
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, pubsub::PubSubChannel};
+
+use sie_core::{codes::Code, event_log::Reason};
+
+use crate::button::Press;
+
+// Eventos pendientes por suscriptor; con la cola llena se pierden los mas
+// viejos y el suscriptor atrasado los salta
+#[cfg(feature = "events")]
+const CAPACITY: usize = 8;
+// Telemetria, pantalla, registro y control
+#[cfg(feature = "events")]
+const SUBSCRIBERS: usize = 4;
+
+// Botones que publican sus gestos
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    // Modo manual (PB13)
+    Manual,
+    // Luz (PB12, PB11 en la black pill)
+    Light,
+    // Boton del encoder (PB14)
+    #[cfg(feature = "encoder")]
+    Select,
+}
+
+// Lecturas y sucesos del sistema. Cada tarea interesada se suscribe en
+// lugar de revisar las variables globales
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    NewLuxReading { zone: u8, lux: f32 },
+    NewDistance { zone: u8, meters: f32 },
+    ButtonPressed { button: Button, press: Press },
+    ModeChanged { manual: bool, enabled: bool },
+    // La lampara de una zona se encendio o se apago
+    LightChanged { zone: u8, on: bool, reason: Reason },
+    Fault(Code),
+}
+
+#[cfg(feature = "events-subscribers")]
+pub type EventSubscriber =
+    Subscriber<'static, CriticalSectionRawMutex, Event, CAPACITY, SUBSCRIBERS, 0>;
+
+#[cfg(feature = "events")]
+static EVENTS: PubSubChannel<CriticalSectionRawMutex, Event, CAPACITY, SUBSCRIBERS, 0> =
+    PubSubChannel::new();
+
+// Publica sin esperar; se puede llamar desde cualquier contexto. Sin la
+// opcion `events` no hace nada
+pub fn publish(event: Event) {
+    #[cfg(feature = "events")]
+    EVENTS.immediate_publisher().publish_immediate(event);
+    #[cfg(not(feature = "events"))]
+    let _ = event;
+}
+
+// Recibe los eventos publicados a partir de ahora. Hay `SUBSCRIBERS` lugares
+// en total
+#[cfg(feature = "events-subscribers")]
+pub fn subscribe() -> EventSubscriber {
+    EVENTS
+        .subscriber()
+        .expect("No quedan suscriptores de eventos")
+}
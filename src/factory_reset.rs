@@ -0,0 +1,101 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_stm32::{gpio::Input, pac};
+use embassy_time::{Duration, Instant, Timer, block_for};
+
+use sie_core::codes::Code;
+
+#[cfg(any(feature = "console", feature = "ambient-learning"))]
+use crate::storage::{self, Page};
+use crate::{
+    board, config,
+    kv::{self, Key},
+};
+
+// Periodo de revision de los botones
+const TICK: Duration = Duration::from_millis(100);
+// Tiempo que el LED de estado confirma el restablecimiento
+const FEEDBACK: Duration = Duration::from_secs(3);
+// Asentamiento de las resistencias de pull-down al arrancar
+const SETTLE: Duration = Duration::from_millis(10);
+
+// El LED de estado esta confirmando un restablecimiento
+static SHOWING: AtomicBool = AtomicBool::new(false);
+
+pub fn is_showing() -> bool {
+    SHOWING.load(Ordering::Relaxed)
+}
+
+// Con los dos botones presionados al arrancar vuelve a la configuracion de
+// fabrica antes de cargar los ajustes. Requiere `kv::init`
+pub fn check_at_boot(manual: Input<'_>, light: Input<'_>) {
+    block_for(SETTLE);
+    if manual.is_high() && light.is_high() {
+        wipe();
+        SHOWING.store(true, Ordering::Relaxed);
+    }
+}
+
+// Borra los ajustes, la calibracion, las reglas y los umbrales aprendidos.
+// Los contadores de vida y el registro de fallas se conservan
+fn wipe() {
+    #[allow(unused_mut)]
+    let mut done = kv::remove(Key::Settings);
+    #[cfg(any(feature = "can", feature = "lora", feature = "i2c-slave"))]
+    {
+        done &= kv::remove(Key::KnownGood);
+    }
+    #[cfg(feature = "console")]
+    {
+        done &= storage::erase(Page::Rules);
+    }
+    #[cfg(feature = "ambient-learning")]
+    {
+        done &= storage::erase(Page::Ambient);
+    }
+
+    match done {
+        true => info!("Configuracion de fabrica restablecida"),
+        false => warn!(
+            Code::FactoryResetIncomplete,
+            "No se pudo borrar toda la configuracion"
+        ),
+    }
+}
+
+// Con los dos botones presionados `factory_reset_hold` se confirma con el
+// LED, se borra la configuracion y se reinicia. Solo cuenta si los botones
+// se soltaron desde el arranque, para no repetir un restablecimiento hecho
+// al arrancar
+#[embassy_executor::task]
+pub async fn factory_reset() {
+    if is_showing() {
+        Timer::after(FEEDBACK).await;
+        SHOWING.store(false, Ordering::Relaxed);
+    }
+
+    let hold = config::get().factory_reset_hold;
+    let mut armed = false;
+    let mut since = None;
+    loop {
+        Timer::after(TICK).await;
+
+        // Los botones ya son de sus tareas; solo se lee el puerto
+        if pac::GPIOB.idr().read().0 & board::BUTTONS != board::BUTTONS {
+            armed = true;
+            since = None;
+            continue;
+        }
+        if !armed || since.get_or_insert_with(Instant::now).elapsed() < hold {
+            continue;
+        }
+
+        info!("Restablecimiento de fabrica");
+        SHOWING.store(true, Ordering::Relaxed);
+        Timer::after(FEEDBACK).await;
+        // Sin esperas entre el borrado y el reinicio, para que ninguna tarea
+        // vuelva a guardar los ajustes en memoria
+        wipe();
+        cortex_m::peripheral::SCB::sys_reset();
+    }
+}
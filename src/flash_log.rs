@@ -0,0 +1,168 @@
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicU16, Ordering},
+};
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::Instant;
+
+use sie_core::codes::Code;
+
+use crate::{
+    events::{self, Event},
+    storage::{self, ERASED, PAGE_SIZE, Page},
+};
+
+// Cada registro: marca de tiempo (4), nivel (1), longitud (1), codigo de
+// falla (2, 0 sin codigo) y el inicio del mensaje. La marca es la hora del dia en segundos si hay reloj de
+// tiempo real ajustado (bit alto en 1), o los segundos desde el arranque
+const WALL_TIME: u32 = 1 << 31;
+const RECORD_SIZE: usize = 32;
+const HEADER_SIZE: usize = 8;
+const TEXT_SIZE: usize = RECORD_SIZE - HEADER_SIZE;
+const RECORDS: u32 = PAGE_SIZE / RECORD_SIZE as u32;
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum Level {
+    Info = 0,
+    Warn = 1,
+    Error = 2,
+}
+
+// Registro circular de advertencias, errores y resumenes diarios en flash, para conservar
+// contexto en equipos sin RTT. Al llenarse la pagina se borra y se
+// empieza de nuevo
+struct FlashLog {
+    next: u32,
+}
+
+static LOG: CriticalSectionMutex<RefCell<Option<FlashLog>>> =
+    CriticalSectionMutex::new(RefCell::new(None));
+
+// Codigo de la ultima falla de esta ejecucion, para la consola, MQTT, los
+// registros I2C y la pantalla
+static LAST_FAULT: AtomicU16 = AtomicU16::new(0);
+
+// Busca el primer espacio libre; requiere `storage::init`. Los mensajes
+// anteriores a esta llamada no se guardan
+pub fn init() {
+    let mut next = RECORDS;
+    for slot in 0..RECORDS {
+        let mut header = [0; 4];
+        if storage::read(Page::Log, slot_offset(slot), &mut header) && header == [ERASED; 4] {
+            next = slot;
+            break;
+        }
+    }
+
+    LOG.lock(|l| *l.borrow_mut() = Some(FlashLog { next }));
+}
+
+// Agrega un mensaje al registro. Si el registro esta ocupado (por ejemplo,
+// un panic durante una escritura) el mensaje se descarta
+pub fn record(level: Level, code: Option<Code>, message: &str) {
+    if let Some(code) = code {
+        LAST_FAULT.store(code.number(), Ordering::Relaxed);
+        events::publish(Event::Fault(code));
+    }
+    LOG.lock(|l| {
+        if let Ok(mut log) = l.try_borrow_mut()
+            && let Some(log) = log.as_mut()
+        {
+            log.append(level, code, message);
+        }
+    });
+}
+
+#[cfg(any(
+    feature = "console",
+    feature = "mqtt",
+    feature = "i2c-slave",
+    feature = "oled",
+    feature = "lcd"
+))]
+pub fn last_fault() -> Option<Code> {
+    Code::from_number(LAST_FAULT.load(Ordering::Relaxed))
+}
+
+// Reporta los mensajes guardados, de una ejecucion anterior o de esta
+pub fn dump() {
+    let Some(next) = LOG.lock(|l| l.borrow().as_ref().map(|log| log.next)) else {
+        return;
+    };
+
+    for slot in 0..next {
+        let mut record = [0; RECORD_SIZE];
+        if !storage::read(Page::Log, slot_offset(slot), &mut record) {
+            break;
+        }
+
+        let stamp = u32::from_le_bytes([record[0], record[1], record[2], record[3]]);
+        let level = match record[4] {
+            l if l == Level::Info as u8 => "INFO",
+            l if l == Level::Error as u8 => "ERROR",
+            _ => "WARN",
+        };
+        let len = (record[5] as usize).min(TEXT_SIZE);
+        let code = u16::from_le_bytes([record[6], record[7]]);
+        let text = core::str::from_utf8(&record[HEADER_SIZE..HEADER_SIZE + len]).unwrap_or("?");
+        if stamp & WALL_TIME != 0 {
+            let time = stamp & !WALL_TIME;
+            info!(
+                "Registro en flash: {}:{} {} E{} {}",
+                time / 3600,
+                time / 60 % 60,
+                level,
+                code,
+                text
+            );
+        } else {
+            info!(
+                "Registro en flash: {} s {} E{} {}",
+                stamp, level, code, text
+            );
+        }
+    }
+}
+
+impl FlashLog {
+    fn append(&mut self, level: Level, code: Option<Code>, message: &str) {
+        if self.next == RECORDS {
+            if !storage::erase(Page::Log) {
+                return;
+            }
+            self.next = 0;
+        }
+
+        // Se corta en un limite de caracter para que el texto siga siendo UTF-8
+        let mut len = message.len().min(TEXT_SIZE);
+        while !message.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut record = [ERASED; RECORD_SIZE];
+        record[..4].copy_from_slice(&timestamp().to_le_bytes());
+        record[4] = level as u8;
+        record[5] = len as u8;
+        record[6..HEADER_SIZE].copy_from_slice(&code.map_or(0, Code::number).to_le_bytes());
+        record[HEADER_SIZE..HEADER_SIZE + len].copy_from_slice(&message.as_bytes()[..len]);
+
+        // Aunque falle la escritura el espacio se da por usado, para no
+        // reintentar sobre una zona que ya no esta borrada
+        storage::write(Page::Log, slot_offset(self.next), &record);
+        self.next += 1;
+    }
+}
+
+fn timestamp() -> u32 {
+    #[cfg(feature = "schedule")]
+    if let Some(time) = crate::wall_clock::now() {
+        return time.seconds() | WALL_TIME;
+    }
+    Instant::now().as_secs() as u32 & !WALL_TIME
+}
+
+fn slot_offset(slot: u32) -> u32 {
+    slot * RECORD_SIZE as u32
+}
@@ -0,0 +1,160 @@
+// Macros de registro. Con la opcion `defmt` se envian por RTT; sin ella se
+// descartan, salvo las advertencias que ademas se guardan en flash
+
+#[cfg(any(feature = "defmt", feature = "console"))]
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+// Los mensajes informativos pueden apagarse desde la consola para no
+// saturar el RTT; las advertencias siempre se registran
+#[cfg(any(feature = "defmt", feature = "console"))]
+pub static LOG_ENABLED: AtomicBool = AtomicBool::new(true);
+
+// Partes del sistema con su propio nivel de registro. Los mensajes que no
+// indican la suya son del sistema
+#[cfg(any(feature = "defmt", feature = "console"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    // Lecturas de los sensores
+    Sensors,
+    // Decisiones de las zonas, modos y ajustes en campo
+    Control,
+    // Enlaces de comunicacion y consola
+    Links,
+    System,
+}
+
+#[cfg(any(feature = "defmt", feature = "console"))]
+impl Subsystem {
+    pub const ALL: [Self; 4] = [Self::Sensors, Self::Control, Self::Links, Self::System];
+
+    #[cfg(feature = "console")]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Sensors => "sensores",
+            Self::Control => "control",
+            Self::Links => "enlaces",
+            Self::System => "sistema",
+        }
+    }
+
+    #[cfg(feature = "console")]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|subsystem| subsystem.name() == name)
+    }
+}
+
+// Nivel minimo que se envia por RTT. Con `Warn` solo quedan las
+// advertencias, que nunca se silencian
+#[cfg(any(feature = "defmt", feature = "console"))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+}
+
+#[cfg(any(feature = "defmt", feature = "console"))]
+impl Level {
+    const ALL: [Self; 3] = [Self::Debug, Self::Info, Self::Warn];
+
+    #[cfg(feature = "console")]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+        }
+    }
+
+    #[cfg(feature = "console")]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|level| level.name() == name)
+    }
+}
+
+// Todo arranca en `Info`: las lecturas de cada muestra (`debug!`) no salen
+// hasta pedirlas
+#[cfg(any(feature = "defmt", feature = "console"))]
+static LEVELS: [AtomicU8; Subsystem::ALL.len()] =
+    [const { AtomicU8::new(Level::Info as u8) }; Subsystem::ALL.len()];
+
+#[cfg(any(feature = "defmt", feature = "console"))]
+pub fn level(subsystem: Subsystem) -> Level {
+    Level::ALL[LEVELS[subsystem as usize].load(Ordering::Relaxed) as usize]
+}
+
+// Desde la consola, o con el gesto libre del boton de modo si no lo usa el
+// visor de 7 segmentos
+#[cfg(any(
+    all(feature = "defmt", not(feature = "seven-segment")),
+    feature = "console"
+))]
+pub fn set_level(subsystem: Subsystem, level: Level) {
+    LEVELS[subsystem as usize].store(level as u8, Ordering::Relaxed);
+}
+
+// Un mensaje de `level` de `subsystem` sale por RTT
+#[cfg(feature = "defmt")]
+pub fn enabled(subsystem: Subsystem, level: Level) -> bool {
+    LOG_ENABLED.load(Ordering::Relaxed) && level >= self::level(subsystem)
+}
+
+// `debug!(Subsistema, ...)` para lo que se repite en cada muestra;
+// `info!` con o sin subsistema para lo demas
+macro_rules! debug {
+    ($subsystem:ident, $s:literal $(, $x:expr)* $(,)?) => {
+        {
+            #[cfg(feature = "defmt")]
+            if $crate::fmt::enabled($crate::fmt::Subsystem::$subsystem, $crate::fmt::Level::Debug) {
+                ::defmt::debug!($s $(, $x)*);
+            }
+            #[cfg(not(feature = "defmt"))]
+            let _ = ($( & $x ),*);
+        }
+    };
+}
+
+macro_rules! info {
+    ($subsystem:ident, $s:literal $(, $x:expr)* $(,)?) => {
+        {
+            #[cfg(feature = "defmt")]
+            if $crate::fmt::enabled($crate::fmt::Subsystem::$subsystem, $crate::fmt::Level::Info) {
+                ::defmt::info!($s $(, $x)*);
+            }
+            #[cfg(not(feature = "defmt"))]
+            let _ = ($( & $x ),*);
+        }
+    };
+    ($s:literal $(, $x:expr)* $(,)?) => {
+        info!(System, $s $(, $x)*)
+    };
+}
+
+// Cada advertencia lleva su codigo de falla (ver sie_core::codes). En flash
+// se guardan el codigo y el texto del formato; los argumentos van
+// unicamente por RTT, en una linea despues del codigo
+macro_rules! warn {
+    ($code:expr, $s:literal $(,)?) => {
+        {
+            let code: ::sie_core::codes::Code = $code;
+            $crate::flash_log::record($crate::flash_log::Level::Warn, Some(code), $s);
+            #[cfg(feature = "defmt")]
+            ::defmt::warn!("E{=u16} {=str}", code.number(), $s);
+        }
+    };
+    ($code:expr, $s:literal $(, $x:expr)+ $(,)?) => {
+        {
+            let code: ::sie_core::codes::Code = $code;
+            $crate::flash_log::record($crate::flash_log::Level::Warn, Some(code), $s);
+            #[cfg(feature = "defmt")]
+            {
+                ::defmt::warn!("E{=u16}", code.number());
+                ::defmt::warn!($s $(, $x)*);
+            }
+            #[cfg(not(feature = "defmt"))]
+            let _ = ($( & $x ),*);
+        }
+    };
+}
@@ -0,0 +1,198 @@
+use core::{
+    cell::{Cell, RefCell},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use embassy_sync::{
+    blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+use embassy_time::{Duration, Instant};
+
+use sie_core::{codes::Code, recorder::Recorder};
+
+use crate::{
+    events::{self, Event},
+    zone::ZONE_COUNT,
+};
+
+// Las zonas muestrean cada 100 ms; se guarda una muestra por zona cada
+// segundo para que el anillo cubra casi un minuto con una zona
+const SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+const SAMPLES: usize = 48;
+const EVENTS: usize = 24;
+// Tras una falla se guardan todavia 5 s de muestras
+const SAMPLES_AFTER: usize = 5 * ZONE_COUNT;
+
+#[derive(Clone, Copy)]
+pub struct Sample {
+    pub at_ms: u32,
+    pub zone: u8,
+    pub lux: f32,
+    pub distance: f32,
+}
+
+// Un evento del bus que no es una lectura
+#[derive(Clone, Copy)]
+pub struct Logged {
+    pub at_ms: u32,
+    pub event: Event,
+}
+
+#[derive(Clone, Copy)]
+pub enum Entry {
+    Sample(Sample),
+    Event(Logged),
+}
+
+impl Entry {
+    fn at_ms(&self) -> u32 {
+        match self {
+            Self::Sample(sample) => sample.at_ms,
+            Self::Event(logged) => logged.at_ms,
+        }
+    }
+}
+
+struct History {
+    samples: Recorder<Sample, SAMPLES>,
+    events: Recorder<Logged, EVENTS>,
+}
+
+// Todo en cero para que el estatico vaya en .bss y no ocupe flash
+static HISTORY: CriticalSectionMutex<RefCell<History>> =
+    CriticalSectionMutex::new(RefCell::new(History {
+        samples: Recorder::new(Sample {
+            at_ms: 0,
+            zone: 0,
+            lux: 0.,
+            distance: 0.,
+        }),
+        events: Recorder::new(Logged {
+            at_ms: 0,
+            event: Event::NewLuxReading { zone: 0, lux: 0. },
+        }),
+    }));
+// Falla que disparo la historia
+static FAULT: CriticalSectionMutex<Cell<Option<Code>>> = CriticalSectionMutex::new(Cell::new(None));
+
+// Mientras la consola la muestra la historia no cambia
+static PAUSED: AtomicBool = AtomicBool::new(false);
+// Se completo la historia de una falla
+static CAPTURED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+pub fn pause(paused: bool) {
+    PAUSED.store(paused, Ordering::Relaxed);
+}
+
+pub async fn fault_captured() {
+    CAPTURED.wait().await;
+}
+
+// Falla que congelo la historia, si la hay
+pub fn frozen_by() -> Option<Code> {
+    let frozen = HISTORY.lock(|h| h.borrow().samples.is_frozen());
+    FAULT.lock(|f| f.get()).filter(|_| frozen)
+}
+
+// Vacia la historia y vuelve a registrar
+pub fn clear() {
+    HISTORY.lock(|h| {
+        let mut h = h.borrow_mut();
+        h.samples.clear();
+        h.events.clear();
+    });
+    FAULT.lock(|f| f.set(None));
+}
+
+// Posicion de la lectura en las muestras y en los eventos
+#[derive(Default)]
+pub struct Cursor {
+    sample: usize,
+    event: usize,
+}
+
+// Siguiente elemento en orden de tiempo, mezclando muestras y eventos
+pub fn next(cursor: &mut Cursor) -> Option<Entry> {
+    HISTORY.lock(|h| {
+        let h = h.borrow();
+        let sample = h.samples.get(cursor.sample).map(Entry::Sample);
+        let event = h.events.get(cursor.event).map(Entry::Event);
+        match (sample, event) {
+            (Some(s), Some(e)) if e.at_ms() < s.at_ms() => {
+                cursor.event += 1;
+                Some(e)
+            }
+            (Some(s), _) => {
+                cursor.sample += 1;
+                Some(s)
+            }
+            (None, e) => {
+                cursor.event += 1;
+                e
+            }
+        }
+    })
+}
+
+// Registro de vuelo: las ultimas muestras de las zonas y los ultimos
+// eventos del bus en RAM. Una falla lo dispara; al completarse la historia
+// posterior se congela y la consola la muestra sola
+#[embassy_executor::task]
+pub async fn history() {
+    let mut events = events::subscribe();
+    let mut lux = [0.; ZONE_COUNT];
+    let mut recorded: [Option<Instant>; ZONE_COUNT] = [None; ZONE_COUNT];
+    loop {
+        let event = events.next_message_pure().await;
+        if PAUSED.load(Ordering::Relaxed) {
+            continue;
+        }
+        let at_ms = Instant::now().as_millis() as u32;
+        match event {
+            Event::NewLuxReading { zone, lux: value } => {
+                if let Some(lux) = lux.get_mut(zone as usize) {
+                    *lux = value;
+                }
+            }
+            // La distancia llega despues de la luz de la misma muestra
+            Event::NewDistance { zone, meters } => {
+                let Some(last) = recorded.get_mut(zone as usize) else {
+                    continue;
+                };
+                if last.is_some_and(|at| at.elapsed() < SAMPLE_PERIOD) {
+                    continue;
+                }
+                *last = Some(Instant::now());
+                let sample = Sample {
+                    at_ms,
+                    zone,
+                    lux: lux[zone as usize],
+                    distance: meters,
+                };
+                let captured = HISTORY.lock(|h| {
+                    let mut h = h.borrow_mut();
+                    let captured = h.samples.push(sample);
+                    if captured {
+                        h.events.trigger(0);
+                    }
+                    captured
+                });
+                if captured {
+                    CAPTURED.signal(());
+                }
+            }
+            Event::Fault(code) => HISTORY.lock(|h| {
+                let mut h = h.borrow_mut();
+                h.events.push(Logged { at_ms, event });
+                if !h.samples.is_triggered() {
+                    h.samples.trigger(SAMPLES_AFTER);
+                    FAULT.lock(|f| f.set(Some(code)));
+                }
+            }),
+            event => HISTORY.lock(|h| {
+                h.borrow_mut().events.push(Logged { at_ms, event });
+            }),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use core::{cell::RefCell, sync::atomic::Ordering};
+
+use embassy_futures::select::{Either, select};
+use embassy_stm32::{
+    bind_interrupts,
+    i2c::I2c,
+    interrupt::typelevel,
+    pac,
+    peripherals::{I2C1, PB8, PB9},
+    time::Hertz,
+};
+use embassy_sync::{
+    blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex},
+    channel::Channel,
+};
+use embassy_time::{Duration, Ticker};
+use heapless::Vec;
+
+use sie_core::{
+    codes::Code,
+    event_log::Reason,
+    i2c_registers::{self, Command, MAP_SIZE, Write, ZoneRegisters, decode_write},
+};
+
+use crate::{
+    I2C_ADDRESS, MANUAL_MODE, SYSTEM_ENABLED, config_guard, flash_log,
+    light::MAX_BRIGHTNESS,
+    set_manual,
+    zone::{ZONE_COUNT, ZONES},
+};
+
+// El driver de embassy solo sabe ser maestro: el modo esclavo se atiende
+// desde las interrupciones de I2C1 con los registros del periferico
+bind_interrupts!(struct Irqs {
+    I2C1_EV => EventHandler;
+    I2C1_ER => ErrorHandler;
+});
+
+// Cada cuanto se copian los valores actuales al mapa de registros
+const REFRESH: Duration = Duration::from_millis(100);
+
+type Data = Vec<u8, 4>;
+
+// Estado de la transaccion en curso, compartido con las interrupciones
+struct Bus {
+    image: [u8; MAP_SIZE],
+    // Siguiente registro a leer o escribir
+    pointer: u8,
+    // El primer byte de una escritura es el numero de registro
+    awaiting_register: bool,
+    // Registro donde empezo la escritura y los bytes recibidos
+    start: u8,
+    data: Data,
+}
+
+static BUS: CriticalSectionMutex<RefCell<Bus>> = CriticalSectionMutex::new(RefCell::new(Bus {
+    image: [0; MAP_SIZE],
+    pointer: 0,
+    awaiting_register: false,
+    start: 0,
+    data: Vec::new(),
+}));
+
+// Escrituras del anfitrion pendientes de aplicar
+static WRITES: Channel<CriticalSectionRawMutex, (u8, Data), 4> = Channel::new();
+
+impl Bus {
+    // Al terminar una escritura (STOP o inicio repetido) se entrega a la tarea
+    fn finish(&mut self) {
+        if !self.data.is_empty() {
+            let data = core::mem::take(&mut self.data);
+            let _ = WRITES.try_send((self.start, data));
+        }
+        self.awaiting_register = false;
+    }
+}
+
+struct EventHandler;
+
+impl typelevel::Handler<typelevel::I2C1_EV> for EventHandler {
+    unsafe fn on_interrupt() {
+        let regs = pac::I2C1;
+        let sr1 = regs.sr1().read();
+        BUS.lock(|bus| {
+            let mut bus = bus.borrow_mut();
+            if sr1.addr() {
+                // Leer SR2 tras SR1 limpia ADDR
+                let transmitting = regs.sr2().read().tra();
+                bus.finish();
+                bus.awaiting_register = !transmitting;
+            }
+            if sr1.rxne() {
+                let byte = regs.dr().read().dr();
+                if bus.awaiting_register {
+                    bus.awaiting_register = false;
+                    bus.pointer = byte;
+                    bus.start = byte;
+                } else {
+                    let _ = bus.data.push(byte);
+                    bus.pointer = bus.pointer.wrapping_add(1);
+                }
+            }
+            if sr1.txe() {
+                let byte = bus.image.get(bus.pointer as usize).copied().unwrap_or(0xFF);
+                regs.dr().write(|w| w.set_dr(byte));
+                bus.pointer = bus.pointer.wrapping_add(1);
+            }
+            if sr1.stopf() {
+                // STOPF se limpia escribiendo CR1 despues de leer SR1
+                regs.cr1().modify(|_| {});
+                bus.finish();
+            }
+        });
+    }
+}
+
+struct ErrorHandler;
+
+impl typelevel::Handler<typelevel::I2C1_ER> for ErrorHandler {
+    unsafe fn on_interrupt() {
+        // El NACK del maestro al ultimo byte leido es el fin normal de una
+        // lectura; los errores de bus solo descartan la transaccion
+        pac::I2C1.sr1().modify(|w| {
+            w.set_af(false);
+            w.set_berr(false);
+            w.set_arlo(false);
+            w.set_ovr(false);
+        });
+        BUS.lock(|bus| bus.borrow_mut().data.clear());
+    }
+}
+
+// Esclavo I2C en PB8 (SCL) y PB9 (SDA) con la direccion `I2C_ADDRESS`: una
+// computadora lo consulta como a cualquier otro sensor. El mapa de
+// registros esta en `sie_core::i2c_registers`; cada escritura cambia un
+// solo registro
+#[embassy_executor::task]
+pub async fn i2c_slave(peripheral: I2C1, scl: PB8, sda: PB9) {
+    // I2C1 en PB8/PB9 es la asignacion alternativa de pines
+    pac::AFIO.mapr().modify(|w| w.set_i2c1_remap(true));
+
+    // El driver configura los pines y el reloj del periferico; queda vivo
+    // con la tarea
+    let _i2c = I2c::new_blocking(peripheral, scl, sda, Hertz::khz(100), Default::default());
+    refresh();
+
+    let regs = pac::I2C1;
+    // El bit 14 de OAR1 debe quedar en 1
+    regs.oar1()
+        .write_value(pac::i2c::regs::Oar1(1 << 14 | (I2C_ADDRESS as u32) << 1));
+    regs.cr1().modify(|w| w.set_ack(true));
+    regs.cr2().modify(|w| {
+        w.set_itevten(true);
+        w.set_itbufen(true);
+        w.set_iterren(true);
+    });
+    info!(Links, "Esclavo I2C en la direccion {}", I2C_ADDRESS);
+
+    let mut ticker = Ticker::every(REFRESH);
+    loop {
+        match select(ticker.next(), WRITES.receive()).await {
+            Either::First(()) => refresh(),
+            Either::Second((register, data)) => match decode_write(register, &data) {
+                Some(write) => {
+                    apply(write);
+                    refresh();
+                }
+                None => warn!(Code::InvalidWrite, "Escritura I2C invalida en {}", register),
+            },
+        }
+    }
+}
+
+fn refresh() {
+    let mut zones = [ZoneRegisters::default(); ZONE_COUNT];
+    for (registers, zone) in zones.iter_mut().zip(&ZONES) {
+        let reading = zone.last_reading.lock(|r| r.get());
+        let thresholds = zone.thresholds.lock(|t| t.get());
+        *registers = ZoneRegisters {
+            brightness: zone.with_light(|l| l.brightness()).unwrap_or(0),
+            lamp_on: zone.light_is_on(),
+            lux: reading.map_or(0, |r| r.lux as u16),
+            distance_cm: reading.map_or(0, |r| (r.distance * 100.) as u16),
+            light_threshold: thresholds.light as u16,
+            distance_threshold_cm: (thresholds.distance * 100.) as u16,
+        };
+    }
+    let image = i2c_registers::image(
+        MANUAL_MODE.load(Ordering::Relaxed),
+        SYSTEM_ENABLED.load(Ordering::Relaxed),
+        flash_log::last_fault().map_or(0, Code::number),
+        &zones,
+    );
+    BUS.lock(|bus| bus.borrow_mut().image = image);
+}
+
+fn apply(write: Write) {
+    match write {
+        Write::Command(Command::Auto) => set_manual(false),
+        Write::Command(Command::Manual) => set_manual(true),
+        Write::Command(command) => {
+            let brightness = if command == Command::LampsOn {
+                MAX_BRIGHTNESS
+            } else {
+                0
+            };
+            set_manual(true);
+            for zone in &ZONES {
+                zone.switch_light(Reason::Remote, |l| l.set_brightness(brightness));
+            }
+        }
+        Write::LightThreshold { zone, lux } => {
+            set_threshold(zone, |t| t.light = lux as f32);
+        }
+        Write::DistanceThreshold { zone, cm } => {
+            set_threshold(zone, |t| t.distance = cm as f32 / 100.);
+        }
+    }
+}
+
+fn set_threshold(zone: usize, change: impl FnOnce(&mut sie_core::control::Thresholds)) {
+    let Some(zone) = ZONES.get(zone) else {
+        return;
+    };
+    config_guard::remote_change(|| {
+        zone.thresholds.lock(|t| {
+            let mut thresholds = t.get();
+            change(&mut thresholds);
+            t.set(thresholds);
+        })
+    });
+}
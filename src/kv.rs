@@ -0,0 +1,63 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+
+use sie_core::kv::{Pages, Store};
+
+use crate::storage::{self, PAGE_SIZE, Page};
+
+// Claves del almacen; cada valor ocupa hasta `sie_core::kv::MAX_VALUE`
+// bytes
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum Key {
+    Counters = 0,
+    Settings = 1,
+    // Ajustes previos a un cambio remoto en prueba; sin valor sin prueba
+    #[cfg(any(feature = "can", feature = "lora", feature = "i2c-slave"))]
+    KnownGood = 2,
+}
+
+const PAGES: [Page; 2] = [Page::StoreA, Page::StoreB];
+
+struct FlashPages;
+
+impl Pages for FlashPages {
+    fn read(&mut self, page: usize, offset: usize, buf: &mut [u8]) -> bool {
+        storage::read(PAGES[page], offset as u32, buf)
+    }
+
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> bool {
+        storage::write(PAGES[page], offset as u32, data)
+    }
+
+    fn erase(&mut self, page: usize) -> bool {
+        storage::erase(PAGES[page])
+    }
+}
+
+static STORE: CriticalSectionMutex<RefCell<Option<Store<FlashPages>>>> =
+    CriticalSectionMutex::new(RefCell::new(None));
+
+// Abre el almacen; requiere `storage::init`
+pub fn init() {
+    let store = Store::open(FlashPages, PAGE_SIZE as usize);
+    STORE.lock(|s| *s.borrow_mut() = Some(store));
+}
+
+// Copia el valor de `key` en `buf` y devuelve su longitud
+pub fn get(key: Key, buf: &mut [u8]) -> Option<usize> {
+    with(|s| s.get(key as u8, buf)).flatten()
+}
+
+pub fn set(key: Key, value: &[u8]) -> bool {
+    with(|s| s.set(key as u8, value)).unwrap_or(false)
+}
+
+pub fn remove(key: Key) -> bool {
+    with(|s| s.remove(key as u8)).unwrap_or(false)
+}
+
+fn with<R>(f: impl FnOnce(&mut Store<FlashPages>) -> R) -> Option<R> {
+    STORE.lock(|s| s.try_borrow_mut().ok()?.as_mut().map(f))
+}
@@ -0,0 +1,246 @@
+use core::cell::Cell;
+
+use embassy_futures::{
+    select::{Either3, select3},
+    yield_now,
+};
+use embassy_stm32::{
+    i2c::I2c,
+    mode::Blocking,
+    pac,
+    peripherals::{I2C1, PB8, PB9},
+    time::Hertz,
+};
+use embassy_sync::{
+    blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+use embassy_time::{Duration, Instant, Timer};
+
+use sie_core::{
+    beep::Beep,
+    codes::Code,
+    dashboard::{LCD_COLUMNS, LcdPage},
+    menu::{Input, Item, Menu},
+    screensaver::{Screen, Timing},
+};
+
+use crate::{
+    button::Press,
+    buzzer,
+    display::Status,
+    error::{self, Error},
+    events::{self, Button},
+    report::ReportRequest,
+};
+
+// Direccion de 7 bits del adaptador PCF8574 (0x3F con el PCF8574A)
+const ADDRESS: u8 = 0x27;
+
+// Salidas del PCF8574: RS, RW (siempre escritura), E y la luz de fondo en
+// P0 a P3, y los datos D4 a D7 en P4 a P7
+const RS: u8 = 0x01;
+const ENABLE: u8 = 0x04;
+const BACKLIGHT: u8 = 0x08;
+
+// Cada pagina de estado se muestra este tiempo
+const PAGE_TIME: Duration = Duration::from_secs(3);
+// Sin tocar los botones el menu se cierra solo
+const MENU_TIMEOUT: Duration = Duration::from_secs(30);
+// Como mucho un redibujo en este tiempo; las lecturas llegan con cada muestra
+const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+// Sin actividad la luz de fondo se apaga a los 5 minutos; el LCD no tiene
+// contraste que atenuar
+const SCREENSAVER: Timing = Timing {
+    dim_ms: 300_000,
+    blank_ms: 300_000,
+};
+
+static MENU: CriticalSectionMutex<Cell<Menu>> = CriticalSectionMutex::new(Cell::new(Menu::new()));
+// Un boton cambio el menu
+static MENU_CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+// Menu con los dos botones: la pulsacion larga del boton de luz lo abre y
+// lo cierra, su clic pasa a la siguiente opcion y el clic del boton de modo
+// la elige. Devuelve true si el gesto era para el menu; con el menu abierto
+// los botones no hacen nada mas
+pub fn button(button: Button, press: Press) -> bool {
+    let handled = MENU.lock(|m| {
+        let mut menu = m.get();
+        let chosen = match (menu.is_open(), button, press) {
+            (false, Button::Light, Press::Long) => {
+                menu.open();
+                None
+            }
+            (false, ..) => return None,
+            (true, Button::Light, Press::Single) => menu.handle(Input::Next),
+            (true, Button::Manual, Press::Single) => menu.handle(Input::Choose),
+            (true, Button::Light, Press::Long) => menu.handle(Input::Close),
+            (true, ..) => None,
+        };
+        m.set(menu);
+        Some(chosen)
+    });
+    let Some(chosen) = handled else {
+        return false;
+    };
+
+    match chosen {
+        Some(Item::Manual) => crate::toggle_manual_mode(),
+        Some(Item::System) => crate::toggle_system(),
+        Some(Item::Teach) => crate::teach_thresholds(),
+        Some(Item::ClosedLoop) => crate::toggle_closed_loop(),
+        Some(Item::Report) => crate::request_report(ReportRequest::Emit),
+        Some(Item::Exit) | None => buzzer::beep(Beep::Click),
+    }
+    MENU_CHANGED.signal(());
+    true
+}
+
+struct Hd44780 {
+    i2c: I2c<'static, Blocking>,
+    backlight: u8,
+}
+
+impl Hd44780 {
+    // Medio byte con un pulso de E; el I2C a 100 kHz ya da los tiempos
+    // minimos del HD44780
+    fn nibble(&mut self, nibble: u8, rs: u8) -> bool {
+        let byte = nibble << 4 | rs | self.backlight;
+        self.i2c
+            .blocking_write(ADDRESS, &[byte | ENABLE, byte])
+            .is_ok()
+    }
+
+    fn byte(&mut self, value: u8, rs: u8) -> bool {
+        self.nibble(value >> 4, rs) && self.nibble(value & 0x0F, rs)
+    }
+
+    // Arranque por software en el modo de 4 bits, 2 lineas
+    async fn init(&mut self) -> bool {
+        Timer::after_millis(50).await;
+        for delay in [5, 1, 1] {
+            if !self.nibble(0x3, 0) {
+                return false;
+            }
+            Timer::after_millis(delay).await;
+        }
+        if !self.nibble(0x2, 0) {
+            return false;
+        }
+        // 2 lineas, encendido sin cursor, avance a la derecha y borrado
+        if ![0x28, 0x0C, 0x06, 0x01]
+            .into_iter()
+            .all(|c| self.byte(c, 0))
+        {
+            return false;
+        }
+        Timer::after_millis(2).await;
+        true
+    }
+
+    // Una linea por vez, cediendo el procesador entre lineas
+    async fn show(&mut self, page: &LcdPage) {
+        for (row, line) in page.iter().enumerate() {
+            if !self.byte(0x80 | (row as u8 * 0x40), 0) {
+                return;
+            }
+            for &c in line {
+                if !self.byte(c, RS) {
+                    return;
+                }
+            }
+            yield_now().await;
+        }
+    }
+
+    fn set_backlight(&mut self, on: bool) {
+        self.backlight = if on { BACKLIGHT } else { 0 };
+        let _ = self.i2c.blocking_write(ADDRESS, &[self.backlight]);
+    }
+}
+
+// Opcion seleccionada del menu y su numero
+fn menu_page(item: Item) -> LcdPage {
+    let mut page = [[b' '; LCD_COLUMNS]; 2];
+    let index = Item::ALL.iter().position(|&i| i == item).unwrap_or(0);
+    page[0][..9].copy_from_slice(b"MENU  /  ");
+    page[0][5] = b'1' + index as u8;
+    page[0][7] = b'0' + Item::ALL.len() as u8;
+    page[1][..2].copy_from_slice(b"> ");
+    let label = item.label().as_bytes();
+    let len = label.len().min(LCD_COLUMNS - 2);
+    page[1][2..2 + len].copy_from_slice(&label[..len]);
+    page
+}
+
+// LCD de caracteres de 16x2 con adaptador I2C en I2C1 remapeado a
+// PB8/PB9: las paginas de estado de sie_core::dashboard por turnos, o el
+// menu mientras esta abierto
+#[embassy_executor::task]
+pub async fn lcd(i2c: I2C1, scl: PB8, sda: PB9) {
+    pac::AFIO.mapr().modify(|w| w.set_i2c1_remap(true));
+    let mut lcd = Hd44780 {
+        i2c: I2c::new_blocking(i2c, scl, sda, Hertz::khz(100), Default::default()),
+        backlight: BACKLIGHT,
+    };
+    if !lcd.init().await {
+        error::degrade(Error::Setup(Code::DisplayMissing));
+        return;
+    }
+
+    let mut events = events::subscribe();
+    let mut status = Status::new(SCREENSAVER);
+    let mut lit = true;
+    let mut page = 0;
+    let mut next_page = Instant::now() + PAGE_TIME;
+    let mut menu_used = Instant::now();
+    let mut shown = None;
+
+    loop {
+        match select3(
+            events.next_message_pure(),
+            MENU_CHANGED.wait(),
+            Timer::at(next_page),
+        )
+        .await
+        {
+            Either3::First(event) => status.apply(event),
+            Either3::Second(()) => menu_used = Instant::now(),
+            Either3::Third(()) => {
+                page += 1;
+                next_page += PAGE_TIME;
+            }
+        }
+        while let Some(event) = events.try_next_message_pure() {
+            status.apply(event);
+        }
+        status.refresh_lamps();
+
+        let screen_lit = status.screensaver.screen() != Screen::Blank;
+        if screen_lit != lit {
+            lit = screen_lit;
+            lcd.set_backlight(lit);
+        }
+
+        let menu = MENU.lock(|m| {
+            let mut menu = m.get();
+            if menu.is_open() && menu_used.elapsed() > MENU_TIMEOUT {
+                menu.close();
+                m.set(menu);
+            }
+            menu
+        });
+        let text = match menu.selected() {
+            Some(item) => menu_page(item),
+            None => status.dashboard.page(page),
+        };
+        // Solo se reescribe lo que cambio
+        if shown != Some(text) {
+            lcd.show(&text).await;
+            shown = Some(text);
+        }
+        Timer::after(MIN_INTERVAL).await;
+    }
+}
@@ -0,0 +1,211 @@
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use embassy_stm32::{
+    peripherals::TIM4,
+    rcc,
+    time::Hertz,
+    timer::{Channel, simple_pwm::SimplePwm},
+};
+use embassy_sync::{
+    blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+use embassy_time::{Duration, Instant, Timer};
+
+use sie_core::{codes::Code, fade::Fade, gamma};
+
+use crate::{diagnostics, zone::ZONES};
+
+// Brillo maximo en porcentaje
+pub const MAX_BRIGHTNESS: u8 = 100;
+
+// Periodo de actualizacion de la rampa de brillo
+const FADE_TICK: Duration = Duration::from_millis(10);
+
+// Limites de la frecuencia del PWM: por debajo se nota el parpadeo y por
+// encima el ciclo de trabajo pierde resolucion para las rampas
+const MIN_PWM_FREQUENCY: Hertz = Hertz(200);
+const MIN_DUTY_STEPS: u32 = 1000;
+
+// Avisa a la tarea de la rampa que hay un nuevo brillo objetivo
+static FADE_START: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+// PWM de las lamparas (TIM4), compartido por los canales de todas las zonas
+static PWM: CriticalSectionMutex<RefCell<Option<SimplePwm<'static, TIM4>>>> =
+    CriticalSectionMutex::new(RefCell::new(None));
+static PWM_FREQUENCY: AtomicU32 = AtomicU32::new(0);
+
+// Instala el PWM de las lamparas con la frecuencia configurada
+pub fn init_pwm(pwm: SimplePwm<'static, TIM4>, frequency: Hertz) {
+    PWM.lock(|p| *p.borrow_mut() = Some(pwm));
+    set_pwm_frequency(frequency);
+}
+
+// Frecuencia maxima que conserva la resolucion minima del ciclo de trabajo
+fn max_pwm_frequency() -> Hertz {
+    Hertz(rcc::frequency::<TIM4>().0 / MIN_DUTY_STEPS)
+}
+
+pub fn pwm_frequency() -> Hertz {
+    Hertz(PWM_FREQUENCY.load(Ordering::Relaxed))
+}
+
+// Cambia la frecuencia del PWM, limitada al rango seguro para el reloj del
+// timer. El ciclo de trabajo de cada canal se reescala en la misma seccion
+// critica, por lo que el brillo no cambia de forma visible.
+// Devuelve la frecuencia aplicada
+pub fn set_pwm_frequency(requested: Hertz) -> Hertz {
+    let frequency = Hertz(
+        requested
+            .0
+            .clamp(MIN_PWM_FREQUENCY.0, max_pwm_frequency().0),
+    );
+    if frequency != requested {
+        warn!(
+            Code::PwmOutOfRange,
+            "Frecuencia de PWM fuera de rango, se usa {} Hz", frequency.0
+        );
+    }
+
+    PWM.lock(|p| {
+        let mut pwm = p.borrow_mut();
+        let Some(pwm) = pwm.as_mut() else {
+            return;
+        };
+
+        const CHANNELS: [Channel; 4] = [Channel::Ch1, Channel::Ch2, Channel::Ch3, Channel::Ch4];
+        let old_max = pwm.max_duty_cycle() as u32;
+        let duties = CHANNELS.map(|c| pwm.channel(c).current_duty_cycle() as u32);
+
+        pwm.set_frequency(frequency);
+
+        let new_max = pwm.max_duty_cycle() as u32;
+        for (c, duty) in CHANNELS.into_iter().zip(duties) {
+            pwm.channel(c)
+                .set_duty_cycle((duty * new_max / old_max) as u16);
+        }
+    });
+    PWM_FREQUENCY.store(frequency.0, Ordering::Relaxed);
+
+    frequency
+}
+
+// Lampara controlada por un canal PWM del TIM4 con brillo de 0 a 100 %.
+// El brillo es perceptual (ver `gamma`) y los cambios se aplican con
+// una rampa
+pub struct Light {
+    channel: Channel,
+    fade: Fade,
+    // Ciclo de trabajo minimo (0 a 1) que el driver reproduce sin parpadeo;
+    // por debajo la salida se apaga
+    min_duty: f32,
+    // Adquisicion de la muestra que provoco el cambio pendiente de aplicar
+    sampled_at: Option<Instant>,
+}
+
+impl Light {
+    // El PWM debe estar instalado con `init_pwm`
+    pub fn new(channel: Channel, fade_time: Duration, min_duty: f32) -> Self {
+        PWM.lock(|p| {
+            if let Some(pwm) = p.borrow_mut().as_mut() {
+                let mut channel = pwm.channel(channel);
+                channel.set_duty_cycle_fully_off();
+                channel.enable();
+            }
+        });
+
+        Self {
+            channel,
+            fade: Fade::new(fade_time.as_millis() as u32),
+            min_duty,
+            sampled_at: None,
+        }
+    }
+
+    // Brillo objetivo; durante una rampa la salida puede ir todavia en camino
+    pub fn brightness(&self) -> u8 {
+        self.fade.target()
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.brightness() > 0
+    }
+
+    // Apagada y sin rampa en curso: la salida queda fija en bajo
+    #[cfg(feature = "stop-mode")]
+    pub fn is_dark(&self) -> bool {
+        !self.is_on() && self.fade.is_settled()
+    }
+
+    // Ciclo de trabajo (0 a 1) del brillo objetivo
+    #[cfg(any(feature = "energy-meter", feature = "dmx"))]
+    pub fn duty(&self) -> f32 {
+        gamma::apply_floor(
+            gamma::duty_fraction(self.brightness() as f32),
+            self.min_duty,
+        )
+    }
+
+    // Ajusta el brillo; valores mayores a 100 se saturan
+    pub fn set_brightness(&mut self, percent: u8) {
+        let percent = percent.min(MAX_BRIGHTNESS);
+        if percent == self.fade.target() {
+            return;
+        }
+
+        self.fade.set_target(percent);
+        FADE_START.signal(());
+    }
+
+    // Igual que `set_brightness`, pero para un cambio decidido a partir de
+    // una muestra; la latencia se mide al escribir el PWM
+    pub fn set_brightness_from_sample(&mut self, percent: u8, sampled_at: Instant) {
+        if percent.min(MAX_BRIGHTNESS) != self.fade.target() {
+            self.sampled_at = Some(sampled_at);
+        }
+        self.set_brightness(percent);
+    }
+
+    // Avanza la rampa y actualiza el PWM. Devuelve true al llegar al objetivo
+    fn step(&mut self, elapsed: Duration) -> bool {
+        let level = self.fade.advance(elapsed.as_millis() as u32);
+
+        PWM.lock(|p| {
+            if let Some(pwm) = p.borrow_mut().as_mut() {
+                let mut channel = pwm.channel(self.channel);
+                let duty = gamma::apply_floor(gamma::duty_fraction(level), self.min_duty);
+                channel.set_duty_cycle((duty * channel.max_duty_cycle() as f32) as u16);
+            }
+        });
+
+        if let Some(sampled_at) = self.sampled_at.take() {
+            diagnostics::record_latency(sampled_at);
+        }
+
+        self.fade.is_settled()
+    }
+}
+
+// Aplica las rampas de brillo de todas las zonas; solo trabaja mientras
+// alguna esta en curso
+#[embassy_executor::task]
+pub async fn fade() {
+    loop {
+        FADE_START.wait().await;
+
+        loop {
+            Timer::after(FADE_TICK).await;
+
+            let mut settled = true;
+            for zone in &ZONES {
+                settled &= zone.with_light(|l| l.step(FADE_TICK)).unwrap_or(true);
+            }
+            if settled {
+                break;
+            }
+        }
+    }
+}
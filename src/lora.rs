@@ -0,0 +1,127 @@
+use core::sync::atomic::Ordering;
+
+use embassy_stm32::{exti::ExtiInput, gpio::Output, mode::Async, spi::Spi};
+use embassy_time::{Duration, Ticker};
+use heapless::Vec;
+
+use sie_core::{
+    can_frames::Command,
+    codes::Code,
+    control::Thresholds,
+    lora_packets::{Header, ZoneSample, decode_downlink, encode_uplink},
+};
+
+use crate::{
+    LORA_FREQUENCY, LORA_NODE_ID, MANUAL_MODE, SYSTEM_ENABLED, config_guard, error, set_manual,
+    sx1276::{Error, Sx1276},
+    watchdog::{self, Task},
+    zone::{ZONE_COUNT, ZONES},
+};
+
+// Cada cuanto se transmite el estado; cuidar el ciclo de trabajo de la
+// banda (1 % en 868 MHz)
+const UPLINK_PERIOD: Duration = Duration::from_secs(60);
+// Tras cada subida el nodo escucha ordenes durante esta ventana, como un
+// dispositivo LoRaWAN de clase A; fuera de ella la radio no recibe
+const RX_WINDOW: Duration = Duration::from_secs(3);
+
+// Enlace LoRa punto a punto con una pasarela: sube el estado de las zonas
+// y atiende las ordenes que llegan justo despues. Ver
+// `sie_core::lora_packets`
+#[embassy_executor::task]
+pub async fn lora(spi: Spi<'static, Async>, nss: Output<'static>, dio0: ExtiInput<'static>) {
+    let mut radio = match Sx1276::new(spi, nss, dio0, LORA_FREQUENCY).await {
+        Ok(radio) => radio,
+        Err(Error::NotFound) => {
+            error::degrade(error::Error::Setup(Code::LoraMissing));
+            return;
+        }
+        Err(error) => {
+            report(error);
+            return;
+        }
+    };
+    info!(Links, "Nodo LoRa {}", LORA_NODE_ID);
+    let mut sequence = 0u8;
+    let mut ticker = Ticker::every(UPLINK_PERIOD);
+    loop {
+        // Al apagarse por bateria baja sube de inmediato el ultimo estado
+        #[cfg(feature = "battery")]
+        embassy_futures::select::select(ticker.next(), crate::battery::shutdown()).await;
+        #[cfg(not(feature = "battery"))]
+        ticker.next().await;
+        watchdog::check_in(Task::Lora);
+
+        let (packet, len) = encode_uplink(
+            Header {
+                node: LORA_NODE_ID,
+                sequence,
+                manual: MANUAL_MODE.load(Ordering::Relaxed),
+                enabled: SYSTEM_ENABLED.load(Ordering::Relaxed),
+            },
+            &samples(),
+        );
+        sequence = sequence.wrapping_add(1);
+        if let Err(error) = radio.transmit(&packet[..len]).await {
+            report(error);
+            continue;
+        }
+
+        match radio.receive(RX_WINDOW).await {
+            Ok(Some(data)) => match decode_downlink(LORA_NODE_ID, &data) {
+                Some(command) => apply(command),
+                None => info!(Links, "Paquete LoRa ajeno o desconocido"),
+            },
+            Ok(None) => {}
+            Err(error) => report(error),
+        }
+    }
+}
+
+fn samples() -> Vec<ZoneSample, ZONE_COUNT> {
+    ZONES
+        .iter()
+        .map(|zone| {
+            let reading = zone.last_reading.lock(|r| r.get());
+            ZoneSample {
+                brightness: zone.with_light(|l| l.brightness()).unwrap_or(0),
+                lux: reading.map_or(0, |r| r.lux as u16),
+                distance_cm: reading.map_or(0, |r| (r.distance * 100.) as u16),
+            }
+        })
+        .collect()
+}
+
+fn apply(command: Command) {
+    match command {
+        Command::SetManual(manual) => {
+            set_manual(manual);
+        }
+        Command::SetLightThreshold(lux) => {
+            set_thresholds(|t| t.light = lux as f32);
+        }
+        Command::SetDistanceThreshold(cm) => {
+            set_thresholds(|t| t.distance = cm as f32 / 100.);
+        }
+    }
+    info!(Links, "Orden LoRa recibida");
+}
+
+fn set_thresholds(change: impl Fn(&mut Thresholds)) {
+    config_guard::remote_change(|| {
+        for zone in &ZONES {
+            zone.thresholds.lock(|t| {
+                let mut thresholds = t.get();
+                change(&mut thresholds);
+                t.set(thresholds);
+            });
+        }
+    });
+}
+
+fn report(error: Error) {
+    match error {
+        Error::Timeout => warn!(Code::LoraTimeout, "LoRa: la transmision no termino"),
+        _ => warn!(Code::RadioBus, "LoRa: fallo el bus SPI"),
+    }
+}
@@ -0,0 +1,76 @@
+use embassy_stm32::pac::{self, pwr::vals::Pdds};
+use embassy_time::{Duration, Timer};
+
+use crate::{rtc, watchdog, zone::ZONES};
+
+// Tiempo despierto entre paradas: las zonas toman algunas muestras, deciden
+// y las tareas supervisadas se reportan
+const AWAKE: Duration = Duration::from_millis(300);
+// Duracion de cada parada; el RTC cuenta segundos y el watchdog (4 s) sigue
+// corriendo en STOP
+const STOP_SECONDS: u32 = 2;
+
+// Linea del EXTI conectada a la alarma del RTC
+const RTC_ALARM_LINE: usize = 17;
+
+// Consumo del MCU en STOP con el regulador en bajo consumo: 14 uA tipico
+// segun la hoja de datos del STM32F103 (3.3 V, 25 C), contra varios mA
+// corriendo. No esta medido en la placa, donde dominan el regulador, el
+// LED de encendido y los sensores; medir en cada instalacion
+
+// La alarma del RTC despierta del modo STOP como evento, sin interrupcion.
+// Con `defmt` el depurador sigue conectado durante las paradas, a cambio
+// de mantener los reguladores encendidos
+pub fn init() {
+    rtc::enable();
+    pac::EXTI
+        .rtsr(0)
+        .modify(|w| w.set_line(RTC_ALARM_LINE, true));
+    pac::EXTI
+        .emr(0)
+        .modify(|w| w.set_line(RTC_ALARM_LINE, true));
+    #[cfg(feature = "defmt")]
+    pac::DBGMCU.cr().modify(|w| w.set_dbg_stop(true));
+}
+
+// Modo de bajo consumo: entre ventanas de muestreo detiene los relojes
+// (modo STOP) hasta la alarma del RTC o una interrupcion externa (los
+// botones, el disparo por presencia). Solo para con todas las lamparas
+// apagadas, porque el PWM tambien se detiene. Mientras para, el tiempo de
+// embassy no avanza: los plazos cuentan solo el tiempo despierto. La hora
+// del RTC si sigue
+#[embassy_executor::task]
+pub async fn low_power() {
+    loop {
+        Timer::after(AWAKE).await;
+
+        let dark = ZONES
+            .iter()
+            .all(|zone| zone.with_light(|l| l.is_dark()).unwrap_or(false));
+        // Con una tarea trabada no se alimenta el watchdog ni se para
+        if dark && watchdog::pet_before_stop() {
+            stop();
+        }
+    }
+}
+
+fn stop() {
+    rtc::set_alarm(STOP_SECONDS);
+    pac::PWR.cr().modify(|w| {
+        w.set_pdds(Pdds::STOP_MODE);
+        w.set_lpds(true);
+    });
+
+    // SAFETY: solo se usa SCB para pasar a sueno profundo y volver
+    let mut core = unsafe { cortex_m::Peripherals::steal() };
+    core.SCB.set_sleepdeep();
+    // El primer WFE descarta un evento viejo; el segundo espera
+    cortex_m::asm::sev();
+    cortex_m::asm::wfe();
+    cortex_m::asm::wfe();
+    // Sin esto el ejecutor entraria en STOP al quedar ocioso
+    core.SCB.clear_sleepdeep();
+
+    pac::EXTI.pr(0).write(|w| w.set_line(RTC_ALARM_LINE, true));
+    rtc::clear_alarm();
+}
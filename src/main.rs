@@ -7,175 +7,914 @@ use embassy_executor::Spawner;
 use embassy_stm32::{
     adc::Adc,
     exti::ExtiInput,
-    gpio::{Level, Output, Pull, Speed},
+    gpio::{Input, Level, Output, Pull, Speed},
+    peripherals::ADC1,
+    wdg::IndependentWatchdog,
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use static_cell::StaticCell;
+
+#[cfg(feature = "defmt")]
+use defmt_rtt as _;
+
+#[cfg(all(feature = "telemetry", feature = "mqtt"))]
+compile_error!("La telemetria y el puente MQTT usan USART2; elegir solo una");
+
+#[cfg(all(feature = "bluetooth", feature = "usb-console"))]
+compile_error!("La consola va por USB o por Bluetooth en USART1; elegir solo una");
+
+#[cfg(all(feature = "can", feature = "usb-console"))]
+compile_error!("CAN y USB comparten la memoria de paquetes; elegir solo uno");
+
+#[cfg(all(feature = "adc-watchdog", feature = "dual-adc"))]
+compile_error!("El watchdog de luz y el modo dual usan ADC2; elegir solo uno");
+
+#[cfg(all(feature = "i2c-slave", feature = "can"))]
+compile_error!("El esclavo I2C y CAN usan PB8/PB9; elegir solo uno");
+
+#[cfg(all(
+    any(feature = "oled", feature = "lcd"),
+    any(feature = "i2c-slave", feature = "can")
+))]
+compile_error!("La pantalla usa I2C1 en PB8/PB9 (el esclavo I2C y CAN); elegir solo uno");
+
+#[cfg(all(feature = "oled", feature = "lcd"))]
+compile_error!("El OLED y el LCD comparten I2C1; elegir solo una pantalla");
+
+#[cfg(all(
+    feature = "seven-segment",
+    any(
+        feature = "lora",
+        feature = "nrf24",
+        feature = "mains-monitor",
+        feature = "stop-mode"
+    )
+))]
+compile_error!(
+    "El visor de 7 segmentos usa PA15, PB3 y PB4 (las radios y el cruce por cero) y se multiplexa sin parar (modo STOP)"
+);
+
+#[cfg(all(
+    feature = "sd-log",
+    any(
+        feature = "lora",
+        feature = "nrf24",
+        feature = "mains-monitor",
+        feature = "seven-segment"
+    )
+))]
+compile_error!(
+    "La tarjeta SD usa SPI1 (PB3/PB4/PB5) y PA15; no se combina con las radios, el cruce por cero ni el visor de 7 segmentos"
+);
+
+#[cfg(all(feature = "energy-meter", feature = "lora"))]
+compile_error!("El medidor de energia y el DIO0 de la radio LoRa usan PA5; elegir solo uno");
+
+#[cfg(all(feature = "mains-monitor", feature = "lora"))]
+compile_error!("El cruce por cero y el MISO de la radio LoRa usan PB4; elegir solo uno");
+
+#[cfg(all(
+    feature = "dmx",
+    any(feature = "ds3231", feature = "lora", feature = "nrf24")
+))]
+compile_error!("La salida DMX usa PB10 (el I2C2 del DS3231) y DMA1_CH2 (el SPI1 de las radios)");
+
+#[cfg(all(feature = "nrf24-sensor", feature = "nrf24-relay"))]
+compile_error!("Un nodo nRF24 es de sensores o de lampara; elegir solo uno");
+
+#[cfg(all(
+    feature = "nrf24",
+    any(feature = "lora", feature = "energy-meter", feature = "mains-monitor")
+))]
+compile_error!(
+    "La radio nRF24 usa SPI1 (PB3/PB4/PB5) y PA5/PA15; no se combina con `lora`, `energy-meter` ni `mains-monitor`"
+);
+
+#[cfg(all(
+    feature = "outdoor-light",
+    any(feature = "lora", feature = "energy-meter", feature = "nrf24")
+))]
+compile_error!(
+    "El sensor de luz exterior usa PA5; no se combina con `lora`, `energy-meter` ni `nrf24`"
+);
+
+#[cfg(all(
+    feature = "adc-calibration",
+    any(feature = "nrf24-relay", feature = "sim", feature = "replay")
+))]
+compile_error!(
+    "El nodo de lampara nRF24, la simulacion y la reproduccion no usan el ADC; no hay nada que calibrar"
+);
+
+#[cfg(all(feature = "battery", feature = "trim-pot"))]
+compile_error!("La medicion de la bateria y el potenciometro usan PA4; elegir solo uno");
+
+#[cfg(all(
+    feature = "stop-mode",
+    any(
+        feature = "console",
+        feature = "mqtt",
+        feature = "can",
+        feature = "i2c-slave",
+        feature = "dmx"
+    )
+))]
+compile_error!(
+    "En modo STOP se detienen los relojes: la consola, MQTT, CAN, el esclavo I2C y DMX pierden datos"
+);
+
+#[cfg(all(feature = "black-pill", feature = "nucleo-f103"))]
+compile_error!("Elegir una sola placa");
+
+#[cfg(all(feature = "black-pill", feature = "ds3231"))]
+compile_error!("En la black pill el boton de luz usa PB11 (el I2C2 del DS3231); elegir solo uno");
+
+#[cfg(all(
+    feature = "nucleo-f103",
+    any(
+        feature = "lora",
+        feature = "nrf24",
+        feature = "energy-meter",
+        feature = "outdoor-light"
+    )
+))]
+compile_error!(
+    "En la Nucleo PA5 es el LED LD2; no se combina con `lora`, `nrf24`, `energy-meter` ni `outdoor-light`"
+);
+
+#[cfg(all(any(feature = "sim", feature = "replay"), feature = "dual-adc"))]
+compile_error!(
+    "La simulacion y la reproduccion no leen los sensores; no se combinan con `dual-adc`"
+);
+
+#[cfg(all(feature = "sim", feature = "replay"))]
+compile_error!("Las lecturas vienen del escenario simulado o de una traza; elegir solo una");
+
+#[cfg(all(feature = "camera-trigger", feature = "encoder"))]
+compile_error!("La salida para camaras y el boton del encoder usan PB14; elegir solo uno");
+
+#[cfg(all(
+    feature = "nrf24-relay",
+    any(
+        feature = "adc-watchdog",
+        feature = "presence-trigger",
+        feature = "dual-adc",
+        feature = "sim",
+        feature = "replay"
+    )
+))]
+compile_error!("El nodo de lampara nRF24 no muestrea sensores propios");
+
+#[macro_use]
+mod fmt;
+#[macro_use]
+mod board;
+
+#[cfg(feature = "adc-calibration")]
+mod adc_calibration;
+#[cfg(feature = "adc-watchdog")]
+mod adc_watchdog;
+#[cfg(feature = "ambient-learning")]
+mod ambient;
+#[cfg(feature = "battery")]
+mod battery;
+mod button;
+mod buzzer;
+#[cfg(feature = "camera-trigger")]
+mod camera_trigger;
+#[cfg(feature = "can")]
+mod can_bus;
+mod clock;
+mod config;
+#[cfg(any(feature = "can", feature = "lora", feature = "i2c-slave"))]
+mod config_guard;
+#[cfg(feature = "console")]
+mod console;
+mod counters;
+mod crash;
+#[cfg(feature = "demo")]
+mod demo;
+mod diagnostics;
+#[cfg(any(feature = "oled", feature = "lcd"))]
+mod display;
+#[cfg(feature = "dmx")]
+mod dmx;
+#[cfg(feature = "ds3231")]
+mod ds3231;
+#[cfg(feature = "dual-adc")]
+mod dual_adc;
+#[cfg(feature = "encoder")]
+mod encoder;
+#[cfg(feature = "energy-meter")]
+mod energy_meter;
+mod error;
+#[cfg(feature = "event-log")]
+mod event_log;
+mod events;
+mod factory_reset;
+mod flash_log;
+#[cfg(feature = "history")]
+mod history;
+#[cfg(feature = "i2c-slave")]
+mod i2c_slave;
+mod kv;
+#[cfg(feature = "lcd")]
+mod lcd;
+mod light;
+#[cfg(feature = "lora")]
+mod lora;
+#[cfg(feature = "stop-mode")]
+mod low_power;
+#[cfg(feature = "mains-monitor")]
+mod mains;
+mod manual_timeout;
+mod memory;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "nrf24")]
+mod nrf24;
+#[cfg(feature = "nrf24")]
+mod nrf24_link;
+#[cfg(feature = "oled")]
+mod oled;
+#[cfg(feature = "outdoor-light")]
+mod outdoor_light;
+#[cfg(feature = "presence-trigger")]
+mod presence_trigger;
+#[cfg(feature = "replay")]
+mod replay;
+mod report;
+#[cfg(any(feature = "schedule", feature = "stop-mode"))]
+mod rtc;
+#[cfg(feature = "console")]
+mod rules;
+#[cfg(feature = "sd-log")]
+mod sd_log;
+mod settings;
+#[cfg(feature = "seven-segment")]
+mod seven_segment;
+mod status_led;
+mod storage;
+#[cfg(feature = "lora")]
+mod sx1276;
+#[cfg(feature = "telemetry")]
+mod telemetry;
+#[cfg(feature = "trim-pot")]
+mod trim_pot;
+#[cfg(feature = "schedule")]
+mod wall_clock;
+mod watchdog;
+#[macro_use]
+mod zone;
+
+use button::{Debounced, Press};
+use events::{Button, Event};
+use light::MAX_BRIGHTNESS;
+use report::ReportRequest;
+use sie_core::{beep::Beep, codes::Code, event_log::Reason, sensor::LuxPolarity};
+use zone::{ZONES, ZoneState};
+
+// Unidades con las que la consola muestra las distancias al arrancar; se
+// cambian con `units`
+#[cfg(feature = "console")]
+const UNITS: sie_core::units::Units = sie_core::units::Units::Metric;
+
+// Grupos de campos de cada muestra de telemetria (ver
+// sie_core::telemetry::Fields); menos campos, tramas mas cortas
+#[cfg(all(feature = "telemetry", not(feature = "stats")))]
+const TELEMETRY_FIELDS: sie_core::telemetry::Fields =
+    sie_core::telemetry::Fields::VALUES.with(sie_core::telemetry::Fields::STATE);
+#[cfg(all(feature = "telemetry", feature = "stats"))]
+const TELEMETRY_FIELDS: sie_core::telemetry::Fields = sie_core::telemetry::Fields::VALUES
+    .with(sie_core::telemetry::Fields::STATE)
+    .with(sie_core::telemetry::Fields::STATS);
+
+// Numero de nodo en el bus CAN (0 a 126); cada lampara del bus lleva uno
+// distinto
+#[cfg(feature = "can")]
+const CAN_NODE_ID: u8 = 1;
+
+// Direccion de 7 bits del equipo como esclavo I2C
+#[cfg(feature = "i2c-slave")]
+const I2C_ADDRESS: u8 = 0x42;
+
+// Medidor de energia con salida S0 del circuito de iluminacion
+#[cfg(feature = "energy-meter")]
+const ENERGY_METER: energy_meter::EnergyMeter = energy_meter::EnergyMeter {
+    pulses_per_kwh: 1000,
+    lamp_watts: 9.,
 };
-use embassy_sync::blocking_mutex::CriticalSectionMutex;
-use embassy_time::Timer;
-
-use {defmt_rtt as _, panic_probe as _};
-
-// Todo el sistema se alimenta de una fuente
-// de 3.3V
-const VOLTAGE_REF: f32 = 3.3; // volts
-
-// stm32 blue pill tiene un adc de 12 bits
-const MAX_ADC_VALUE: f32 = 0b1111_1111_1111 as f32; // 4095.0
-
-// Umbrales para el sensor
-const LIGHT_THRESHOLD: f32 = 1000.; // Luxes
-const DISTANCE_THRESHOLD: f32 = 2.5; // Metters
 
-// Variables globales compartidas entre loop principal
-// e interrupciones
+// Frecuencia nominal de la red
+#[cfg(feature = "mains-monitor")]
+const MAINS_HZ: f32 = 60.;
+
+// Canal DMX (1 a 512) del dimmer de cada zona
+#[cfg(all(feature = "dmx", not(feature = "second-zone")))]
+const DMX_CHANNELS: [u16; zone::ZONE_COUNT] = [1];
+#[cfg(all(feature = "dmx", feature = "second-zone"))]
+const DMX_CHANNELS: [u16; zone::ZONE_COUNT] = [1, 2];
+
+// Numero del nodo de sensores del enlace nRF24; el nodo de lampara solo
+// acepta lecturas de ese numero
+#[cfg(feature = "nrf24")]
+const NRF24_NODE_ID: u8 = 1;
+
+// Nodo y frecuencia portadora del enlace LoRa; la frecuencia depende de la
+// banda de la region (433, 868 o 915 MHz)
+#[cfg(feature = "lora")]
+const LORA_NODE_ID: u8 = 1;
+#[cfg(feature = "lora")]
+const LORA_FREQUENCY: u32 = 915_000_000;
+
+// ADC compartido entre las tareas que leen sensores
+type SharedAdc = Mutex<CriticalSectionRawMutex, Adc<'static, ADC1>>;
+static ADC: StaticCell<SharedAdc> = StaticCell::new();
+
+// Variables globales compartidas entre los controladores de zona
+// e interrupciones. El modo manual se cambia con `set_manual`, que
+// despierta a las tareas que esperan el cambio
 static MANUAL_MODE: AtomicBool = AtomicBool::new(false);
-static LIGHT: CriticalSectionMutex<Option<Output<'static>>> = CriticalSectionMutex::new(None);
+static SYSTEM_ENABLED: AtomicBool = AtomicBool::new(true);
+// En lazo cerrado el brillo sigue a la luz ambiental en lugar de ser fijo
+static CLOSED_LOOP: AtomicBool = AtomicBool::new(false);
+
+// Cambian el modo, despiertan a los controladores de zona y lo publican en
+// el bus de eventos
+fn set_manual(manual: bool) {
+    MANUAL_MODE.store(manual, Ordering::Relaxed);
+    // Cambiar el modo cuenta como actividad y despierta a la expiracion
+    manual_timeout::activity();
+    publish_mode();
+}
 
-// Convertir el valor del ADC a un voltaje
-fn get_voltage(adc_value: f32) -> f32 {
-    (adc_value / MAX_ADC_VALUE) * VOLTAGE_REF
+fn set_enabled(enabled: bool) {
+    SYSTEM_ENABLED.store(enabled, Ordering::Relaxed);
+    publish_mode();
 }
 
-// Valores de un sensor GP2Y0A710K0F
-const DIST_MIN_V: f32 = 1.4; // 550 cm (5.5m)
-const DIST_MAX_V: f32 = 2.5; // 100 cm (1.0m)
+fn publish_mode() {
+    for zone in &ZONES {
+        zone.mode_changed.signal(());
+    }
+    events::publish(Event::ModeChanged {
+        manual: MANUAL_MODE.load(Ordering::Relaxed),
+        enabled: SYSTEM_ENABLED.load(Ordering::Relaxed),
+    });
+}
 
-// Distancias correspondientes
-const DIST_MIN_M: f32 = 5.5; // 5.5 metros (voltaje mínimo)
-const DIST_MAX_M: f32 = 1.0; // 1.0 metro (voltaje máximo)
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    // Para medir cuanta pila llega a usarse
+    memory::paint();
+
+    let mut p = embassy_stm32::init(board::config());
+
+    // Watchdog independiente desde el arranque; lo alimenta la tarea de
+    // supervision mientras las tareas criticas respondan
+    let mut wdg = IndependentWatchdog::new(p.IWDG, watchdog::TIMEOUT.as_micros() as u32);
+    wdg.unleash();
+
+    // Reloj de tiempo real para el horario y las marcas del registro
+    #[cfg(feature = "schedule")]
+    {
+        static INTERNAL_RTC: StaticCell<rtc::InternalRtc> = StaticCell::new();
+        let internal = INTERNAL_RTC.init(rtc::InternalRtc::new());
+
+        #[cfg(feature = "ds3231")]
+        {
+            use embassy_stm32::{i2c::I2c, time::Hertz};
+
+            static DS3231: StaticCell<ds3231::Ds3231> = StaticCell::new();
+            let i2c =
+                I2c::new_blocking(p.I2C2, p.PB10, p.PB11, Hertz::khz(100), Default::default());
+            match ds3231::Ds3231::probe(i2c) {
+                Some(external) => {
+                    wall_clock::init(DS3231.init(external));
+                    info!("Hora del DS3231");
+                }
+                None => {
+                    wall_clock::init(internal);
+                    info!("DS3231 ausente, se usa el RTC interno");
+                }
+            }
+        }
+        #[cfg(not(feature = "ds3231"))]
+        wall_clock::init(internal);
+    }
 
-fn voltage_to_distance(voltage: f32) -> f32 {
-    // Aplicamos saturación a los límites del sensor
-    let clamped_voltage = voltage.clamp(DIST_MIN_V, DIST_MAX_V);
+    // El ADC se comparte entre los controladores de zona
+    let adc: &'static SharedAdc = ADC.init(Mutex::new(Adc::new(p.ADC1)));
+    #[cfg(feature = "dual-adc")]
+    dual_adc::init(p.ADC2);
+
+    // Recalibracion nocturna del ADC
+    #[cfg(feature = "adc-calibration")]
+    error::spawn(
+        spawner,
+        adc_calibration::adc_calibration(adc),
+        "adc_calibration",
+    );
+
+    // Un nodo solar apagado por bateria baja vuelve a dormir si el panel
+    // todavia no la recargo
+    #[cfg(feature = "battery")]
+    battery::check_at_boot(&mut p.PA4, adc).await;
+
+    // Registro de advertencias en flash; se reporta lo que haya quedado
+    // de la ejecucion anterior
+    storage::init(p.FLASH);
+    flash_log::init();
+    flash_log::dump();
+    crash::check_at_boot();
+    kv::init();
+    #[cfg(feature = "event-log")]
+    event_log::init();
+    counters::init();
+    // Los dos botones presionados al arrancar: configuracion de fabrica
+    factory_reset::check_at_boot(
+        Input::new(&mut pin!(p, manual_button), Pull::Down),
+        Input::new(&mut pin!(p, light_button), Pull::Down),
+    );
+    let saved = settings::load();
+    #[cfg(any(feature = "can", feature = "lora", feature = "i2c-slave"))]
+    config_guard::load();
+    #[cfg(feature = "console")]
+    rules::load();
+    let config = config::get();
+
+    // Potenciometro opcional para ajustar el umbral de luz
+    #[cfg(feature = "trim-pot")]
+    error::spawn(spawner, trim_pot::trim_pot(p.PA4, adc), "trim_pot");
+
+    // Apagado ordenado con la bateria baja
+    #[cfg(feature = "battery")]
+    error::spawn(spawner, battery::battery(p.PA4, adc), "battery");
+
+    // Sensor de luz exterior para las reglas de las zonas
+    #[cfg(feature = "outdoor-light")]
+    error::spawn(
+        spawner,
+        outdoor_light::outdoor_light(p.PA5, adc),
+        "outdoor_light",
+    );
 
-    // Mapeo lineal inverso (voltaje alto = distancia corta)
-    let factor = (clamped_voltage - DIST_MIN_V) / (DIST_MAX_V - DIST_MIN_V);
-    DIST_MIN_M + (DIST_MAX_M - DIST_MIN_M) * (1.0 - factor)
-}
+    // Configurar un pin para EXTI
+    let toggle_manual_btn = Debounced::new(
+        ExtiInput::new(pin!(p, manual_button), pin!(p, manual_exti), Pull::Down),
+        config.debounce_time,
+        config.long_press_time,
+        config.click_window,
+    );
+    let toggle_light_btn = Debounced::new(
+        ExtiInput::new(pin!(p, light_button), pin!(p, light_exti), Pull::Down),
+        config.debounce_time,
+        config.long_press_time,
+        config.click_window,
+    );
+
+    let off = Level::from(board::STATUS_LED_ACTIVE_LOW);
+    #[cfg(not(all(
+        any(feature = "lora", feature = "nrf24", feature = "sd-log"),
+        not(feature = "nucleo-f103")
+    )))]
+    let status_led = Output::new(pin!(p, status_led), off, Speed::Low);
+    // PB5 es el MOSI de la radio o de la tarjeta SD; se usa el LED de la placa
+    #[cfg(all(
+        any(feature = "lora", feature = "nrf24", feature = "sd-log"),
+        not(feature = "nucleo-f103")
+    ))]
+    let status_led = Output::new(pin!(p, board_led), off, Speed::Low);
+
+    // Zonas: cada una con sus sensores y su lampara en un canal del TIM4
+    zones! {
+        spawner, p, adc;
+        0 => {
+            distance: PB0 (8),
+            light: PA7 (7),
+            light_polarity: LuxPolarity::Rising,
+            distance_model: Gp2y0a710,
+            light_model: Dfr0026,
+            output: PB7 (Ch2),
+            min_duty: config.min_duty,
+            thresholds: saved.map(|s| s.zones[0].thresholds),
+            timeout: config.timeout,
+            presence: config.presence,
+        },
+        #[cfg(feature = "second-zone")]
+        1 => {
+            distance: PB1 (9),
+            light: PA6 (6),
+            light_polarity: LuxPolarity::Rising,
+            distance_model: Gp2y0a710,
+            light_model: Dfr0026,
+            output: PB6 (Ch1),
+            min_duty: config.min_duty,
+            thresholds: saved.map(|s| s.zones[1].thresholds),
+            timeout: config.timeout,
+            presence: config.presence,
+        },
+    }
+    info!("PWM de las lamparas a {} Hz", light::pwm_frequency().0);
 
-// Valores reales de un sensor DFRobot (DFR0026)
-const LUX_MIN_V: f32 = 0.3; // 0 lux
-const LUX_MAX_V: f32 = 3.0; // 6000 lux
+    // Comparador externo en PB15 sobre el sensor de distancia de la zona 0
+    #[cfg(feature = "presence-trigger")]
+    error::spawn(
+        spawner,
+        presence_trigger::presence_trigger(ExtiInput::new(p.PB15, p.EXTI15, Pull::Down), 0),
+        "presence_trigger",
+    );
 
-const MAX_LUX_VALUE: f32 = 6000.;
+    // Rampas de brillo de las lamparas
+    error::spawn(spawner, light::fade(), "fade");
 
-fn voltage_to_lux(voltage: f32) -> f32 {
-    // Aplicamos saturación a los límites del sensor
-    let clamped_voltage = voltage.clamp(LUX_MIN_V, LUX_MAX_V);
+    // Reporte periodico de la latencia de muestra a actuacion
+    error::spawn(spawner, diagnostics::diagnostics(), "diagnostics");
 
-    // Mapeo lineal directo
-    let factor = (clamped_voltage - LUX_MIN_V) / (LUX_MAX_V - LUX_MIN_V);
-    factor * MAX_LUX_VALUE
-}
+    // LED de estado: modo manual, sistema deshabilitado, fallas, etc.
+    error::spawn(spawner, status_led::status_led(status_led), "status_led");
 
-#[embassy_executor::main]
-async fn main(spawner: Spawner) {
-    let p = embassy_stm32::init(Default::default());
+    // Supervision de las tareas criticas
+    error::spawn(spawner, watchdog::watchdog(wdg), "watchdog");
 
-    let mut adc = Adc::new(p.ADC1);
+    // Guardado periodico de los contadores de toda la vida
+    error::spawn(spawner, counters::counters(), "counters");
 
-    // Pines asignados a los sensores
-    let mut distance_sensor = p.PB0;
-    let mut light_sensor = p.PA7;
+    // Guardado de los umbrales, la calibracion y los modos al cambiar
+    error::spawn(spawner, settings::settings(saved), "settings");
 
-    // Configurar un pin para EXTI
-    let toggle_manual_btn = ExtiInput::new(p.PB13, p.EXTI13, Pull::Down);
-    let toggle_light_btn = ExtiInput::new(p.PB12, p.EXTI12, Pull::Down);
+    // Vuelta atras de los umbrales cambiados a distancia que resulten malos
+    #[cfg(any(feature = "can", feature = "lora", feature = "i2c-slave"))]
+    error::spawn(spawner, config_guard::config_guard(), "config_guard");
 
-    // Leds de salida
-    let manual_mode_light = Output::new(p.PB5, Level::Low, Speed::Low);
-    let light = Output::new(p.PB7, Level::Low, Speed::Low);
+    // Configuracion de fabrica con los dos botones presionados un rato
+    error::spawn(spawner, factory_reset::factory_reset(), "factory_reset");
 
-    // Inicializar variable global entre interrupciones
-    unsafe { LIGHT.lock_mut(|l| *l = Some(light)) }
+    // Regreso al modo automatico por inactividad
+    error::spawn(spawner, manual_timeout::manual_timeout(), "manual_timeout");
 
     // Inicializar interrupcion para establecer modo manual
-    spawner
-        .spawn(toggle_manual(toggle_manual_btn, manual_mode_light))
-        .expect("Cannot create toggle_manual task");
+    error::spawn(spawner, toggle_manual(toggle_manual_btn), "toggle_manual");
 
     // Inicializar interrupcion para encender o apagar manualmente la luz
-    spawner
-        .spawn(toggle_light(toggle_light_btn))
-        .expect("Cannot create toggle_manual task");
-
-    loop {
-        Timer::after_millis(100).await;
-        if MANUAL_MODE.load(Ordering::Relaxed) {
-            continue;
-        }
+    error::spawn(spawner, toggle_light(toggle_light_btn), "toggle_light");
+
+    // Zumbador para avisos sonoros
+    #[cfg(feature = "buzzer")]
+    {
+        use embassy_stm32::{
+            gpio::OutputType,
+            time::Hertz,
+            timer::{
+                low_level::CountingMode,
+                simple_pwm::{PwmPin, SimplePwm},
+            },
+        };
 
-        let raw_distance = adc.read(&mut distance_sensor).await;
-        let raw_luminicence = adc.read(&mut light_sensor).await;
+        let pwm = SimplePwm::new(
+            p.TIM1,
+            Some(PwmPin::new_ch1(p.PA8, OutputType::PushPull)),
+            None,
+            None,
+            None,
+            Hertz::khz(2),
+            CountingMode::EdgeAlignedUp,
+        );
+        error::spawn(spawner, buzzer::buzzer(pwm), "buzzer");
+    }
 
-        let distance_voltage = get_voltage(raw_distance as f32);
-        let luminicence_voltaje = get_voltage(raw_luminicence as f32);
+    // Consola serie para inspeccionar y configurar el equipo
+    #[cfg(all(feature = "console", not(feature = "usb-console")))]
+    error::spawn(
+        spawner,
+        console::console(p.USART1, p.PA9, p.PA10),
+        "console",
+    );
+    #[cfg(feature = "usb-console")]
+    error::spawn(spawner, console::console(p.USB, p.PA12, p.PA11), "console");
+
+    // Ultimas muestras y eventos para ver por la consola lo que paso antes
+    // de una falla
+    #[cfg(feature = "history")]
+    error::spawn(spawner, history::history(), "history");
+
+    // Sucesos guardados en flash para leerlos por la consola
+    #[cfg(feature = "event-log")]
+    error::spawn(spawner, event_log::event_log(), "event_log");
+
+    // Secuencias de demostracion pedidas por la consola
+    #[cfg(feature = "demo")]
+    error::spawn(spawner, demo::demo(), "demo");
+
+    // Puente MQTT con un ESP-01 para consultar y operar a distancia
+    #[cfg(feature = "mqtt")]
+    error::spawn(spawner, mqtt::mqtt(p.USART2, p.PA2, p.PA3), "mqtt");
+
+    // Nodo del bus CAN para operar varias lamparas desde un mismo bus
+    #[cfg(feature = "can")]
+    error::spawn(spawner, can_bus::can_bus(p.CAN, p.PB8, p.PB9), "can_bus");
+
+    // Registros I2C para que una computadora consulte el equipo
+    #[cfg(feature = "i2c-slave")]
+    error::spawn(
+        spawner,
+        i2c_slave::i2c_slave(p.I2C1, p.PB8, p.PB9),
+        "i2c_slave",
+    );
+
+    // Pantalla de estado para usar el equipo sin sonda de depuracion
+    #[cfg(feature = "oled")]
+    error::spawn(spawner, oled::oled(p.I2C1, p.PB8, p.PB9), "oled");
+    #[cfg(feature = "lcd")]
+    error::spawn(spawner, lcd::lcd(p.I2C1, p.PB8, p.PB9), "lcd");
+
+    // Visor de 7 segmentos con la luz o la distancia de la zona 0
+    #[cfg(feature = "seven-segment")]
+    error::spawn(
+        spawner,
+        seven_segment::seven_segment(p.PA15, p.PB3, p.PB4),
+        "seven_segment",
+    );
+
+    // Registro CSV de las zonas en la tarjeta SD
+    #[cfg(feature = "sd-log")]
+    error::spawn(
+        spawner,
+        sd_log::sd_log(p.SPI1, p.PB3, p.PB5, p.PB4, p.PA15),
+        "sd_log",
+    );
+
+    // Enlace LoRa para instalaciones fuera del alcance del WiFi
+    #[cfg(feature = "lora")]
+    {
+        let spi = remapped_spi1(p.SPI1, p.PB3, p.PB5, p.PB4, p.DMA1_CH3, p.DMA1_CH2);
+        let nss = Output::new(p.PA15, Level::High, Speed::VeryHigh);
+        let dio0 = ExtiInput::new(p.PA5, p.EXTI5, Pull::Down);
+        error::spawn(spawner, lora::lora(spi, nss, dio0), "lora");
+    }
 
-        let entity_distance = voltage_to_distance(distance_voltage);
-        let ambient_luminance = voltage_to_lux(luminicence_voltaje);
+    // Enlace nRF24 entre el nodo de sensores y el de la lampara
+    #[cfg(feature = "nrf24")]
+    {
+        let spi = remapped_spi1(p.SPI1, p.PB3, p.PB5, p.PB4, p.DMA1_CH3, p.DMA1_CH2);
+        let csn = Output::new(p.PA15, Level::High, Speed::VeryHigh);
+        let ce = Output::new(p.PA5, Level::Low, Speed::VeryHigh);
+        error::spawn(spawner, nrf24_link::nrf24_link(spi, csn, ce), "nrf24_link");
+    }
 
-        defmt::info!(
-            "Objeto a {} metros. Voltaje: {}",
-            entity_distance,
-            distance_voltage
+    // Dimmers DMX512 con el brillo de cada zona
+    #[cfg(feature = "dmx")]
+    error::spawn(spawner, dmx::dmx(p.USART3, p.PB10, p.DMA1_CH2), "dmx");
+
+    // Pulsos del medidor de energia del circuito de iluminacion
+    #[cfg(feature = "energy-meter")]
+    error::spawn(
+        spawner,
+        energy_meter::energy_meter(ExtiInput::new(p.PA5, p.EXTI5, Pull::Up)),
+        "energy_meter",
+    );
+
+    // Frecuencia y cortes de la red con el detector de cruce por cero
+    #[cfg(feature = "mains-monitor")]
+    {
+        // PB4 es NJTRST del JTAG; solo se libera esa senal
+        embassy_stm32::pac::AFIO
+            .mapr()
+            .modify(|w| w.set_swj_cfg(0b001));
+        error::spawn(
+            spawner,
+            mains::mains(ExtiInput::new(p.PB4, p.EXTI4, Pull::Up)),
+            "mains",
         );
-        defmt::info!(
-            "Luminosidad de {} luxes. Voltaje {}",
-            ambient_luminance,
-            luminicence_voltaje
+    }
+
+    // Muestras binarias para registrar desde una computadora
+    #[cfg(feature = "telemetry")]
+    error::spawn(
+        spawner,
+        telemetry::telemetry(p.USART2, p.PA2, p.DMA1_CH7),
+        "telemetry",
+    );
+
+    // Perilla para ajustar los umbrales: canales del TIM2 en PA0/PA1
+    // y el boton del encoder en PB14
+    #[cfg(feature = "encoder")]
+    {
+        use embassy_stm32::timer::qei::{Qei, QeiPin};
+        use embassy_time::Duration;
+
+        let qei = Qei::new(p.TIM2, QeiPin::new_ch1(p.PA0), QeiPin::new_ch2(p.PA1));
+        let select_btn = Debounced::new(
+            ExtiInput::new(p.PB14, p.EXTI14, Pull::Down),
+            config.debounce_time,
+            config.long_press_time,
+            Duration::from_ticks(0),
         );
+        error::spawn(spawner, encoder::encoder(qei, select_btn), "encoder");
+    }
 
-        // Determinar si se enciende la luz
-        let level = if ambient_luminance < LIGHT_THRESHOLD && entity_distance < DISTANCE_THRESHOLD {
-            Level::High
-        } else {
-            Level::Low
-        };
+    // Pulsos para camaras de seguridad en cada deteccion de presencia
+    #[cfg(feature = "camera-trigger")]
+    error::spawn(
+        spawner,
+        camera_trigger::camera_trigger(Output::new(p.PB14, Level::Low, Speed::Low)),
+        "camera_trigger",
+    );
+
+    // Paradas en modo STOP entre muestras con las lamparas apagadas
+    #[cfg(feature = "stop-mode")]
+    {
+        low_power::init();
+        error::spawn(spawner, low_power::low_power(), "low_power");
+    }
+}
 
-        unsafe {
-            LIGHT.lock_mut(|l| {
-                if let Some(l) = l {
-                    l.set_level(level);
-                }
-            })
-        }
+// El mensaje del panic queda en RAM y se reporta al arrancar de nuevo (ver
+// `crash`). Con defmt ademas se imprime y el depurador se detiene, como con
+// panic-probe; en campo el watchdog reinicia. Sin defmt se reinicia de
+// inmediato
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    crash::store(info);
+    #[cfg(feature = "defmt")]
+    {
+        defmt::error!("{}", defmt::Display2Format(info));
+        cortex_m::asm::udf();
+    }
+    #[cfg(not(feature = "defmt"))]
+    cortex_m::peripheral::SCB::sys_reset();
+}
+
+// Envia la misma solicitud al resumen de cada zona
+fn request_report(request: ReportRequest) {
+    for zone in &ZONES {
+        zone.report_request.signal(request);
     }
 }
 
+// SPI1 remapeado a PB3 (SCK), PB4 (MISO) y PB5 (MOSI) para las radios.
+// PB3, PB4 y PA15 son del JTAG, que se apaga dejando SWD para el depurador
+#[cfg(any(feature = "lora", feature = "nrf24"))]
+fn remapped_spi1(
+    spi: embassy_stm32::peripherals::SPI1,
+    sck: embassy_stm32::peripherals::PB3,
+    mosi: embassy_stm32::peripherals::PB5,
+    miso: embassy_stm32::peripherals::PB4,
+    tx_dma: embassy_stm32::peripherals::DMA1_CH3,
+    rx_dma: embassy_stm32::peripherals::DMA1_CH2,
+) -> embassy_stm32::spi::Spi<'static, embassy_stm32::mode::Async> {
+    use embassy_stm32::{
+        spi::{Config, Spi},
+        time::Hertz,
+    };
+
+    embassy_stm32::pac::AFIO.mapr().modify(|w| {
+        w.set_swj_cfg(0b010);
+        w.set_spi1_remap(true);
+    });
+    let mut config = Config::default();
+    config.frequency = Hertz::mhz(1);
+    Spi::new(spi, sck, mosi, miso, tx_dma, rx_dma, config)
+}
+
 #[embassy_executor::task]
-async fn toggle_manual(
-    mut toggle_manual_btn: ExtiInput<'static>,
-    mut manual_mode_light: Output<'static>,
-) {
+async fn toggle_manual(mut toggle_manual_btn: Debounced<'static>) {
     loop {
-        toggle_manual_btn.wait_for_falling_edge().await;
-        Timer::after_millis(50).await;
-
-        let current = MANUAL_MODE.load(Ordering::Relaxed);
-        MANUAL_MODE.store(!current, Ordering::Relaxed);
-        manual_mode_light.toggle();
-        defmt::info!("Modo manual {}", manual_mode_light.is_set_high());
+        let config = config::get();
+        let press = toggle_manual_btn.wait_for_press().await;
+        events::publish(Event::ButtonPressed {
+            button: Button::Manual,
+            press,
+        });
+        // Con el menu del LCD abierto los gestos son para el menu
+        #[cfg(feature = "lcd")]
+        if lcd::button(Button::Manual, press) {
+            continue;
+        }
+        match press {
+            press if press == config.manual_gesture => toggle_manual_mode(),
+            // Habilita o deshabilita todo el sistema
+            press if press == config.system_gesture() => toggle_system(),
+            // Toma la luz actual como umbral de oscuridad de cada zona; el
+            // ajuste se guarda en flash como los demas
+            press if press == config.teach_gesture() => teach_thresholds(),
+            // Alterna lo que muestra el visor de 7 segmentos
+            #[cfg(feature = "seven-segment")]
+            press if press == config.spare_gesture() => seven_segment::toggle(),
+            // Muestra u oculta las lecturas de cada muestra por RTT
+            #[cfg(all(feature = "defmt", not(feature = "seven-segment")))]
+            press if press == config.spare_gesture() => {
+                use fmt::{Level, Subsystem};
+
+                let level = match fmt::level(Subsystem::Sensors) {
+                    Level::Debug => Level::Info,
+                    _ => Level::Debug,
+                };
+                fmt::set_level(Subsystem::Sensors, level);
+                buzzer::beep(Beep::Click);
+            }
+            // Cualquier otro gesto se ignora
+            _ => {}
+        }
     }
 }
 
 #[embassy_executor::task]
-async fn toggle_light(mut toggle_light_btn: ExtiInput<'static>) {
+async fn toggle_light(mut toggle_light_btn: Debounced<'static>) {
     loop {
-        toggle_light_btn.wait_for_falling_edge().await;
-        Timer::after_millis(50).await;
-
-        let manual = MANUAL_MODE.load(Ordering::Relaxed);
-        if !manual {
+        let press = toggle_light_btn.wait_for_press().await;
+        events::publish(Event::ButtonPressed {
+            button: Button::Light,
+            press,
+        });
+        #[cfg(feature = "lcd")]
+        if lcd::button(Button::Light, press) {
             continue;
         }
+        buzzer::beep(Beep::Click);
+        manual_timeout::activity();
+
+        match press {
+            // Clic sencillo: encender o apagar la luz en modo manual. En modo
+            // automatico quita el limite de tiempo encendida
+            Press::Single => {
+                if !SYSTEM_ENABLED.load(Ordering::Relaxed) {
+                    continue;
+                }
+                if !MANUAL_MODE.load(Ordering::Relaxed) {
+                    for zone in &ZONES {
+                        zone.on_limit_release.signal(());
+                    }
+                    continue;
+                }
 
-        Timer::after_millis(10).await;
-        unsafe {
-            LIGHT.lock_mut(|l| {
-                if let Some(l) = l {
-                    l.toggle();
-                    defmt::info!("Foco encendido: {}", l.is_set_high());
+                // Todas las lamparas pasan al mismo estado aunque
+                // alguna zona las haya dejado distintas
+                let brightness = if ZONES.iter().any(ZoneState::light_is_on) {
+                    0
+                } else {
+                    MAX_BRIGHTNESS
+                };
+                for zone in &ZONES {
+                    zone.switch_light(Reason::Button, |l| l.set_brightness(brightness));
                 }
-            })
+                info!(Control, "Focos al {}%", brightness);
+            }
+            // Doble clic: emitir el resumen en este momento
+            Press::Double => request_report(ReportRequest::Emit),
+            // Triple clic: reiniciar los contadores del resumen
+            Press::Triple => {
+                request_report(ReportRequest::Reset);
+                info!(Control, "Contadores reiniciados");
+            }
+            // Pulsacion larga: alternar entre brillo fijo y lazo cerrado
+            Press::Long => toggle_closed_loop(),
         }
     }
 }
+
+// Acciones de los gestos de los botones, que tambien se eligen en el menu
+// del LCD
+fn toggle_manual_mode() {
+    let manual = !MANUAL_MODE.load(Ordering::Relaxed);
+    set_manual(manual);
+    buzzer::beep(if manual {
+        Beep::ManualOn
+    } else {
+        Beep::ManualOff
+    });
+    info!(Control, "Modo manual {}", manual);
+}
+
+fn toggle_system() {
+    buzzer::beep(Beep::Click);
+    let enabled = !SYSTEM_ENABLED.load(Ordering::Relaxed);
+    set_enabled(enabled);
+
+    // Con el sistema deshabilitado la luz queda apagada
+    if !enabled {
+        for zone in &ZONES {
+            zone.switch_light(Reason::Disabled, |l| l.set_brightness(0));
+        }
+    }
+    info!(Control, "Sistema habilitado {}", enabled);
+}
+
+fn teach_thresholds() {
+    buzzer::beep(Beep::Click);
+    for (id, zone) in ZONES.iter().enumerate() {
+        match zone.teach_light_threshold() {
+            Some(lux) => info!(Control, "Zona {}: umbral de luz {} luxes", id, lux),
+            None => warn!(
+                Code::NoReadingToTeach,
+                "Zona {}: sin lecturas para tomar el umbral", id
+            ),
+        }
+    }
+}
+
+fn toggle_closed_loop() {
+    let closed_loop = !CLOSED_LOOP.load(Ordering::Relaxed);
+    CLOSED_LOOP.store(closed_loop, Ordering::Relaxed);
+    info!(Control, "Brillo en lazo cerrado {}", closed_loop);
+}
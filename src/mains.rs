@@ -0,0 +1,52 @@
+use embassy_stm32::exti::ExtiInput;
+use embassy_time::{Duration, Instant, with_timeout};
+
+use sie_core::{
+    codes::Code,
+    mains::{Condition, MainsMonitor, frequency},
+};
+
+use crate::MAINS_HZ;
+
+// Cruces que se promedian en cada medicion (un segundo)
+const CROSSINGS: u32 = 2 * MAINS_HZ as u32;
+// Sin cruces durante tres semiciclos se considera un corte
+const DROPOUT: Duration = Duration::from_millis(3 * 500 / MAINS_HZ as u64);
+// Desviacion aceptada; la red suele estar dentro de +-0.2 Hz
+const TOLERANCE_HZ: f32 = 0.5;
+
+// Mide la frecuencia de la red con un detector de cruce por cero (por
+// ejemplo un H11AA1 con la salida en colector abierto) en PB4 y registra
+// las desviaciones y los cortes en el registro de advertencias
+#[embassy_executor::task]
+pub async fn mains(mut zero_cross: ExtiInput<'static>) {
+    let mut monitor = MainsMonitor::new(MAINS_HZ, TOLERANCE_HZ);
+    loop {
+        let start = Instant::now();
+        let mut complete = true;
+        for _ in 0..CROSSINGS {
+            if with_timeout(DROPOUT, zero_cross.wait_for_falling_edge())
+                .await
+                .is_err()
+            {
+                complete = false;
+                break;
+            }
+        }
+
+        let change = if complete {
+            let hz = frequency(CROSSINGS, start.elapsed().as_micros());
+            info!(Sensors, "Red: {} Hz", hz);
+            monitor.update(hz)
+        } else {
+            monitor.dropout()
+        };
+        match change {
+            Some(Condition::Dropout) => warn!(Code::MainsDropout, "Red: sin cruces por cero"),
+            Some(Condition::Low) => warn!(Code::MainsLow, "Red: frecuencia baja"),
+            Some(Condition::High) => warn!(Code::MainsHigh, "Red: frecuencia alta"),
+            Some(Condition::Normal) => info!(Sensors, "Red: frecuencia normal"),
+            None => {}
+        }
+    }
+}
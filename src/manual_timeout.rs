@@ -0,0 +1,59 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::{Duration, Instant, Timer};
+
+use sie_core::beep::Beep;
+
+use crate::{MANUAL_MODE, buzzer, config, set_manual};
+
+// Periodo de revision de la inactividad
+const TICK: Duration = Duration::from_secs(1);
+
+// Pulsaciones y cambios de modo; reinician la cuenta de inactividad
+static ACTIVITY: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+// El modo manual esta por expirar (se avisa con el LED de estado)
+static EXPIRING: AtomicBool = AtomicBool::new(false);
+
+pub fn activity() {
+    ACTIVITY.signal(());
+}
+
+pub fn is_expiring() -> bool {
+    EXPIRING.load(Ordering::Relaxed)
+}
+
+// Regresa al modo automatico si el modo manual se queda sin actividad,
+// para no perder la automatizacion por olvido
+#[embassy_executor::task]
+pub async fn manual_timeout() {
+    let mut last_activity = Instant::now();
+
+    loop {
+        // En modo automatico no hay nada que contar: se espera al manual
+        while !MANUAL_MODE.load(Ordering::Relaxed) {
+            EXPIRING.store(false, Ordering::Relaxed);
+            ACTIVITY.wait().await;
+            last_activity = Instant::now();
+        }
+        Timer::after(TICK).await;
+
+        // Salir del modo manual tambien avisa con `ACTIVITY`
+        if ACTIVITY.try_take().is_some() {
+            last_activity = Instant::now();
+        }
+
+        let config = config::get();
+        let idle = last_activity.elapsed();
+        EXPIRING.store(
+            idle >= config.manual_timeout - config.manual_warning,
+            Ordering::Relaxed,
+        );
+
+        if idle >= config.manual_timeout {
+            set_manual(false);
+            buzzer::beep(Beep::ManualOff);
+            info!(Control, "Modo manual expirado por inactividad");
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use core::ptr::read_volatile;
+
+use sie_core::stack::{MemoryUsage, PAINT};
+
+// Bytes bajo el puntero de pila que no se pintan, para no pisar el marco
+// de `paint`
+const MARGIN: usize = 64;
+
+// Simbolos del link.x de cortex-m-rt
+unsafe extern "C" {
+    // Inicio de los estaticos (.data)
+    static __sdata: u32;
+    // Fondo de la pila (despues de los estaticos) y su tope (fin de la RAM)
+    static _stack_end: u32;
+    static _stack_start: u32;
+}
+
+// Con Embassy las tareas no tienen pila propia: su estado vive en los
+// estaticos y todas corren sobre la pila principal, que es la que se mide
+
+// Pinta la pila que no se ha usado. Va al inicio de `main`
+pub fn paint() {
+    let (bottom, _) = stack_bounds();
+    let sp = cortex_m::register::msp::read() as usize;
+    let words = (sp - MARGIN - bottom) / 4;
+    // SAFETY: memoria bajo el puntero de pila, sin usar todavia; una
+    // interrupcion la puede ocupar, pero ya no la usa al regresar
+    unsafe { core::slice::from_raw_parts_mut(bottom as *mut u32, words) }.fill(PAINT);
+}
+
+// Estaticos y maximo de pila usado desde el arranque
+pub fn usage() -> MemoryUsage {
+    let (bottom, top) = stack_bounds();
+    let statics = bottom - &raw const __sdata as usize;
+    // SAFETY: solo se lee la RAM de la pila, alineada a 4 bytes
+    let words = (bottom..top)
+        .step_by(4)
+        .map(|addr| unsafe { read_volatile(addr as *const u32) });
+    MemoryUsage::measure(statics as u32, (top - bottom) as u32, words)
+}
+
+fn stack_bounds() -> (usize, usize) {
+    (
+        &raw const _stack_end as usize,
+        &raw const _stack_start as usize,
+    )
+}
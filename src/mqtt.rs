@@ -0,0 +1,559 @@
+use core::sync::atomic::Ordering;
+
+use embassy_stm32::{
+    bind_interrupts,
+    peripherals::{PA2, PA3, USART2},
+    usart::{self, BufferedUart},
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::{Duration, Instant, Timer, with_timeout};
+use embedded_io_async::{Read, Write};
+use heapless::Vec;
+use static_cell::StaticCell;
+
+use sie_core::{
+    codes::Code,
+    esp_at::{RemoteCommand, Response, escaped, parse_message},
+    event_log::Reason,
+    ha_discovery::{COMMAND_TOPICS, Command, Entity, Parts, config, config_topic, length},
+    report::Summary,
+};
+
+use crate::{
+    MANUAL_MODE,
+    error::{self, Error},
+    events::{self, Event, EventSubscriber},
+    flash_log,
+    light::{Light, MAX_BRIGHTNESS},
+    set_manual,
+    watchdog::{self, Task},
+    zone::{ZONE_COUNT, ZONES},
+};
+
+bind_interrupts!(struct Irqs {
+    USART2 => usart::BufferedInterruptHandler<USART2>;
+});
+
+// Red y broker; se toman del entorno al compilar para no guardarlos en el
+// repositorio (por ejemplo `SIE_WIFI_SSID=casa cargo build --features mqtt`)
+const WIFI_SSID: &str = env_or(option_env!("SIE_WIFI_SSID"), "");
+const WIFI_PASSWORD: &str = env_or(option_env!("SIE_WIFI_PASSWORD"), "");
+const BROKER_HOST: &str = env_or(option_env!("SIE_MQTT_HOST"), "192.168.1.2");
+const BROKER_PORT: &str = env_or(option_env!("SIE_MQTT_PORT"), "1883");
+const CLIENT_ID: &str = "sie";
+
+// Temas: `sie/zona/N/{luz,distancia,lampara,brillo,presencia,resumen}`,
+// `sie/manual`, el codigo de la ultima falla en `sie/falla` (ver
+// sie_core::codes) y las ordenes (`on`, `off`, `auto`) en `sie/cmd`. Home
+// Assistant usa ademas los temas de `ha_discovery::COMMAND_TOPICS`
+const TOPIC_PREFIX: &str = "sie/zona/";
+const MANUAL_TOPIC: &str = "sie/manual";
+const FAULT_TOPIC: &str = "sie/falla";
+const COMMAND_TOPIC: &str = "sie/cmd";
+
+// Cada cuanto se publican las lecturas
+const PUBLISH_PERIOD: Duration = Duration::from_secs(10);
+// Espera de la respuesta de un comando; conectarse a la red tarda mas
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+// Pausa antes de reintentar la conexion
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+const LINE_LENGTH: usize = 128;
+
+const fn env_or(value: Option<&'static str>, default: &'static str) -> &'static str {
+    match value {
+        Some(value) => value,
+        None => default,
+    }
+}
+
+// Resumenes diarios pendientes de publicar
+static SUMMARIES: Channel<CriticalSectionRawMutex, (usize, Summary), 2> = Channel::new();
+
+// Encola el resumen diario de una zona; si la cola esta llena se descarta
+pub fn publish_summary(zone: usize, summary: Summary) {
+    let _ = SUMMARIES.try_send((zone, summary));
+}
+
+// Puente MQTT con un ESP-01 (firmware ESP-AT) en USART2 (TX en PA2, RX en
+// PA3, 115200 8N1): publica las lecturas y el estado de las lamparas de
+// cada zona y recibe ordenes para encenderlas o apagarlas a distancia. Al
+// conectarse anuncia las entidades a Home Assistant
+#[embassy_executor::task]
+pub async fn mqtt(usart: USART2, tx: PA2, rx: PA3) {
+    static TX_BUF: StaticCell<[u8; 128]> = StaticCell::new();
+    static RX_BUF: StaticCell<[u8; 128]> = StaticCell::new();
+
+    let Ok(uart) = BufferedUart::new(
+        usart,
+        Irqs,
+        rx,
+        tx,
+        TX_BUF.init([0; 128]),
+        RX_BUF.init([0; 128]),
+        usart::Config::default(),
+    ) else {
+        error::degrade(Error::Setup(Code::EspSetup));
+        return;
+    };
+
+    let mut esp = Esp {
+        uart,
+        line: Vec::new(),
+        complete: false,
+        events: events::subscribe(),
+    };
+    loop {
+        watchdog::check_in(Task::Mqtt);
+        if esp.connect().await && esp.announce().await {
+            info!(Links, "MQTT conectado");
+            esp.serve().await;
+        }
+        warn!(Code::MqttDisconnected, "Sin conexion MQTT, se reintenta");
+        Timer::after(RETRY_DELAY).await;
+    }
+}
+
+struct Esp {
+    uart: BufferedUart<'static>,
+    line: Vec<u8, LINE_LENGTH>,
+    // `line` tiene una linea completa; si no, es el inicio de una lectura
+    // interrumpida por un tiempo de espera
+    complete: bool,
+    events: EventSubscriber,
+}
+
+impl Esp {
+    // Red WiFi, broker y suscripcion al tema de comandos
+    async fn connect(&mut self) -> bool {
+        self.command(&[Part::Text("AT+RST")], COMMAND_TIMEOUT).await;
+        // El reinicio imprime basura un momento
+        Timer::after_secs(2).await;
+
+        self.command(&[Part::Text("ATE0")], COMMAND_TIMEOUT).await
+            && self
+                .command(&[Part::Text("AT+CWMODE=1")], COMMAND_TIMEOUT)
+                .await
+            && self
+                .command(
+                    &[
+                        Part::Text("AT+CWJAP=\""),
+                        Part::Quoted(WIFI_SSID),
+                        Part::Text("\",\""),
+                        Part::Quoted(WIFI_PASSWORD),
+                        Part::Text("\""),
+                    ],
+                    CONNECT_TIMEOUT,
+                )
+                .await
+            && self
+                .command(
+                    &[
+                        Part::Text("AT+MQTTUSERCFG=0,1,\""),
+                        Part::Quoted(CLIENT_ID),
+                        Part::Text("\",\"\",\"\",0,0,\"\""),
+                    ],
+                    COMMAND_TIMEOUT,
+                )
+                .await
+            && self
+                .command(
+                    &[
+                        Part::Text("AT+MQTTCONN=0,\""),
+                        Part::Quoted(BROKER_HOST),
+                        Part::Text("\","),
+                        Part::Text(BROKER_PORT),
+                        Part::Text(",1"),
+                    ],
+                    CONNECT_TIMEOUT,
+                )
+                .await
+            && self.subscribe(COMMAND_TOPIC).await
+            && self.subscribe(COMMAND_TOPICS[0]).await
+            && self.subscribe(COMMAND_TOPICS[1]).await
+    }
+
+    async fn subscribe(&mut self, topic: &str) -> bool {
+        self.command(
+            &[
+                Part::Text("AT+MQTTSUB=0,\""),
+                Part::Quoted(topic),
+                Part::Text("\",0"),
+            ],
+            COMMAND_TIMEOUT,
+        )
+        .await
+    }
+
+    // Publica (retenida) la configuracion de descubrimiento de cada
+    // entidad para que Home Assistant agregue el equipo por su cuenta
+    async fn announce(&mut self) -> bool {
+        let entities = (0..ZONE_COUNT as u8)
+            .flat_map(|zone| Entity::PER_ZONE.map(|entity| (entity, zone)))
+            .chain([(Entity::Manual, 0)]);
+        for (entity, zone) in entities {
+            if !self
+                .publish_raw(&config_topic(entity, zone), &config(entity, zone))
+                .await
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Publica con `AT+MQTTPUBRAW`, que no limita la longitud del mensaje
+    // ni requiere escaparlo: tras el OK el ESP pide los datos con `>`
+    async fn publish_raw(&mut self, topic: &Parts, payload: &Parts) -> bool {
+        let mut len = Payload::new();
+        push_number(&mut len, length(payload) as u32);
+
+        let mut parts: Vec<Part, 20> = Vec::new();
+        let _ = parts.push(Part::Text("AT+MQTTPUBRAW=0,\""));
+        for piece in topic {
+            let _ = parts.push(Part::Bytes(piece));
+        }
+        let _ = parts.push(Part::Text("\","));
+        let _ = parts.push(Part::Bytes(&len));
+        let _ = parts.push(Part::Text(",0,1"));
+        if !self.command(&parts, COMMAND_TIMEOUT).await {
+            return false;
+        }
+
+        let prompt = async {
+            let mut byte = [0];
+            while self.uart.read_exact(&mut byte).await.is_ok() && byte[0] != b'>' {}
+        };
+        if with_timeout(COMMAND_TIMEOUT, prompt).await.is_err() {
+            return false;
+        }
+        for piece in payload {
+            if self.uart.write_all(piece).await.is_err() {
+                return false;
+            }
+        }
+        self.response(COMMAND_TIMEOUT).await
+    }
+
+    // Publica periodicamente y atiende las ordenes recibidas; regresa si
+    // una publicacion falla
+    async fn serve(&mut self) {
+        let mut next_publish = Instant::now();
+        loop {
+            watchdog::check_in(Task::Mqtt);
+            // Los cambios de modo y las fallas se publican de inmediato; al
+            // apagarse por bateria baja sale asi el ultimo estado
+            while let Some(event) = self.events.try_next_message_pure() {
+                if matches!(event, Event::ModeChanged { .. } | Event::Fault(_)) {
+                    next_publish = Instant::now();
+                }
+            }
+
+            while let Ok((zone, summary)) = SUMMARIES.try_receive() {
+                if !self.publish_summary(zone, &summary).await {
+                    return;
+                }
+            }
+
+            if Instant::now() >= next_publish {
+                next_publish += PUBLISH_PERIOD;
+                if !self.publish_readings().await {
+                    return;
+                }
+            }
+
+            // Entre publicaciones solo llegan ordenes y avisos
+            if let Ok(Some(_)) = with_timeout(Duration::from_millis(500), self.read_line()).await {
+                self.handle_line();
+            }
+        }
+    }
+
+    async fn publish_readings(&mut self) -> bool {
+        for (id, zone) in ZONES.iter().enumerate() {
+            let mut payload = Payload::new();
+            push_number(&mut payload, zone.light_is_on() as u32);
+            if !self.publish(id, "lampara", &payload).await {
+                return false;
+            }
+            let mut payload = Payload::new();
+            push_number(
+                &mut payload,
+                zone.with_light(|l| l.brightness()).unwrap_or(0) as u32,
+            );
+            if !self.publish(id, "brillo", &payload).await {
+                return false;
+            }
+            let mut payload = Payload::new();
+            push_number(&mut payload, zone.occupied.load(Ordering::Relaxed) as u32);
+            if !self.publish(id, "presencia", &payload).await {
+                return false;
+            }
+
+            let Some(reading) = zone.last_reading.lock(|r| r.get()) else {
+                continue;
+            };
+            let mut payload = Payload::new();
+            push_decimal(&mut payload, reading.lux);
+            if !self.publish(id, "luz", &payload).await {
+                return false;
+            }
+            let mut payload = Payload::new();
+            push_decimal(&mut payload, reading.distance);
+            if !self.publish(id, "distancia", &payload).await {
+                return false;
+            }
+        }
+
+        let manual = MANUAL_MODE.load(Ordering::Relaxed);
+        if !self
+            .command(
+                &[
+                    Part::Text("AT+MQTTPUB=0,\""),
+                    Part::Text(MANUAL_TOPIC),
+                    Part::Text(if manual {
+                        "\",\"1\",0,0"
+                    } else {
+                        "\",\"0\",0,0"
+                    }),
+                ],
+                COMMAND_TIMEOUT,
+            )
+            .await
+        {
+            return false;
+        }
+
+        // Codigo de la ultima falla (0 sin fallas)
+        let mut code = Payload::new();
+        push_number(
+            &mut code,
+            flash_log::last_fault().map_or(0, Code::number) as u32,
+        );
+        self.command(
+            &[
+                Part::Text("AT+MQTTPUB=0,\""),
+                Part::Text(FAULT_TOPIC),
+                Part::Text("\",\""),
+                Part::Bytes(&code),
+                Part::Text("\",0,0"),
+            ],
+            COMMAND_TIMEOUT,
+        )
+        .await
+    }
+
+    // `activaciones minutos_encendida maximo_seguido fallas`
+    async fn publish_summary(&mut self, zone: usize, summary: &Summary) -> bool {
+        const MINUTE: u64 = 60 * 1000;
+
+        let mut payload = Payload::new();
+        for (i, value) in [
+            summary.activations,
+            (summary.lamp_on_ms / MINUTE) as u32,
+            (summary.longest_on_ms / MINUTE) as u32,
+            summary.faults,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if i > 0 {
+                let _ = payload.push(b' ');
+            }
+            push_number(&mut payload, value);
+        }
+        self.publish(zone, "resumen", &payload).await
+    }
+
+    async fn publish(&mut self, zone: usize, name: &str, payload: &[u8]) -> bool {
+        let mut zone_id = Payload::new();
+        push_number(&mut zone_id, zone as u32);
+
+        self.command(
+            &[
+                Part::Text("AT+MQTTPUB=0,\""),
+                Part::Text(TOPIC_PREFIX),
+                Part::Bytes(&zone_id),
+                Part::Text("/"),
+                Part::Text(name),
+                Part::Text("\",\""),
+                Part::Bytes(payload),
+                Part::Text("\",0,0"),
+            ],
+            COMMAND_TIMEOUT,
+        )
+        .await
+    }
+
+    // Envia un comando y espera OK o ERROR. Las ordenes que lleguen
+    // mientras tanto se atienden
+    async fn command(&mut self, parts: &[Part<'_>], timeout: Duration) -> bool {
+        watchdog::check_in(Task::Mqtt);
+        for part in parts {
+            let result = match part {
+                Part::Text(text) => self.uart.write_all(text.as_bytes()).await,
+                Part::Bytes(bytes) => self.uart.write_all(bytes).await,
+                Part::Quoted(text) => {
+                    let mut result = Ok(());
+                    for byte in escaped(text) {
+                        result = self.uart.write_all(&[byte]).await;
+                    }
+                    result
+                }
+            };
+            if result.is_err() {
+                return false;
+            }
+        }
+        if self.uart.write_all(b"\r\n").await.is_err() {
+            return false;
+        }
+        self.response(timeout).await
+    }
+
+    // Espera OK o ERROR
+    async fn response(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Ok(Some(line)) = with_timeout(remaining, self.read_line()).await else {
+                return false;
+            };
+            if let Some(response) = Response::parse(line) {
+                return response == Response::Ok;
+            }
+            self.handle_line();
+        }
+    }
+
+    // Lee hasta el fin de linea; None si la linea no es texto
+    async fn read_line(&mut self) -> Option<&str> {
+        if self.complete {
+            self.line.clear();
+            self.complete = false;
+        }
+        loop {
+            let mut byte = [0];
+            if self.uart.read_exact(&mut byte).await.is_err() {
+                continue;
+            }
+            match byte[0] {
+                b'\n' => {
+                    self.complete = true;
+                    break;
+                }
+                b'\r' => {}
+                // Lo que no cabe se descarta
+                c => {
+                    let _ = self.line.push(c);
+                }
+            }
+        }
+        core::str::from_utf8(&self.line).ok()
+    }
+
+    // Atiende una orden recibida en la ultima linea leida
+    fn handle_line(&self) {
+        let Ok(line) = core::str::from_utf8(&self.line) else {
+            return;
+        };
+        let Some((topic, payload)) = parse_message(line) else {
+            return;
+        };
+
+        let handled = if topic == COMMAND_TOPIC {
+            remote_command(payload)
+        } else {
+            home_assistant_command(topic, payload)
+        };
+        if handled {
+            info!(Links, "Orden MQTT recibida");
+        } else {
+            warn!(Code::UnknownCommand, "Orden MQTT desconocida");
+        }
+    }
+}
+
+fn remote_command(payload: &str) -> bool {
+    match RemoteCommand::parse(payload) {
+        Some(RemoteCommand::Auto) => set_manual(false),
+        Some(command) => {
+            let brightness = if command == RemoteCommand::On {
+                MAX_BRIGHTNESS
+            } else {
+                0
+            };
+            for id in 0..ZONE_COUNT {
+                set_light(id, |l| l.set_brightness(brightness));
+            }
+        }
+        None => return false,
+    }
+    true
+}
+
+fn home_assistant_command(topic: &str, payload: &str) -> bool {
+    match Command::parse(topic, payload) {
+        Some(Command::Manual(manual)) => {
+            set_manual(manual);
+            true
+        }
+        // Encender sin brillo conserva el que tenga si ya esta encendida
+        Some(Command::Lamp { zone, on }) => set_light(zone as usize, |l| {
+            if !on {
+                l.set_brightness(0);
+            } else if !l.is_on() {
+                l.set_brightness(MAX_BRIGHTNESS);
+            }
+        }),
+        Some(Command::Brightness { zone, percent }) => set_light(zone as usize, |l| {
+            l.set_brightness(percent.min(MAX_BRIGHTNESS))
+        }),
+        None => false,
+    }
+}
+
+// Controlar una lampara a distancia pasa al modo manual
+fn set_light(zone: usize, f: impl FnOnce(&mut Light)) -> bool {
+    let Some(zone) = ZONES.get(zone) else {
+        return false;
+    };
+    set_manual(true);
+    zone.switch_light(Reason::Remote, f);
+    true
+}
+
+// Fragmentos de un comando; los parametros de texto libre van escapados
+enum Part<'a> {
+    Text(&'a str),
+    Bytes(&'a [u8]),
+    Quoted(&'a str),
+}
+
+type Payload = Vec<u8, 32>;
+
+fn push_number(payload: &mut Payload, value: u32) {
+    let mut digits = [0; 10];
+    let mut len = 0;
+    let mut rest = value;
+    loop {
+        digits[len] = b'0' + (rest % 10) as u8;
+        len += 1;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    for &digit in digits[..len].iter().rev() {
+        let _ = payload.push(digit);
+    }
+}
+
+// Valor positivo con un decimal
+fn push_decimal(payload: &mut Payload, value: f32) {
+    let tenths = (value * 10. + 0.5) as u32;
+    push_number(payload, tenths / 10);
+    let _ = payload.push(b'.');
+    push_number(payload, tenths % 10);
+}
@@ -0,0 +1,173 @@
+use embassy_stm32::{gpio::Output, mode::Async, spi::Spi};
+#[cfg(feature = "nrf24-sensor")]
+use embassy_time::Instant;
+use embassy_time::{Duration, Timer};
+
+use sie_core::nrf24_packets::{ADDRESS, CHANNEL, PAYLOAD};
+
+// Registros
+const REG_CONFIG: u8 = 0x00;
+const REG_EN_AA: u8 = 0x01;
+const REG_EN_RXADDR: u8 = 0x02;
+const REG_SETUP_AW: u8 = 0x03;
+const REG_RF_CH: u8 = 0x05;
+const REG_RF_SETUP: u8 = 0x06;
+const REG_STATUS: u8 = 0x07;
+const REG_RX_ADDR_P0: u8 = 0x0A;
+const REG_TX_ADDR: u8 = 0x10;
+const REG_RX_PW_P0: u8 = 0x11;
+
+// Ordenes
+const W_REGISTER: u8 = 0x20;
+#[cfg(feature = "nrf24-relay")]
+const R_RX_PAYLOAD: u8 = 0x61;
+#[cfg(feature = "nrf24-sensor")]
+const W_TX_PAYLOAD: u8 = 0xA0;
+const FLUSH_TX: u8 = 0xE1;
+const FLUSH_RX: u8 = 0xE2;
+const NOP: u8 = 0xFF;
+
+// CRC de 2 bytes y encendido; el nodo de lampara ademas recibe (PRIM_RX)
+const CONFIG: u8 = if cfg!(feature = "nrf24-relay") {
+    0x0F
+} else {
+    0x0E
+};
+// 250 kbit/s a 0 dBm: el mayor alcance
+const RF_SETUP: u8 = 0x26;
+#[cfg(feature = "nrf24-relay")]
+const STATUS_RX_DR: u8 = 0x40;
+#[cfg(feature = "nrf24-sensor")]
+const STATUS_TX_DS: u8 = 0x20;
+const STATUS_CLEAR: u8 = 0x70;
+
+// Arranque del oscilador al encender
+const POWER_UP: Duration = Duration::from_millis(2);
+// Un paquete de 8 bytes a 250 kbit/s tarda menos de 1 ms
+#[cfg(feature = "nrf24-sensor")]
+const TX_TIMEOUT: Duration = Duration::from_millis(5);
+#[cfg(feature = "nrf24-relay")]
+const POLL: Duration = Duration::from_millis(5);
+
+// Radio nRF24L01+ en SPI1 remapeado: SCK en PB3, MISO en PB4, MOSI en PB5,
+// CSN en PA15 y CE en PA5. Sin acuse de recibo: las lecturas se repiten
+// cada 100 ms y una perdida no importa. IRQ no se conecta; el estado se
+// consulta por SPI. El nodo de sensores solo transmite y el de lampara
+// solo recibe
+pub struct Nrf24 {
+    spi: Spi<'static, Async>,
+    csn: Output<'static>,
+    ce: Output<'static>,
+}
+
+impl Nrf24 {
+    // Devuelve la radio configurada, o None si no responde
+    pub async fn new(
+        spi: Spi<'static, Async>,
+        csn: Output<'static>,
+        ce: Output<'static>,
+    ) -> Option<Self> {
+        let mut radio = Self { spi, csn, ce };
+        radio.write(REG_SETUP_AW, 0x03).await?;
+        // Un registro que no se lee igual indica que no hay modulo
+        if radio.read(REG_SETUP_AW).await? != 0x03 {
+            return None;
+        }
+        radio.write(REG_EN_AA, 0).await?;
+        radio.write(REG_EN_RXADDR, 0x01).await?;
+        radio.write(REG_RF_CH, CHANNEL).await?;
+        radio.write(REG_RF_SETUP, RF_SETUP).await?;
+        radio.write_bytes(REG_RX_ADDR_P0, &ADDRESS).await?;
+        radio.write_bytes(REG_TX_ADDR, &ADDRESS).await?;
+        radio.write(REG_RX_PW_P0, PAYLOAD as u8).await?;
+        radio.command(&mut [FLUSH_TX]).await?;
+        radio.command(&mut [FLUSH_RX]).await?;
+        radio.write(REG_STATUS, STATUS_CLEAR).await?;
+
+        radio.write(REG_CONFIG, CONFIG).await?;
+        Timer::after(POWER_UP).await;
+        // En recepcion CE queda alto; en transmision solo se pulsa
+        #[cfg(feature = "nrf24-relay")]
+        radio.ce.set_high();
+        Some(radio)
+    }
+
+    // Transmite un paquete; false si no se confirmo el envio
+    #[cfg(feature = "nrf24-sensor")]
+    pub async fn transmit(&mut self, payload: &[u8; PAYLOAD]) -> bool {
+        let mut frame = [0; PAYLOAD + 1];
+        frame[0] = W_TX_PAYLOAD;
+        frame[1..].copy_from_slice(payload);
+        if self.command(&mut frame).await.is_none() {
+            return false;
+        }
+        self.ce.set_high();
+        Timer::after_micros(15).await;
+        self.ce.set_low();
+
+        let start = Instant::now();
+        while start.elapsed() < TX_TIMEOUT {
+            if let Some(status) = self.status().await
+                && status & STATUS_TX_DS != 0
+            {
+                let _ = self.write(REG_STATUS, STATUS_TX_DS).await;
+                return true;
+            }
+            Timer::after_micros(100).await;
+        }
+        let _ = self.command(&mut [FLUSH_TX]).await;
+        false
+    }
+
+    // Espera el siguiente paquete recibido
+    #[cfg(feature = "nrf24-relay")]
+    pub async fn receive(&mut self) -> [u8; PAYLOAD] {
+        loop {
+            if let Some(status) = self.status().await
+                && status & STATUS_RX_DR != 0
+            {
+                let mut frame = [0; PAYLOAD + 1];
+                frame[0] = R_RX_PAYLOAD;
+                let received = self.command(&mut frame).await;
+                let _ = self.write(REG_STATUS, STATUS_RX_DR).await;
+                if received.is_some() {
+                    let mut payload = [0; PAYLOAD];
+                    payload.copy_from_slice(&frame[1..]);
+                    return payload;
+                }
+            }
+            Timer::after(POLL).await;
+        }
+    }
+
+    async fn status(&mut self) -> Option<u8> {
+        let mut frame = [NOP];
+        self.command(&mut frame).await?;
+        Some(frame[0])
+    }
+
+    async fn read(&mut self, register: u8) -> Option<u8> {
+        let mut frame = [register, 0];
+        self.command(&mut frame).await?;
+        Some(frame[1])
+    }
+
+    async fn write(&mut self, register: u8, value: u8) -> Option<()> {
+        self.command(&mut [W_REGISTER | register, value]).await
+    }
+
+    async fn write_bytes(&mut self, register: u8, value: &[u8; 5]) -> Option<()> {
+        let mut frame = [0; 6];
+        frame[0] = W_REGISTER | register;
+        frame[1..].copy_from_slice(value);
+        self.command(&mut frame).await
+    }
+
+    // Una transaccion completa; la respuesta queda en `frame`
+    async fn command(&mut self, frame: &mut [u8]) -> Option<()> {
+        self.csn.set_low();
+        let result = self.spi.transfer_in_place(frame).await;
+        self.csn.set_high();
+        result.ok()
+    }
+}
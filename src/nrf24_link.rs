@@ -0,0 +1,89 @@
+use embassy_stm32::{gpio::Output, mode::Async, spi::Spi};
+#[cfg(feature = "nrf24-sensor")]
+use embassy_time::{Duration, Ticker};
+
+#[cfg(feature = "nrf24-relay")]
+use sie_core::nrf24_packets::lost;
+use sie_core::{codes::Code, nrf24_packets::Sample};
+
+use crate::{
+    NRF24_NODE_ID,
+    error::{self, Error},
+    nrf24::Nrf24,
+    zone::ZONES,
+};
+
+// Igual que el muestreo de los controladores
+#[cfg(feature = "nrf24-sensor")]
+const SEND_PERIOD: Duration = Duration::from_millis(100);
+
+// Nodo de sensores: transmite las lecturas crudas que toma cada zona
+#[cfg(feature = "nrf24-sensor")]
+#[embassy_executor::task]
+pub async fn nrf24_link(spi: Spi<'static, Async>, csn: Output<'static>, ce: Output<'static>) {
+    let Some(mut radio) = Nrf24::new(spi, csn, ce).await else {
+        error::degrade(Error::Setup(Code::Nrf24Missing));
+        return;
+    };
+    info!(Links, "Nodo de sensores nRF24 {}", NRF24_NODE_ID);
+
+    let mut sequence = 0u8;
+    let mut ticker = Ticker::every(SEND_PERIOD);
+    loop {
+        ticker.next().await;
+        for (zone, state) in ZONES.iter().enumerate() {
+            let Some((raw_distance, raw_light)) = state.raw_sample.try_take() else {
+                continue;
+            };
+            let sample = Sample {
+                node: NRF24_NODE_ID,
+                sequence,
+                zone: zone as u8,
+                raw_distance,
+                raw_light,
+            };
+            sequence = sequence.wrapping_add(1);
+            if !radio.transmit(&sample.encode()).await {
+                info!(Links, "nRF24: envio sin confirmar");
+            }
+        }
+    }
+}
+
+// Nodo de la lampara: entrega a cada zona las lecturas del nodo de
+// sensores con el numero `NRF24_NODE_ID`
+#[cfg(feature = "nrf24-relay")]
+#[embassy_executor::task]
+pub async fn nrf24_link(spi: Spi<'static, Async>, csn: Output<'static>, ce: Output<'static>) {
+    let Some(mut radio) = Nrf24::new(spi, csn, ce).await else {
+        error::degrade(Error::Setup(Code::Nrf24Missing));
+        return;
+    };
+    info!(
+        Links,
+        "Nodo de lampara nRF24, escuchando al nodo {}", NRF24_NODE_ID
+    );
+
+    let mut previous: Option<u8> = None;
+    loop {
+        let Some(sample) = Sample::decode(&radio.receive().await) else {
+            continue;
+        };
+        if sample.node != NRF24_NODE_ID {
+            continue;
+        }
+        if let Some(previous) = previous {
+            let lost = lost(previous, sample.sequence);
+            if lost > 0 {
+                info!(Links, "nRF24: {} paquetes perdidos", lost);
+            }
+        }
+        previous = Some(sample.sequence);
+
+        if let Some(state) = ZONES.get(sample.zone as usize) {
+            state
+                .raw_sample
+                .signal((sample.raw_distance, sample.raw_light));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use embassy_futures::{
+    select::{Either, select},
+    yield_now,
+};
+use embassy_stm32::{
+    i2c::I2c,
+    mode::Blocking,
+    pac,
+    peripherals::{I2C1, PB8, PB9},
+    time::Hertz,
+};
+use embassy_time::{Duration, Timer};
+
+use sie_core::{
+    codes::Code,
+    framebuffer::{Framebuffer, WIDTH},
+    screensaver::{Screen, Timing},
+};
+
+use crate::{
+    display::Status,
+    error::{self, Error},
+    events,
+};
+
+// Direccion de 7 bits de los modulos SSD1306 (0x3D con el puente SA0)
+const ADDRESS: u8 = 0x3C;
+
+// Sin eventos la pantalla se redibuja igual, por la lampara, que no
+// publica sus cambios
+const REFRESH: Duration = Duration::from_secs(1);
+// Como mucho un redibujo en este tiempo; las lecturas llegan con cada muestra
+const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+// Sin actividad (botones, cambios de modo o fallas) se atenua al minuto y
+// se apaga a los 10 minutos para no marcar el panel
+const SCREENSAVER: Timing = Timing {
+    dim_ms: 60_000,
+    blank_ms: 600_000,
+};
+const CONTRAST: u8 = 0xCF;
+const DIMMED_CONTRAST: u8 = 0x10;
+
+// Primer byte de cada escritura: lo que sigue son ordenes o datos
+const COMMANDS: u8 = 0x00;
+const DATA: u8 = 0x40;
+
+// Arranque de un panel de 128x64 con la bomba de carga interna y el
+// direccionamiento horizontal, en el que los datos recorren las paginas
+// en el orden del framebuffer
+const INIT: [u8; 25] = [
+    0xAE, // apagado mientras se configura
+    0xD5, 0x80, // reloj
+    0xA8, 0x3F, // 64 filas
+    0xD3, 0x00, // sin desplazamiento
+    0x40, // primera fila
+    0x8D, 0x14, // bomba de carga
+    0x20, 0x00, // direccionamiento horizontal
+    0xA1, // columnas invertidas
+    0xC8, // filas invertidas
+    0xDA, 0x12, // pines de las filas
+    0x81, CONTRAST, // contraste
+    0xD9, 0xF1, // precarga
+    0xDB, 0x40, // VCOMH
+    0xA4, // muestra la RAM
+    0xA6, // sin invertir
+    0xAF, // encendido
+];
+
+struct Ssd1306 {
+    i2c: I2c<'static, Blocking>,
+}
+
+impl Ssd1306 {
+    fn commands(&mut self, commands: &[u8]) -> bool {
+        commands.iter().all(|&command| {
+            self.i2c
+                .blocking_write(ADDRESS, &[COMMANDS, command])
+                .is_ok()
+        })
+    }
+
+    // Una pagina por escritura, cediendo el procesador entre paginas: la
+    // imagen completa tarda unos 25 ms a 400 kHz. Un error del bus deja la
+    // imagen a medias hasta el siguiente redibujo
+    async fn show(&mut self, fb: &Framebuffer) {
+        if !self.commands(&[0x21, 0, WIDTH as u8 - 1, 0x22, 0, 7]) {
+            return;
+        }
+        for page in fb.bytes().chunks(WIDTH) {
+            let mut data = [DATA; WIDTH + 1];
+            data[1..].copy_from_slice(page);
+            if self.i2c.blocking_write(ADDRESS, &data).is_err() {
+                return;
+            }
+            yield_now().await;
+        }
+    }
+
+    fn set_screen(&mut self, screen: Screen) {
+        let _ = match screen.contrast(CONTRAST, DIMMED_CONTRAST) {
+            Some(contrast) => self.commands(&[0x81, contrast, 0xAF]),
+            None => self.commands(&[0xAE]),
+        };
+    }
+}
+
+// Pantalla de estado (ver sie_core::dashboard) en I2C1 remapeado a
+// PB8/PB9, actualizada con el bus de eventos
+#[embassy_executor::task]
+pub async fn oled(i2c: I2C1, scl: PB8, sda: PB9) {
+    pac::AFIO.mapr().modify(|w| w.set_i2c1_remap(true));
+    let mut display = Ssd1306 {
+        i2c: I2c::new_blocking(i2c, scl, sda, Hertz::khz(400), Default::default()),
+    };
+    if !display.commands(&INIT) {
+        error::degrade(Error::Setup(Code::DisplayMissing));
+        return;
+    }
+
+    let mut events = events::subscribe();
+    let mut status = Status::new(SCREENSAVER);
+    let mut screen = Screen::On;
+    let mut fb = Framebuffer::new();
+
+    loop {
+        if let Either::First(event) =
+            select(events.next_message_pure(), Timer::after(REFRESH)).await
+        {
+            status.apply(event);
+        }
+        while let Some(event) = events.try_next_message_pure() {
+            status.apply(event);
+        }
+        status.refresh_lamps();
+
+        let now = status.screensaver.screen();
+        if now != screen {
+            screen = now;
+            display.set_screen(screen);
+        }
+        if screen != Screen::Blank {
+            status.dashboard.draw(&mut fb);
+            display.show(&fb).await;
+        }
+        Timer::after(MIN_INTERVAL).await;
+    }
+}
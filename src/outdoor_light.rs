@@ -0,0 +1,39 @@
+use core::cell::Cell;
+
+use embassy_stm32::peripherals::PA5;
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::{Duration, Timer};
+
+use sie_core::sensor::{LightModel, LuxPolarity, get_voltage};
+
+use crate::SharedAdc;
+
+// Periodo de lectura del sensor exterior
+const READ_PERIOD: Duration = Duration::from_secs(1);
+
+// Ultima lectura del sensor exterior, en luxes
+static OUTDOOR_LUX: CriticalSectionMutex<Cell<Option<f32>>> =
+    CriticalSectionMutex::new(Cell::new(None));
+
+// Luz exterior para las reglas de las zonas (condiciones `afuera` y
+// `diferencia`); None hasta la primera lectura
+pub fn lux() -> Option<f32> {
+    OUTDOOR_LUX.lock(|l| l.get())
+}
+
+// Sensor de luz que mira hacia afuera (una ventana), del mismo modelo que
+// los de las zonas
+#[embassy_executor::task]
+pub async fn outdoor_light(mut pin: PA5, adc: &'static SharedAdc) {
+    let profile = LightModel::default().profile();
+
+    loop {
+        let raw = adc.lock().await.read(&mut pin).await;
+        let voltage = get_voltage(raw as f32);
+        let lux = profile.value(LuxPolarity::Rising.normalize_in(voltage, &profile));
+        OUTDOOR_LUX.lock(|l| l.set(Some(lux)));
+        info!(Sensors, "Luz exterior: {} luxes. Voltaje {}", lux, voltage);
+
+        Timer::after(READ_PERIOD).await;
+    }
+}
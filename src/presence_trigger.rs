@@ -0,0 +1,28 @@
+use embassy_stm32::exti::ExtiInput;
+
+use crate::zone::ZONES;
+
+// El STM32F103 no tiene comparadores: uno externo (por ejemplo un LM393)
+// compara la salida del sensor de distancia de una zona con una referencia
+// ajustada al umbral de presencia y su salida llega a una linea EXTI. Al
+// acercarse alguien el flanco despierta al controlador de la zona sin
+// esperar su siguiente muestreo. Las lineas EXTI tambien sacan al
+// microcontrolador del modo Stop
+#[embassy_executor::task]
+pub async fn presence_trigger(mut comparator: ExtiInput<'static>, zone: usize) {
+    let state = &ZONES[zone];
+    let threshold = state.thresholds.lock(|t| t.get()).distance;
+    let (distance, _) = state.models();
+    info!(
+        Control,
+        "Zona {}: ajustar la referencia del comparador a {} V",
+        zone,
+        distance.profile().voltage(threshold)
+    );
+
+    loop {
+        // Voltaje alto = objeto cerca
+        comparator.wait_for_rising_edge().await;
+        state.sample_now.signal(());
+    }
+}
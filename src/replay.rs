@@ -0,0 +1,86 @@
+use embassy_time::Instant;
+
+use sie_core::{
+    codes::Code,
+    control::Decision,
+    hal::Sensors,
+    replay::{self, Expected, Outputs, Samples, Verdict},
+};
+
+// Traza reproducida y sus decisiones esperadas, compiladas en el programa.
+// Para otra traza, cambiar los archivos por otros de sie-core/tests/fixtures
+// o por una captura convertida con `cargo run --bin replay -- convert`
+const TRACE: &str = include_str!("../sie-core/tests/fixtures/dusk_approach.trace.csv");
+const EXPECTED: &str = include_str!("../sie-core/tests/fixtures/dusk_approach.expected.csv");
+
+// Muestras de la traza en lugar de los sensores de una zona. Tras cada
+// decision del controlador se compara con la esperada; al terminar la
+// traza se informa el resultado y vuelve a empezar
+pub struct ReplaySensors {
+    zone: usize,
+    samples: Samples<'static>,
+    outputs: Outputs<'static>,
+    expected: Option<Expected>,
+    verdict: Verdict,
+    pub sampled_at: Instant,
+}
+
+impl ReplaySensors {
+    pub fn new(zone: usize) -> Self {
+        info!(Control, "Zona {}: reproduciendo una traza grabada", zone);
+        Self {
+            zone,
+            samples: replay::samples(TRACE),
+            outputs: replay::outputs(EXPECTED),
+            expected: None,
+            verdict: Verdict::default(),
+            sampled_at: Instant::now(),
+        }
+    }
+
+    // Compara la decision tomada con la ultima muestra
+    pub fn check(&mut self, decision: &Decision) {
+        if let Some(expected) = self.expected.take() {
+            self.verdict.record(&expected, decision);
+        }
+    }
+
+    fn next(&mut self) -> Option<(u16, u16)> {
+        // Las lineas invalidas ya las rechazan las pruebas golden
+        let sample = self.samples.find_map(Result::ok)?;
+        self.expected = self.outputs.find_map(Result::ok);
+        Some((sample.raw_distance, sample.raw_lux))
+    }
+
+    fn finish(&mut self) {
+        let verdict = core::mem::take(&mut self.verdict);
+        match verdict.first_mismatch {
+            None => info!(
+                Control,
+                "Zona {}: traza reproducida, {} decisiones iguales", self.zone, verdict.samples
+            ),
+            Some(t_ms) => warn!(
+                Code::ReplayMismatch,
+                "Zona {}: {} de {} decisiones distintas, la primera a {} ms",
+                self.zone,
+                verdict.mismatches,
+                verdict.samples,
+                t_ms
+            ),
+        }
+        self.samples = replay::samples(TRACE);
+        self.outputs = replay::outputs(EXPECTED);
+    }
+}
+
+impl Sensors for ReplaySensors {
+    async fn sample(&mut self) -> (u16, u16) {
+        self.sampled_at = Instant::now();
+        if let Some(sample) = self.next() {
+            return sample;
+        }
+        self.finish();
+        // Una traza sin muestras deja la zona a oscuras y sin nadie
+        self.next().unwrap_or((0, 0))
+    }
+}
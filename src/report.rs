@@ -0,0 +1,160 @@
+use embassy_time::{Duration, Instant};
+use sie_core::{
+    codes::Code,
+    report::{ConsistencyReport, Summary},
+    schedule::TimeOfDay,
+};
+
+use crate::{clock::SystemClock, counters};
+
+// Cada cuanto se emite el resumen si no hay hora del dia (con el horario
+// se emite a la hora `summary_time` de la configuracion)
+const REPORT_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Diferencia tolerada entre el tiempo encendido y el esperado
+const TOLERANCE: Duration = Duration::from_secs(10 * 60);
+
+// Acciones sobre el resumen solicitadas desde los botones
+#[derive(Clone, Copy)]
+pub enum ReportRequest {
+    Emit,
+    Reset,
+}
+
+// Resumen diario de consistencia de una zona, reportado en el registro
+pub struct DailyReport {
+    zone: usize,
+    report: ConsistencyReport<SystemClock>,
+    // Para sumar a los contadores de toda la vida
+    was_on: bool,
+    last: Instant,
+}
+
+impl DailyReport {
+    pub fn new(zone: usize) -> Self {
+        let report = ConsistencyReport::new(SystemClock, REPORT_PERIOD.as_millis());
+        #[cfg(feature = "schedule")]
+        let report = report.with_rollover(crate::config::get().summary_time);
+
+        Self {
+            zone,
+            report,
+            was_on: false,
+            last: Instant::now(),
+        }
+    }
+
+    // Registrar un ciclo del controlador de la zona. `expected` y `dark` son None
+    // cuando no hubo medicion (modo manual); `time` es la hora del dia si
+    // se conoce
+    pub fn record(
+        &mut self,
+        lamp_on: bool,
+        expected: Option<bool>,
+        dark: Option<bool>,
+        time: Option<TimeOfDay>,
+    ) {
+        let now = Instant::now();
+        if self.was_on {
+            counters::add_on_time(now - self.last);
+        }
+        if lamp_on && !self.was_on {
+            counters::activation();
+        }
+        self.was_on = lamp_on;
+        self.last = now;
+
+        if let Some(summary) = self.report.record_at(lamp_on, expected, dark, time) {
+            emit(self.zone, &summary);
+        }
+    }
+
+    // Falla de la zona, contada en el resumen
+    pub fn fault(&mut self) {
+        self.report.fault();
+        counters::fault();
+    }
+
+    pub fn reset(&mut self) {
+        self.report.reset();
+    }
+
+    pub fn emit(&self) {
+        emit(self.zone, &self.report.summary());
+    }
+}
+
+fn emit(zone: usize, summary: &Summary) {
+    const MINUTE: u64 = 60 * 1000;
+
+    info!(
+        Control,
+        "Zona {}: resumen diario: lampara {} min encendida (maximo {} min seguidos), esperado {} min, oscuridad {} min, {} activaciones ({} sin explicacion), {} fallas",
+        zone,
+        summary.lamp_on_ms / MINUTE,
+        summary.longest_on_ms / MINUTE,
+        summary.expected_on_ms / MINUTE,
+        summary.dark_ms / MINUTE,
+        summary.activations,
+        summary.unexplained,
+        summary.faults
+    );
+    record_summary(zone, summary);
+    #[cfg(feature = "mqtt")]
+    crate::mqtt::publish_summary(zone, *summary);
+
+    if summary.deviation_ms() > TOLERANCE.as_millis() || summary.unexplained > 0 {
+        warn!(
+            Code::Inconsistent,
+            "Zona {}: comportamiento inconsistente, desviacion de {} min respecto a lo esperado",
+            zone,
+            summary.deviation_ms() / MINUTE
+        );
+    }
+}
+
+// Version compacta en el registro en flash, para equipos sin RTT:
+// `Z0 12a 340m 95m 0f` (zona, activaciones, minutos encendida, maximo
+// seguido y fallas). Sin core::fmt, que ocupa bastante flash
+fn record_summary(zone: usize, summary: &Summary) {
+    const MINUTE: u64 = 60 * 1000;
+
+    let fields = [
+        (zone as u64, " "),
+        (summary.activations as u64, "a "),
+        (summary.lamp_on_ms / MINUTE, "m "),
+        (summary.longest_on_ms / MINUTE, "m "),
+        (summary.faults as u64, "f"),
+    ];
+
+    let mut text = [b'Z'; 80];
+    let mut len = 1;
+    for (value, suffix) in fields {
+        len += write_number(&mut text[len..], value);
+        text[len..len + suffix.len()].copy_from_slice(suffix.as_bytes());
+        len += suffix.len();
+    }
+
+    if let Ok(message) = core::str::from_utf8(&text[..len]) {
+        crate::flash_log::record(crate::flash_log::Level::Info, None, message);
+    }
+}
+
+// Escribe los digitos de `value` y devuelve cuantos ocupo
+fn write_number(out: &mut [u8], value: u64) -> usize {
+    let mut digits = [0; 20];
+    let mut len = 0;
+    let mut rest = value;
+    loop {
+        digits[len] = b'0' + (rest % 10) as u8;
+        len += 1;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    for (out, &digit) in out.iter_mut().zip(digits[..len].iter().rev()) {
+        *out = digit;
+    }
+    len
+}
@@ -0,0 +1,122 @@
+use embassy_stm32::pac::{self, rtc::vals::Rtoff};
+
+#[cfg(feature = "schedule")]
+use sie_core::{clock::WallClock, schedule::TimeOfDay};
+
+// Marca en el registro de respaldo BKP_DR1 de que la hora ya fue ajustada;
+// se pierde junto con la hora si el dominio de respaldo se queda sin VBAT
+#[cfg(feature = "schedule")]
+const TIME_SET_MARK: u16 = 0x5AE5;
+
+// Divisor del LSE (32.768 kHz) para que el contador avance cada segundo
+#[cfg(feature = "schedule")]
+const PRESCALER: u32 = 32_768 - 1;
+
+// RTC del F1: un contador de 32 bits en el dominio de respaldo que sigue
+// corriendo con VBAT. embassy-stm32 no tiene controlador para esta
+// version, por lo que se usa el PAC. El reloj (LSE) lo configura
+// embassy_stm32::init con `LsConfig::default_lse()`
+#[cfg(feature = "schedule")]
+pub struct InternalRtc(());
+
+#[cfg(feature = "schedule")]
+impl InternalRtc {
+    pub fn new() -> Self {
+        enable();
+        Self(())
+    }
+}
+
+#[cfg(feature = "schedule")]
+impl WallClock for InternalRtc {
+    // None si nunca se ajusto
+    fn now(&mut self) -> Option<TimeOfDay> {
+        if pac::BKP.dr(0).read().d() != TIME_SET_MARK {
+            return None;
+        }
+
+        Some(TimeOfDay::from_seconds(counter()))
+    }
+
+    fn set(&mut self, time: TimeOfDay) {
+        set_counter(time.seconds());
+        pac::BKP.dr(0).write(|w| w.set_d(TIME_SET_MARK));
+    }
+}
+
+// Acceso al dominio de respaldo, donde estan los registros del RTC
+pub fn enable() {
+    pac::RCC.apb1enr().modify(|w| {
+        w.set_pwren(true);
+        w.set_bkpen(true);
+    });
+    pac::PWR.cr().modify(|w| w.set_dbp(true));
+    sync();
+}
+
+// Despues de un reinicio o del modo STOP hay que esperar a que los
+// registros del RTC se sincronicen antes de leerlos
+fn sync() {
+    pac::RTC.crl().modify(|w| w.set_rsf(false));
+    while !pac::RTC.crl().read().rsf() {}
+}
+
+fn counter() -> u32 {
+    // La parte alta puede cambiar entre las dos lecturas
+    loop {
+        let high = pac::RTC.cnth().read().cnth();
+        let low = pac::RTC.cntl().read().cntl();
+        if high == pac::RTC.cnth().read().cnth() {
+            return ((high as u32) << 16) | low as u32;
+        }
+    }
+}
+
+// Programa la alarma para dentro de `seconds` segundos (con el segundo en
+// curso, entre `seconds - 1` y `seconds`). La alarma sale por la linea 17
+// del EXTI, que despierta del modo STOP
+#[cfg(feature = "stop-mode")]
+pub fn set_alarm(seconds: u32) {
+    sync();
+    let alarm = counter().wrapping_add(seconds);
+    configure(|| {
+        pac::RTC.alrh().write(|w| w.set_alrh((alarm >> 16) as u16));
+        pac::RTC.alrl().write(|w| w.set_alrl(alarm as u16));
+        pac::RTC.crl().modify(|w| w.set_alrf(false));
+        pac::RTC.crh().modify(|w| w.set_alrie(true));
+    });
+}
+
+#[cfg(feature = "stop-mode")]
+pub fn clear_alarm() {
+    configure(|| {
+        pac::RTC.crh().modify(|w| w.set_alrie(false));
+        pac::RTC.crl().modify(|w| w.set_alrf(false));
+    });
+}
+
+#[cfg(feature = "schedule")]
+fn set_counter(seconds: u32) {
+    configure(|| {
+        pac::RTC
+            .prlh()
+            .write(|w| w.set_prlh((PRESCALER >> 16) as u8));
+        pac::RTC.prll().write(|w| w.set_prll(PRESCALER as u16));
+        pac::RTC
+            .cnth()
+            .write(|w| w.set_cnth((seconds >> 16) as u16));
+        pac::RTC.cntl().write(|w| w.set_cntl(seconds as u16));
+    });
+}
+
+// Los registros del RTC solo se escriben en modo de configuracion y cada
+// escritura termina cuando RTOFF vuelve a 1
+fn configure(f: impl FnOnce()) {
+    let wait = || while pac::RTC.crl().read().rtoff() == Rtoff::ONGOING {};
+
+    wait();
+    pac::RTC.crl().modify(|w| w.set_cnf(true));
+    f();
+    pac::RTC.crl().modify(|w| w.set_cnf(false));
+    wait();
+}
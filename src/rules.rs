@@ -0,0 +1,61 @@
+use sie_core::{
+    codes::Code,
+    rules::{ENCODED_MAX, RuleSet},
+};
+
+use crate::{
+    storage::{self, ERASED, Page},
+    zone::ZONES,
+};
+
+// Cada zona ocupa un bloque fijo de la pagina: marca (1), longitud (1) y
+// el conjunto codificado, con relleno para escribir de a medias palabras
+const BLOCK_SIZE: usize = (ENCODED_MAX + 2).next_multiple_of(4);
+const MARK: u8 = 0xA5;
+
+// Carga las reglas guardadas de cada zona; las zonas sin reglas validas
+// conservan las estandar
+pub fn load() {
+    for (id, zone) in ZONES.iter().enumerate() {
+        let mut block = [ERASED; BLOCK_SIZE];
+        if !storage::read(Page::Rules, block_offset(id), &mut block) || block[0] != MARK {
+            continue;
+        }
+
+        let len = (block[1] as usize).min(ENCODED_MAX);
+        match RuleSet::decode(&block[2..2 + len]) {
+            Some(rules) => {
+                zone.rules.lock(|r| *r.borrow_mut() = rules);
+                info!(
+                    Control,
+                    "Zona {}: {} reglas guardadas",
+                    id,
+                    rules.rules().len()
+                );
+            }
+            None => warn!(Code::RulesInvalid, "Reglas guardadas invalidas"),
+        }
+    }
+}
+
+// Guarda las reglas vigentes de todas las zonas
+pub fn save() -> bool {
+    if !storage::erase(Page::Rules) {
+        return false;
+    }
+
+    ZONES.iter().enumerate().all(|(id, zone)| {
+        let mut encoded = [0; ENCODED_MAX];
+        let len = zone.rules.lock(|r| r.borrow().encode(&mut encoded));
+
+        let mut block = [ERASED; BLOCK_SIZE];
+        block[0] = MARK;
+        block[1] = len as u8;
+        block[2..2 + len].copy_from_slice(&encoded[..len]);
+        storage::write(Page::Rules, block_offset(id), &block)
+    })
+}
+
+fn block_offset(zone: usize) -> u32 {
+    (zone * BLOCK_SIZE) as u32
+}
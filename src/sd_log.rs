@@ -0,0 +1,163 @@
+use core::sync::atomic::Ordering;
+
+use embassy_stm32::{
+    gpio::{Level, Output, Speed},
+    mode::Blocking,
+    pac,
+    peripherals::{PA15, PB3, PB4, PB5, SPI1},
+    spi::{self, Spi},
+    time::Hertz,
+};
+use embassy_time::{Delay, Instant, Ticker};
+use embedded_hal_bus::spi::{ExclusiveDevice, NoDelay};
+use embedded_sdmmc::{Mode, SdCard, SdCardError, TimeSource, Timestamp, VolumeIdx, VolumeManager};
+
+use sie_core::{
+    codes::Code,
+    csv_log::{self, MAX_ROW, Row},
+    schedule::TimeOfDay,
+};
+
+use crate::{
+    MANUAL_MODE, SYSTEM_ENABLED, config,
+    error::{self, Error},
+    zone::ZONES,
+};
+
+// Un solo archivo en la raiz; nombre corto (8.3) de FAT
+const FILE_NAME: &str = "SIE.CSV";
+
+// La tarjeta arranca en modo SPI a no mas de 400 kHz; despues acepta 25 MHz
+// pero los cables de un modulo suelto no, se queda en 4 MHz
+const INIT_FREQUENCY: Hertz = Hertz::khz(400);
+const FREQUENCY: Hertz = Hertz::mhz(4);
+
+type Card = SdCard<ExclusiveDevice<Spi<'static, Blocking>, Output<'static>, NoDelay>, Delay>;
+// Un volumen, el directorio raiz y un archivo abiertos a la vez
+type Volumes = VolumeManager<Card, FatClock, 1, 1, 1>;
+
+fn time_of_day() -> Option<TimeOfDay> {
+    #[cfg(feature = "schedule")]
+    let time = crate::wall_clock::now();
+    #[cfg(not(feature = "schedule"))]
+    let time = None;
+    time
+}
+
+// Fecha y hora de modificacion del archivo. El RTC solo lleva la hora del
+// dia: la fecha queda en el 1 de enero de 1980, el origen de FAT
+struct FatClock;
+
+impl TimeSource for FatClock {
+    fn get_timestamp(&self) -> Timestamp {
+        let seconds = time_of_day().map_or(0, TimeOfDay::seconds);
+        Timestamp {
+            year_since_1970: 10,
+            zero_indexed_month: 0,
+            zero_indexed_day: 0,
+            hours: (seconds / 3600) as u8,
+            minutes: (seconds / 60 % 60) as u8,
+            seconds: (seconds % 60) as u8,
+        }
+    }
+}
+
+fn set_frequency(card: &Card, frequency: Hertz) {
+    let mut config = spi::Config::default();
+    config.frequency = frequency;
+    card.spi(|device| {
+        let _ = device.bus_mut().set_config(&config);
+    });
+}
+
+// Agrega una fila por zona. El archivo se abre y se cierra cada vez para
+// que la entrada del directorio quede al dia: un corte de energia o sacar
+// la tarjeta pierde a lo sumo el intervalo en curso
+fn append(volumes: &Volumes) -> Result<(), embedded_sdmmc::Error<SdCardError>> {
+    let volume = volumes.open_volume(VolumeIdx(0))?;
+    let root = volume.open_root_dir()?;
+    let file = root.open_file_in_dir(FILE_NAME, Mode::ReadWriteCreateOrAppend)?;
+    if file.length() == 0 {
+        file.write(csv_log::HEADER)?;
+    }
+
+    let uptime_s = Instant::now().as_secs() as u32;
+    let time = time_of_day();
+    let manual = MANUAL_MODE.load(Ordering::Relaxed);
+    let enabled = SYSTEM_ENABLED.load(Ordering::Relaxed);
+    let mut out = [0; MAX_ROW];
+    for (zone, state) in ZONES.iter().enumerate() {
+        let reading = state.last_reading.lock(|r| r.get());
+        let row = Row {
+            uptime_s,
+            time,
+            zone: zone as u8,
+            lux: reading.map(|r| r.lux),
+            distance: reading.map(|r| r.distance),
+            occupied: state.occupied.load(Ordering::Relaxed),
+            light_on: state.light_is_on(),
+            manual,
+            enabled,
+        };
+        let len = csv_log::format(&row, &mut out);
+        file.write(&out[..len])?;
+    }
+    file.close()?;
+    root.close()?;
+    volume.close()
+}
+
+// Registro CSV en una tarjeta SD (FAT16 o FAT32, primera particion) en
+// SPI1 remapeado a PB3/PB4/PB5 con CS en PA15. Las escrituras son
+// bloqueantes y pueden tardar decenas de milisegundos; con intervalos de
+// un minuto no se notan en las zonas
+#[embassy_executor::task]
+pub async fn sd_log(spi: SPI1, sck: PB3, mosi: PB5, miso: PB4, cs: PA15) {
+    // PB3, PB4 y PA15 son del JTAG; queda SWD para el depurador
+    pac::AFIO.mapr().modify(|w| {
+        w.set_swj_cfg(0b010);
+        w.set_spi1_remap(true);
+    });
+    let mut spi_config = spi::Config::default();
+    spi_config.frequency = INIT_FREQUENCY;
+    let spi = Spi::new_blocking(spi, sck, mosi, miso, spi_config);
+    let cs = Output::new(cs, Level::High, Speed::VeryHigh);
+    let Ok(device) = ExclusiveDevice::new_no_delay(spi, cs);
+    let card = SdCard::new(device, Delay);
+
+    if card.num_bytes().is_err() {
+        error::degrade(Error::Setup(Code::SdCardMissing));
+        return;
+    }
+    set_frequency(&card, FREQUENCY);
+    let volumes: Volumes = VolumeManager::new_with_limits(card, FatClock, 0);
+    info!(System, "Registro en la tarjeta SD ({})", FILE_NAME);
+
+    let mut ready = true;
+    let mut ticker = Ticker::every(config::get().sd_log_interval);
+    loop {
+        ticker.next().await;
+        // Tras una falla se vuelve a inicializar, por si se cambio la
+        // tarjeta
+        if !ready {
+            ready = volumes.device(|card| {
+                let found = card.num_bytes().is_ok();
+                if found {
+                    set_frequency(card, FREQUENCY);
+                }
+                found
+            });
+            if !ready {
+                continue;
+            }
+        }
+        if append(&volumes).is_err() {
+            warn!(Code::SdCardWrite, "No se pudo escribir en la tarjeta SD");
+            volumes.device(|card| {
+                card.mark_card_uninit();
+                set_frequency(card, INIT_FREQUENCY);
+            });
+            ready = false;
+        }
+    }
+}
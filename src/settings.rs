@@ -0,0 +1,94 @@
+use core::sync::atomic::Ordering;
+
+use embassy_time::{Duration, Timer};
+
+use sie_core::{
+    codes::Code,
+    settings::{BLOCK_SIZE, MAX_ZONES, Settings, ZoneSettings},
+};
+
+use crate::{
+    CLOSED_LOOP, MANUAL_MODE, SYSTEM_ENABLED,
+    kv::{self, Key},
+    storage::ERASED,
+    zone::{ZONE_COUNT, ZONES},
+};
+
+const _: () = assert!(ZONE_COUNT <= MAX_ZONES && BLOCK_SIZE <= sie_core::kv::MAX_VALUE);
+
+// Cada cuanto se revisa si cambiaron los ajustes. Un cambio se guarda
+// cuando pasa una revision sin otros, para no gastar la flash mientras se
+// gira la perilla
+const CHECK_PERIOD: Duration = Duration::from_secs(10);
+
+// Recupera los ajustes guardados y aplica los modos y la calibracion. Los
+// umbrales se entregan a cada zona al crearla; sin ajustes guardados cada
+// zona usa los de sus modelos de sensores. Requiere `kv::init`
+pub fn load() -> Option<Settings> {
+    let mut block = [ERASED; BLOCK_SIZE];
+    let stored = kv::get(Key::Settings, &mut block).is_some();
+    let Some(settings) = Settings::decode(&block) else {
+        if stored {
+            warn!(
+                Code::SettingsInvalid,
+                "Ajustes guardados invalidos, se usan los de por defecto"
+            );
+        } else {
+            info!("Sin ajustes guardados, se usan los de por defecto");
+        }
+        return None;
+    };
+
+    info!("Ajustes recuperados");
+    MANUAL_MODE.store(settings.manual, Ordering::Relaxed);
+    SYSTEM_ENABLED.store(settings.enabled, Ordering::Relaxed);
+    CLOSED_LOOP.store(settings.closed_loop, Ordering::Relaxed);
+    for (zone, saved) in ZONES.iter().zip(&settings.zones) {
+        zone.set_lux_scale(saved.lux_scale);
+    }
+    Some(settings)
+}
+
+// Guarda los ajustes vigentes cuando dejan de cambiar
+#[embassy_executor::task]
+pub async fn settings(mut saved: Option<Settings>) {
+    let mut last = current();
+    loop {
+        Timer::after(CHECK_PERIOD).await;
+
+        let now = current();
+        if now == last && saved != Some(now) {
+            if kv::set(Key::Settings, &now.encode()) {
+                saved = Some(now);
+                info!("Ajustes guardados");
+            } else {
+                warn!(Code::SettingsNotSaved, "No se pudieron guardar los ajustes");
+            }
+        }
+        last = now;
+    }
+}
+
+// Guarda los ajustes vigentes de inmediato, por ejemplo antes de apagar o
+// de reiniciar
+pub fn save() {
+    if !kv::set(Key::Settings, &current().encode()) {
+        warn!(Code::SettingsNotSaved, "No se pudieron guardar los ajustes");
+    }
+}
+
+fn current() -> Settings {
+    let mut zones = [ZoneSettings::default(); MAX_ZONES];
+    for (zone, saved) in ZONES.iter().zip(&mut zones) {
+        *saved = ZoneSettings {
+            thresholds: zone.thresholds.lock(|t| t.get()),
+            lux_scale: zone.lux_scale(),
+        };
+    }
+    Settings {
+        manual: MANUAL_MODE.load(Ordering::Relaxed),
+        enabled: SYSTEM_ENABLED.load(Ordering::Relaxed),
+        closed_loop: CLOSED_LOOP.load(Ordering::Relaxed),
+        zones,
+    }
+}
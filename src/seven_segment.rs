@@ -0,0 +1,89 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_stm32::{
+    gpio::{Level, Output, Speed},
+    pac,
+    peripherals::{PA15, PB3, PB4},
+};
+use embassy_time::{Duration, Ticker};
+
+use sie_core::seven_segment::{self, DASHES, DIGITS};
+
+use crate::zone::ZONES;
+
+// Un digito encendido por vez; cada uno dura esto, unos 125 Hz por digito
+// para que no parpadee
+const DIGIT_TIME: Duration = Duration::from_millis(2);
+
+// Los modulos comunes son de anodo comun: el segmento enciende en 0
+const SEGMENTS_ACTIVE_LOW: bool = true;
+
+// La distancia en lugar de la luz
+static SHOW_DISTANCE: AtomicBool = AtomicBool::new(false);
+
+pub fn toggle() {
+    let distance = !SHOW_DISTANCE.load(Ordering::Relaxed);
+    SHOW_DISTANCE.store(distance, Ordering::Relaxed);
+    info!(Control, "Visor con la distancia {}", distance);
+}
+
+// Dos 74HC595 en cadena: el primer byte son los segmentos y el segundo el
+// digito que se enciende (bit 0 el de la izquierda)
+struct Shift595 {
+    data: Output<'static>,
+    clock: Output<'static>,
+    latch: Output<'static>,
+}
+
+impl Shift595 {
+    // El bit mas significativo primero; los pines a velocidad baja ya dan
+    // mas que los 20 ns que pide el 74HC595
+    fn write(&mut self, segments: u8, select: u8) {
+        for byte in [segments, select] {
+            for bit in (0..8).rev() {
+                self.data.set_level(Level::from(byte & 1 << bit != 0));
+                self.clock.set_high();
+                self.clock.set_low();
+            }
+        }
+        self.latch.set_high();
+        self.latch.set_low();
+    }
+}
+
+// Lo que se muestra de la zona 0
+fn digits() -> [u8; DIGITS] {
+    let Some(reading) = ZONES[0].last_reading.lock(|r| r.get()) else {
+        return DASHES;
+    };
+    if SHOW_DISTANCE.load(Ordering::Relaxed) {
+        seven_segment::centimeters(reading.distance)
+    } else {
+        seven_segment::lux(reading.lux)
+    }
+}
+
+#[embassy_executor::task]
+pub async fn seven_segment(data: PA15, clock: PB3, latch: PB4) {
+    // PA15, PB3 y PB4 son del JTAG; queda SWD para el depurador
+    pac::AFIO.mapr().modify(|w| w.set_swj_cfg(0b010));
+    let mut display = Shift595 {
+        data: Output::new(data, Level::Low, Speed::Low),
+        clock: Output::new(clock, Level::Low, Speed::Low),
+        latch: Output::new(latch, Level::Low, Speed::Low),
+    };
+
+    let mut ticker = Ticker::every(DIGIT_TIME);
+    loop {
+        // Las cifras cambian solo entre vueltas completas
+        for (digit, segments) in digits().into_iter().enumerate() {
+            let segments = if SEGMENTS_ACTIVE_LOW {
+                !segments
+            } else {
+                segments
+            };
+            display.write(segments, 1 << digit);
+            ticker.next().await;
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use core::sync::atomic::Ordering;
+
+use embassy_stm32::gpio::Output;
+use embassy_time::{Duration, Instant, Timer};
+
+use sie_core::status::Status;
+
+use crate::{
+    MANUAL_MODE, SYSTEM_ENABLED, board, error, factory_reset, manual_timeout,
+    watchdog::{self, Task},
+};
+
+// Resolucion de los patrones de parpadeo
+const TICK: Duration = Duration::from_millis(50);
+
+// Estado actual segun las banderas globales; el de mayor prioridad primero
+fn current_status() -> Status {
+    if factory_reset::is_showing() {
+        Status::FactoryReset
+    } else if let Some(code) = error::degraded() {
+        // Equipo degradado: tantos pulsos como el modulo de la falla
+        Status::Fault(code.module() as u8)
+    } else if !SYSTEM_ENABLED.load(Ordering::Relaxed) {
+        Status::Disabled
+    } else if MANUAL_MODE.load(Ordering::Relaxed) {
+        if manual_timeout::is_expiring() {
+            Status::ManualExpiring
+        } else {
+            Status::Manual
+        }
+    } else {
+        Status::Normal
+    }
+}
+
+// Muestra el estado del sistema en el LED (PB5 o el de la placa). El patron reinicia con
+// cada cambio de estado para que los codigos de pulsos se lean completos
+#[embassy_executor::task]
+pub async fn status_led(mut led: Output<'static>) {
+    let mut status = current_status();
+    let mut since = Instant::now();
+
+    loop {
+        watchdog::check_in(Task::StatusLed);
+
+        let now = current_status();
+        if now != status {
+            status = now;
+            since = Instant::now();
+        }
+
+        let on = status.pattern().level(since.elapsed().as_millis());
+        led.set_level((on != board::STATUS_LED_ACTIVE_LOW).into());
+        Timer::after(TICK).await;
+    }
+}
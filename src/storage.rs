@@ -0,0 +1,72 @@
+use core::cell::RefCell;
+
+use embassy_stm32::{
+    flash::{Blocking, FLASH_SIZE, Flash, MAX_ERASE_SIZE},
+    peripherals::FLASH,
+};
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+
+pub const PAGE_SIZE: u32 = MAX_ERASE_SIZE as u32;
+
+// Paginas al final de la flash, excluidas del programa en memory.x.
+// El valor es la posicion contando desde el final
+#[derive(Clone, Copy)]
+pub enum Page {
+    Log = 1,
+    #[cfg(feature = "ambient-learning")]
+    Ambient = 2,
+    #[cfg(feature = "console")]
+    Rules = 3,
+    // Almacen clave-valor de los contadores y los ajustes; las dos paginas
+    // se usan por turnos (ver sie_core::kv)
+    StoreA = 4,
+    StoreB = 5,
+    // Registro de sucesos, tambien por turnos (ver sie_core::event_log);
+    // build.rs las quita del programa
+    #[cfg(feature = "event-log")]
+    EventsA = 6,
+    #[cfg(feature = "event-log")]
+    EventsB = 7,
+}
+
+impl Page {
+    fn offset(self) -> u32 {
+        FLASH_SIZE as u32 - self as u32 * PAGE_SIZE
+    }
+}
+
+// Una pagina borrada queda en 0xFF
+pub const ERASED: u8 = 0xFF;
+
+static FLASH: CriticalSectionMutex<RefCell<Option<Flash<'static, Blocking>>>> =
+    CriticalSectionMutex::new(RefCell::new(None));
+
+pub fn init(flash: FLASH) {
+    FLASH.lock(|f| *f.borrow_mut() = Some(Flash::new_blocking(flash)));
+}
+
+// Las operaciones devuelven false si fallan o si la flash esta ocupada
+// (por ejemplo, un panic durante una escritura)
+fn with_flash(f: impl FnOnce(&mut Flash<'static, Blocking>) -> bool) -> bool {
+    FLASH.lock(|flash| {
+        let Ok(mut flash) = flash.try_borrow_mut() else {
+            return false;
+        };
+        flash.as_mut().is_some_and(f)
+    })
+}
+
+pub fn read(page: Page, offset: u32, buf: &mut [u8]) -> bool {
+    with_flash(|f| f.blocking_read(page.offset() + offset, buf).is_ok())
+}
+
+pub fn write(page: Page, offset: u32, data: &[u8]) -> bool {
+    with_flash(|f| f.blocking_write(page.offset() + offset, data).is_ok())
+}
+
+pub fn erase(page: Page) -> bool {
+    with_flash(|f| {
+        f.blocking_erase(page.offset(), page.offset() + PAGE_SIZE)
+            .is_ok()
+    })
+}
@@ -0,0 +1,176 @@
+use embassy_stm32::{exti::ExtiInput, gpio::Output, mode::Async, spi::Spi};
+use embassy_time::{Duration, with_timeout};
+use heapless::Vec;
+
+use sie_core::lora_packets::frequency_register;
+
+// Registros del modo LoRa
+const REG_FIFO: u8 = 0x00;
+const REG_OP_MODE: u8 = 0x01;
+const REG_FRF: u8 = 0x06;
+const REG_PA_CONFIG: u8 = 0x09;
+const REG_FIFO_ADDR_PTR: u8 = 0x0D;
+const REG_FIFO_TX_BASE: u8 = 0x0E;
+const REG_FIFO_RX_BASE: u8 = 0x0F;
+const REG_FIFO_RX_CURRENT: u8 = 0x10;
+const REG_IRQ_FLAGS: u8 = 0x12;
+const REG_RX_BYTES: u8 = 0x13;
+const REG_MODEM_CONFIG_1: u8 = 0x1D;
+const REG_MODEM_CONFIG_2: u8 = 0x1E;
+const REG_PAYLOAD_LENGTH: u8 = 0x22;
+const REG_MODEM_CONFIG_3: u8 = 0x26;
+const REG_DIO_MAPPING_1: u8 = 0x40;
+const REG_VERSION: u8 = 0x42;
+
+const VERSION: u8 = 0x12;
+// Bit 7 de RegOpMode: modo LoRa en lugar de FSK
+const LONG_RANGE: u8 = 0x80;
+const MODE_SLEEP: u8 = 0x00;
+const MODE_STANDBY: u8 = 0x01;
+const MODE_TX: u8 = 0x03;
+const MODE_RX_CONTINUOUS: u8 = 0x05;
+// Que indica DIO0
+const DIO0_RX_DONE: u8 = 0x00;
+const DIO0_TX_DONE: u8 = 0x40;
+const IRQ_CRC_ERROR: u8 = 0x20;
+
+// 125 kHz, codigo 4/5, encabezado explicito
+const MODEM_CONFIG_1: u8 = 0x72;
+// Factor de dispersion 9 con CRC: alcance de algunos kilometros
+const MODEM_CONFIG_2: u8 = 0x94;
+// Ganancia automatica del LNA
+const MODEM_CONFIG_3: u8 = 0x04;
+// Salida PA_BOOST a +17 dBm
+const PA_CONFIG: u8 = 0x8F;
+
+// Un paquete de SF9 a 125 kHz dura menos de medio segundo
+const TX_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub const MAX_PAYLOAD: usize = 64;
+
+#[derive(Debug)]
+pub enum Error {
+    Spi,
+    // El modulo no responde o no es un SX1276/77/78/79
+    NotFound,
+    Timeout,
+}
+
+impl From<embassy_stm32::spi::Error> for Error {
+    fn from(_: embassy_stm32::spi::Error) -> Self {
+        Self::Spi
+    }
+}
+
+// Radio LoRa SX1276 (modulos RFM95W, Ra-02) en SPI1 remapeado: SCK en PB3,
+// MISO en PB4, MOSI en PB5, NSS en PA15 y DIO0 en PA5. RESET queda a
+// 3.3 V; el modulo se reinicia al encender
+pub struct Sx1276 {
+    spi: Spi<'static, Async>,
+    nss: Output<'static>,
+    dio0: ExtiInput<'static>,
+}
+
+impl Sx1276 {
+    pub async fn new(
+        spi: Spi<'static, Async>,
+        nss: Output<'static>,
+        dio0: ExtiInput<'static>,
+        frequency: u32,
+    ) -> Result<Self, Error> {
+        let mut radio = Self { spi, nss, dio0 };
+        if radio.read(REG_VERSION).await? != VERSION {
+            return Err(Error::NotFound);
+        }
+
+        // El modo LoRa solo se puede elegir dormido
+        radio.write(REG_OP_MODE, MODE_SLEEP).await?;
+        radio.write(REG_OP_MODE, LONG_RANGE | MODE_SLEEP).await?;
+        let [msb, mid, lsb] = frequency_register(frequency);
+        radio.write(REG_FRF, msb).await?;
+        radio.write(REG_FRF + 1, mid).await?;
+        radio.write(REG_FRF + 2, lsb).await?;
+        // Toda la FIFO para cada direccion; no se usan a la vez
+        radio.write(REG_FIFO_TX_BASE, 0).await?;
+        radio.write(REG_FIFO_RX_BASE, 0).await?;
+        radio.write(REG_MODEM_CONFIG_1, MODEM_CONFIG_1).await?;
+        radio.write(REG_MODEM_CONFIG_2, MODEM_CONFIG_2).await?;
+        radio.write(REG_MODEM_CONFIG_3, MODEM_CONFIG_3).await?;
+        radio.write(REG_PA_CONFIG, PA_CONFIG).await?;
+        radio.write(REG_OP_MODE, LONG_RANGE | MODE_STANDBY).await?;
+        Ok(radio)
+    }
+
+    pub async fn transmit(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.write(REG_OP_MODE, LONG_RANGE | MODE_STANDBY).await?;
+        self.write(REG_DIO_MAPPING_1, DIO0_TX_DONE).await?;
+        self.write(REG_FIFO_ADDR_PTR, 0).await?;
+        self.nss.set_low();
+        let written = async {
+            self.spi.write(&[REG_FIFO | 0x80]).await?;
+            self.spi.write(data).await
+        }
+        .await;
+        self.nss.set_high();
+        written?;
+        self.write(REG_PAYLOAD_LENGTH, data.len() as u8).await?;
+        self.write(REG_OP_MODE, LONG_RANGE | MODE_TX).await?;
+
+        let done = with_timeout(TX_TIMEOUT, self.dio0.wait_for_high()).await;
+        self.write(REG_IRQ_FLAGS, 0xFF).await?;
+        done.map_err(|_| Error::Timeout)
+    }
+
+    // Escucha durante `window`; None si no llega un paquete valido
+    pub async fn receive(
+        &mut self,
+        window: Duration,
+    ) -> Result<Option<Vec<u8, MAX_PAYLOAD>>, Error> {
+        self.write(REG_DIO_MAPPING_1, DIO0_RX_DONE).await?;
+        self.write(REG_FIFO_ADDR_PTR, 0).await?;
+        self.write(REG_OP_MODE, LONG_RANGE | MODE_RX_CONTINUOUS)
+            .await?;
+        let received = with_timeout(window, self.dio0.wait_for_high()).await;
+        self.write(REG_OP_MODE, LONG_RANGE | MODE_STANDBY).await?;
+        if received.is_err() {
+            return Ok(None);
+        }
+
+        let flags = self.read(REG_IRQ_FLAGS).await?;
+        self.write(REG_IRQ_FLAGS, 0xFF).await?;
+        if flags & IRQ_CRC_ERROR != 0 {
+            return Ok(None);
+        }
+        let len = (self.read(REG_RX_BYTES).await? as usize).min(MAX_PAYLOAD);
+        let start = self.read(REG_FIFO_RX_CURRENT).await?;
+        self.write(REG_FIFO_ADDR_PTR, start).await?;
+
+        let mut data = Vec::new();
+        let _ = data.resize(len, 0);
+        self.nss.set_low();
+        let read = async {
+            self.spi.write(&[REG_FIFO]).await?;
+            self.spi.read(&mut data).await
+        }
+        .await;
+        self.nss.set_high();
+        read?;
+        Ok(Some(data))
+    }
+
+    async fn read(&mut self, register: u8) -> Result<u8, Error> {
+        let mut frame = [register & 0x7F, 0];
+        self.nss.set_low();
+        let result = self.spi.transfer_in_place(&mut frame).await;
+        self.nss.set_high();
+        result?;
+        Ok(frame[1])
+    }
+
+    async fn write(&mut self, register: u8, value: u8) -> Result<(), Error> {
+        self.nss.set_low();
+        let result = self.spi.write(&[register | 0x80, value]).await;
+        self.nss.set_high();
+        Ok(result?)
+    }
+}
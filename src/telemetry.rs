@@ -0,0 +1,81 @@
+use core::sync::atomic::Ordering;
+
+use embassy_stm32::{
+    peripherals::{DMA1_CH7, PA2, USART2},
+    usart::{self, UartTx},
+};
+use embassy_time::{Duration, Instant, Ticker};
+
+use sie_core::{
+    codes::Code,
+    telemetry::{FRAME_MAX, Mode, Sample, State, Values, Voltages},
+};
+
+use crate::{
+    MANUAL_MODE, SYSTEM_ENABLED, TELEMETRY_FIELDS, counters,
+    error::{self, Error},
+    watchdog::{self, Task},
+    zone::ZONES,
+};
+
+// Periodo de las muestras de cada zona
+const PERIOD: Duration = Duration::from_secs(1);
+
+// Telemetria binaria en USART2 (solo TX en PA2, 115200 8N1): una trama
+// COBS por zona y periodo con los grupos de `TELEMETRY_FIELDS`: voltajes,
+// lectura, brillo y modo, contadores y, con `stats`, las estadisticas de
+// los sensores (ver sie_core::telemetry)
+#[embassy_executor::task]
+pub async fn telemetry(usart: USART2, tx: PA2, dma: DMA1_CH7) {
+    let Ok(mut uart) = UartTx::new(usart, tx, dma, usart::Config::default()) else {
+        error::degrade(Error::Setup(Code::TelemetrySetup));
+        return;
+    };
+
+    let mut ticker = Ticker::every(PERIOD);
+    loop {
+        ticker.next().await;
+        watchdog::check_in(Task::Telemetry);
+
+        let mode = if !SYSTEM_ENABLED.load(Ordering::Relaxed) {
+            Mode::Disabled
+        } else if MANUAL_MODE.load(Ordering::Relaxed) {
+            Mode::Manual
+        } else {
+            Mode::Auto
+        };
+
+        for (id, zone) in ZONES.iter().enumerate() {
+            // Sin lectura todavia no hay nada que enviar
+            let Some(reading) = zone.last_reading.lock(|r| r.get()) else {
+                continue;
+            };
+            let sample = Sample {
+                uptime_ms: Instant::now().as_millis(),
+                zone: id as u8,
+                raw: Some(Voltages {
+                    lux: reading.lux_voltage,
+                    distance: reading.distance_voltage,
+                }),
+                values: Some(Values {
+                    lux: reading.lux,
+                    distance: reading.distance,
+                }),
+                state: Some(State {
+                    brightness: zone.with_light(|l| l.brightness()).unwrap_or(0),
+                    mode,
+                }),
+                counters: Some(counters::get()),
+                #[cfg(feature = "stats")]
+                stats: Some(zone.stats.lock(|s| s.borrow_mut().summary())),
+                #[cfg(not(feature = "stats"))]
+                stats: None,
+            }
+            .select(TELEMETRY_FIELDS);
+
+            let mut frame = [0; FRAME_MAX];
+            let len = sample.frame(&mut frame);
+            let _ = uart.write(&frame[..len]).await;
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use embassy_stm32::peripherals::PA4;
+use embassy_time::{Duration, Timer};
+
+use sie_core::sensor::{MAX_ADC_VALUE, MAX_LUX_VALUE};
+
+use crate::{SharedAdc, zone::ZONES};
+
+// Periodo de lectura del potenciometro
+const READ_PERIOD: Duration = Duration::from_millis(500);
+
+// Cambio minimo para aplicar una nueva posicion; evita que el ruido del
+// ADC pise los ajustes hechos por otros medios (perilla)
+const DEADBAND: f32 = 100.; // Luxes
+
+// Potenciometro de ajuste cuya posicion se mapea al umbral de luz de
+// todas las zonas
+#[embassy_executor::task]
+pub async fn trim_pot(mut pin: PA4, adc: &'static SharedAdc) {
+    let mut last: Option<f32> = None;
+
+    loop {
+        let raw = adc.lock().await.read(&mut pin).await;
+        let threshold = (raw as f32 / MAX_ADC_VALUE) * MAX_LUX_VALUE;
+
+        if !last.is_some_and(|last| (threshold - last).abs() < DEADBAND) {
+            last = Some(threshold);
+
+            for zone in &ZONES {
+                zone.thresholds.lock(|t| {
+                    let mut thresholds = t.get();
+                    thresholds.light = threshold;
+                    t.set(thresholds);
+                });
+            }
+            info!(
+                Sensors,
+                "Umbral de luz (potenciometro): {} luxes", threshold
+            );
+        }
+
+        Timer::after(READ_PERIOD).await;
+    }
+}
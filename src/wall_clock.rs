@@ -0,0 +1,27 @@
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+
+use sie_core::{clock::WallClock, schedule::TimeOfDay};
+
+// Reloj de tiempo real en uso: el DS3231 si esta presente o el RTC interno
+static CLOCK: CriticalSectionMutex<RefCell<Option<&'static mut (dyn WallClock + Send)>>> =
+    CriticalSectionMutex::new(RefCell::new(None));
+
+pub fn init(clock: &'static mut (dyn WallClock + Send)) {
+    CLOCK.lock(|c| *c.borrow_mut() = Some(clock));
+}
+
+// Hora actual, o None si el reloj no esta ajustado (o esta ocupado, por
+// ejemplo al registrar un mensaje mientras se consulta)
+pub fn now() -> Option<TimeOfDay> {
+    CLOCK.lock(|c| c.try_borrow_mut().ok()?.as_mut()?.now())
+}
+
+pub fn set(time: TimeOfDay) {
+    CLOCK.lock(|c| {
+        if let Some(clock) = c.borrow_mut().as_mut() {
+            clock.set(time);
+        }
+    });
+}
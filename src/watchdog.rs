@@ -0,0 +1,186 @@
+use core::cell::RefCell;
+
+use embassy_stm32::{peripherals::IWDG, wdg::IndependentWatchdog};
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::{Duration, Timer};
+
+use sie_core::{codes::Code, event_log::Reason, supervisor::Supervisor};
+
+use crate::{
+    clock::SystemClock,
+    counters,
+    error::{self, Error},
+    light::MAX_BRIGHTNESS,
+    settings,
+    zone::{ZONE_COUNT, ZONES},
+};
+
+// Sin alimentarlo durante este tiempo el watchdog reinicia el equipo
+pub const TIMEOUT: Duration = Duration::from_secs(4);
+// Periodo de revision de las tareas
+const TICK: Duration = Duration::from_millis(500);
+
+// Tareas supervisadas: los controladores de zona (muestreo y control), el
+// LED de estado, que corre siempre y delata un ejecutor trabado, y los
+// enlaces de comunicacion
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Task {
+    Zone(usize),
+    StatusLed,
+    #[cfg(feature = "telemetry")]
+    Telemetry,
+    #[cfg(feature = "mqtt")]
+    Mqtt,
+    #[cfg(feature = "lora")]
+    Lora,
+}
+
+const TASKS: [Task; TASK_COUNT] = {
+    let mut tasks = [Task::StatusLed; TASK_COUNT];
+    let mut zone = 0;
+    while zone < ZONE_COUNT {
+        tasks[zone] = Task::Zone(zone);
+        zone += 1;
+    }
+    #[cfg(feature = "telemetry")]
+    {
+        tasks[Task::Telemetry.slot()] = Task::Telemetry;
+    }
+    #[cfg(feature = "mqtt")]
+    {
+        tasks[Task::Mqtt.slot()] = Task::Mqtt;
+    }
+    #[cfg(feature = "lora")]
+    {
+        tasks[Task::Lora.slot()] = Task::Lora;
+    }
+    tasks
+};
+const TASK_COUNT: usize = ZONE_COUNT
+    + 1
+    + cfg!(feature = "telemetry") as usize
+    + cfg!(feature = "mqtt") as usize
+    + cfg!(feature = "lora") as usize;
+
+impl Task {
+    const fn slot(self) -> usize {
+        match self {
+            Task::Zone(zone) => zone,
+            Task::StatusLed => ZONE_COUNT,
+            // La telemetria y MQTT no se combinan
+            #[cfg(feature = "telemetry")]
+            Task::Telemetry => ZONE_COUNT + 1,
+            #[cfg(feature = "mqtt")]
+            Task::Mqtt => ZONE_COUNT + 1,
+            #[cfg(feature = "lora")]
+            Task::Lora => TASK_COUNT - 1,
+        }
+    }
+
+    // El numero de la zona se registra aparte (ver `error::degrade`)
+    pub fn name(self) -> &'static str {
+        match self {
+            Task::Zone(_) => "zona",
+            Task::StatusLed => "status_led",
+            #[cfg(feature = "telemetry")]
+            Task::Telemetry => "telemetry",
+            #[cfg(feature = "mqtt")]
+            Task::Mqtt => "mqtt",
+            #[cfg(feature = "lora")]
+            Task::Lora => "lora",
+        }
+    }
+}
+
+// Plazos de cada tarea. El nodo de lampara nRF24 espera hasta 2 s las
+// lecturas del otro nodo. MQTT se reporta en cada orden AT, pero puede
+// tardar la conexion al broker (20 s) y la pausa antes de reconectar
+// (30 s); LoRa sube cada minuto
+const ALLOWED_MS: [u64; TASK_COUNT] = {
+    let mut allowed = [3000; TASK_COUNT];
+    allowed[Task::StatusLed.slot()] = 1000;
+    #[cfg(feature = "mqtt")]
+    {
+        allowed[Task::Mqtt.slot()] = 60_000;
+    }
+    #[cfg(feature = "lora")]
+    {
+        allowed[Task::Lora.slot()] = 90_000;
+    }
+    allowed
+};
+
+static SUPERVISOR: CriticalSectionMutex<RefCell<Supervisor<SystemClock, TASK_COUNT>>> =
+    CriticalSectionMutex::new(RefCell::new(Supervisor::new(SystemClock, ALLOWED_MS)));
+
+// Alarga el plazo del watchdog a su maximo (~26 s), para despertar
+// periodicamente del modo Standby
+#[cfg(feature = "battery")]
+pub fn slowest() {
+    use embassy_stm32::pac::{
+        self,
+        iwdg::vals::{Key, Pr},
+    };
+
+    let iwdg = pac::IWDG;
+    iwdg.kr().write(|w| w.set_key(Key::ENABLE));
+    iwdg.pr().write(|w| w.set_pr(Pr::DIVIDE_BY256));
+    iwdg.rlr().write(|w| w.set_rl(0xFFF));
+    iwdg.kr().write(|w| w.set_key(Key::RESET));
+}
+
+// Alimenta el watchdog antes de una parada en modo STOP, que lo deja sin
+// alimentar unos segundos. No lo hace si hay una tarea trabada
+#[cfg(feature = "stop-mode")]
+pub fn pet_before_stop() -> bool {
+    use embassy_stm32::pac::{self, iwdg::vals::Key};
+
+    if SUPERVISOR.lock(|s| s.borrow().stalled()).is_some() {
+        return false;
+    }
+    pac::IWDG.kr().write(|w| w.set_key(Key::RESET));
+    true
+}
+
+// Una vuelta del bucle de `task`
+pub fn check_in(task: Task) {
+    SUPERVISOR.lock(|s| s.borrow_mut().check_in(task.slot()));
+}
+
+// Alimenta el watchdog mientras todas las tareas supervisadas se reporten
+// a tiempo. Si una deja de hacerlo se registra cual y:
+// - con el LED de estado (el ejecutor no atiende las tareas) se guardan los
+//   contadores y los ajustes y se reinicia el equipo;
+// - con un controlador de zona su lampara queda encendida al maximo, el
+//   estado mas seguro, y el equipo sigue degradado;
+// - con un enlace de comunicacion el equipo sigue degradado, sin el.
+// Si el ejecutor se traba del todo esta tarea tampoco corre y el watchdog
+// reinicia el equipo
+#[embassy_executor::task]
+pub async fn watchdog(mut wdg: IndependentWatchdog<'static, IWDG>) {
+    loop {
+        Timer::after(TICK).await;
+
+        let Some(slot) = SUPERVISOR.lock(|s| s.borrow().stalled()) else {
+            wdg.pet();
+            continue;
+        };
+        let task = TASKS[slot];
+        if task == Task::StatusLed {
+            warn!(
+                Code::TaskStalled,
+                "Tarea trabada: {}, se reinicia",
+                task.name()
+            );
+            counters::save();
+            settings::save();
+            cortex_m::peripheral::SCB::sys_reset();
+        }
+        if let Task::Zone(zone) = task {
+            ZONES[zone].switch_light(Reason::Watchdog, |l| l.set_brightness(MAX_BRIGHTNESS));
+        }
+        error::degrade(Error::Stalled(task));
+        SUPERVISOR.lock(|s| s.borrow_mut().release(slot));
+        wdg.pet();
+    }
+}
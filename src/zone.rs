@@ -0,0 +1,781 @@
+use core::{
+    cell::{Cell, RefCell},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use embassy_futures::select::select;
+use embassy_stm32::{adc::AnyAdcChannel, peripherals::ADC1};
+use embassy_sync::{
+    blocking_mutex::{CriticalSectionMutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+#[cfg(not(feature = "replay"))]
+use embassy_time::Instant;
+#[cfg(feature = "nrf24-relay")]
+use embassy_time::with_timeout;
+use embassy_time::{Duration, Timer};
+
+#[cfg(feature = "teaching")]
+use sie_core::control::Decision;
+use sie_core::{
+    background::Presence,
+    codes::Code,
+    control::{DISTANCE_THRESHOLD, LIGHT_THRESHOLD, Reading, Thresholds},
+    event_log::Reason,
+    hal::Sensors,
+    histogram::DistanceHistogram,
+    occupancy::Timeout,
+    regulator::LuxRegulator,
+    rules::RuleSet,
+    sensor::{DistanceModel, LightModel, LuxPolarity},
+    zone::{Limits, Step, ZoneLogic},
+};
+#[cfg(feature = "sim")]
+use sie_core::{sensor::Profile, sim::Scenario};
+
+#[cfg(all(
+    feature = "adc-calibration",
+    not(any(feature = "sim", feature = "replay"))
+))]
+use crate::adc_calibration;
+use crate::{
+    CLOSED_LOOP, MANUAL_MODE, SYSTEM_ENABLED, SharedAdc,
+    clock::SystemClock,
+    config::{self, Config},
+    diagnostics,
+    events::{self, Event},
+    light::Light,
+    report::{DailyReport, ReportRequest},
+    watchdog::{self, Task},
+};
+
+// Numero de zonas; cada una tiene sus propios sensores, lampara y umbrales
+pub const ZONE_COUNT: usize = if cfg!(feature = "second-zone") { 2 } else { 1 };
+
+// Reglas estandar de cada zona; con el sensor exterior solo encienden si
+// tambien esta oscuro afuera
+pub const fn standard_rules(config: &Config) -> RuleSet {
+    let rules = RuleSet::standard(config.presence_brightness, config.idle_brightness);
+    #[cfg(feature = "outdoor-light")]
+    let rules = rules.with_outdoor(config.outdoor_dark);
+    rules
+}
+
+// Ventana del histograma de distancias
+const HISTOGRAM_WINDOW_MS: u64 = 60 * 60 * 1000;
+
+// Ventanas de las estadisticas de los sensores, de la corta a la larga
+#[cfg(feature = "stats")]
+pub const STATS_WINDOWS_MS: [u64; sie_core::stats::WINDOWS] = [60 * 1000, 60 * 60 * 1000];
+
+// Apagado o en modo manual el controlador solo despierta con este periodo
+// o al cambiar el modo
+const IDLE_TICK: Duration = Duration::from_secs(1);
+
+// Dia simulado y periodo de las visitas con `sim`
+#[cfg(feature = "sim")]
+const SIM_DAY: Duration = Duration::from_secs(10 * 60);
+#[cfg(feature = "sim")]
+const SIM_VISIT: Duration = Duration::from_secs(2 * 60);
+
+// Sin lecturas del nodo de sensores durante este tiempo la lampara se
+// apaga
+#[cfg(feature = "nrf24-relay")]
+const LINK_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Etapas de una muestra, de la lectura del ADC al brillo, para explicar la
+// conversion en clase
+#[cfg(feature = "teaching")]
+#[derive(Clone, Copy)]
+pub struct Trace {
+    pub raw_distance: u16,
+    pub raw_lux: u16,
+    // Voltajes y valores fisicos, con la luz sin calibrar
+    pub converted: Reading,
+    pub lux_scale: f32,
+    pub thresholds: Thresholds,
+    pub decision: Decision,
+    // Presencia con el fondo aprendido y ocupacion con la espera
+    pub present: bool,
+    pub occupied: bool,
+    pub brightness: u8,
+}
+
+// Estado de una zona compartido entre su controlador, los botones y la
+// tarea de rampas
+pub struct ZoneState {
+    light: CriticalSectionMutex<RefCell<Option<Light>>>,
+    // Umbrales vigentes, inician con los valores por defecto
+    // y pueden ajustarse en campo
+    pub thresholds: CriticalSectionMutex<Cell<Thresholds>>,
+    // Reglas que deciden el brillo en modo automatico
+    pub rules: CriticalSectionMutex<RefCell<RuleSet>>,
+    pub report_request: Signal<CriticalSectionRawMutex, ReportRequest>,
+    // Pulsacion manual que quita el limite de tiempo encendida
+    pub on_limit_release: Signal<CriticalSectionRawMutex, ()>,
+    // Cambio el modo manual o el encendido (ver `crate::publish_mode`)
+    pub mode_changed: Signal<CriticalSectionRawMutex, ()>,
+    // Distancias medidas en la ultima hora, para orientar el sensor
+    pub distances: CriticalSectionMutex<RefCell<DistanceHistogram<SystemClock>>>,
+    // Minimo, maximo, media y varianza de la luz y la distancia en cada
+    // ventana, para elegir los umbrales
+    #[cfg(feature = "stats")]
+    pub stats: CriticalSectionMutex<RefCell<sie_core::stats::ZoneStats<SystemClock>>>,
+    // Ultima lectura de los sensores, para la consola y la telemetria
+    pub last_reading: CriticalSectionMutex<Cell<Option<Reading>>>,
+    // Etapas de la ultima muestra en modo automatico
+    #[cfg(feature = "teaching")]
+    pub trace: CriticalSectionMutex<Cell<Option<Trace>>>,
+    // Factor de calibracion del sensor de luz (ver `calibrate_lux`)
+    lux_scale: CriticalSectionMutex<Cell<f32>>,
+    // Modelos de los sensores, para convertir umbrales a voltajes
+    #[cfg(any(feature = "adc-watchdog", feature = "presence-trigger"))]
+    models: CriticalSectionMutex<Cell<(DistanceModel, LightModel)>>,
+    // Zona ocupada, para Home Assistant y la calibracion del ADC
+    pub occupied: AtomicBool,
+    // El watchdog del ADC detecto un cruce de umbral o el comparador detecto
+    // presencia: muestrear de inmediato
+    #[cfg(any(feature = "adc-watchdog", feature = "presence-trigger"))]
+    pub sample_now: Signal<CriticalSectionRawMutex, ()>,
+    // Lectura cruda (distancia, luz) que va o viene por el enlace nRF24
+    #[cfg(feature = "nrf24")]
+    pub raw_sample: Signal<CriticalSectionRawMutex, (u16, u16)>,
+}
+
+impl ZoneState {
+    const fn new() -> Self {
+        Self {
+            light: CriticalSectionMutex::new(RefCell::new(None)),
+            thresholds: CriticalSectionMutex::new(Cell::new(Thresholds {
+                light: LIGHT_THRESHOLD,
+                distance: DISTANCE_THRESHOLD,
+            })),
+            rules: CriticalSectionMutex::new(RefCell::new(standard_rules(&config::DEFAULT))),
+            report_request: Signal::new(),
+            on_limit_release: Signal::new(),
+            mode_changed: Signal::new(),
+            distances: CriticalSectionMutex::new(RefCell::new(DistanceHistogram::new(
+                SystemClock,
+                HISTOGRAM_WINDOW_MS,
+            ))),
+            #[cfg(feature = "stats")]
+            stats: CriticalSectionMutex::new(RefCell::new(sie_core::stats::ZoneStats::new(
+                SystemClock,
+                STATS_WINDOWS_MS,
+            ))),
+            last_reading: CriticalSectionMutex::new(Cell::new(None)),
+            #[cfg(feature = "teaching")]
+            trace: CriticalSectionMutex::new(Cell::new(None)),
+            lux_scale: CriticalSectionMutex::new(Cell::new(1.)),
+            #[cfg(any(feature = "adc-watchdog", feature = "presence-trigger"))]
+            models: CriticalSectionMutex::new(Cell::new((
+                DistanceModel::Gp2y0a710,
+                LightModel::Dfr0026,
+            ))),
+            occupied: AtomicBool::new(false),
+            #[cfg(any(feature = "adc-watchdog", feature = "presence-trigger"))]
+            sample_now: Signal::new(),
+            #[cfg(feature = "nrf24")]
+            raw_sample: Signal::new(),
+        }
+    }
+
+    // Lectura cruda del sensor de luz por debajo de la cual la zona esta
+    // oscura, considerando la calibracion
+    #[cfg(feature = "adc-watchdog")]
+    pub fn dark_level(&self) -> u16 {
+        let threshold = self.thresholds.lock(|t| t.get()).light;
+        let (_, light) = self.models();
+        light.profile().adc(threshold / self.lux_scale())
+    }
+
+    // Lectura cruda del sensor de distancia por arriba de la cual hay
+    // alguien dentro del umbral
+    #[cfg(feature = "adc-watchdog")]
+    pub fn near_level(&self) -> u16 {
+        let threshold = self.thresholds.lock(|t| t.get()).distance;
+        let (distance, _) = self.models();
+        distance.profile().adc(threshold)
+    }
+
+    // La ultima lectura quedo bajo el umbral de luz; false sin lecturas
+    #[cfg(feature = "adc-watchdog")]
+    pub fn is_night(&self) -> bool {
+        let threshold = self.thresholds.lock(|t| t.get()).light;
+        self.last_reading
+            .lock(|r| r.get())
+            .is_some_and(|reading| reading.lux < threshold)
+    }
+
+    // Ajusta la escala del sensor de luz para que la ultima lectura
+    // corresponda a `reference` luxes (medidos con un luxometro). Devuelve
+    // el nuevo factor, o None si todavia no hay lectura o esta en cero
+    #[cfg(feature = "console")]
+    pub fn calibrate_lux(&self, reference: f32) -> Option<f32> {
+        let lux = self.last_reading.lock(|r| r.get())?.lux;
+        if lux <= 0. {
+            return None;
+        }
+        let scale = self.lux_scale() * reference / lux;
+        self.set_lux_scale(scale);
+        Some(scale)
+    }
+
+    // Toma la luz de la ultima lectura como umbral de oscuridad: se usa con
+    // la luz ambiental con la que se quiere que la lampara empiece a
+    // encender. Devuelve el nuevo umbral, o None si todavia no hay lectura
+    pub fn teach_light_threshold(&self) -> Option<f32> {
+        let lux = self.last_reading.lock(|r| r.get())?.lux;
+        self.thresholds.lock(|t| {
+            let mut thresholds = t.get();
+            thresholds.light = lux;
+            t.set(thresholds);
+        });
+        Some(lux)
+    }
+
+    // Quita la calibracion del sensor de luz
+    #[cfg(feature = "console")]
+    pub fn reset_lux_calibration(&self) {
+        self.set_lux_scale(1.);
+    }
+
+    pub fn lux_scale(&self) -> f32 {
+        self.lux_scale.lock(|s| s.get())
+    }
+
+    pub fn set_lux_scale(&self, scale: f32) {
+        self.lux_scale.lock(|s| s.set(scale));
+    }
+
+    // Estado actual de la lampara
+    pub fn light_is_on(&self) -> bool {
+        self.light
+            .lock(|l| l.borrow().as_ref().is_some_and(Light::is_on))
+    }
+
+    // Opera sobre la lampara de la zona si ya fue instalada. Si `f`
+    // vuelve a acceder a la lampara de la misma zona no opera (None)
+    pub fn with_light<R>(&self, f: impl FnOnce(&mut Light) -> R) -> Option<R> {
+        self.light
+            .lock(|l| l.try_borrow_mut().ok()?.as_mut().map(f))
+    }
+
+    // Como `with_light`, para cambiar el brillo: si la lampara se enciende
+    // o se apaga lo publica en el bus con el motivo
+    pub fn switch_light<R>(&self, reason: Reason, f: impl FnOnce(&mut Light) -> R) -> Option<R> {
+        let (was_on, result, on) = self.with_light(|l| {
+            let was_on = l.is_on();
+            let result = f(l);
+            (was_on, result, l.is_on())
+        })?;
+        if on != was_on {
+            let zone = ZONES.iter().position(|z| core::ptr::eq(z, self));
+            events::publish(Event::LightChanged {
+                zone: zone.unwrap_or(0) as u8,
+                on,
+                reason,
+            });
+        }
+        Some(result)
+    }
+
+    fn install(&self, light: Light, thresholds: Thresholds, models: (DistanceModel, LightModel)) {
+        self.light.lock(|l| *l.borrow_mut() = Some(light));
+        self.thresholds.lock(|t| t.set(thresholds));
+        #[cfg(any(feature = "adc-watchdog", feature = "presence-trigger"))]
+        self.models.lock(|m| m.set(models));
+        #[cfg(not(any(feature = "adc-watchdog", feature = "presence-trigger")))]
+        let _ = models;
+    }
+
+    // Modelos de los sensores de distancia y de luz
+    #[cfg(any(feature = "adc-watchdog", feature = "presence-trigger"))]
+    pub fn models(&self) -> (DistanceModel, LightModel) {
+        self.models.lock(|m| m.get())
+    }
+}
+
+pub static ZONES: [ZoneState; ZONE_COUNT] = [const { ZoneState::new() }; ZONE_COUNT];
+
+// Con el modo dual la luz se mide en ADC2 al mismo tiempo que la distancia
+#[cfg(feature = "dual-adc")]
+pub type LightChannel = crate::dual_adc::Adc2Channel;
+#[cfg(not(feature = "dual-adc"))]
+pub type LightChannel = AnyAdcChannel<ADC1>;
+
+// Sensores y salida de una zona
+pub struct Zone {
+    pub id: usize,
+    pub distance_sensor: AnyAdcChannel<ADC1>,
+    pub light_sensor: LightChannel,
+    pub light_polarity: LuxPolarity,
+    pub distance_model: DistanceModel,
+    pub light_model: LightModel,
+    pub light: Light,
+    // Sin umbrales guardados se usan los de los modelos
+    pub thresholds: Option<Thresholds>,
+    // Tiempo que la luz sigue encendida al dejar de detectar presencia
+    pub timeout: Timeout,
+    // Umbral fijo de distancia o desviacion del fondo aprendido
+    pub presence: Presence,
+}
+
+// Sensores de una zona en el ADC compartido
+#[cfg(not(any(feature = "sim", feature = "replay")))]
+struct ZoneSensors {
+    adc: &'static SharedAdc,
+    distance: AnyAdcChannel<ADC1>,
+    light: LightChannel,
+    // Instante de la ultima muestra, con el ADC ya tomado
+    sampled_at: Instant,
+}
+
+#[cfg(not(any(feature = "sim", feature = "replay")))]
+impl Sensors for ZoneSensors {
+    // Ambas lecturas se toman seguidas (o juntas, en modo dual) para que
+    // correspondan al mismo instante aunque otra zona espere el ADC
+    async fn sample(&mut self) -> (u16, u16) {
+        let mut adc = self.adc.lock().await;
+        self.sampled_at = Instant::now();
+        #[cfg(feature = "dual-adc")]
+        let (raw_distance, raw_lux) =
+            crate::dual_adc::read(&mut adc, &mut self.distance, &mut self.light).await;
+        #[cfg(not(feature = "dual-adc"))]
+        let (raw_distance, raw_lux) = (
+            adc.read(&mut self.distance).await,
+            adc.read(&mut self.light).await,
+        );
+        // Como si el ADC tuviera exactamente 3.3 V
+        #[cfg(feature = "adc-calibration")]
+        let (raw_distance, raw_lux) = (
+            adc_calibration::correct(raw_distance),
+            adc_calibration::correct(raw_lux),
+        );
+        (raw_distance, raw_lux)
+    }
+}
+
+// Lecturas del escenario sintetico en lugar de los sensores, con los
+// modelos y la orientacion de la zona
+#[cfg(feature = "sim")]
+struct SimSensors {
+    scenario: Scenario,
+    distance: Profile,
+    light: Profile,
+    polarity: LuxPolarity,
+    sampled_at: Instant,
+}
+
+#[cfg(feature = "sim")]
+impl Sensors for SimSensors {
+    async fn sample(&mut self) -> (u16, u16) {
+        self.sampled_at = Instant::now();
+        self.scenario.sample(
+            self.sampled_at.as_millis(),
+            &self.distance,
+            &self.light,
+            self.polarity,
+        )
+    }
+}
+
+// Controlador de una zona: mide sus sensores y decide el brillo de su
+// lampara. El ADC se comparte entre todas las zonas
+#[embassy_executor::task(pool_size = ZONE_COUNT)]
+pub async fn controller(zone: Zone, adc: &'static SharedAdc) {
+    let Zone {
+        id,
+        distance_sensor,
+        light_sensor,
+        light_polarity,
+        distance_model,
+        light_model,
+        light,
+        thresholds,
+        timeout,
+        presence,
+    } = zone;
+
+    let state = &ZONES[id];
+    state.install(
+        light,
+        thresholds.unwrap_or(Thresholds::for_models(distance_model, light_model)),
+        (distance_model, light_model),
+    );
+    let distance_profile = distance_model.profile();
+    let light_profile = light_model.profile();
+
+    #[cfg(not(any(feature = "sim", feature = "replay")))]
+    let mut sensors = ZoneSensors {
+        adc,
+        distance: distance_sensor,
+        light: light_sensor,
+        sampled_at: Instant::now(),
+    };
+    // Las visitas de cada zona se reparten en el periodo
+    #[cfg(feature = "sim")]
+    let mut sensors = {
+        let _ = (adc, distance_sensor, light_sensor);
+        info!(Sensors, "Zona {}: lecturas simuladas", id);
+        SimSensors {
+            scenario: Scenario::new(
+                SIM_DAY.as_millis(),
+                SIM_VISIT.as_millis(),
+                id as u64 * SIM_VISIT.as_millis() / ZONE_COUNT as u64,
+            ),
+            distance: distance_profile,
+            light: light_profile,
+            polarity: light_polarity,
+            sampled_at: Instant::now(),
+        }
+    };
+    #[cfg(feature = "replay")]
+    let mut sensors = {
+        let _ = (adc, distance_sensor, light_sensor);
+        crate::replay::ReplaySensors::new(id)
+    };
+    let mut report = DailyReport::new(id);
+    let config = config::get();
+    let mut logic = ZoneLogic::new(
+        SystemClock,
+        timeout,
+        presence,
+        LuxRegulator::new(config.lux_setpoint, config.regulator_gain),
+        Limits {
+            max_on_ms: config.max_on_time.as_millis(),
+            cooldown_ms: config.on_limit_cooldown.as_millis(),
+        },
+    );
+    #[cfg(feature = "ambient-learning")]
+    let mut learned = crate::ambient::LearnedThreshold::new(id, state);
+    #[cfg(feature = "nrf24-relay")]
+    let mut link_lost = false;
+    #[cfg(feature = "camera-trigger")]
+    let mut was_present = false;
+
+    loop {
+        watchdog::check_in(Task::Zone(id));
+
+        // El nodo de lampara muestrea al ritmo de las lecturas que recibe
+        #[cfg(feature = "nrf24-relay")]
+        let remote = with_timeout(LINK_TIMEOUT, state.raw_sample.wait())
+            .await
+            .ok();
+        #[cfg(any(feature = "adc-watchdog", feature = "presence-trigger"))]
+        select(Timer::after_millis(100), state.sample_now.wait()).await;
+        #[cfg(not(any(
+            feature = "adc-watchdog",
+            feature = "presence-trigger",
+            feature = "nrf24-relay"
+        )))]
+        Timer::after_millis(100).await;
+        match state.report_request.try_take() {
+            Some(ReportRequest::Emit) => report.emit(),
+            Some(ReportRequest::Reset) => report.reset(),
+            None => {}
+        }
+        if state.on_limit_release.try_take().is_some() {
+            logic.release();
+        }
+
+        // Hora del dia, si hay reloj de tiempo real ajustado
+        #[cfg(feature = "schedule")]
+        let time = crate::wall_clock::now();
+        #[cfg(not(feature = "schedule"))]
+        let time = None;
+
+        // El nodo se apaga por bateria baja
+        #[cfg(feature = "battery")]
+        if crate::battery::is_shutting_down() {
+            state.switch_light(Reason::Battery, |l| l.set_brightness(0));
+            continue;
+        }
+
+        // Apagado o en modo manual no hay nada que controlar: en lugar de
+        // revisar el modo cada 100 ms se espera su cambio, despertando solo
+        // para el resumen y el watchdog
+        if MANUAL_MODE.load(Ordering::Relaxed) || !SYSTEM_ENABLED.load(Ordering::Relaxed) {
+            report.record(state.light_is_on(), None, None, time);
+            diagnostics::loop_idle(id);
+            select(Timer::after(IDLE_TICK), state.mode_changed.wait()).await;
+            continue;
+        }
+
+        // Fuera del horario el modo automatico no se arma y la lampara
+        // queda apagada
+        #[cfg(feature = "schedule")]
+        if time.is_some_and(|now| !config::get().schedule.is_active(now)) {
+            diagnostics::loop_idle(id);
+            state.switch_light(Reason::Schedule, |l| l.set_brightness(0));
+            report.record(state.light_is_on(), Some(false), None, time);
+            continue;
+        }
+
+        // Los sensores estan en el otro nodo
+        #[cfg(feature = "nrf24-relay")]
+        let (raw_distance, raw_luminicence, sampled_at) = {
+            let _ = &mut sensors;
+            let Some((raw_distance, raw_luminicence)) = remote else {
+                if !link_lost {
+                    warn!(
+                        Code::SensorLinkLost,
+                        "Zona {}: sin lecturas del nodo de sensores", id
+                    );
+                    link_lost = true;
+                }
+                diagnostics::loop_idle(id);
+                state.switch_light(Reason::LinkLost, |l| l.set_brightness(0));
+                report.record(state.light_is_on(), Some(false), None, time);
+                continue;
+            };
+            link_lost = false;
+            (raw_distance, raw_luminicence, Instant::now())
+        };
+        #[cfg(not(feature = "nrf24-relay"))]
+        let (raw_distance, raw_luminicence, sampled_at) = {
+            let (raw_distance, raw_luminicence) = sensors.sample().await;
+            (raw_distance, raw_luminicence, sensors.sampled_at)
+        };
+        #[cfg(feature = "nrf24-sensor")]
+        state.raw_sample.signal((raw_distance, raw_luminicence));
+        let mut reading = Reading::from_raw_profiled(
+            raw_distance,
+            raw_luminicence,
+            &distance_profile,
+            &light_profile,
+            light_polarity,
+        );
+        #[cfg(feature = "teaching")]
+        let converted = reading;
+        reading.lux *= state.lux_scale();
+        // Durante una demostracion mandan las lecturas inventadas
+        #[cfg(feature = "demo")]
+        if let Some(fake) = crate::demo::reading() {
+            reading.lux = fake.lux;
+            reading.distance = fake.distance;
+        }
+        state.last_reading.lock(|r| r.set(Some(reading)));
+        events::publish(Event::NewLuxReading {
+            zone: id as u8,
+            lux: reading.lux,
+        });
+        events::publish(Event::NewDistance {
+            zone: id as u8,
+            meters: reading.distance,
+        });
+        state
+            .distances
+            .lock(|h| h.borrow_mut().record(reading.distance));
+        #[cfg(feature = "stats")]
+        state
+            .stats
+            .lock(|s| s.borrow_mut().record(reading.lux, reading.distance));
+
+        #[cfg(feature = "ambient-learning")]
+        learned.update(state, reading.lux, state.light_is_on());
+
+        debug!(
+            Sensors,
+            "Zona {}: objeto a {} metros. Voltaje: {}",
+            id,
+            reading.distance,
+            reading.distance_voltage
+        );
+        debug!(
+            Sensors,
+            "Zona {}: luminosidad de {} luxes. Voltaje {}", id, reading.lux, reading.lux_voltage
+        );
+
+        // Determinar si se enciende la luz
+        let thresholds = state.thresholds.lock(|t| t.get());
+        #[cfg(feature = "outdoor-light")]
+        let outdoor = crate::outdoor_light::lux();
+        #[cfg(not(feature = "outdoor-light"))]
+        let outdoor = None;
+        let step = state.rules.lock(|r| {
+            logic.step(
+                &reading,
+                &thresholds,
+                &r.borrow(),
+                outdoor,
+                CLOSED_LOOP.load(Ordering::Relaxed),
+                time,
+            )
+        });
+        let Step {
+            decision,
+            occupied,
+            light_on,
+            brightness,
+            ..
+        } = step;
+        state.occupied.store(occupied, Ordering::Relaxed);
+        // Con una traza grabada la decision se compara con la esperada
+        #[cfg(feature = "replay")]
+        sensors.check(&decision);
+        // Cada deteccion nueva dispara la salida para camaras
+        #[cfg(feature = "camera-trigger")]
+        {
+            if step.present && !was_present {
+                crate::camera_trigger::presence(id);
+            }
+            was_present = step.present;
+        }
+        if step.limit_reached {
+            warn!(
+                Code::OnTooLong,
+                "Zona {}: encendida demasiado tiempo, se apaga", id
+            );
+            report.fault();
+        }
+
+        #[cfg(feature = "teaching")]
+        state.trace.lock(|t| {
+            t.set(Some(Trace {
+                raw_distance,
+                raw_lux: raw_luminicence,
+                converted,
+                lux_scale: state.lux_scale(),
+                thresholds,
+                decision,
+                present: step.present,
+                occupied,
+                brightness,
+            }))
+        });
+
+        state.switch_light(step.reason(), |l| {
+            l.set_brightness_from_sample(brightness, sampled_at)
+        });
+        diagnostics::record_loop(id, sampled_at);
+
+        report.record(
+            state.light_is_on(),
+            Some(light_on),
+            Some(decision.dark),
+            time,
+        );
+    }
+}
+
+// Declara las zonas en un solo bloque: sensores, salida y politicas de
+// cada una. Configura el PWM de las lamparas (TIM4) con los canales usados
+// y lanza un controlador por zona. Cada zona indica:
+//   distance: pin del sensor de distancia y su entrada ADC12_INx (ADC1)
+//   light:    pin del sensor de luz y su entrada ADC12_INx (ADC2)
+//   light_polarity: si el modulo de luz sube o baja su voltaje con la luz
+//   distance_model, light_model: modelos de los sensores por su nombre
+//             (ver sie_core::sensor::DistanceModel y LightModel)
+//   output:   pin de la lampara y su canal del TIM4
+//   min_duty: ciclo de trabajo minimo del driver
+//   thresholds: umbrales iniciales; con None los de los modelos
+//   timeout:  politica de espera al dejar de detectar presencia
+//   presence: deteccion por umbral fijo o por desviacion del fondo
+// Con `adc-watchdog` lanza ademas la vigilancia de los sensores en ADC2.
+// Los atributos (por ejemplo `#[cfg(...)]`) se aplican a toda la zona
+macro_rules! zones {
+    (
+        $spawner:ident, $p:ident, $adc:ident;
+        $(
+            $(#[$attr:meta])*
+            $id:literal => {
+                distance: $distance:ident ($distance_in:literal),
+                light: $light:ident ($light_in:literal),
+                light_polarity: $light_polarity:expr,
+                distance_model: $distance_model:ident,
+                light_model: $light_model:ident,
+                output: $output:ident ($channel:ident),
+                min_duty: $min_duty:expr,
+                thresholds: $thresholds:expr,
+                timeout: $timeout:expr,
+                presence: $presence:expr $(,)?
+            }
+        ),+ $(,)?
+    ) => {{
+        let mut pins = (None, None, None, None);
+        $(
+            $(#[$attr])*
+            zones!(@pin pins, $channel, $p.$output);
+        )+
+        $crate::light::init_pwm(
+            ::embassy_stm32::timer::simple_pwm::SimplePwm::new(
+                $p.TIM4,
+                pins.0,
+                pins.1,
+                pins.2,
+                pins.3,
+                $crate::config::get().pwm_frequency,
+                ::embassy_stm32::timer::low_level::CountingMode::EdgeAlignedUp,
+            ),
+            $crate::config::get().pwm_frequency,
+        );
+
+        $(
+            $(#[$attr])*
+            $crate::error::spawn($spawner, $crate::zone::controller(
+                    $crate::zone::Zone {
+                        id: $id,
+                        distance_sensor: ::embassy_stm32::adc::AdcChannel::degrade_adc($p.$distance),
+                        #[cfg(not(feature = "dual-adc"))]
+                        light_sensor: ::embassy_stm32::adc::AdcChannel::degrade_adc($p.$light),
+                        #[cfg(feature = "dual-adc")]
+                        light_sensor: $crate::dual_adc::Adc2Channel::new($p.$light, $light_in),
+                        light_polarity: $light_polarity,
+                        distance_model: ::sie_core::sensor::DistanceModel::$distance_model,
+                        light_model: ::sie_core::sensor::LightModel::$light_model,
+                        light: $crate::light::Light::new(
+                            ::embassy_stm32::timer::Channel::$channel,
+                            $crate::config::get().fade_time,
+                            $min_duty,
+                        ),
+                        thresholds: $thresholds,
+                        timeout: $timeout,
+                        presence: $presence,
+                    },
+                    $adc,
+                ), "zone");
+        )+
+
+        #[cfg(feature = "adc-watchdog")]
+        {
+            let mut inputs = ::heapless::Vec::new();
+            $(
+                $(#[$attr])*
+                if $light_polarity == ::sie_core::sensor::LuxPolarity::Rising {
+                    let _ = inputs.push($crate::adc_watchdog::Inputs {
+                        zone: $id,
+                        light: $light_in,
+                        distance: $distance_in,
+                    });
+                }
+            )+
+            $crate::error::spawn($spawner, $crate::adc_watchdog::adc_watchdog($p.ADC2, inputs), "adc_watchdog");
+        }
+    }};
+
+    (@pin $pins:ident, Ch1, $pin:expr) => {
+        $pins.0 = Some(::embassy_stm32::timer::simple_pwm::PwmPin::new_ch1(
+            $pin,
+            ::embassy_stm32::gpio::OutputType::PushPull,
+        ));
+    };
+    (@pin $pins:ident, Ch2, $pin:expr) => {
+        $pins.1 = Some(::embassy_stm32::timer::simple_pwm::PwmPin::new_ch2(
+            $pin,
+            ::embassy_stm32::gpio::OutputType::PushPull,
+        ));
+    };
+    (@pin $pins:ident, Ch3, $pin:expr) => {
+        $pins.2 = Some(::embassy_stm32::timer::simple_pwm::PwmPin::new_ch3(
+            $pin,
+            ::embassy_stm32::gpio::OutputType::PushPull,
+        ));
+    };
+    (@pin $pins:ident, Ch4, $pin:expr) => {
+        $pins.3 = Some(::embassy_stm32::timer::simple_pwm::PwmPin::new_ch4(
+            $pin,
+            ::embassy_stm32::gpio::OutputType::PushPull,
+        ));
+    };
+}
@@ -0,0 +1,228 @@
+// Pruebas en la placa con defmt-test: el ADC, el antirrebote de los botones
+// y la flash de datos contra el hardware real de una blue pill.
+// Uso (con la sonda conectada, probe-rs corre las pruebas):
+//     cargo test --test hardware --features hardware-tests
+//
+// Montaje: los sensores de la zona 0 en PB0 (distancia) y PA7 (luz), y un
+// puente de PB14 a PB13; PB14 hace de boton sobre la entrada de PB13.
+// Cuidado: las pruebas de flash borran las paginas de datos (el registro,
+// los ajustes y contadores y, segun las opciones, las reglas y la luz
+// ambiental aprendida)
+
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use panic_probe as _;
+
+#[path = "../src/clock.rs"]
+mod clock;
+#[path = "../src/storage.rs"]
+mod storage;
+
+#[defmt_test::tests]
+mod tests {
+    use embassy_futures::{block_on, join::join};
+    use embassy_stm32::{
+        adc::{Adc, AdcChannel, AnyAdcChannel, SampleTime},
+        exti::ExtiInput,
+        gpio::{Level, Output, Pull, Speed},
+        peripherals::ADC1,
+    };
+    use embassy_time::{Delay, Timer};
+    use sie_core::{
+        button::{Press, Timing},
+        control::Reading,
+        hal::Debounced,
+        kv::{Pages, Store},
+        sensor::{MAX_ADC_VALUE, supply_from_vrefint},
+    };
+
+    use crate::{
+        clock::SystemClock,
+        storage::{self, ERASED, PAGE_SIZE, Page},
+    };
+
+    // Los tiempos de arranque de los botones (src/config.rs)
+    const TIMING: Timing = Timing {
+        settle_ms: 50,
+        long_press_ms: 2000,
+        click_window_ms: 400,
+    };
+
+    // Clave del almacen que el firmware no usa
+    const TEST_KEY: u8 = 0x7F;
+
+    struct State {
+        adc: Adc<'static, ADC1>,
+        distance: AnyAdcChannel<ADC1>,
+        light: AnyAdcChannel<ADC1>,
+        button: Debounced<ExtiInput<'static>, Delay, SystemClock>,
+        press: Output<'static>,
+    }
+
+    // Las paginas del almacen como en src/kv.rs
+    struct FlashPages;
+
+    const PAGES: [Page; 2] = [Page::StoreA, Page::StoreB];
+
+    impl Pages for FlashPages {
+        fn read(&mut self, page: usize, offset: usize, buf: &mut [u8]) -> bool {
+            storage::read(PAGES[page], offset as u32, buf)
+        }
+
+        fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> bool {
+            storage::write(PAGES[page], offset as u32, data)
+        }
+
+        fn erase(&mut self, page: usize) -> bool {
+            storage::erase(PAGES[page])
+        }
+    }
+
+    #[init]
+    fn init() -> State {
+        let p = embassy_stm32::init(Default::default());
+        storage::init(p.FLASH);
+
+        let input = ExtiInput::new(p.PB13, p.EXTI13, Pull::Down);
+        State {
+            adc: Adc::new(p.ADC1),
+            distance: p.PB0.degrade_adc(),
+            light: p.PA7.degrade_adc(),
+            button: Debounced::new(input, Delay, SystemClock, TIMING),
+            press: Output::new(p.PB14, Level::Low, Speed::Low),
+        }
+    }
+
+    // La referencia interna de 1.2 V da una alimentacion razonable y las
+    // lecturas repetidas apenas varian
+    #[test]
+    fn vrefint_gives_the_supply(state: &mut State) {
+        let mut vref = state.adc.enable_vref();
+        state.adc.set_sample_time(SampleTime::CYCLES239_5);
+        let mut raws = [0u16; 16];
+        for raw in &mut raws {
+            *raw = block_on(state.adc.read(&mut vref));
+        }
+        state.adc.set_sample_time(SampleTime::CYCLES1_5);
+
+        let min = raws.iter().min().copied().unwrap_or(0);
+        let max = raws.iter().max().copied().unwrap_or(0);
+        defmt::assert!(max - min < 16, "VREFINT entre {} y {}", min, max);
+        let supply = supply_from_vrefint(raws[0]);
+        defmt::assert!((3.0..3.6).contains(&supply), "alimentacion de {} V", supply);
+    }
+
+    // Los canales de la zona 0 convierten dentro de la escala y dan una
+    // lectura valida
+    #[test]
+    fn zone_channels_convert(state: &mut State) {
+        let raw_distance = block_on(state.adc.read(&mut state.distance));
+        let raw_lux = block_on(state.adc.read(&mut state.light));
+        defmt::assert!(raw_distance as f32 <= MAX_ADC_VALUE);
+        defmt::assert!(raw_lux as f32 <= MAX_ADC_VALUE);
+
+        let reading = Reading::from_raw(raw_distance, raw_lux);
+        defmt::assert!(reading.distance.is_finite() && reading.distance >= 0.);
+        defmt::assert!(reading.lux.is_finite() && reading.lux >= 0.);
+    }
+
+    // Un clic con rebotes de 2 ms cuenta como uno solo
+    #[test]
+    fn bouncing_click_is_a_single_press(state: &mut State) {
+        let press = &mut state.press;
+        let click = async {
+            for _ in 0..3 {
+                press.set_high();
+                Timer::after_millis(2).await;
+                press.set_low();
+                Timer::after_millis(2).await;
+            }
+            press.set_high();
+            Timer::after_millis(150).await;
+            press.set_low();
+        };
+
+        let (gesture, ()) = block_on(join(state.button.wait_for_press(), click));
+        defmt::assert!(gesture == Press::Single);
+    }
+
+    // Dos clics dentro de la ventana son un doble clic
+    #[test]
+    fn two_clicks_are_a_double_press(state: &mut State) {
+        let press = &mut state.press;
+        let clicks = async {
+            for _ in 0..2 {
+                press.set_high();
+                Timer::after_millis(100).await;
+                press.set_low();
+                Timer::after_millis(100).await;
+            }
+        };
+
+        let (gesture, ()) = block_on(join(state.button.wait_for_press(), clicks));
+        defmt::assert!(gesture == Press::Double);
+    }
+
+    // Mantenerlo presionado es una pulsacion larga
+    #[test]
+    fn held_button_is_a_long_press(state: &mut State) {
+        let press = &mut state.press;
+        let hold = async {
+            press.set_high();
+            Timer::after_millis(TIMING.long_press_ms + 200).await;
+            press.set_low();
+        };
+
+        let (gesture, ()) = block_on(join(state.button.wait_for_press(), hold));
+        defmt::assert!(gesture == Press::Long);
+        // Deja pasar la liberacion antes de la siguiente prueba
+        block_on(Timer::after_millis(TIMING.click_window_ms));
+    }
+
+    // Cada pagina de datos se escribe, se lee igual y se borra a 0xFF
+    #[test]
+    fn data_pages_round_trip() {
+        let pages = [
+            Page::Log,
+            #[cfg(feature = "ambient-learning")]
+            Page::Ambient,
+            #[cfg(feature = "console")]
+            Page::Rules,
+            Page::StoreA,
+            Page::StoreB,
+        ];
+        let data = [0x12, 0x34, 0x56, 0x78];
+        for page in pages {
+            defmt::assert!(storage::erase(page));
+            defmt::assert!(storage::write(page, 8, &data));
+            let mut back = [0; 4];
+            defmt::assert!(storage::read(page, 8, &mut back));
+            defmt::assert_eq!(back, data);
+
+            defmt::assert!(storage::erase(page));
+            defmt::assert!(storage::read(page, 8, &mut back));
+            defmt::assert_eq!(back, [ERASED; 4]);
+        }
+    }
+
+    // El almacen conserva el ultimo valor al reabrirlo, tambien despues de
+    // pasar a la otra pagina
+    #[test]
+    fn store_survives_reopen_and_compaction() {
+        let mut store = Store::open(FlashPages, PAGE_SIZE as usize);
+        // Bastantes escrituras para llenar una pagina
+        for i in 0..=150u8 {
+            defmt::assert!(store.set(TEST_KEY, &[i; 8]));
+        }
+
+        let mut store = Store::open(FlashPages, PAGE_SIZE as usize);
+        let mut value = [0; 8];
+        defmt::assert_eq!(store.get(TEST_KEY, &mut value), Some(8));
+        defmt::assert_eq!(value, [150; 8]);
+
+        defmt::assert!(store.remove(TEST_KEY));
+        defmt::assert_eq!(store.get(TEST_KEY, &mut value), None);
+    }
+}